@@ -0,0 +1,306 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use tokio::sync::oneshot;
+
+use super::Backend;
+use crate::RespFrame;
+
+/// What a blocked client runs when one of its keys may be ready: its reply, or None to keep
+/// waiting.
+pub type BlockingOp = Box<dyn FnMut(&Backend) -> Option<RespFrame> + Send>;
+
+/// The clients blocked on keys, served in the order they blocked.
+///
+/// A write that may make a key ready calls `signal_ready` once it released its key locks. The
+/// operations of the waiters are run outside of this registry's lock, so an operation may
+/// itself signal a key (BLMOVE pushing to a list another client waits on): that key is queued
+/// and served by the same loop instead of recursing.
+#[derive(Default)]
+pub(crate) struct BlockedClients {
+    next_id: u64,
+    // waiter ids per key, oldest first
+    queues: HashMap<String, VecDeque<u64>>,
+    waiters: HashMap<u64, Waiter>,
+    // keys signaled and not served yet, and whether a thread is serving them
+    ready: VecDeque<String>,
+    serving: bool,
+}
+
+struct Waiter {
+    keys: Vec<String>,
+    // taken out while the operation runs
+    op: Option<BlockingOp>,
+    reply: oneshot::Sender<RespFrame>,
+    // the client gave up while its operation was running
+    cancelled: bool,
+}
+
+impl fmt::Debug for BlockedClients {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlockedClients")
+            .field("waiters", &self.waiters.len())
+            .field("ready", &self.ready)
+            .field("serving", &self.serving)
+            .finish()
+    }
+}
+
+impl BlockedClients {
+    fn register(
+        &mut self,
+        keys: Vec<String>,
+        op: BlockingOp,
+    ) -> (u64, oneshot::Receiver<RespFrame>) {
+        let id = self.next_id;
+        self.next_id += 1;
+        for key in &keys {
+            self.queues.entry(key.clone()).or_default().push_back(id);
+        }
+        let (tx, rx) = oneshot::channel();
+        let waiter = Waiter {
+            keys,
+            op: Some(op),
+            reply: tx,
+            cancelled: false,
+        };
+        self.waiters.insert(id, waiter);
+        (id, rx)
+    }
+
+    fn remove(&mut self, id: u64) -> Option<Waiter> {
+        let waiter = self.waiters.remove(&id)?;
+        for key in &waiter.keys {
+            if let Some(queue) = self.queues.get_mut(key) {
+                queue.retain(|&other| other != id);
+                if queue.is_empty() {
+                    self.queues.remove(key);
+                }
+            }
+        }
+        Some(waiter)
+    }
+
+    // stop waiting: returns false if the operation is running right now, the waiter is then
+    // removed by whoever runs it
+    fn cancel(&mut self, id: u64) -> bool {
+        match self.waiters.get_mut(&id) {
+            Some(waiter) if waiter.op.is_none() => {
+                waiter.cancelled = true;
+                false
+            }
+            Some(_) => {
+                self.remove(id);
+                true
+            }
+            None => true,
+        }
+    }
+}
+
+// deregisters the waiter however `block_on` ends, including when its future is dropped on
+// disconnect
+struct WaitGuard<'a> {
+    backend: &'a Backend,
+    id: u64,
+}
+
+impl Drop for WaitGuard<'_> {
+    fn drop(&mut self) {
+        self.backend.blocked().cancel(self.id);
+        self.backend.waiting.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl Backend {
+    /// Wait until `op` replies for one of the keys, None once the timeout elapsed. Without a
+    /// timeout it waits forever. Clients blocked on the same key are served first come, first
+    /// served.
+    pub async fn block_on(
+        &self,
+        mut keys: Vec<String>,
+        timeout: Option<Duration>,
+        op: BlockingOp,
+    ) -> Option<RespFrame> {
+        keys.sort();
+        keys.dedup();
+        // counted before the first try, so a push done after it can not miss this client
+        self.waiting.fetch_add(1, Ordering::SeqCst);
+        let (id, mut rx) = self.blocked().register(keys.clone(), op);
+        let _guard = WaitGuard { backend: self, id };
+        // the data may have arrived since the caller's own try
+        for key in &keys {
+            self.signal_ready(key);
+        }
+
+        let Some(timeout) = timeout else {
+            return rx.await.ok();
+        };
+        match tokio::time::timeout(timeout, &mut rx).await {
+            Ok(reply) => reply.ok(),
+            Err(_) => {
+                let cancelled = self.blocked().cancel(id);
+                if cancelled {
+                    // removed before anyone ran it, unless it was served just now
+                    rx.try_recv().ok()
+                } else {
+                    // the operation is running, wait for its outcome
+                    rx.await.ok()
+                }
+            }
+        }
+    }
+
+    /// Serve the clients blocked on a key that may have become ready. Must be called without
+    /// holding any key lock.
+    pub(crate) fn signal_ready(&self, key: &str) {
+        if self.waiting.load(Ordering::SeqCst) == 0 {
+            return;
+        }
+        let mut blocked = self.blocked();
+        blocked.ready.push_back(key.to_string());
+        if blocked.serving {
+            return;
+        }
+        blocked.serving = true;
+        while let Some(key) = blocked.ready.pop_front() {
+            let ids = match blocked.queues.get(&key) {
+                Some(queue) => queue.iter().copied().collect::<Vec<_>>(),
+                None => continue,
+            };
+            for id in ids {
+                let Some(mut op) = blocked.waiters.get_mut(&id).and_then(|w| w.op.take()) else {
+                    continue;
+                };
+                drop(blocked);
+                let reply = op(self);
+                blocked = self.blocked();
+                let Some(waiter) = blocked.waiters.get_mut(&id) else {
+                    continue;
+                };
+                match reply {
+                    Some(reply) => {
+                        let waiter = blocked.remove(id).expect("checked above");
+                        let _ = waiter.reply.send(reply);
+                    }
+                    None if waiter.cancelled => {
+                        // dropping the sender wakes the client up with nothing
+                        blocked.remove(id);
+                    }
+                    None => waiter.op = Some(op),
+                }
+            }
+        }
+        blocked.serving = false;
+    }
+
+    #[cfg(test)]
+    pub(crate) fn blocked_clients(&self) -> usize {
+        self.blocked().waiters.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BulkString, ListEnd};
+
+    fn pop_op(key: &str) -> BlockingOp {
+        let key = key.to_string();
+        Box::new(
+            move |backend: &Backend| match backend.pop(&key, ListEnd::Left, 1) {
+                Ok(Some(mut values)) => values.pop(),
+                _ => None,
+            },
+        )
+    }
+
+    fn push(backend: &Backend, key: &str, value: &str) {
+        let value = BulkString::from(value).into();
+        backend
+            .push(key.to_string(), vec![value], ListEnd::Right, false)
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_block_on_times_out() {
+        let backend = Backend::new();
+        let ret = backend
+            .block_on(
+                vec!["l".to_string()],
+                Some(Duration::from_millis(10)),
+                pop_op("l"),
+            )
+            .await;
+        assert_eq!(ret, None);
+        assert_eq!(backend.waiting.load(Ordering::SeqCst), 0);
+        assert!(backend.blocked().waiters.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_block_on_serves_in_order() {
+        let backend = Backend::new();
+        let mut tasks = vec![];
+        for _ in 0..3 {
+            let client = backend.clone();
+            tasks.push(tokio::spawn(async move {
+                client
+                    .block_on(vec!["l".to_string()], None, pop_op("l"))
+                    .await
+            }));
+            // let each client block before the next one
+            while backend.blocked().waiters.len() < tasks.len() {
+                tokio::task::yield_now().await;
+            }
+        }
+        push(&backend, "l", "a");
+        push(&backend, "l", "b");
+        push(&backend, "l", "c");
+        let mut replies = vec![];
+        for task in tasks {
+            replies.push(task.await.unwrap());
+        }
+        assert_eq!(
+            replies,
+            vec![Some(b"a".into()), Some(b"b".into()), Some(b"c".into())]
+        );
+        assert!(backend.db.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_block_on_ignores_other_types() {
+        let backend = Backend::new();
+        let client = backend.clone();
+        let waiter = tokio::spawn(async move {
+            client
+                .block_on(vec!["l".to_string()], None, pop_op("l"))
+                .await
+        });
+        while backend.blocked().waiters.is_empty() {
+            tokio::task::yield_now().await;
+        }
+        // a string under the key, then no key at all: the client keeps waiting
+        backend.set("l".to_string(), b"v".into());
+        backend.signal_ready("l");
+        backend.del(&["l".to_string()]);
+        assert_eq!(backend.blocked().waiters.len(), 1);
+
+        push(&backend, "l", "a");
+        assert_eq!(waiter.await.unwrap(), Some(b"a".into()));
+    }
+
+    #[tokio::test]
+    async fn test_dropped_waiter_is_removed() {
+        let backend = Backend::new();
+        let wait = backend.block_on(vec!["l".to_string()], None, pop_op("l"));
+        let ret = tokio::time::timeout(Duration::from_millis(10), wait).await;
+        assert!(ret.is_err());
+        assert!(backend.blocked().waiters.is_empty());
+        assert_eq!(backend.waiting.load(Ordering::SeqCst), 0);
+
+        push(&backend, "l", "a");
+        assert_eq!(backend.llen("l"), Ok(1));
+    }
+}
@@ -0,0 +1,362 @@
+use std::collections::HashMap;
+use std::sync::MutexGuard;
+use std::time::{Duration, Instant};
+
+use rand::seq::index;
+use tracing::debug;
+
+use super::{Backend, Entry, RedisValue};
+
+// the active expire cycle runs 10 times per second and may use up to 25% of that time,
+// mirroring Redis' default `hz 10` and ACTIVE_EXPIRE_CYCLE_SLOW_TIME_PERC
+const ACTIVE_EXPIRE_PERIOD: Duration = Duration::from_millis(100);
+const ACTIVE_EXPIRE_BUDGET: Duration = Duration::from_millis(25);
+const ACTIVE_EXPIRE_KEYS_PER_LOOP: usize = 20;
+// stop sampling once no more than this percentage of a sample turned out to be expired
+const ACTIVE_EXPIRE_ACCEPTABLE_STALE: usize = 10;
+
+/// The NX / XX / GT / LT condition of the expire commands.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExpireCondition {
+    #[default]
+    Always,
+    // only if there is no deadline yet
+    Nx,
+    // only if there is a deadline already
+    Xx,
+    // only if the new deadline is later, no deadline counts as infinite
+    Gt,
+    // only if the new deadline is earlier
+    Lt,
+}
+
+impl ExpireCondition {
+    pub(crate) fn allows(self, current: Option<u64>, at: u64) -> bool {
+        match (self, current) {
+            (ExpireCondition::Always, _) => true,
+            (ExpireCondition::Nx, current) => current.is_none(),
+            (ExpireCondition::Xx, current) => current.is_some(),
+            (ExpireCondition::Gt, current) => current.is_some_and(|c| at > c),
+            (ExpireCondition::Lt, current) => current.is_none_or(|c| at < c),
+        }
+    }
+}
+
+/// The set of keys carrying a deadline, with O(1) insert, remove and random sampling.
+///
+/// It is only touched while the corresponding keyspace slot is locked, so it never disagrees
+/// with the `expire_at` stored in the entry.
+#[derive(Debug, Default)]
+pub(crate) struct VolatileKeys {
+    keys: Vec<String>,
+    index: HashMap<String, usize>,
+}
+
+impl VolatileKeys {
+    pub(crate) fn insert(&mut self, key: &str) {
+        if !self.index.contains_key(key) {
+            self.index.insert(key.to_string(), self.keys.len());
+            self.keys.push(key.to_string());
+        }
+    }
+
+    pub(crate) fn remove(&mut self, key: &str) {
+        if let Some(pos) = self.index.remove(key) {
+            self.keys.swap_remove(pos);
+            if let Some(moved) = self.keys.get(pos) {
+                self.index.insert(moved.clone(), pos);
+            }
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn keys_len(&self) -> usize {
+        self.keys.len()
+    }
+
+    fn sample(&self, n: usize) -> Vec<String> {
+        let mut rng = rand::thread_rng();
+        index::sample(&mut rng, self.keys.len(), n.min(self.keys.len()))
+            .into_iter()
+            .map(|i| self.keys[i].clone())
+            .collect()
+    }
+}
+
+impl Backend {
+    /// Set the deadline of a key in unix milliseconds. A deadline that already passed deletes
+    /// the key. Returns false if the key does not exist.
+    pub fn expire_at(&self, key: &str, at: u64) -> bool {
+        let _guard = self.locks.write([key]);
+        self.expire_if_needed(key);
+        if at <= self.now_ms() {
+            return self.remove_entry(key).is_some();
+        }
+        match self.db.get_mut(key) {
+            Some(mut entry) => {
+                self.track_expire(key, entry.expire_at, Some(at));
+                entry.expire_at = Some(at);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drop the deadline of a key. Returns false if the key does not exist or has no deadline.
+    pub fn persist(&self, key: &str) -> bool {
+        let _guard = self.locks.write([key]);
+        self.expire_if_needed(key);
+        match self.db.get_mut(key) {
+            Some(mut entry) if entry.expire_at.is_some() => {
+                self.track_expire(key, entry.expire_at, None);
+                entry.expire_at = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// The deadline of a key: None if the key does not exist, Some(None) if it never expires.
+    pub fn expire_time(&self, key: &str) -> Option<Option<u64>> {
+        let _guard = self.locks.read([key]);
+        self.lookup(key).map(|entry| entry.expire_at)
+    }
+
+    /// Run one adaptive expire cycle: sample volatile keys, drop the expired ones and keep
+    /// going while the sample shows many stale keys and the time budget allows it. Hashes
+    /// with volatile fields get the same treatment for their fields afterwards. Returns how
+    /// many keys and fields were removed.
+    pub fn active_expire_cycle(&self, budget: Duration) -> usize {
+        let start = Instant::now();
+        let keys = self.sample_expired(start, budget, Self::volatile, |key, now| {
+            self.remove_expired(key, now) as usize
+        });
+        let fields = self.sample_expired(start, budget, Self::volatile_hashes, |key, now| {
+            self.remove_expired_fields(key, now)
+        });
+        keys + fields
+    }
+
+    // the adaptive loop of the expire cycle over one index, `expire` removes what expired
+    // under a sampled key and returns how much it removed
+    fn sample_expired(
+        &self,
+        start: Instant,
+        budget: Duration,
+        index: fn(&Self) -> MutexGuard<'_, VolatileKeys>,
+        expire: impl Fn(&str, u64) -> usize,
+    ) -> usize {
+        let mut total = 0;
+        loop {
+            let sample = index(self).sample(ACTIVE_EXPIRE_KEYS_PER_LOOP);
+            if sample.is_empty() {
+                break;
+            }
+            let now = self.now_ms();
+            let mut stale = 0;
+            for key in &sample {
+                let removed = expire(key, now);
+                stale += (removed > 0) as usize;
+                total += removed;
+            }
+            if stale * 100 <= sample.len() * ACTIVE_EXPIRE_ACCEPTABLE_STALE
+                || start.elapsed() > budget
+            {
+                break;
+            }
+        }
+        total
+    }
+
+    /// Run the active expire cycle forever, meant to be spawned once per server.
+    pub async fn run_active_expire(self) {
+        let mut interval = tokio::time::interval(ACTIVE_EXPIRE_PERIOD);
+        loop {
+            interval.tick().await;
+            let expired = self.active_expire_cycle(ACTIVE_EXPIRE_BUDGET);
+            if expired > 0 {
+                debug!("Active expire cycle removed {expired} keys and fields");
+            }
+        }
+    }
+
+    // remove the key if it expired, or else the expired fields of the hash it holds
+    pub(crate) fn expire_if_needed(&self, key: &str) {
+        let now = self.now_ms();
+        let (expired, fields_expired) = match self.db.get(key) {
+            Some(e) => (
+                e.is_expired(now),
+                matches!(&e.value, RedisValue::Hash(hash) if hash.has_expired(now)),
+            ),
+            None => (false, false),
+        };
+        if expired {
+            self.remove_expired(key, now);
+        } else if fields_expired {
+            self.remove_expired_fields(key, now);
+        }
+    }
+
+    /// Remove a key regardless of its deadline.
+    pub(crate) fn remove_entry(&self, key: &str) -> Option<Entry> {
+        self.db
+            .remove_if(key, |k, e| {
+                self.track_expire(k, e.expire_at, None);
+                self.track_field_expire(k, e.value.has_volatile_fields(), false);
+                self.track_key(k, false);
+                true
+            })
+            .map(|(_, e)| e)
+    }
+
+    // keep the volatile index in sync, must be called while the key's slot is locked
+    pub(crate) fn track_expire(&self, key: &str, old: Option<u64>, new: Option<u64>) {
+        match (old, new) {
+            (None, None) => {}
+            (_, Some(_)) => self.volatile().insert(key),
+            (Some(_), None) => self.volatile().remove(key),
+        }
+    }
+
+    // same as `track_expire` for the index of hashes with volatile fields
+    pub(crate) fn track_field_expire(&self, key: &str, old: bool, new: bool) {
+        match (old, new) {
+            (false, true) => self.volatile_hashes().insert(key),
+            (true, false) => self.volatile_hashes().remove(key),
+            _ => {}
+        }
+    }
+
+    fn remove_expired(&self, key: &str, now: u64) -> bool {
+        self.db
+            .remove_if(key, |k, e| {
+                let expired = e.is_expired(now);
+                if expired {
+                    self.volatile().remove(k);
+                    self.track_field_expire(k, e.value.has_volatile_fields(), false);
+                    self.track_key(k, false);
+                }
+                expired
+            })
+            .is_some()
+    }
+
+    // drop the expired fields of a hash and the hash itself once empty, returns how many
+    // fields were removed
+    fn remove_expired_fields(&self, key: &str, now: u64) -> usize {
+        let (removed, empty) = match self.db.get_mut(key) {
+            Some(mut entry) => match &mut entry.value {
+                RedisValue::Hash(hash) => {
+                    let removed = hash.remove_expired(now);
+                    self.track_field_expire(key, true, hash.has_volatile());
+                    (removed, hash.is_empty())
+                }
+                _ => (0, false),
+            },
+            None => (0, false),
+        };
+        if empty {
+            // re-checked under the shard lock, a concurrent write may have refilled it
+            self.db.remove_if(key, |k, e| {
+                let empty = matches!(&e.value, RedisValue::Hash(hash) if hash.is_empty());
+                if empty {
+                    self.track_expire(k, e.expire_at, None);
+                    self.track_key(k, false);
+                }
+                empty
+            });
+        }
+        removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ManualClock;
+    use std::sync::Arc;
+
+    fn backend_at(now: u64) -> (Backend, Arc<ManualClock>) {
+        let clock = Arc::new(ManualClock::new(now));
+        (Backend::with_clock(clock.clone()), clock)
+    }
+
+    #[test]
+    fn test_volatile_keys_insert_remove() {
+        let mut keys = VolatileKeys::default();
+        keys.insert("a");
+        keys.insert("b");
+        keys.insert("c");
+        keys.insert("a");
+        assert_eq!(keys.keys.len(), 3);
+
+        keys.remove("a");
+        keys.remove("missing");
+        assert_eq!(keys.keys.len(), 2);
+        let mut sample = keys.sample(10);
+        sample.sort();
+        assert_eq!(sample, vec!["b".to_string(), "c".to_string()]);
+    }
+
+    #[test]
+    fn test_lazy_expire() {
+        let (backend, clock) = backend_at(1000);
+        backend.set("key".to_string(), b"value".into());
+        assert!(backend.expire_at("key", 2000));
+        assert_eq!(backend.expire_time("key"), Some(Some(2000)));
+
+        clock.set(2000);
+        assert_eq!(backend.get("key"), Ok(Some(b"value".into())));
+
+        clock.set(2001);
+        assert_eq!(backend.get("key"), Ok(None));
+        assert_eq!(backend.expire_time("key"), None);
+        assert!(backend.db.is_empty());
+        assert_eq!(backend.volatile().keys.len(), 0);
+    }
+
+    #[test]
+    fn test_expire_in_the_past_deletes_key() {
+        let (backend, _) = backend_at(1000);
+        backend.set("key".to_string(), b"value".into());
+        assert!(backend.expire_at("key", 1000));
+        assert_eq!(backend.get("key"), Ok(None));
+        assert!(!backend.expire_at("key", 5000));
+    }
+
+    #[test]
+    fn test_persist_and_set_clear_deadline() {
+        let (backend, _) = backend_at(1000);
+        backend.set_with_expire("key".to_string(), b"value".into(), Some(2000));
+        assert!(backend.persist("key"));
+        assert!(!backend.persist("key"));
+        assert_eq!(backend.expire_time("key"), Some(None));
+
+        backend.set_with_expire("key".to_string(), b"value".into(), Some(2000));
+        backend.set("key".to_string(), b"other".into());
+        assert_eq!(backend.expire_time("key"), Some(None));
+        assert_eq!(backend.volatile().keys.len(), 0);
+    }
+
+    #[test]
+    fn test_active_expire_cycle() {
+        let (backend, clock) = backend_at(1000);
+        for i in 0..100 {
+            let at = if i % 2 == 0 { 1500 } else { 5000 };
+            backend.set_with_expire(format!("key{i}"), b"value".into(), Some(at));
+        }
+        backend.set("persistent".to_string(), b"value".into());
+
+        clock.set(2000);
+        let mut expired = 0;
+        for _ in 0..1000 {
+            expired += backend.active_expire_cycle(Duration::from_secs(1));
+            if expired == 50 {
+                break;
+            }
+        }
+        assert_eq!(expired, 50);
+        assert_eq!(backend.db.len(), 51);
+        assert_eq!(backend.volatile().keys.len(), 50);
+    }
+}
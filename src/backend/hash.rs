@@ -0,0 +1,529 @@
+use std::collections::HashMap;
+
+use rand::seq::{index, SliceRandom};
+
+use super::scan::ScanPage;
+use super::string::{parse_f64, parse_i64};
+use super::{Backend, BackendError, Entry, ExpireCondition, HashValue, RedisValue};
+use crate::{BulkString, RespFrame};
+
+impl Backend {
+    pub fn hget(&self, key: &str, field: &str) -> Result<Option<RespFrame>, BackendError> {
+        let _guard = self.locks.read([key]);
+        Ok(self
+            .read_hash(key, |hash| hash.get(field).cloned())?
+            .flatten())
+    }
+
+    /// HMGET: the values of the fields, None for the missing ones.
+    pub fn hmget(
+        &self,
+        key: &str,
+        fields: &[String],
+    ) -> Result<Vec<Option<RespFrame>>, BackendError> {
+        let _guard = self.locks.read([key]);
+        let values = self.read_hash(key, |hash| {
+            fields.iter().map(|f| hash.get(f).cloned()).collect()
+        })?;
+        Ok(values.unwrap_or_else(|| vec![None; fields.len()]))
+    }
+
+    /// HSET: write the fields, creating the hash if needed. Returns how many fields are new.
+    pub fn hset(
+        &self,
+        key: String,
+        fields: Vec<(String, RespFrame)>,
+    ) -> Result<usize, BackendError> {
+        let _guard = self.locks.write([key.as_str()]);
+        let added = self.write_value::<HashValue, _>(&key, true, |hash| {
+            let mut added = 0;
+            for (field, value) in fields {
+                if hash.insert(field, value).is_none() {
+                    added += 1;
+                }
+            }
+            Ok(added)
+        })?;
+        Ok(added.unwrap_or(0))
+    }
+
+    /// HSETNX: write the field only if it does not exist yet.
+    pub fn hsetnx(
+        &self,
+        key: String,
+        field: String,
+        value: RespFrame,
+    ) -> Result<bool, BackendError> {
+        let _guard = self.locks.write([key.as_str()]);
+        let added = self.write_value::<HashValue, _>(&key, true, |hash| {
+            if hash.contains_key(&field) {
+                return Ok(false);
+            }
+            hash.insert(field, value);
+            Ok(true)
+        })?;
+        Ok(added.unwrap_or(false))
+    }
+
+    /// HDEL: remove the fields, and the key with its last field. Returns how many existed.
+    pub fn hdel(&self, key: &str, fields: &[String]) -> Result<usize, BackendError> {
+        let _guard = self.locks.write([key]);
+        let removed = self.write_value::<HashValue, _>(key, false, |hash| {
+            Ok(fields.iter().filter(|f| hash.remove(f).is_some()).count())
+        })?;
+        Ok(removed.unwrap_or(0))
+    }
+
+    pub fn hexists(&self, key: &str, field: &str) -> Result<bool, BackendError> {
+        let _guard = self.locks.read([key]);
+        Ok(self
+            .read_hash(key, |hash| hash.contains_key(field))?
+            .unwrap_or(false))
+    }
+
+    pub fn hlen(&self, key: &str) -> Result<usize, BackendError> {
+        let _guard = self.locks.read([key]);
+        Ok(self.read_hash(key, |hash| hash.len())?.unwrap_or(0))
+    }
+
+    pub fn hgetall(&self, key: &str) -> Result<Option<HashMap<String, RespFrame>>, BackendError> {
+        let _guard = self.locks.read([key]);
+        self.read_hash(key, |hash| HashMap::clone(hash))
+    }
+
+    /// One HSCAN step over the fields of the hash, same cursor guarantees as SCAN. Only the
+    /// fields of the page are cloned.
+    pub fn hscan(
+        &self,
+        key: &str,
+        cursor: u64,
+        count: usize,
+    ) -> Result<ScanPage<(String, RespFrame)>, BackendError> {
+        let _guard = self.locks.read([key]);
+        let page = self.read_hash(key, |hash| {
+            let (next, page) = hash.scan(cursor, count);
+            let page = page
+                .into_iter()
+                .map(|(field, value)| (field.to_string(), value.clone()))
+                .collect();
+            (next, page)
+        })?;
+        Ok(page.unwrap_or((0, vec![])))
+    }
+
+    pub fn hkeys(&self, key: &str) -> Result<Vec<String>, BackendError> {
+        let _guard = self.locks.read([key]);
+        Ok(self
+            .read_hash(key, |hash| hash.keys().cloned().collect())?
+            .unwrap_or_default())
+    }
+
+    pub fn hvals(&self, key: &str) -> Result<Vec<RespFrame>, BackendError> {
+        let _guard = self.locks.read([key]);
+        Ok(self
+            .read_hash(key, |hash| hash.values().cloned().collect())?
+            .unwrap_or_default())
+    }
+
+    /// HSTRLEN: the length of the value of the field, 0 if it does not exist.
+    pub fn hstrlen(&self, key: &str, field: &str) -> Result<usize, BackendError> {
+        let _guard = self.locks.read([key]);
+        let len = self.read_hash(key, |hash| hash.get(field).map(value_len))?;
+        Ok(len.flatten().unwrap_or(0))
+    }
+
+    /// HINCRBY: add `delta` to the integer in the field, a missing field counts as 0.
+    pub fn hincr_by(&self, key: String, field: String, delta: i64) -> Result<i64, BackendError> {
+        let _guard = self.locks.write([key.as_str()]);
+        let ret = self.write_value::<HashValue, _>(&key, true, |hash| {
+            let current = match hash.get(&field) {
+                Some(v) => parse_i64(v).ok_or(BackendError::HashNotInteger)?,
+                None => 0,
+            };
+            let ret = current.checked_add(delta).ok_or(BackendError::Overflow)?;
+            hash.update(field, BulkString::from(ret.to_string()).into());
+            Ok(ret)
+        })?;
+        Ok(ret.unwrap_or_default())
+    }
+
+    /// HINCRBYFLOAT: add `delta` to the number in the field, a missing field counts as 0.
+    /// Returns the new value formatted the way it is stored.
+    pub fn hincr_by_float(
+        &self,
+        key: String,
+        field: String,
+        delta: f64,
+    ) -> Result<String, BackendError> {
+        let _guard = self.locks.write([key.as_str()]);
+        let ret = self.write_value::<HashValue, _>(&key, true, |hash| {
+            let current = match hash.get(&field) {
+                Some(v) => parse_f64(v).ok_or(BackendError::HashNotFloat)?,
+                None => 0.0,
+            };
+            let ret = current + delta;
+            if !ret.is_finite() {
+                return Err(BackendError::NanOrInfinity);
+            }
+            let ret = ret.to_string();
+            hash.update(field, BulkString::from(ret.clone()).into());
+            Ok(ret)
+        })?;
+        Ok(ret.unwrap_or_default())
+    }
+
+    /// HRANDFIELD: `count` distinct random fields, or `-count` fields that may repeat when it
+    /// is negative.
+    pub fn hrandfield(
+        &self,
+        key: &str,
+        count: i64,
+    ) -> Result<Vec<(String, RespFrame)>, BackendError> {
+        let _guard = self.locks.read([key]);
+        let fields = self.read_hash(key, |hash| {
+            let mut rng = rand::thread_rng();
+            let all = hash.iter().collect::<Vec<_>>();
+            let picked: Vec<_> = if count >= 0 {
+                let n = (count as usize).min(all.len());
+                index::sample(&mut rng, all.len(), n)
+                    .into_iter()
+                    .map(|i| all[i])
+                    .collect()
+            } else {
+                (0..count.unsigned_abs())
+                    .filter_map(|_| all.choose(&mut rng).copied())
+                    .collect()
+            };
+            picked
+                .into_iter()
+                .map(|(f, v)| (f.clone(), v.clone()))
+                .collect()
+        })?;
+        Ok(fields.unwrap_or_default())
+    }
+
+    /// HEXPIRE family: set the deadline of the fields in unix milliseconds when `condition`
+    /// allows it. Per field, like Redis: -2 if it does not exist, 0 if the condition is not
+    /// met, 1 if the deadline was set and 2 if the field was deleted since it already passed.
+    pub fn hexpire_at(
+        &self,
+        key: &str,
+        fields: &[String],
+        at: u64,
+        condition: ExpireCondition,
+    ) -> Result<Vec<i64>, BackendError> {
+        let _guard = self.locks.write([key]);
+        let now = self.now_ms();
+        let ret = self.write_value::<HashValue, _>(key, false, |hash| {
+            let ret = fields
+                .iter()
+                .map(|field| {
+                    if !hash.contains_key(field) {
+                        -2
+                    } else if !condition.allows(hash.expire_time(field), at) {
+                        0
+                    } else if at <= now {
+                        hash.remove(field);
+                        2
+                    } else {
+                        hash.set_expire(field, at);
+                        1
+                    }
+                })
+                .collect();
+            Ok(ret)
+        })?;
+        Ok(ret.unwrap_or_else(|| vec![-2; fields.len()]))
+    }
+
+    /// HPERSIST: drop the deadline of the fields. Per field: -2 if it does not exist, -1 if
+    /// it has no deadline and 1 if it was dropped.
+    pub fn hpersist(&self, key: &str, fields: &[String]) -> Result<Vec<i64>, BackendError> {
+        let _guard = self.locks.write([key]);
+        let ret = self.write_value::<HashValue, _>(key, false, |hash| {
+            let ret = fields
+                .iter()
+                .map(|field| match hash.contains_key(field) {
+                    false => -2,
+                    true if hash.persist(field) => 1,
+                    true => -1,
+                })
+                .collect();
+            Ok(ret)
+        })?;
+        Ok(ret.unwrap_or_else(|| vec![-2; fields.len()]))
+    }
+
+    /// The deadline of each field like `expire_time`: None if the field does not exist,
+    /// Some(None) if it never expires.
+    pub fn hexpire_time(
+        &self,
+        key: &str,
+        fields: &[String],
+    ) -> Result<Vec<Option<Option<u64>>>, BackendError> {
+        let _guard = self.locks.read([key]);
+        let ret = self.read_hash(key, |hash| {
+            fields
+                .iter()
+                .map(|field| hash.contains_key(field).then(|| hash.expire_time(field)))
+                .collect()
+        })?;
+        Ok(ret.unwrap_or_else(|| vec![None; fields.len()]))
+    }
+
+    // run `f` over the hash stored at the key, None if the key does not exist
+    fn read_hash<T>(
+        &self,
+        key: &str,
+        f: impl FnOnce(&HashValue) -> T,
+    ) -> Result<Option<T>, BackendError> {
+        match self.lookup(key).as_deref() {
+            Some(Entry {
+                value: RedisValue::Hash(hash),
+                ..
+            }) => Ok(Some(f(hash))),
+            Some(_) => Err(BackendError::WrongType),
+            None => Ok(None),
+        }
+    }
+}
+
+// the length of a value as a string
+fn value_len(value: &RespFrame) -> usize {
+    match value {
+        RespFrame::BulkString(s) => s.len(),
+        RespFrame::SimpleString(s) => s.len(),
+        RespFrame::Integer(n) => n.to_string().len(),
+        RespFrame::Double(n) => n.to_string().len(),
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ManualClock;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn fields(pairs: &[(&str, &str)]) -> Vec<(String, RespFrame)> {
+        pairs
+            .iter()
+            .map(|(f, v)| (f.to_string(), BulkString::from(*v).into()))
+            .collect()
+    }
+
+    #[test]
+    fn test_hset_counts_new_fields() {
+        let backend = Backend::new();
+        let added = backend.hset("h".to_string(), fields(&[("a", "1"), ("b", "2")]));
+        assert_eq!(added, Ok(2));
+        let added = backend.hset("h".to_string(), fields(&[("a", "3"), ("c", "4")]));
+        assert_eq!(added, Ok(1));
+        assert_eq!(backend.hlen("h"), Ok(3));
+        assert_eq!(backend.hget("h", "a"), Ok(Some(b"3".into())));
+        assert_eq!(
+            backend.hsetnx("h".to_string(), "a".to_string(), b"x".into()),
+            Ok(false)
+        );
+        assert_eq!(
+            backend.hsetnx("h".to_string(), "d".to_string(), b"x".into()),
+            Ok(true)
+        );
+    }
+
+    #[test]
+    fn test_hdel_removes_empty_hash() {
+        let backend = Backend::new();
+        backend
+            .hset("h".to_string(), fields(&[("a", "1"), ("b", "2")]))
+            .unwrap();
+        backend.expire_at("h", u64::MAX);
+        let del = ["a", "missing"].map(String::from);
+        assert_eq!(backend.hdel("h", &del), Ok(1));
+        assert_eq!(backend.key_type("h"), "hash");
+        assert_eq!(backend.hdel("h", &["b".to_string()]), Ok(1));
+        assert_eq!(backend.key_type("h"), "none");
+        assert!(backend.db.is_empty());
+        assert_eq!(backend.hdel("h", &["b".to_string()]), Ok(0));
+    }
+
+    #[test]
+    fn test_hincr_by() {
+        let backend = Backend::new();
+        assert_eq!(backend.hincr_by("h".to_string(), "n".to_string(), 5), Ok(5));
+        assert_eq!(
+            backend.hincr_by("h".to_string(), "n".to_string(), -7),
+            Ok(-2)
+        );
+        assert_eq!(
+            backend.hincr_by_float("h".to_string(), "n".to_string(), 0.5),
+            Ok("-1.5".to_string())
+        );
+        assert_eq!(
+            backend.hincr_by("h".to_string(), "n".to_string(), 1),
+            Err(BackendError::HashNotInteger)
+        );
+        backend
+            .hset("h".to_string(), fields(&[("s", "abc")]))
+            .unwrap();
+        assert_eq!(
+            backend.hincr_by_float("h".to_string(), "s".to_string(), 1.0),
+            Err(BackendError::HashNotFloat)
+        );
+        assert_eq!(backend.hstrlen("h", "s"), Ok(3));
+        assert_eq!(backend.hstrlen("h", "missing"), Ok(0));
+    }
+
+    #[test]
+    fn test_failed_create_leaves_no_key() {
+        let backend = Backend::new();
+        assert_eq!(
+            backend.hincr_by_float("h".to_string(), "n".to_string(), f64::INFINITY),
+            Err(BackendError::NanOrInfinity)
+        );
+        assert_eq!(backend.key_type("h"), "none");
+    }
+
+    #[test]
+    fn test_hscan_pages_through_every_field() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        let pairs = (0..100)
+            .map(|i| (format!("f{i}"), BulkString::from(i.to_string()).into()))
+            .collect::<Vec<_>>();
+        backend.hset("h".to_string(), pairs)?;
+
+        let mut cursor = 0;
+        let mut seen = HashMap::new();
+        loop {
+            let (next, page) = backend.hscan("h", cursor, 7)?;
+            assert!(page.len() <= 7);
+            seen.extend(page);
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+        assert_eq!(seen.len(), 100);
+        assert_eq!(seen["f42"], BulkString::from("42").into());
+
+        assert_eq!(backend.hscan("missing", 0, 10)?, (0, vec![]));
+        backend.set("s".to_string(), b"v".into());
+        assert_eq!(backend.hscan("s", 0, 10), Err(BackendError::WrongType));
+        Ok(())
+    }
+
+    #[test]
+    fn test_hrandfield() {
+        let backend = Backend::new();
+        assert_eq!(backend.hrandfield("h", 3), Ok(vec![]));
+        backend
+            .hset(
+                "h".to_string(),
+                fields(&[("a", "1"), ("b", "2"), ("c", "3")]),
+            )
+            .unwrap();
+
+        let mut distinct = backend.hrandfield("h", 10).unwrap();
+        distinct.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(distinct, fields(&[("a", "1"), ("b", "2"), ("c", "3")]));
+        assert_eq!(backend.hrandfield("h", 2).unwrap().len(), 2);
+        assert_eq!(backend.hrandfield("h", -7).unwrap().len(), 7);
+        assert_eq!(backend.hrandfield("h", 0), Ok(vec![]));
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn test_field_expire_lazily() -> Result<(), BackendError> {
+        let clock = Arc::new(ManualClock::new(1000));
+        let backend = Backend::with_clock(clock.clone());
+        backend.hset("h".to_string(), fields(&[("a", "1"), ("b", "2")]))?;
+
+        let ab = names(&["a", "b", "c"]);
+        assert_eq!(
+            backend.hexpire_at("h", &ab[..1], 2000, ExpireCondition::Always)?,
+            vec![1]
+        );
+        assert_eq!(
+            backend.hexpire_time("h", &ab)?,
+            vec![Some(Some(2000)), Some(None), None]
+        );
+        // NX and GT refuse, XX and LT accept
+        assert_eq!(
+            backend.hexpire_at("h", &ab, 3000, ExpireCondition::Nx)?,
+            vec![0, 1, -2]
+        );
+        assert_eq!(
+            backend.hexpire_at("h", &ab[..2], 2500, ExpireCondition::Gt)?,
+            vec![1, 0]
+        );
+        assert_eq!(
+            backend.hexpire_at("h", &ab[..2], 2000, ExpireCondition::Lt)?,
+            vec![1, 1]
+        );
+        assert_eq!(backend.hpersist("h", &ab)?, vec![1, 1, -2]);
+        assert_eq!(backend.hpersist("h", &ab[..1])?, vec![-1]);
+
+        backend.hexpire_at("h", &ab[..1], 2000, ExpireCondition::Always)?;
+        clock.set(2001);
+        assert_eq!(backend.hget("h", "a")?, None);
+        assert_eq!(backend.hlen("h")?, 1);
+        assert_eq!(backend.volatile_hashes().keys_len(), 0);
+
+        // a deadline in the past deletes the field, and the hash with its last field
+        assert_eq!(
+            backend.hexpire_at("h", &ab[1..2], 2001, ExpireCondition::Always)?,
+            vec![2]
+        );
+        assert!(backend.db.is_empty());
+        assert_eq!(
+            backend.hexpire_at("h", &ab[..2], 5000, ExpireCondition::Always)?,
+            vec![-2, -2]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_hset_drops_field_deadline() -> Result<(), BackendError> {
+        let clock = Arc::new(ManualClock::new(1000));
+        let backend = Backend::with_clock(clock.clone());
+        backend.hset("h".to_string(), fields(&[("a", "1")]))?;
+        let a = names(&["a"]);
+        backend.hexpire_at("h", &a, 2000, ExpireCondition::Always)?;
+        backend.hincr_by("h".to_string(), "a".to_string(), 1)?;
+        assert_eq!(backend.hexpire_time("h", &a)?, vec![Some(Some(2000))]);
+        backend.hset("h".to_string(), fields(&[("a", "1")]))?;
+        assert_eq!(backend.hexpire_time("h", &a)?, vec![Some(None)]);
+        assert_eq!(backend.volatile_hashes().keys_len(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_field_expire_actively() -> Result<(), BackendError> {
+        let clock = Arc::new(ManualClock::new(1000));
+        let backend = Backend::with_clock(clock.clone());
+        for i in 0..20 {
+            let key = format!("h{i}");
+            backend.hset(key.clone(), fields(&[("a", "1"), ("b", "2")]))?;
+            backend.hexpire_at(&key, &names(&["a"]), 1500, ExpireCondition::Always)?;
+        }
+        backend.hset("gone".to_string(), fields(&[("a", "1")]))?;
+        backend.hexpire_at("gone", &names(&["a"]), 1500, ExpireCondition::Always)?;
+
+        clock.set(2000);
+        let mut expired = 0;
+        for _ in 0..100 {
+            expired += backend.active_expire_cycle(Duration::from_secs(1));
+            if expired == 21 {
+                break;
+            }
+        }
+        assert_eq!(expired, 21);
+        assert_eq!(backend.db.len(), 20);
+        assert_eq!(backend.volatile_hashes().keys_len(), 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,191 @@
+use tokio::runtime::Handle;
+
+use super::{Backend, BackendError};
+
+// values made of more elements than this are dropped off the connection task by UNLINK,
+// Redis' LAZYFREE_THRESHOLD
+const LAZYFREE_THRESHOLD: usize = 64;
+
+impl Backend {
+    /// Remove the keys, returns how many existed.
+    pub fn del(&self, keys: &[String]) -> usize {
+        let removed = {
+            let _guard = self.locks.write(keys.iter().map(String::as_str));
+            keys.iter()
+                .filter(|key| {
+                    self.expire_if_needed(key);
+                    self.remove_entry(key).is_some()
+                })
+                .collect::<Vec<_>>()
+        };
+        self.signal_removed(&removed);
+        removed.len()
+    }
+
+    /// Like `del`, but big values are freed on a blocking thread instead of the caller's.
+    pub fn unlink(&self, keys: &[String]) -> usize {
+        let mut lazy = vec![];
+        let removed = {
+            let _guard = self.locks.write(keys.iter().map(String::as_str));
+            keys.iter()
+                .filter(|key| {
+                    self.expire_if_needed(key);
+                    match self.remove_entry(key) {
+                        Some(entry) if entry.value.free_effort() > LAZYFREE_THRESHOLD => {
+                            lazy.push(entry);
+                            true
+                        }
+                        Some(_) => true,
+                        None => false,
+                    }
+                })
+                .collect::<Vec<_>>()
+        };
+        self.signal_removed(&removed);
+        if !lazy.is_empty() {
+            // outside of a runtime (tests, tools) there is nothing to offload to
+            if let Ok(handle) = Handle::try_current() {
+                handle.spawn_blocking(move || drop(lazy));
+            }
+        }
+        removed.len()
+    }
+
+    // a client blocked in XREADGROUP on a removed stream is told its group is gone
+    fn signal_removed(&self, keys: &[&String]) {
+        for key in keys {
+            self.signal_ready(key);
+        }
+    }
+
+    /// How many of the keys exist, a key given twice is counted twice.
+    pub fn exists(&self, keys: &[String]) -> usize {
+        let _guard = self.locks.read(keys.iter().map(String::as_str));
+        keys.iter().filter(|key| self.lookup(key).is_some()).count()
+    }
+
+    /// Move a key and its TTL to a new name, overwriting the destination unless `nx` is set.
+    /// Returns false if `nx` prevented the rename.
+    pub fn rename(&self, src: &str, dst: &str, nx: bool) -> Result<bool, BackendError> {
+        let renamed = self.rename_entry(src, dst, nx)?;
+        // a list moved into place serves the clients blocked on its new name
+        if renamed {
+            self.signal_ready(dst);
+        }
+        Ok(renamed)
+    }
+
+    /// Copy a key and its TTL to a new name. Returns false if the source does not exist, or
+    /// the destination exists and `replace` is not set.
+    pub fn copy(&self, src: &str, dst: &str, replace: bool) -> Result<bool, BackendError> {
+        let copied = self.copy_entry(src, dst, replace)?;
+        if copied {
+            self.signal_ready(dst);
+        }
+        Ok(copied)
+    }
+
+    fn rename_entry(&self, src: &str, dst: &str, nx: bool) -> Result<bool, BackendError> {
+        let _guard = self.locks.write([src, dst]);
+        self.expire_if_needed(src);
+        if !self.db.contains_key(src) {
+            return Err(BackendError::NoSuchKey);
+        }
+        if src == dst {
+            return Ok(!nx);
+        }
+        self.expire_if_needed(dst);
+        if nx && self.db.contains_key(dst) {
+            return Ok(false);
+        }
+        if let Some(entry) = self.remove_entry(src) {
+            self.insert_entry(dst.to_string(), entry);
+        }
+        Ok(true)
+    }
+
+    fn copy_entry(&self, src: &str, dst: &str, replace: bool) -> Result<bool, BackendError> {
+        if src == dst {
+            return Err(BackendError::SameObject);
+        }
+        let _guard = self.locks.lock([src], [dst]);
+        let Some(entry) = self.lookup(src).map(|e| e.clone()) else {
+            return Ok(false);
+        };
+        self.expire_if_needed(dst);
+        if !replace && self.db.contains_key(dst) {
+            return Ok(false);
+        }
+        self.insert_entry(dst.to_string(), entry);
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ManualClock, RespFrame};
+    use std::sync::Arc;
+
+    fn keys(keys: &[&str]) -> Vec<String> {
+        keys.iter().map(|k| k.to_string()).collect()
+    }
+
+    #[test]
+    fn test_del_and_exists() {
+        let backend = Backend::new();
+        backend.set("a".to_string(), b"1".into());
+        backend
+            .hset("h".to_string(), vec![("f".to_string(), b"v".into())])
+            .unwrap();
+        assert_eq!(backend.exists(&keys(&["a", "a", "h", "missing"])), 3);
+        assert_eq!(backend.del(&keys(&["a", "h", "a", "missing"])), 2);
+        assert_eq!(backend.exists(&keys(&["a", "h"])), 0);
+    }
+
+    #[tokio::test]
+    async fn test_unlink_big_value() {
+        let backend = Backend::new();
+        for i in 0..=LAZYFREE_THRESHOLD {
+            backend
+                .hset("big".to_string(), vec![(format!("f{i}"), b"v".into())])
+                .unwrap();
+        }
+        backend.set_with_expire("small".to_string(), b"v".into(), Some(u64::MAX));
+        assert_eq!(backend.unlink(&keys(&["big", "small", "missing"])), 2);
+        assert!(backend.db.is_empty());
+    }
+
+    #[test]
+    fn test_rename_keeps_ttl() {
+        let backend = Backend::with_clock(Arc::new(ManualClock::new(1000)));
+        backend.set_with_expire("a".to_string(), b"1".into(), Some(5000));
+        backend.set("b".to_string(), b"2".into());
+
+        assert_eq!(backend.rename("a", "b", true), Ok(false));
+        assert_eq!(backend.rename("a", "b", false), Ok(true));
+        assert_eq!(backend.get("b"), Ok(Some(RespFrame::from(b"1"))));
+        assert_eq!(backend.expire_time("b"), Some(Some(5000)));
+        assert_eq!(backend.expire_time("a"), None);
+        assert_eq!(
+            backend.rename("a", "c", false),
+            Err(BackendError::NoSuchKey)
+        );
+        assert_eq!(backend.rename("b", "b", false), Ok(true));
+        assert_eq!(backend.rename("b", "b", true), Ok(false));
+    }
+
+    #[test]
+    fn test_copy() {
+        let backend = Backend::with_clock(Arc::new(ManualClock::new(1000)));
+        backend.set_with_expire("a".to_string(), b"1".into(), Some(5000));
+        backend.set("b".to_string(), b"2".into());
+
+        assert_eq!(backend.copy("a", "b", false), Ok(false));
+        assert_eq!(backend.copy("a", "b", true), Ok(true));
+        assert_eq!(backend.expire_time("b"), Some(Some(5000)));
+        assert_eq!(backend.get("a"), Ok(Some(RespFrame::from(b"1"))));
+        assert_eq!(backend.copy("missing", "c", false), Ok(false));
+        assert_eq!(backend.copy("a", "a", false), Err(BackendError::SameObject));
+    }
+}
@@ -0,0 +1,455 @@
+use std::collections::VecDeque;
+
+use super::{Backend, BackendError, Entry, RedisValue};
+use crate::RespFrame;
+
+type List = VecDeque<RespFrame>;
+
+/// The end of a list a command works on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListEnd {
+    Left,
+    Right,
+}
+
+/// The RANK, COUNT and MAXLEN options of LPOS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LposOptions {
+    // which match to start from, negative to search from the tail
+    pub rank: i64,
+    // how many matches to return, 0 for all of them
+    pub count: usize,
+    // how many elements to compare at most, 0 for the whole list
+    pub max_len: usize,
+}
+
+impl Default for LposOptions {
+    fn default() -> Self {
+        LposOptions {
+            rank: 1,
+            count: 1,
+            max_len: 0,
+        }
+    }
+}
+
+impl Backend {
+    /// LPUSH / RPUSH: push the values one after the other, creating the list unless
+    /// `only_existing` is set (LPUSHX / RPUSHX). Returns the length of the list after the push.
+    pub fn push(
+        &self,
+        key: String,
+        values: Vec<RespFrame>,
+        end: ListEnd,
+        only_existing: bool,
+    ) -> Result<usize, BackendError> {
+        let len = {
+            let _guard = self.locks.write([key.as_str()]);
+            self.write_value::<List, _>(&key, !only_existing, |list| {
+                for value in values {
+                    match end {
+                        ListEnd::Left => list.push_front(value),
+                        ListEnd::Right => list.push_back(value),
+                    }
+                }
+                Ok(list.len())
+            })?
+        };
+        if len.is_some() {
+            self.signal_ready(&key);
+        }
+        Ok(len.unwrap_or(0))
+    }
+
+    /// LPOP / RPOP: remove up to `count` elements from one end, None if the key does not exist.
+    pub fn pop(
+        &self,
+        key: &str,
+        end: ListEnd,
+        count: usize,
+    ) -> Result<Option<Vec<RespFrame>>, BackendError> {
+        let _guard = self.locks.write([key]);
+        self.write_value::<List, _>(key, false, |list| Ok(pop_n(list, end, count)))
+    }
+
+    pub fn llen(&self, key: &str) -> Result<usize, BackendError> {
+        let _guard = self.locks.read([key]);
+        Ok(self.read_list(key, |list| list.len())?.unwrap_or(0))
+    }
+
+    /// LRANGE: the elements between two inclusive indexes, negative ones counting from the tail.
+    pub fn lrange(&self, key: &str, start: i64, stop: i64) -> Result<Vec<RespFrame>, BackendError> {
+        let _guard = self.locks.read([key]);
+        let ret = self.read_list(key, |list| match clamp_range(start, stop, list.len()) {
+            Some((start, stop)) => list.range(start..=stop).cloned().collect(),
+            None => vec![],
+        })?;
+        Ok(ret.unwrap_or_default())
+    }
+
+    pub fn lindex(&self, key: &str, index: i64) -> Result<Option<RespFrame>, BackendError> {
+        let _guard = self.locks.read([key]);
+        let ret = self.read_list(key, |list| {
+            normalize_index(index, list.len()).and_then(|i| list.get(i).cloned())
+        })?;
+        Ok(ret.flatten())
+    }
+
+    pub fn lset(&self, key: &str, index: i64, value: RespFrame) -> Result<(), BackendError> {
+        let _guard = self.locks.write([key]);
+        let ret = self.write_value::<List, _>(key, false, |list| {
+            let i = normalize_index(index, list.len()).ok_or(BackendError::IndexOutOfRange)?;
+            list[i] = value;
+            Ok(())
+        })?;
+        ret.ok_or(BackendError::NoSuchKey)
+    }
+
+    /// LREM: remove the first `count` occurrences of the value, from the tail if `count` is
+    /// negative and all of them if it is 0. Returns how many were removed.
+    pub fn lrem(&self, key: &str, count: i64, value: &RespFrame) -> Result<usize, BackendError> {
+        let _guard = self.locks.write([key]);
+        let ret = self.write_value::<List, _>(key, false, |list| {
+            let limit = if count == 0 {
+                usize::MAX
+            } else {
+                count.unsigned_abs() as usize
+            };
+            let mut removed = 0;
+            if count >= 0 {
+                list.retain(|v| {
+                    let remove = removed < limit && v == value;
+                    removed += remove as usize;
+                    !remove
+                });
+            } else {
+                let mut i = list.len();
+                while i > 0 && removed < limit {
+                    i -= 1;
+                    if &list[i] == value {
+                        list.remove(i);
+                        removed += 1;
+                    }
+                }
+            }
+            Ok(removed)
+        })?;
+        Ok(ret.unwrap_or(0))
+    }
+
+    /// LTRIM: keep only the elements between two inclusive indexes.
+    pub fn ltrim(&self, key: &str, start: i64, stop: i64) -> Result<(), BackendError> {
+        let _guard = self.locks.write([key]);
+        self.write_value::<List, _>(key, false, |list| {
+            match clamp_range(start, stop, list.len()) {
+                Some((start, stop)) => {
+                    list.truncate(stop + 1);
+                    list.drain(..start);
+                }
+                None => list.clear(),
+            }
+            Ok(())
+        })?;
+        Ok(())
+    }
+
+    /// LINSERT: insert the value next to the first occurrence of the pivot. Returns the new
+    /// length, -1 if the pivot was not found and 0 if the key does not exist.
+    pub fn linsert(
+        &self,
+        key: &str,
+        before: bool,
+        pivot: &RespFrame,
+        value: RespFrame,
+    ) -> Result<i64, BackendError> {
+        let _guard = self.locks.write([key]);
+        let ret = self.write_value::<List, _>(key, false, |list| {
+            let Some(i) = list.iter().position(|v| v == pivot) else {
+                return Ok(-1);
+            };
+            list.insert(if before { i } else { i + 1 }, value);
+            Ok(list.len() as i64)
+        })?;
+        Ok(ret.unwrap_or(0))
+    }
+
+    /// LPOS: the indexes of the matching elements, see `LposOptions`.
+    pub fn lpos(
+        &self,
+        key: &str,
+        element: &RespFrame,
+        opts: LposOptions,
+    ) -> Result<Vec<usize>, BackendError> {
+        let _guard = self.locks.read([key]);
+        let ret = self.read_list(key, |list| {
+            let max_len = if opts.max_len == 0 {
+                list.len()
+            } else {
+                opts.max_len
+            };
+            let count = if opts.count == 0 {
+                usize::MAX
+            } else {
+                opts.count
+            };
+            let skip = (opts.rank.unsigned_abs() - 1) as usize;
+            let indexes: Box<dyn Iterator<Item = usize>> = if opts.rank > 0 {
+                Box::new(0..list.len())
+            } else {
+                Box::new((0..list.len()).rev())
+            };
+            indexes
+                .take(max_len)
+                .filter(|&i| &list[i] == element)
+                .skip(skip)
+                .take(count)
+                .collect()
+        })?;
+        Ok(ret.unwrap_or_default())
+    }
+
+    /// LMOVE: pop an element from one end of `src` and push it to one end of `dst`, atomically.
+    /// None if `src` does not exist.
+    pub fn lmove(
+        &self,
+        src: &str,
+        dst: &str,
+        from: ListEnd,
+        to: ListEnd,
+    ) -> Result<Option<RespFrame>, BackendError> {
+        let value = {
+            let _guard = self.locks.write([src, dst]);
+            // check the destination type first, nothing is popped when the push would fail
+            self.read_list(dst, |_| ())?;
+            let Some(value) = self
+                .write_value::<List, _>(src, false, |list| Ok(pop_n(list, from, 1)))?
+                .and_then(|mut popped| popped.pop())
+            else {
+                return Ok(None);
+            };
+            self.write_value::<List, _>(dst, true, |list| {
+                match to {
+                    ListEnd::Left => list.push_front(value.clone()),
+                    ListEnd::Right => list.push_back(value.clone()),
+                }
+                Ok(())
+            })?;
+            value
+        };
+        self.signal_ready(dst);
+        Ok(Some(value))
+    }
+
+    // run `f` over the list stored at the key, None if the key does not exist
+    fn read_list<T>(
+        &self,
+        key: &str,
+        f: impl FnOnce(&List) -> T,
+    ) -> Result<Option<T>, BackendError> {
+        match self.lookup(key).as_deref() {
+            Some(Entry {
+                value: RedisValue::List(list),
+                ..
+            }) => Ok(Some(f(list))),
+            Some(_) => Err(BackendError::WrongType),
+            None => Ok(None),
+        }
+    }
+}
+
+fn pop_n(list: &mut List, end: ListEnd, count: usize) -> Vec<RespFrame> {
+    let count = count.min(list.len());
+    match end {
+        ListEnd::Left => list.drain(..count).collect(),
+        ListEnd::Right => list.drain(list.len() - count..).rev().collect(),
+    }
+}
+
+// a list index to a position, negative indexes count from the tail
+fn normalize_index(index: i64, len: usize) -> Option<usize> {
+    let i = if index < 0 { len as i64 + index } else { index };
+    (0..len as i64).contains(&i).then_some(i as usize)
+}
+
+// an inclusive LRANGE / LTRIM range clamped to the list, None if it selects nothing
+pub(super) fn clamp_range(start: i64, stop: i64, len: usize) -> Option<(usize, usize)> {
+    let len = len as i64;
+    let start = if start < 0 {
+        (len + start).max(0)
+    } else {
+        start
+    };
+    let stop = if stop < 0 {
+        len + stop
+    } else {
+        stop.min(len - 1)
+    };
+    (start <= stop && start < len).then_some((start as usize, stop as usize))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+
+    fn values(values: &[&str]) -> Vec<RespFrame> {
+        values.iter().map(|v| BulkString::from(*v).into()).collect()
+    }
+
+    fn list(backend: &Backend, key: &str) -> Vec<RespFrame> {
+        backend.lrange(key, 0, -1).unwrap()
+    }
+
+    #[test]
+    fn test_push_pop() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        assert_eq!(
+            backend.push("l".to_string(), values(&["a"]), ListEnd::Left, true)?,
+            0
+        );
+        assert_eq!(
+            backend.push("l".to_string(), values(&["b", "a"]), ListEnd::Left, false)?,
+            2
+        );
+        assert_eq!(
+            backend.push("l".to_string(), values(&["c", "d"]), ListEnd::Right, true)?,
+            4
+        );
+        assert_eq!(list(&backend, "l"), values(&["a", "b", "c", "d"]));
+
+        assert_eq!(
+            backend.pop("l", ListEnd::Right, 2)?,
+            Some(values(&["d", "c"]))
+        );
+        assert_eq!(
+            backend.pop("l", ListEnd::Left, 10)?,
+            Some(values(&["a", "b"]))
+        );
+        assert_eq!(backend.pop("l", ListEnd::Left, 1)?, None);
+        assert!(backend.db.is_empty());
+
+        backend.set("s".to_string(), b"v".into());
+        assert_eq!(
+            backend.push("s".to_string(), values(&["a"]), ListEnd::Left, false),
+            Err(BackendError::WrongType)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_ranges_and_indexes() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        backend.push(
+            "l".to_string(),
+            values(&["a", "b", "c", "d"]),
+            ListEnd::Right,
+            false,
+        )?;
+        assert_eq!(backend.lrange("l", 1, 2)?, values(&["b", "c"]));
+        assert_eq!(backend.lrange("l", -2, 100)?, values(&["c", "d"]));
+        assert_eq!(backend.lrange("l", -100, 0)?, values(&["a"]));
+        assert_eq!(backend.lrange("l", 3, 1)?, values(&[]));
+        assert_eq!(backend.lrange("l", 5, 10)?, values(&[]));
+        assert_eq!(backend.lindex("l", -1)?, Some(b"d".into()));
+        assert_eq!(backend.lindex("l", 4)?, None);
+
+        backend.lset("l", -2, b"x".into())?;
+        assert_eq!(backend.lindex("l", 2)?, Some(b"x".into()));
+        assert_eq!(
+            backend.lset("l", 4, b"x".into()),
+            Err(BackendError::IndexOutOfRange)
+        );
+        assert_eq!(
+            backend.lset("missing", 0, b"x".into()),
+            Err(BackendError::NoSuchKey)
+        );
+
+        backend.ltrim("l", 1, -2)?;
+        assert_eq!(list(&backend, "l"), values(&["b", "x"]));
+        backend.ltrim("l", 5, 10)?;
+        assert!(backend.db.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_lrem_linsert() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        let items = values(&["a", "b", "a", "c", "a"]);
+        backend.push("l".to_string(), items, ListEnd::Right, false)?;
+        assert_eq!(backend.lrem("l", -2, &b"a".into())?, 2);
+        assert_eq!(list(&backend, "l"), values(&["a", "b", "c"]));
+        assert_eq!(backend.lrem("l", 0, &b"z".into())?, 0);
+
+        assert_eq!(backend.linsert("l", true, &b"c".into(), b"x".into())?, 4);
+        assert_eq!(backend.linsert("l", false, &b"c".into(), b"y".into())?, 5);
+        assert_eq!(backend.linsert("l", false, &b"z".into(), b"y".into())?, -1);
+        assert_eq!(
+            backend.linsert("missing", false, &b"z".into(), b"y".into())?,
+            0
+        );
+        assert_eq!(list(&backend, "l"), values(&["a", "b", "x", "c", "y"]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_lpos() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        let items = values(&["a", "b", "c", "1", "2", "3", "c", "c"]);
+        backend.push("l".to_string(), items, ListEnd::Right, false)?;
+        let c = b"c".into();
+        let opts = LposOptions::default();
+        assert_eq!(backend.lpos("l", &c, opts)?, vec![2]);
+        let opts = LposOptions { rank: -1, ..opts };
+        assert_eq!(backend.lpos("l", &c, opts)?, vec![7]);
+        let opts = LposOptions {
+            rank: 2,
+            count: 0,
+            ..opts
+        };
+        assert_eq!(backend.lpos("l", &c, opts)?, vec![6, 7]);
+        let opts = LposOptions {
+            rank: 1,
+            count: 0,
+            max_len: 7,
+        };
+        assert_eq!(backend.lpos("l", &c, opts)?, vec![2, 6]);
+        assert_eq!(backend.lpos("missing", &c, opts)?, vec![]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_lmove() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        backend.push(
+            "src".to_string(),
+            values(&["a", "b"]),
+            ListEnd::Right,
+            false,
+        )?;
+        assert_eq!(
+            backend.lmove("src", "dst", ListEnd::Right, ListEnd::Left)?,
+            Some(b"b".into())
+        );
+        assert_eq!(
+            backend.lmove("src", "src", ListEnd::Left, ListEnd::Right)?,
+            Some(b"a".into())
+        );
+        assert_eq!(
+            backend.lmove("src", "dst", ListEnd::Left, ListEnd::Left)?,
+            Some(b"a".into())
+        );
+        assert_eq!(list(&backend, "dst"), values(&["a", "b"]));
+        assert_eq!(
+            backend.lmove("src", "dst", ListEnd::Left, ListEnd::Left)?,
+            None
+        );
+
+        backend.set("s".to_string(), b"v".into());
+        assert_eq!(
+            backend.lmove("dst", "s", ListEnd::Left, ListEnd::Left),
+            Err(BackendError::WrongType)
+        );
+        assert_eq!(backend.llen("dst")?, 2);
+        Ok(())
+    }
+}
@@ -0,0 +1,103 @@
+use std::collections::hash_map::RandomState;
+use std::collections::BTreeMap;
+use std::hash::BuildHasher;
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+const KEY_LOCK_STRIPES: usize = 1024;
+
+/// Striped read/write locks over key names.
+///
+/// The `DashMap` shards only make single operations atomic. Every public `Backend` operation
+/// holds the stripes of the keys it touches, so an operation on several keys (MSET, RENAME,
+/// SINTERSTORE...) is seen by other clients either entirely or not at all. Stripes are always
+/// acquired in index order, which keeps concurrent multi-key operations deadlock free.
+#[derive(Debug)]
+pub(crate) struct KeyLocks {
+    stripes: Vec<RwLock<()>>,
+    hasher: RandomState,
+}
+
+#[must_use]
+pub(crate) struct KeyGuard<'a> {
+    _guards: Vec<StripeGuard<'a>>,
+}
+
+// the guards are only held for their Drop
+#[allow(dead_code)]
+enum StripeGuard<'a> {
+    Read(RwLockReadGuard<'a, ()>),
+    Write(RwLockWriteGuard<'a, ()>),
+}
+
+impl Default for KeyLocks {
+    fn default() -> Self {
+        Self {
+            stripes: (0..KEY_LOCK_STRIPES).map(|_| RwLock::new(())).collect(),
+            hasher: RandomState::new(),
+        }
+    }
+}
+
+impl KeyLocks {
+    pub(crate) fn read<'a, 'k>(&'a self, keys: impl IntoIterator<Item = &'k str>) -> KeyGuard<'a> {
+        self.lock(keys, [])
+    }
+
+    pub(crate) fn write<'a, 'k>(&'a self, keys: impl IntoIterator<Item = &'k str>) -> KeyGuard<'a> {
+        self.lock([], keys)
+    }
+
+    /// Lock some keys for reading and others for writing, a key in both is locked for writing.
+    pub(crate) fn lock<'a, 'k>(
+        &'a self,
+        read: impl IntoIterator<Item = &'k str>,
+        write: impl IntoIterator<Item = &'k str>,
+    ) -> KeyGuard<'a> {
+        let mut stripes = BTreeMap::new();
+        for key in read {
+            stripes.entry(self.stripe(key)).or_insert(false);
+        }
+        for key in write {
+            stripes.insert(self.stripe(key), true);
+        }
+        let guards = stripes
+            .into_iter()
+            .map(|(i, write)| {
+                let lock = &self.stripes[i];
+                if write {
+                    StripeGuard::Write(lock.write().unwrap_or_else(|e| e.into_inner()))
+                } else {
+                    StripeGuard::Read(lock.read().unwrap_or_else(|e| e.into_inner()))
+                }
+            })
+            .collect();
+        KeyGuard { _guards: guards }
+    }
+
+    fn stripe(&self, key: &str) -> usize {
+        self.hasher.hash_one(key) as usize % self.stripes.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_key_read_and_write_locks_once() {
+        let locks = KeyLocks::default();
+        let guard = locks.lock(["a", "b", "a"], ["a"]);
+        assert!(guard._guards.len() <= 2);
+        assert!(locks.stripes[locks.stripe("a")].try_read().is_err());
+        drop(guard);
+        assert!(locks.stripes[locks.stripe("a")].try_write().is_ok());
+    }
+
+    #[test]
+    fn test_readers_share_a_stripe() {
+        let locks = KeyLocks::default();
+        let _g1 = locks.read(["a"]);
+        let _g2 = locks.read(["a"]);
+        assert!(locks.stripes[locks.stripe("a")].try_write().is_err());
+    }
+}
@@ -1,17 +1,120 @@
-use std::sync::Arc;
+mod blocking;
+mod clock;
+mod expire;
+mod glob;
+mod hash;
+mod keys;
+mod list;
+mod lock;
+mod pubsub;
+mod ranked;
+mod scan;
+mod set;
+mod stream;
+mod string;
+mod value;
+mod zset;
 
+use std::sync::atomic::AtomicUsize;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use dashmap::mapref::entry::Entry as MapEntry;
+use dashmap::mapref::one::Ref;
 use dashmap::DashMap;
 use std::ops::Deref;
+use thiserror::Error;
+
+use crate::{RespFrame, SimpleError};
 
-use crate::RespFrame;
+use blocking::BlockedClients;
+pub use blocking::BlockingOp;
+pub use clock::{Clock, ManualClock, SystemClock};
+pub use expire::ExpireCondition;
+use expire::VolatileKeys;
+pub use glob::glob_match;
+pub use list::{ListEnd, LposOptions};
+use lock::KeyLocks;
+use pubsub::Broker;
+pub use pubsub::{pubsub_message, subscriber, Inbox, Subscriber, SUBSCRIBER_OUTPUT_LIMIT};
+use scan::ScanIndex;
+pub use scan::ScanPage;
+pub use set::SetOp;
+pub use stream::{
+    AutoClaim, ClaimOptions, ConsumerInfo, GroupInfo, PendingInfo, PendingRange, PendingSummary,
+    Stream, StreamFields, StreamId, StreamInfo, StreamReads, StreamTrim, TrimStrategy, XAddId,
+};
+pub use string::{SetCondition, SetOptions};
+pub(crate) use value::Entry;
+pub use value::{HashValue, RedisValue, SortedSet};
+pub use zset::{Aggregate, ZAddOptions, ZLimit, ZRangeBy};
 
 #[derive(Debug, Clone)]
 pub struct Backend(Arc<BackendInner>);
 
 #[derive(Debug)]
 pub struct BackendInner {
-    pub(crate) map: DashMap<String, RespFrame>,
-    pub(crate) hmap: DashMap<String, DashMap<String, RespFrame>>,
+    pub(crate) db: DashMap<String, Entry>,
+    pub(crate) locks: KeyLocks,
+    // every key ordered by its scan hash, SCAN reads the next keys instead of walking them all
+    keyspace: Mutex<ScanIndex>,
+    // keys with a deadline, sampled by the active expire cycle
+    volatile: Mutex<VolatileKeys>,
+    // hashes with fields set to expire, sampled by the same cycle
+    volatile_hashes: Mutex<VolatileKeys>,
+    // clients waiting on keys (BLPOP...), and how many so writes can skip the registry
+    blocked: Mutex<BlockedClients>,
+    waiting: AtomicUsize,
+    // the pub/sub subscriptions of the clients
+    broker: Mutex<Broker>,
+    clock: Arc<dyn Clock>,
+}
+
+// like CommandError, the Display output is what the client receives
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum BackendError {
+    #[error("WRONGTYPE Operation against a key holding the wrong kind of value")]
+    WrongType,
+    #[error("ERR value is not an integer or out of range")]
+    NotInteger,
+    #[error("ERR value is not a valid float")]
+    NotFloat,
+    #[error("ERR increment or decrement would overflow")]
+    Overflow,
+    #[error("ERR increment would produce NaN or Infinity")]
+    NanOrInfinity,
+    #[error("ERR hash value is not an integer")]
+    HashNotInteger,
+    #[error("ERR hash value is not a float")]
+    HashNotFloat,
+    #[error("ERR index out of range")]
+    IndexOutOfRange,
+    #[error("ERR no such key")]
+    NoSuchKey,
+    #[error("ERR source and destination objects are the same")]
+    SameObject,
+    #[error("ERR resulting score is not a number (NaN)")]
+    ScoreNan,
+    #[error("ERR The ID specified in XADD must be greater than 0-0")]
+    StreamIdZero,
+    #[error("ERR The ID specified in XADD is equal or smaller than the target stream top item")]
+    StreamIdTooSmall,
+    #[error("ERR The stream has exhausted the last possible ID, unable to add more items")]
+    StreamExhausted,
+    #[error("BUSYGROUP Consumer Group name already exists")]
+    BusyGroup,
+    // the key, then the group
+    #[error("NOGROUP No such key '{0}' or consumer group '{1}'")]
+    NoGroup(String, String),
+    #[error("NOGROUP No such consumer group '{1}' for key name '{0}'")]
+    NoSuchGroup(String, String),
+    #[error("ERR The XGROUP subcommand requires the key to exist. Note that for CREATE you may want to use the MKSTREAM option to create an empty stream automatically.")]
+    StreamKeyMissing,
+}
+
+impl From<BackendError> for RespFrame {
+    fn from(e: BackendError) -> Self {
+        SimpleError::new(e.to_string()).into()
+    }
 }
 
 impl Deref for Backend {
@@ -31,8 +134,15 @@ impl Default for Backend {
 impl Default for BackendInner {
     fn default() -> Self {
         Self {
-            map: DashMap::new(),
-            hmap: DashMap::new(),
+            db: DashMap::new(),
+            locks: KeyLocks::default(),
+            keyspace: Mutex::new(ScanIndex::default()),
+            volatile: Mutex::new(VolatileKeys::default()),
+            volatile_hashes: Mutex::new(VolatileKeys::default()),
+            blocked: Mutex::new(BlockedClients::default()),
+            waiting: AtomicUsize::new(0),
+            broker: Mutex::new(Broker::default()),
+            clock: Arc::new(SystemClock),
         }
     }
 }
@@ -42,27 +152,136 @@ impl Backend {
         Self::default()
     }
 
-    pub fn get(&self, key: &str) -> Option<RespFrame> {
-        self.0.map.get(key).map(|v| v.value().clone())
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        Self(Arc::new(BackendInner {
+            clock,
+            ..Default::default()
+        }))
+    }
+
+    pub fn now_ms(&self) -> u64 {
+        self.clock.now_ms()
     }
 
+    pub fn get(&self, key: &str) -> Result<Option<RespFrame>, BackendError> {
+        let _guard = self.locks.read([key]);
+        match self.lookup(key).as_deref() {
+            Some(Entry {
+                value: RedisValue::String(v),
+                ..
+            }) => Ok(Some(v.clone())),
+            Some(_) => Err(BackendError::WrongType),
+            None => Ok(None),
+        }
+    }
+
+    /// SET replaces whatever is stored under the key, regardless of its type, and drops its TTL.
     pub fn set(&self, key: String, value: RespFrame) {
-        self.0.map.insert(key, value);
+        self.set_with_expire(key, value, None);
+    }
+
+    pub fn set_with_expire(&self, key: String, value: RespFrame, expire_at: Option<u64>) {
+        let _guard = self.locks.write([key.as_str()]);
+        self.insert_entry(key, Entry::new(value, expire_at));
+    }
+
+    /// The type of the value stored under the key, "none" if it does not exist.
+    pub fn key_type(&self, key: &str) -> &'static str {
+        let _guard = self.locks.read([key]);
+        self.lookup(key)
+            .map(|e| e.value.type_name())
+            .unwrap_or("none")
     }
 
-    pub fn hget(&self, key: &str, field: &str) -> Option<RespFrame> {
-        self.0
-            .hmap
-            .get(key)
-            .and_then(|m| m.get(field).map(|v| v.value().clone()))
+    // read access to a live key, an expired one is removed first
+    pub(crate) fn lookup(&self, key: &str) -> Option<Ref<'_, String, Entry>> {
+        self.expire_if_needed(key);
+        self.db.get(key)
     }
 
-    pub fn hset(&self, key: String, field: String, value: RespFrame) {
-        let entry = self.0.hmap.entry(key).or_default();
-        entry.insert(field, value);
+    // write access to a live key, an expired one is removed first
+    pub(crate) fn lookup_mut(&self, key: String) -> MapEntry<'_, String, Entry> {
+        self.expire_if_needed(&key);
+        self.db.entry(key)
+    }
+
+    // replace whatever is stored under the key, keeping the volatile index in sync
+    pub(crate) fn insert_entry(&self, key: String, entry: Entry) {
+        let slot = self.db.entry(key);
+        let (old, old_fields) = match &slot {
+            MapEntry::Occupied(e) => (e.get().expire_at, e.get().value.has_volatile_fields()),
+            MapEntry::Vacant(e) => {
+                self.track_key(e.key(), true);
+                (None, false)
+            }
+        };
+        self.track_expire(slot.key(), old, entry.expire_at);
+        self.track_field_expire(slot.key(), old_fields, entry.value.has_volatile_fields());
+        slot.insert(entry);
+    }
+
+    fn keyspace(&self) -> MutexGuard<'_, ScanIndex> {
+        self.keyspace.lock().expect("keyspace index lock poisoned")
+    }
+
+    fn volatile(&self) -> MutexGuard<'_, VolatileKeys> {
+        self.volatile.lock().expect("volatile keys lock poisoned")
+    }
+
+    fn blocked(&self) -> MutexGuard<'_, BlockedClients> {
+        self.blocked.lock().expect("blocked clients lock poisoned")
+    }
+
+    fn broker(&self) -> MutexGuard<'_, Broker> {
+        self.broker.lock().expect("pub/sub broker lock poisoned")
+    }
+
+    fn volatile_hashes(&self) -> MutexGuard<'_, VolatileKeys> {
+        self.volatile_hashes
+            .lock()
+            .expect("volatile hashes lock poisoned")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_string_and_hash_share_keyspace() {
+        let backend = Backend::new();
+        backend.set("key".to_string(), b"value".into());
+        assert_eq!(
+            backend.hset(
+                "key".to_string(),
+                vec![("field".to_string(), b"value".into())]
+            ),
+            Err(BackendError::WrongType)
+        );
+        assert_eq!(backend.hget("key", "field"), Err(BackendError::WrongType));
+        assert_eq!(backend.key_type("key"), "string");
+
+        backend
+            .hset(
+                "hash".to_string(),
+                vec![("field".to_string(), b"value".into())],
+            )
+            .unwrap();
+        assert_eq!(backend.get("hash"), Err(BackendError::WrongType));
+        assert_eq!(backend.key_type("hash"), "hash");
+        assert_eq!(backend.key_type("missing"), "none");
     }
 
-    pub fn hgetall(&self, key: &str) -> Option<DashMap<String, RespFrame>> {
-        self.hmap.get(key).map(|v| v.clone())
+    #[test]
+    fn test_set_overwrites_any_type() {
+        let backend = Backend::new();
+        backend
+            .hset(
+                "key".to_string(),
+                vec![("field".to_string(), b"value".into())],
+            )
+            .unwrap();
+        backend.set("key".to_string(), b"value".into());
+        assert_eq!(backend.get("key"), Ok(Some(b"value".into())));
     }
 }
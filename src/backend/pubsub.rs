@@ -0,0 +1,327 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TryRecvError;
+
+use super::{glob_match, Backend};
+use crate::{BulkString, RespFrame, RespPush};
+
+/// How many bytes of messages may wait for a client before it is disconnected, the hard
+/// limit Redis gives the output buffer of pub/sub clients.
+pub const SUBSCRIBER_OUTPUT_LIMIT: usize = 32 * 1024 * 1024;
+
+/// Where the messages for one client are sent, its connection writes them out.
+#[derive(Debug, Clone)]
+pub struct Subscriber {
+    sender: mpsc::UnboundedSender<RespFrame>,
+    queue: Arc<OutputQueue>,
+}
+
+/// The receiving end of a `Subscriber`, read by the connection of the client.
+#[derive(Debug)]
+pub struct Inbox {
+    receiver: mpsc::UnboundedReceiver<RespFrame>,
+    queue: Arc<OutputQueue>,
+}
+
+// the size of the messages waiting for a client, and whether they ever went over the limit
+#[derive(Debug, Default)]
+struct OutputQueue {
+    bytes: AtomicUsize,
+    overflowed: AtomicBool,
+}
+
+/// A new client's `Subscriber` and the `Inbox` its messages arrive in.
+pub fn subscriber() -> (Subscriber, Inbox) {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let queue = Arc::new(OutputQueue::default());
+    let subscriber = Subscriber {
+        sender,
+        queue: queue.clone(),
+    };
+    (subscriber, Inbox { receiver, queue })
+}
+
+impl Subscriber {
+    /// Queue a message for the client. Returns false if the connection is gone, or if the
+    /// client let too many messages wait: it then gets none anymore and is disconnected.
+    pub fn send(&self, message: RespFrame) -> bool {
+        if self.queue.overflowed.load(Ordering::Relaxed) {
+            return false;
+        }
+        let size = queued_size(&message);
+        let queued = self.queue.bytes.fetch_add(size, Ordering::Relaxed) + size;
+        if queued > SUBSCRIBER_OUTPUT_LIMIT {
+            self.queue.overflowed.store(true, Ordering::Relaxed);
+            return false;
+        }
+        self.sender.send(message).is_ok()
+    }
+}
+
+impl Inbox {
+    /// The next message, None once the client let too many wait and must be disconnected.
+    pub async fn recv(&mut self) -> Option<RespFrame> {
+        if self.queue.overflowed.load(Ordering::Relaxed) {
+            return None;
+        }
+        let message = self.receiver.recv().await?;
+        self.dequeued(&message);
+        Some(message)
+    }
+
+    pub fn try_recv(&mut self) -> Result<RespFrame, TryRecvError> {
+        if self.queue.overflowed.load(Ordering::Relaxed) {
+            return Err(TryRecvError::Disconnected);
+        }
+        let message = self.receiver.try_recv()?;
+        self.dequeued(&message);
+        Ok(message)
+    }
+
+    fn dequeued(&self, message: &RespFrame) {
+        self.queue
+            .bytes
+            .fetch_sub(queued_size(message), Ordering::Relaxed);
+    }
+}
+
+// about the memory a queued message holds: its strings plus some for each frame
+fn queued_size(frame: &RespFrame) -> usize {
+    const FRAME: usize = 16;
+    match frame {
+        RespFrame::Push(push) => FRAME + push.iter().map(queued_size).sum::<usize>(),
+        RespFrame::Array(array) => FRAME + array.iter().map(queued_size).sum::<usize>(),
+        RespFrame::BulkString(s) => FRAME + s.len(),
+        _ => FRAME,
+    }
+}
+
+/// The clients subscribed to each channel and each pattern, by client id.
+///
+/// A client whose connection is gone, or that is disconnected for letting too many messages
+/// wait, is dropped by the next message it can't be sent.
+#[derive(Debug, Default)]
+pub(crate) struct Broker {
+    channels: HashMap<String, HashMap<u64, Subscriber>>,
+    patterns: HashMap<String, HashMap<u64, Subscriber>>,
+}
+
+impl Backend {
+    pub fn subscribe(&self, channel: &str, client: u64, subscriber: &Subscriber) {
+        let mut broker = self.broker();
+        let subscribers = broker.channels.entry(channel.to_string()).or_default();
+        subscribers.insert(client, subscriber.clone());
+    }
+
+    pub fn psubscribe(&self, pattern: &str, client: u64, subscriber: &Subscriber) {
+        let mut broker = self.broker();
+        let subscribers = broker.patterns.entry(pattern.to_string()).or_default();
+        subscribers.insert(client, subscriber.clone());
+    }
+
+    pub fn unsubscribe(&self, channel: &str, client: u64) {
+        let mut broker = self.broker();
+        if let Some(subscribers) = broker.channels.get_mut(channel) {
+            subscribers.remove(&client);
+            if subscribers.is_empty() {
+                broker.channels.remove(channel);
+            }
+        }
+    }
+
+    pub fn punsubscribe(&self, pattern: &str, client: u64) {
+        let mut broker = self.broker();
+        if let Some(subscribers) = broker.patterns.get_mut(pattern) {
+            subscribers.remove(&client);
+            if subscribers.is_empty() {
+                broker.patterns.remove(pattern);
+            }
+        }
+    }
+
+    /// Send a message to the subscribers of the channel and of the patterns matching it. A
+    /// client subscribed to the channel and to matching patterns receives it once for each.
+    /// Returns the number of messages sent.
+    pub fn publish(&self, channel: &str, message: &RespFrame) -> usize {
+        let mut broker = self.broker();
+        let mut sent = 0;
+        if let Some(subscribers) = broker.channels.get_mut(channel) {
+            sent += deliver(subscribers, || {
+                pubsub_message(vec![
+                    BulkString::from("message").into(),
+                    BulkString::from(channel).into(),
+                    message.clone(),
+                ])
+            });
+            if subscribers.is_empty() {
+                broker.channels.remove(channel);
+            }
+        }
+        broker.patterns.retain(|pattern, subscribers| {
+            if glob_match(pattern.as_bytes(), channel.as_bytes()) {
+                sent += deliver(subscribers, || {
+                    pubsub_message(vec![
+                        BulkString::from("pmessage").into(),
+                        BulkString::from(pattern.as_str()).into(),
+                        BulkString::from(channel).into(),
+                        message.clone(),
+                    ])
+                });
+            }
+            !subscribers.is_empty()
+        });
+        sent
+    }
+
+    /// The channels with at least one subscriber, those matching the pattern if any, sorted.
+    pub fn pubsub_channels(&self, pattern: Option<&str>) -> Vec<String> {
+        let broker = self.broker();
+        let mut channels = broker
+            .channels
+            .keys()
+            .filter(|channel| {
+                pattern.is_none_or(|pattern| glob_match(pattern.as_bytes(), channel.as_bytes()))
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        channels.sort();
+        channels
+    }
+
+    /// The number of subscribers of each channel, not counting the pattern subscriptions.
+    pub fn pubsub_numsub(&self, channels: &[String]) -> Vec<usize> {
+        let broker = self.broker();
+        channels
+            .iter()
+            .map(|channel| broker.channels.get(channel).map_or(0, HashMap::len))
+            .collect()
+    }
+
+    /// The number of patterns at least one client is subscribed to.
+    pub fn pubsub_numpat(&self) -> usize {
+        self.broker().patterns.len()
+    }
+}
+
+// send the message to each subscriber, dropping the closed ones. Returns how many received it.
+fn deliver(subscribers: &mut HashMap<u64, Subscriber>, message: impl Fn() -> RespFrame) -> usize {
+    subscribers.retain(|_, subscriber| subscriber.send(message()));
+    subscribers.len()
+}
+
+/// The frame of a message or of a subscription change sent to a subscriber: a push, which
+/// RESP2 clients receive as an array.
+pub fn pubsub_message(parts: Vec<RespFrame>) -> RespFrame {
+    RespPush::new(parts).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(parts: &[&str]) -> RespFrame {
+        let parts = parts.iter().map(|s| BulkString::from(*s).into()).collect();
+        pubsub_message(parts)
+    }
+
+    #[test]
+    fn test_publish_to_channels_and_patterns() {
+        let backend = Backend::new();
+        let (tx1, mut rx1) = subscriber();
+        let (tx2, mut rx2) = subscriber();
+        backend.subscribe("news", 1, &tx1);
+        backend.psubscribe("n*", 1, &tx1);
+        backend.psubscribe("n*", 2, &tx2);
+        backend.psubscribe("sport.*", 2, &tx2);
+
+        let hi = BulkString::from("hi").into();
+        assert_eq!(backend.publish("news", &hi), 3);
+        assert_eq!(rx1.try_recv().unwrap(), message(&["message", "news", "hi"]));
+        assert_eq!(
+            rx1.try_recv().unwrap(),
+            message(&["pmessage", "n*", "news", "hi"])
+        );
+        assert!(rx1.try_recv().is_err());
+        assert_eq!(
+            rx2.try_recv().unwrap(),
+            message(&["pmessage", "n*", "news", "hi"])
+        );
+        assert!(rx2.try_recv().is_err());
+
+        assert_eq!(backend.publish("weather", &hi), 0);
+
+        backend.unsubscribe("news", 1);
+        backend.punsubscribe("n*", 1);
+        assert_eq!(backend.publish("news", &hi), 1);
+        assert!(rx1.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_closed_subscribers_are_dropped() {
+        let backend = Backend::new();
+        let (tx1, rx1) = subscriber();
+        let (tx2, _rx2) = subscriber();
+        backend.subscribe("news", 1, &tx1);
+        backend.subscribe("news", 2, &tx2);
+        backend.psubscribe("*", 1, &tx1);
+        drop(rx1);
+
+        let hi = BulkString::from("hi").into();
+        assert_eq!(backend.publish("news", &hi), 1);
+        assert_eq!(backend.pubsub_numsub(&["news".to_string()]), vec![1]);
+        assert_eq!(backend.pubsub_numpat(), 0);
+    }
+
+    #[test]
+    fn test_slow_subscribers_are_dropped() {
+        let backend = Backend::new();
+        let (tx, mut rx) = subscriber();
+        backend.subscribe("news", 1, &tx);
+        let big: RespFrame = BulkString::new(vec![0; SUBSCRIBER_OUTPUT_LIMIT / 4]).into();
+        for _ in 0..3 {
+            assert_eq!(backend.publish("news", &big), 1);
+        }
+        // reading makes room again
+        assert!(rx.try_recv().is_ok());
+        assert_eq!(backend.publish("news", &big), 1);
+        assert_eq!(backend.publish("news", &big), 0);
+        assert_eq!(backend.pubsub_numsub(&["news".to_string()]), vec![0]);
+        // what was queued is never written, the connection is closed instead
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_pubsub_introspection() {
+        let backend = Backend::new();
+        let (tx, _rx) = subscriber();
+        backend.subscribe("news.tech", 1, &tx);
+        backend.subscribe("news.art", 1, &tx);
+        backend.subscribe("weather", 2, &tx);
+        backend.subscribe("weather", 3, &tx);
+        backend.psubscribe("news.*", 1, &tx);
+        backend.psubscribe("news.*", 2, &tx);
+
+        assert_eq!(
+            backend.pubsub_channels(None),
+            vec!["news.art", "news.tech", "weather"]
+        );
+        assert_eq!(
+            backend.pubsub_channels(Some("news.*")),
+            vec!["news.art", "news.tech"]
+        );
+        let channels = [
+            "weather".to_string(),
+            "news.tech".to_string(),
+            "x".to_string(),
+        ];
+        assert_eq!(backend.pubsub_numsub(&channels), vec![2, 1, 0]);
+        assert_eq!(backend.pubsub_numpat(), 1);
+
+        backend.unsubscribe("weather", 2);
+        backend.unsubscribe("weather", 3);
+        assert_eq!(backend.pubsub_channels(Some("w*")), Vec::<String>::new());
+    }
+}
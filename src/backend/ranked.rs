@@ -0,0 +1,350 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+/// An ordered set that also knows the rank of its elements: a treap whose nodes count the
+/// elements under them. Finding the rank of an element or where a range of ranks starts is
+/// O(log n), and walking the range costs one step per element.
+#[derive(Clone)]
+pub(crate) struct RankedSet<T> {
+    root: Link<T>,
+}
+
+type Link<T> = Option<Box<Node<T>>>;
+
+#[derive(Clone)]
+struct Node<T> {
+    value: T,
+    // heap ordered, random so the tree stays balanced whatever the insertion order
+    priority: u64,
+    // the elements in this subtree, this one included
+    size: usize,
+    left: Link<T>,
+    right: Link<T>,
+}
+
+impl<T: Ord> RankedSet<T> {
+    pub(crate) fn len(&self) -> usize {
+        size(&self.root)
+    }
+
+    /// Returns false if the value was already there.
+    pub(crate) fn insert(&mut self, value: T) -> bool {
+        if self.contains(&value) {
+            return false;
+        }
+        let node = Box::new(Node {
+            value,
+            priority: rand::random(),
+            size: 1,
+            left: None,
+            right: None,
+        });
+        let (less, rest) = split(self.root.take(), &node.value);
+        self.root = merge(merge(less, Some(node)), rest);
+        true
+    }
+
+    pub(crate) fn remove(&mut self, value: &T) -> bool {
+        remove(&mut self.root, value)
+    }
+
+    pub(crate) fn contains(&self, value: &T) -> bool {
+        let mut link = &self.root;
+        while let Some(node) = link {
+            link = match value.cmp(&node.value) {
+                Ordering::Less => &node.left,
+                Ordering::Greater => &node.right,
+                Ordering::Equal => return true,
+            };
+        }
+        false
+    }
+
+    /// How many elements come before the value, whether it is in the set or not.
+    pub(crate) fn rank(&self, value: &T) -> usize {
+        self.partition_point(|v| v < value)
+    }
+
+    /// Like `slice::partition_point`: the number of elements of the leading run for which the
+    /// predicate holds, which must be every element it holds for.
+    pub(crate) fn partition_point(&self, pred: impl Fn(&T) -> bool) -> usize {
+        let mut rank = 0;
+        let mut link = &self.root;
+        while let Some(node) = link {
+            if pred(&node.value) {
+                rank += size(&node.left) + 1;
+                link = &node.right;
+            } else {
+                link = &node.left;
+            }
+        }
+        rank
+    }
+
+    pub(crate) fn pop_first(&mut self) -> Option<T> {
+        pop_first(&mut self.root)
+    }
+
+    pub(crate) fn pop_last(&mut self) -> Option<T> {
+        pop_last(&mut self.root)
+    }
+
+    pub(crate) fn iter(&self) -> Iter<'_, T> {
+        self.range(0, self.len())
+    }
+
+    /// The elements ranked from `start` up to but not including `end`.
+    pub(crate) fn range(&self, start: usize, end: usize) -> Iter<'_, T> {
+        let end = end.min(self.len());
+        let mut iter = Iter {
+            front: vec![],
+            back: vec![],
+            len: end.saturating_sub(start),
+        };
+        if iter.len > 0 {
+            iter.seek_front(&self.root, start);
+            iter.seek_back(&self.root, end - 1);
+        }
+        iter
+    }
+}
+
+impl<T> Default for RankedSet<T> {
+    fn default() -> Self {
+        RankedSet { root: None }
+    }
+}
+
+impl<T: Ord + fmt::Debug> fmt::Debug for RankedSet<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+// the shape of the tree depends on the priorities, only the elements matter
+impl<T: Ord> PartialEq for RankedSet<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+/// The elements of a rank range in order, from both ends.
+pub(crate) struct Iter<'a, T> {
+    // the nodes still to visit from each end: the next one on top, then its ancestors
+    front: Vec<&'a Node<T>>,
+    back: Vec<&'a Node<T>>,
+    len: usize,
+}
+
+impl<'a, T> Iter<'a, T> {
+    fn seek_front(&mut self, mut link: &'a Link<T>, mut rank: usize) {
+        while let Some(node) = link {
+            let left = size(&node.left);
+            if rank <= left {
+                self.front.push(node);
+                if rank == left {
+                    return;
+                }
+                link = &node.left;
+            } else {
+                rank -= left + 1;
+                link = &node.right;
+            }
+        }
+    }
+
+    fn seek_back(&mut self, mut link: &'a Link<T>, mut rank: usize) {
+        while let Some(node) = link {
+            let left = size(&node.left);
+            if rank >= left {
+                self.back.push(node);
+                if rank == left {
+                    return;
+                }
+                rank -= left + 1;
+                link = &node.right;
+            } else {
+                link = &node.left;
+            }
+        }
+    }
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        let node = self.front.pop()?;
+        let mut link = &node.right;
+        while let Some(next) = link {
+            self.front.push(next);
+            link = &next.left;
+        }
+        Some(&node.value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T> DoubleEndedIterator for Iter<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        let node = self.back.pop()?;
+        let mut link = &node.left;
+        while let Some(next) = link {
+            self.back.push(next);
+            link = &next.right;
+        }
+        Some(&node.value)
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+impl<T> Node<T> {
+    fn update(&mut self) {
+        self.size = size(&self.left) + size(&self.right) + 1;
+    }
+}
+
+fn size<T>(link: &Link<T>) -> usize {
+    link.as_ref().map_or(0, |node| node.size)
+}
+
+// the elements below the value, and the others
+fn split<T: Ord>(link: Link<T>, value: &T) -> (Link<T>, Link<T>) {
+    let Some(mut node) = link else {
+        return (None, None);
+    };
+    if node.value < *value {
+        let (less, rest) = split(node.right.take(), value);
+        node.right = less;
+        node.update();
+        (Some(node), rest)
+    } else {
+        let (less, rest) = split(node.left.take(), value);
+        node.left = rest;
+        node.update();
+        (less, Some(node))
+    }
+}
+
+// join two trees, every element of `a` being below those of `b`
+fn merge<T>(a: Link<T>, b: Link<T>) -> Link<T> {
+    match (a, b) {
+        (None, b) => b,
+        (a, None) => a,
+        (Some(mut a), Some(mut b)) => {
+            if a.priority > b.priority {
+                a.right = merge(a.right.take(), Some(b));
+                a.update();
+                Some(a)
+            } else {
+                b.left = merge(Some(a), b.left.take());
+                b.update();
+                Some(b)
+            }
+        }
+    }
+}
+
+fn remove<T: Ord>(link: &mut Link<T>, value: &T) -> bool {
+    let Some(node) = link else {
+        return false;
+    };
+    let removed = match value.cmp(&node.value) {
+        Ordering::Less => remove(&mut node.left, value),
+        Ordering::Greater => remove(&mut node.right, value),
+        Ordering::Equal => {
+            let node = link.take().expect("matched above");
+            *link = merge(node.left, node.right);
+            return true;
+        }
+    };
+    if removed {
+        node.size -= 1;
+    }
+    removed
+}
+
+fn pop_first<T>(link: &mut Link<T>) -> Option<T> {
+    let node = link.as_mut()?;
+    if node.left.is_some() {
+        let value = pop_first(&mut node.left);
+        node.size -= 1;
+        return value;
+    }
+    let node = link.take().expect("checked above");
+    *link = node.right;
+    Some(node.value)
+}
+
+fn pop_last<T>(link: &mut Link<T>) -> Option<T> {
+    let node = link.as_mut()?;
+    if node.right.is_some() {
+        let value = pop_last(&mut node.right);
+        node.size -= 1;
+        return value;
+    }
+    let node = link.take().expect("checked above");
+    *link = node.left;
+    Some(node.value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn test_ranked_set_matches_btree_set() {
+        let mut set = RankedSet::default();
+        let mut expected = BTreeSet::new();
+        // a permutation of 0..500, with every third value removed again
+        for i in 0..500u32 {
+            let value = i * 7 % 500;
+            assert!(set.insert(value));
+            expected.insert(value);
+            if value % 3 == 0 {
+                assert!(set.remove(&value));
+                expected.remove(&value);
+            }
+        }
+        assert!(!set.insert(1));
+        assert!(!set.remove(&3));
+        assert_eq!(set.len(), expected.len());
+        assert!(set.iter().eq(expected.iter()));
+        assert!(set.iter().rev().eq(expected.iter().rev()));
+
+        let all = expected.iter().copied().collect::<Vec<_>>();
+        assert_eq!(set.rank(&100), all.partition_point(|v| *v < 100));
+        assert_eq!(set.rank(&1000), all.len());
+        assert!(set.range(10, 20).eq(all[10..20].iter()));
+        assert!(set.range(10, 20).rev().eq(all[10..20].iter().rev()));
+        assert_eq!(set.range(20, 10).count(), 0);
+        assert!(set
+            .range(all.len() - 2, usize::MAX)
+            .eq(all[all.len() - 2..].iter()));
+
+        // both ends of the same iterator meet without crossing
+        let mut range = set.range(0, 3);
+        assert_eq!(range.next(), Some(&all[0]));
+        assert_eq!(range.next_back(), Some(&all[2]));
+        assert_eq!(range.next(), Some(&all[1]));
+        assert_eq!(range.next_back(), None);
+
+        assert_eq!(set.pop_first(), Some(all[0]));
+        assert_eq!(set.pop_last(), all.last().copied());
+        assert_eq!(set.len(), all.len() - 2);
+        assert_eq!(set.rank(&all[1]), 0);
+    }
+}
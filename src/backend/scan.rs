@@ -0,0 +1,285 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeSet;
+use std::hash::{Hash, Hasher};
+
+use rand::Rng;
+
+use super::Backend;
+
+/// A page of a cursor-based iteration: the cursor to continue from (0 once done) and the items.
+pub type ScanPage<T> = (u64, Vec<T>);
+
+/// Walk items in the order of a fixed hash of their name. The cursor is the lowest hash not
+/// returned yet, so an item present for the whole iteration is returned exactly once, whatever
+/// is inserted or removed between two calls. Each call is O(n log count) over the collection.
+pub(crate) fn scan_by_hash<T>(
+    items: impl IntoIterator<Item = (u64, T)>,
+    cursor: u64,
+    count: usize,
+) -> ScanPage<T> {
+    let count = count.max(1);
+    // COUNT comes from the client, the page grows past this if it must
+    let mut page = Vec::with_capacity(count.min(1024) * 2);
+    let mut more = false;
+    for (hash, item) in items {
+        if hash < cursor {
+            continue;
+        }
+        page.push((hash, item));
+        if page.len() >= count.saturating_mul(2) {
+            more |= keep_lowest(&mut page, count);
+        }
+    }
+    more |= keep_lowest(&mut page, count);
+    let next = match page.last() {
+        Some(&(last, _)) if more => last + 1,
+        _ => 0,
+    };
+    (next, page.into_iter().map(|(_, item)| item).collect())
+}
+
+/// Names ordered by their scan hash, so a scan step reads the next names instead of walking
+/// the whole collection: O(log n + count). The cursor is the same as `scan_by_hash`'s.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct ScanIndex(BTreeSet<(u64, String)>);
+
+impl ScanIndex {
+    pub(crate) fn insert(&mut self, name: &str) {
+        self.0.insert((scan_hash(name), name.to_string()));
+    }
+
+    pub(crate) fn remove(&mut self, name: &str) {
+        self.0.remove(&(scan_hash(name), name.to_string()));
+    }
+
+    /// The next `count` names from the cursor, plus any sharing the last one's hash since the
+    /// cursor can not point in between them.
+    pub(crate) fn page(&self, cursor: u64, count: usize) -> ScanPage<&str> {
+        let count = count.max(1);
+        let mut page: Vec<&str> = vec![];
+        let mut last = None;
+        for (hash, name) in self.0.range((cursor, String::new())..) {
+            if page.len() >= count && last != Some(*hash) {
+                // the first hash not returned, never 0 since it is above one that was
+                return (*hash, page);
+            }
+            page.push(name);
+            last = Some(*hash);
+        }
+        (0, page)
+    }
+
+    /// The first name at or after a random hash, wrapping around: O(log n). Not exactly
+    /// uniform, a name after a wider gap in the hashes is picked more often.
+    pub(crate) fn random(&self, rng: &mut impl Rng) -> Option<&str> {
+        let hash = rng.gen::<u64>();
+        let (_, name) = self
+            .0
+            .range((hash, String::new())..)
+            .next()
+            .or_else(|| self.0.first())?;
+        Some(name)
+    }
+}
+
+/// The stable hash SCAN orders names by.
+pub(crate) fn scan_hash(name: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    name.hash(&mut hasher);
+    hasher.finish()
+}
+
+// sort and keep the `count` lowest hashes, plus any item sharing the last kept hash since the
+// cursor can not point in between them. Returns whether something was dropped.
+fn keep_lowest<T>(page: &mut Vec<(u64, T)>, count: usize) -> bool {
+    page.sort_unstable_by_key(|(hash, _)| *hash);
+    if page.len() <= count {
+        return false;
+    }
+    let cut = page[count - 1].0;
+    let end = page.partition_point(|(hash, _)| *hash <= cut);
+    let dropped = end < page.len();
+    page.truncate(end);
+    dropped
+}
+
+impl Backend {
+    /// One SCAN step over the live keys, with their type so callers can filter on it.
+    pub fn scan(&self, cursor: u64, count: usize) -> ScanPage<(String, &'static str)> {
+        let (next, keys) = {
+            let keyspace = self.keyspace();
+            let (next, keys) = keyspace.page(cursor, count);
+            (
+                next,
+                keys.into_iter().map(str::to_string).collect::<Vec<_>>(),
+            )
+        };
+        // looked up once the index is released, it is locked after the shards by writers
+        let now = self.now_ms();
+        let page = keys
+            .into_iter()
+            .filter_map(|key| {
+                let entry = self.db.get(&key)?;
+                let live = !entry.is_expired(now);
+                live.then(|| (key.clone(), entry.value.type_name()))
+            })
+            .collect();
+        (next, page)
+    }
+
+    // keep the keyspace scan index in sync, must be called while the key's slot is locked
+    pub(crate) fn track_key(&self, key: &str, present: bool) {
+        if present {
+            self.keyspace().insert(key);
+        } else {
+            self.keyspace().remove(key);
+        }
+    }
+
+    /// Every live key matching the glob pattern.
+    pub fn keys(&self, pattern: &str) -> Vec<String> {
+        let now = self.now_ms();
+        let all = pattern == "*";
+        self.db
+            .iter()
+            .filter(|e| !e.is_expired(now))
+            .filter(|e| all || super::glob_match(pattern.as_bytes(), e.key().as_bytes()))
+            .map(|e| e.key().clone())
+            .collect()
+    }
+
+    /// The number of keys, including the expired ones not reclaimed yet, like Redis.
+    pub fn dbsize(&self) -> usize {
+        self.db.len()
+    }
+
+    /// A random live key, sampled from the scan index instead of walking the keyspace.
+    pub fn random_key(&self) -> Option<String> {
+        let mut rng = rand::thread_rng();
+        loop {
+            let key = self.keyspace().random(&mut rng)?.to_string();
+            // an expired pick is reclaimed and the next draw can not return it again
+            if self.lookup(&key).is_some() {
+                return Some(key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ManualClock;
+    use std::collections::HashSet;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_scan_by_hash_pages() {
+        let items = (0..100u64).map(|i| (i * 7 % 100, i)).collect::<Vec<_>>();
+        let mut cursor = 0;
+        let mut seen = vec![];
+        loop {
+            let (next, page) = scan_by_hash(items.clone(), cursor, 10);
+            assert!(page.len() <= 10);
+            seen.extend(page);
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+        seen.sort();
+        assert_eq!(seen, (0..100).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_scan_by_hash_keeps_ties_together() {
+        let items = vec![(1, 'a'), (2, 'b'), (2, 'c'), (3, 'd')];
+        let (next, page) = scan_by_hash(items.clone(), 0, 2);
+        assert_eq!(next, 3);
+        assert_eq!(page.len(), 3);
+        assert_eq!(scan_by_hash(items, next, 2), (0, vec!['d']));
+    }
+
+    #[test]
+    fn test_scan_index_pages() {
+        let mut index = ScanIndex::default();
+        for i in 0..100 {
+            index.insert(&format!("name{i}"));
+        }
+        index.remove("name7");
+        let mut cursor = 0;
+        let mut seen = vec![];
+        loop {
+            let (next, page) = index.page(cursor, 10);
+            assert!(page.len() >= 10 || next == 0);
+            seen.extend(page.into_iter().map(str::to_string));
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+        seen.sort();
+        let mut expected = (0..100)
+            .filter(|i| *i != 7)
+            .map(|i| format!("name{i}"))
+            .collect::<Vec<_>>();
+        expected.sort();
+        assert_eq!(seen, expected);
+
+        // a huge COUNT is only bounded by the collection
+        assert_eq!(index.page(0, usize::MAX).1.len(), 99);
+        assert_eq!(scan_by_hash(vec![(1, 'a')], 0, usize::MAX), (0, vec!['a']));
+    }
+
+    #[test]
+    fn test_scan_survives_concurrent_writes() {
+        let backend = Backend::new();
+        for i in 0..200 {
+            backend.set(format!("key{i}"), b"v".into());
+        }
+        let mut cursor = 0;
+        let mut seen = HashSet::new();
+        let mut round = 0;
+        loop {
+            let (next, page) = backend.scan(cursor, 15);
+            seen.extend(page.into_iter().map(|(key, _)| key));
+            // churn between calls: new keys and deleted keys must not hide the stable ones
+            backend.set(format!("new{round}"), b"v".into());
+            backend.del(&[format!("key{}", 100 + round)]);
+            round += 1;
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+        for i in 0..100 {
+            assert!(seen.contains(&format!("key{i}")));
+        }
+    }
+
+    #[test]
+    fn test_keys_dbsize_randomkey() {
+        let backend = Backend::with_clock(Arc::new(ManualClock::new(1000)));
+        assert_eq!(backend.random_key(), None);
+        backend.set("user:1".to_string(), b"v".into());
+        backend.set("user:2".to_string(), b"v".into());
+        backend.set("other".to_string(), b"v".into());
+        backend.set_with_expire("user:3".to_string(), b"v".into(), Some(500));
+
+        let mut keys = backend.keys("user:*");
+        keys.sort();
+        assert_eq!(keys, vec!["user:1".to_string(), "user:2".to_string()]);
+        assert_eq!(backend.keys("*").len(), 3);
+        assert_eq!(backend.dbsize(), 4);
+        assert_ne!(backend.random_key().as_deref(), Some("user:3"));
+
+        // the expired keys drawn are reclaimed on the way
+        backend.del(&[
+            "user:1".to_string(),
+            "user:2".to_string(),
+            "other".to_string(),
+        ]);
+        assert_eq!(backend.random_key(), None);
+        assert_eq!(backend.dbsize(), 0);
+    }
+}
@@ -0,0 +1,356 @@
+use std::collections::HashSet;
+
+use rand::seq::{index, IteratorRandom, SliceRandom};
+
+use super::scan::{scan_by_hash, scan_hash, ScanPage};
+use super::{Backend, BackendError, Entry, RedisValue};
+
+type Set = HashSet<String>;
+
+/// How SINTER, SUNION and SDIFF combine their sets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetOp {
+    Inter,
+    Union,
+    // the members of the first set missing from all the others
+    Diff,
+}
+
+impl Backend {
+    /// SADD: add the members, creating the set if needed. Returns how many were not in it yet.
+    pub fn sadd(&self, key: String, members: Vec<String>) -> Result<usize, BackendError> {
+        let _guard = self.locks.write([key.as_str()]);
+        let added = self.write_value::<Set, _>(&key, true, |set| {
+            Ok(members
+                .into_iter()
+                .filter(|m| set.insert(m.clone()))
+                .count())
+        })?;
+        Ok(added.unwrap_or(0))
+    }
+
+    /// SREM: remove the members. Returns how many were in the set.
+    pub fn srem(&self, key: &str, members: &[String]) -> Result<usize, BackendError> {
+        let _guard = self.locks.write([key]);
+        let removed = self.write_value::<Set, _>(key, false, |set| {
+            Ok(members.iter().filter(|m| set.remove(*m)).count())
+        })?;
+        Ok(removed.unwrap_or(0))
+    }
+
+    pub fn smembers(&self, key: &str) -> Result<Vec<String>, BackendError> {
+        let _guard = self.locks.read([key]);
+        Ok(self
+            .read_set(key, |set| set.iter().cloned().collect())?
+            .unwrap_or_default())
+    }
+
+    pub fn sismember(&self, key: &str, member: &str) -> Result<bool, BackendError> {
+        let _guard = self.locks.read([key]);
+        Ok(self
+            .read_set(key, |set| set.contains(member))?
+            .unwrap_or(false))
+    }
+
+    /// SMISMEMBER: whether each member is in the set.
+    pub fn smismember(&self, key: &str, members: &[String]) -> Result<Vec<bool>, BackendError> {
+        let _guard = self.locks.read([key]);
+        let ret = self.read_set(key, |set| members.iter().map(|m| set.contains(m)).collect())?;
+        Ok(ret.unwrap_or_else(|| vec![false; members.len()]))
+    }
+
+    pub fn scard(&self, key: &str) -> Result<usize, BackendError> {
+        let _guard = self.locks.read([key]);
+        Ok(self.read_set(key, |set| set.len())?.unwrap_or(0))
+    }
+
+    /// SPOP: remove up to `count` random members and return them.
+    pub fn spop(&self, key: &str, count: usize) -> Result<Vec<String>, BackendError> {
+        let _guard = self.locks.write([key]);
+        let popped = self.write_value::<Set, _>(key, false, |set| {
+            // COUNT comes from the client, never sample more than the set holds
+            if count >= set.len() {
+                return Ok(set.drain().collect());
+            }
+            let mut rng = rand::thread_rng();
+            let picked = set.iter().cloned().choose_multiple(&mut rng, count);
+            for member in &picked {
+                set.remove(member);
+            }
+            Ok(picked)
+        })?;
+        Ok(popped.unwrap_or_default())
+    }
+
+    /// SRANDMEMBER: up to `count` distinct random members, or exactly `-count` members that
+    /// may repeat when `count` is negative.
+    pub fn srandmember(&self, key: &str, count: i64) -> Result<Vec<String>, BackendError> {
+        let _guard = self.locks.read([key]);
+        let members = self.read_set(key, |set| {
+            let mut rng = rand::thread_rng();
+            let all = set.iter().collect::<Vec<_>>();
+            let picked: Vec<_> = if count >= 0 {
+                let n = (count as usize).min(all.len());
+                index::sample(&mut rng, all.len(), n)
+                    .into_iter()
+                    .map(|i| all[i])
+                    .collect()
+            } else {
+                (0..count.unsigned_abs())
+                    .filter_map(|_| all.choose(&mut rng).copied())
+                    .collect()
+            };
+            picked.into_iter().cloned().collect()
+        })?;
+        Ok(members.unwrap_or_default())
+    }
+
+    /// SSCAN: a page of members, with the cursor semantics of SCAN.
+    pub fn sscan(
+        &self,
+        key: &str,
+        cursor: u64,
+        count: usize,
+    ) -> Result<ScanPage<String>, BackendError> {
+        let _guard = self.locks.read([key]);
+        let page = self.read_set(key, |set| {
+            let (next, page) = scan_by_hash(set.iter().map(|m| (scan_hash(m), m)), cursor, count);
+            (next, page.into_iter().cloned().collect())
+        })?;
+        Ok(page.unwrap_or((0, vec![])))
+    }
+
+    /// SINTER / SUNION / SDIFF: combine the sets, missing keys counting as empty sets. All
+    /// keys are locked together so the result is a snapshot even while other clients write.
+    pub fn set_op(&self, op: SetOp, keys: &[String]) -> Result<Vec<String>, BackendError> {
+        let _guard = self.locks.read(keys.iter().map(String::as_str));
+        Ok(self.combine(op, keys)?.into_iter().collect())
+    }
+
+    /// The STORE variants: write the result to `dst`, replacing whatever it held, or delete it
+    /// if the result is empty. Returns the size of the result.
+    pub fn set_op_store(
+        &self,
+        op: SetOp,
+        dst: &str,
+        keys: &[String],
+    ) -> Result<usize, BackendError> {
+        let _guard = self.locks.lock(keys.iter().map(String::as_str), [dst]);
+        let set = self.combine(op, keys)?;
+        let len = set.len();
+        if set.is_empty() {
+            self.remove_entry(dst);
+        } else {
+            self.insert_entry(dst.to_string(), Entry::new(set, None));
+        }
+        Ok(len)
+    }
+
+    /// SINTERCARD: the size of the intersection, counting at most `limit` members unless it
+    /// is 0.
+    pub fn sintercard(&self, keys: &[String], limit: usize) -> Result<usize, BackendError> {
+        let _guard = self.locks.read(keys.iter().map(String::as_str));
+        let len = self.combine(SetOp::Inter, keys)?.len();
+        Ok(if limit == 0 { len } else { len.min(limit) })
+    }
+
+    /// SMOVE: move a member from one set to another. Returns whether it was in the source.
+    pub fn smove(&self, src: &str, dst: &str, member: &str) -> Result<bool, BackendError> {
+        let _guard = self.locks.write([src, dst]);
+        // both keys must hold sets before anything changes
+        self.read_set(dst, |_| ())?;
+        if src == dst {
+            return Ok(self
+                .read_set(src, |set| set.contains(member))?
+                .unwrap_or(false));
+        }
+        let moved = self
+            .write_value::<Set, _>(src, false, |set| Ok(set.remove(member)))?
+            .unwrap_or(false);
+        if moved {
+            self.write_value::<Set, _>(dst, true, |set| Ok(set.insert(member.to_string())))?;
+        }
+        Ok(moved)
+    }
+
+    // the caller holds the locks of the keys. Each set is folded in under its own slot guard,
+    // never holding two at once since `lookup` may write to a shard to expire a key.
+    fn combine(&self, op: SetOp, keys: &[String]) -> Result<Set, BackendError> {
+        let mut acc: Option<Set> = None;
+        for key in keys {
+            let found = self.read_set(key, |set| match (op, acc.as_mut()) {
+                (_, None) => acc = Some(set.clone()),
+                (SetOp::Inter, Some(acc)) => acc.retain(|m| set.contains(m)),
+                (SetOp::Union, Some(acc)) => acc.extend(set.iter().cloned()),
+                (SetOp::Diff, Some(acc)) => acc.retain(|m| !set.contains(m)),
+            })?;
+            if found.is_none() {
+                match (op, acc.as_mut()) {
+                    (SetOp::Inter, Some(acc)) => acc.clear(),
+                    (_, None) => acc = Some(Set::new()),
+                    _ => {}
+                }
+            }
+        }
+        Ok(acc.unwrap_or_default())
+    }
+
+    fn read_set<T>(&self, key: &str, f: impl FnOnce(&Set) -> T) -> Result<Option<T>, BackendError> {
+        match self.lookup(key).as_deref() {
+            Some(Entry {
+                value: RedisValue::Set(set),
+                ..
+            }) => Ok(Some(f(set))),
+            Some(_) => Err(BackendError::WrongType),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn members(members: &[&str]) -> Vec<String> {
+        members.iter().map(|m| m.to_string()).collect()
+    }
+
+    fn sorted(mut members: Vec<String>) -> Vec<String> {
+        members.sort();
+        members
+    }
+
+    #[test]
+    fn test_sadd_srem() {
+        let backend = Backend::new();
+        assert_eq!(
+            backend.sadd("s".to_string(), members(&["a", "b", "a"])),
+            Ok(2)
+        );
+        assert_eq!(backend.sadd("s".to_string(), members(&["b", "c"])), Ok(1));
+        assert_eq!(backend.scard("s"), Ok(3));
+        assert_eq!(
+            sorted(backend.smembers("s").unwrap()),
+            members(&["a", "b", "c"])
+        );
+        assert_eq!(backend.sismember("s", "a"), Ok(true));
+        assert_eq!(
+            backend.smismember("s", &members(&["a", "x"])),
+            Ok(vec![true, false])
+        );
+
+        assert_eq!(backend.srem("s", &members(&["a", "x"])), Ok(1));
+        assert_eq!(backend.srem("s", &members(&["b", "c"])), Ok(2));
+        assert_eq!(backend.key_type("s"), "none");
+        assert_eq!(backend.smismember("s", &members(&["a"])), Ok(vec![false]));
+
+        backend.set("str".to_string(), b"v".into());
+        assert_eq!(
+            backend.sadd("str".to_string(), members(&["a"])),
+            Err(BackendError::WrongType)
+        );
+    }
+
+    #[test]
+    fn test_set_ops() {
+        let backend = Backend::new();
+        backend
+            .sadd("a".to_string(), members(&["1", "2", "3"]))
+            .unwrap();
+        backend
+            .sadd("b".to_string(), members(&["2", "3", "4"]))
+            .unwrap();
+        let keys = members(&["a", "b"]);
+        let op = |op, keys: &[String]| sorted(backend.set_op(op, keys).unwrap());
+        assert_eq!(op(SetOp::Inter, &keys), members(&["2", "3"]));
+        assert_eq!(op(SetOp::Union, &keys), members(&["1", "2", "3", "4"]));
+        assert_eq!(op(SetOp::Diff, &keys), members(&["1"]));
+        assert_eq!(op(SetOp::Inter, &members(&["a", "missing"])), members(&[]));
+        assert_eq!(op(SetOp::Diff, &members(&["missing", "a"])), members(&[]));
+        assert_eq!(op(SetOp::Union, &members(&["missing", "a"])).len(), 3);
+        assert_eq!(backend.sintercard(&keys, 0), Ok(2));
+        assert_eq!(backend.sintercard(&keys, 1), Ok(1));
+
+        backend.set("str".to_string(), b"v".into());
+        assert_eq!(
+            backend.set_op(SetOp::Diff, &members(&["a", "str"])),
+            Err(BackendError::WrongType)
+        );
+    }
+
+    #[test]
+    fn test_set_op_store() {
+        let backend = Backend::new();
+        backend.sadd("a".to_string(), members(&["1", "2"])).unwrap();
+        backend.set("dst".to_string(), b"v".into());
+        assert_eq!(
+            backend.set_op_store(SetOp::Union, "dst", &members(&["a", "missing"])),
+            Ok(2)
+        );
+        assert_eq!(backend.key_type("dst"), "set");
+        // the destination may be one of the sources
+        assert_eq!(
+            backend.set_op_store(SetOp::Diff, "a", &members(&["a", "dst"])),
+            Ok(0)
+        );
+        assert_eq!(backend.key_type("a"), "none");
+        assert_eq!(backend.scard("dst"), Ok(2));
+    }
+
+    #[test]
+    fn test_smove() {
+        let backend = Backend::new();
+        backend.sadd("a".to_string(), members(&["1"])).unwrap();
+        assert_eq!(backend.smove("a", "b", "x"), Ok(false));
+        assert_eq!(backend.smove("a", "a", "1"), Ok(true));
+        assert_eq!(backend.smove("a", "b", "1"), Ok(true));
+        assert_eq!(backend.key_type("a"), "none");
+        assert_eq!(backend.smembers("b"), Ok(members(&["1"])));
+
+        backend.set("str".to_string(), b"v".into());
+        assert_eq!(backend.smove("b", "str", "1"), Err(BackendError::WrongType));
+        assert_eq!(backend.scard("b"), Ok(1));
+    }
+
+    #[test]
+    fn test_spop_srandmember() {
+        let backend = Backend::new();
+        backend
+            .sadd("s".to_string(), members(&["a", "b", "c"]))
+            .unwrap();
+        let picked = backend.srandmember("s", 5).unwrap();
+        assert_eq!(sorted(picked), members(&["a", "b", "c"]));
+        assert_eq!(backend.srandmember("s", -5).unwrap().len(), 5);
+        assert_eq!(backend.srandmember("missing", -5), Ok(vec![]));
+
+        let popped = backend.spop("s", 2).unwrap();
+        assert_eq!(popped.len(), 2);
+        assert_eq!(backend.scard("s"), Ok(1));
+        assert_eq!(backend.spop("s", 2).unwrap().len(), 1);
+        assert_eq!(backend.key_type("s"), "none");
+
+        // a huge COUNT takes the whole set
+        backend.sadd("s".to_string(), members(&["a", "b"])).unwrap();
+        let popped = backend.spop("s", usize::MAX).unwrap();
+        assert_eq!(sorted(popped), members(&["a", "b"]));
+        assert_eq!(backend.key_type("s"), "none");
+    }
+
+    #[test]
+    fn test_sscan_returns_every_member() {
+        let backend = Backend::new();
+        let all = (0..50).map(|i| i.to_string()).collect::<Vec<_>>();
+        backend.sadd("s".to_string(), all.clone()).unwrap();
+        let mut seen = vec![];
+        let mut cursor = 0;
+        loop {
+            let (next, page) = backend.sscan("s", cursor, 7).unwrap();
+            seen.extend(page);
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+        assert_eq!(sorted(seen), sorted(all));
+    }
+}
@@ -0,0 +1,1414 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::ops::{Bound, Deref};
+
+use super::{Backend, BackendError, Entry, RedisValue};
+use crate::RespFrame;
+
+/// The ID of a stream entry, `<ms>-<seq>`, ordered by time then sequence number.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StreamId {
+    pub ms: u64,
+    pub seq: u64,
+}
+
+/// The field value pairs of a stream entry, in the order they were added.
+pub type StreamFields = Vec<(String, RespFrame)>;
+
+/// The entries read from each stream by XREAD and XREADGROUP. XREADGROUP replaying the
+/// pending entries gives the entries deleted since without fields.
+pub type StreamReads<T = StreamFields> = Vec<(String, Vec<(StreamId, T)>)>;
+
+/// The entries of a stream by ID, and its consumer groups.
+///
+/// The last ID is kept apart from the entries: it never goes back, even once the entries are
+/// deleted or trimmed, so new IDs always grow. Reads of the entries go through `Deref`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stream {
+    entries: BTreeMap<StreamId, StreamFields>,
+    last_id: StreamId,
+    // the greatest ID deleted by XDEL, 0-0 if none was
+    max_deleted_id: StreamId,
+    // how many entries were ever added
+    entries_added: u64,
+    groups: BTreeMap<String, ConsumerGroup>,
+}
+
+// a consumer group: where it is in the stream, and the entries delivered to its consumers
+// and not acknowledged yet, the pending entries list (PEL)
+#[derive(Debug, Clone, PartialEq)]
+struct ConsumerGroup {
+    last_id: StreamId,
+    // how many entries of the stream the group read, None when it can't be told since
+    // entries were deleted in the middle of the stream
+    entries_read: Option<u64>,
+    pending: BTreeMap<StreamId, PendingEntry>,
+    consumers: BTreeMap<String, Consumer>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct PendingEntry {
+    consumer: String,
+    delivered_at: u64,
+    deliveries: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Consumer {
+    // the last time the consumer read or claimed, successfully or not
+    seen_at: u64,
+    // the last time it was given an entry, None if it never was
+    active_at: Option<u64>,
+    pending: BTreeSet<StreamId>,
+}
+
+// entries per node of the radix tree Redis stores streams in, `stream-node-max-entries`:
+// approximate trimming only drops whole nodes
+const NODE_ENTRIES: usize = 100;
+
+/// The ID given to XADD: `*`, `<ms>-*` or an explicit one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XAddId {
+    Auto,
+    AutoSeq(u64),
+    Explicit(StreamId),
+}
+
+/// The MAXLEN / MINID options of XADD and XTRIM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamTrim {
+    pub strategy: TrimStrategy,
+    // `~`: trim only when whole nodes can go, which may leave a few extra entries
+    pub approx: bool,
+    // with `~`, how many entries to remove at most, 0 for no limit
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrimStrategy {
+    // keep at most that many entries
+    MaxLen(usize),
+    // remove the entries with a smaller ID
+    MinId(StreamId),
+}
+
+/// The options of XCLAIM besides the entries and the minimum idle time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ClaimOptions {
+    // IDLE / TIME: when the entries are recorded as delivered, now by default
+    pub delivered_at: Option<u64>,
+    // RETRYCOUNT: the delivery count to set, else it is incremented
+    pub deliveries: Option<u64>,
+    // claim the entries that are not pending, if they exist
+    pub force: bool,
+    // reply with the IDs only, the delivery count is left alone
+    pub just_id: bool,
+    // move the last delivered ID of the group forward to this one
+    pub last_id: Option<StreamId>,
+}
+
+/// The entries of XPENDING in its extended form.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingRange {
+    pub start: StreamId,
+    pub end: StreamId,
+    pub count: usize,
+    pub consumer: Option<String>,
+    // only the entries idle for at least that many milliseconds
+    pub min_idle: u64,
+}
+
+/// The summary form of XPENDING.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PendingSummary {
+    pub count: usize,
+    // the smallest and greatest pending IDs
+    pub range: Option<(StreamId, StreamId)>,
+    // how many entries each consumer has pending, for the consumers with some
+    pub consumers: Vec<(String, usize)>,
+}
+
+/// A pending entry in the extended form of XPENDING.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingInfo {
+    pub id: StreamId,
+    pub consumer: String,
+    pub idle: u64,
+    pub deliveries: u64,
+}
+
+/// The reply of XAUTOCLAIM.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AutoClaim {
+    // where the next call goes on scanning, 0-0 once all the pending entries were scanned
+    pub next: StreamId,
+    pub claimed: Vec<(StreamId, StreamFields)>,
+    // the pending entries found deleted from the stream
+    pub deleted: Vec<StreamId>,
+}
+
+/// XINFO STREAM.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamInfo {
+    pub length: usize,
+    pub last_id: StreamId,
+    pub max_deleted_id: StreamId,
+    pub entries_added: u64,
+    pub first_id: StreamId,
+    pub groups: usize,
+    pub first_entry: Option<(StreamId, StreamFields)>,
+    pub last_entry: Option<(StreamId, StreamFields)>,
+}
+
+/// XINFO GROUPS, for one group.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupInfo {
+    pub name: String,
+    pub consumers: usize,
+    pub pending: usize,
+    pub last_id: StreamId,
+    pub entries_read: Option<u64>,
+    // how many entries are left for the group to read, None when it can't be told
+    pub lag: Option<u64>,
+}
+
+/// XINFO CONSUMERS, for one consumer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsumerInfo {
+    pub name: String,
+    pub pending: usize,
+    // milliseconds since the last read or claim attempt
+    pub idle: u64,
+    // milliseconds since the last successful one, None if it never was
+    pub inactive: Option<u64>,
+}
+
+impl StreamId {
+    pub const MIN: StreamId = StreamId::new(0, 0);
+    pub const MAX: StreamId = StreamId::new(u64::MAX, u64::MAX);
+
+    pub const fn new(ms: u64, seq: u64) -> Self {
+        StreamId { ms, seq }
+    }
+
+    /// The smallest ID after this one, None for the last possible ID.
+    pub fn next(self) -> Option<StreamId> {
+        match self.seq.checked_add(1) {
+            Some(seq) => Some(StreamId::new(self.ms, seq)),
+            None => Some(StreamId::new(self.ms.checked_add(1)?, 0)),
+        }
+    }
+
+    /// The greatest ID before this one, None for 0-0.
+    pub fn prev(self) -> Option<StreamId> {
+        match self.seq.checked_sub(1) {
+            Some(seq) => Some(StreamId::new(self.ms, seq)),
+            None => Some(StreamId::new(self.ms.checked_sub(1)?, u64::MAX)),
+        }
+    }
+}
+
+impl fmt::Display for StreamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.ms, self.seq)
+    }
+}
+
+impl Stream {
+    /// The greatest ID the stream ever had, 0-0 for a new stream.
+    pub fn last_id(&self) -> StreamId {
+        self.last_id
+    }
+
+    /// Add an entry, its ID must be greater than the last one.
+    pub fn insert(&mut self, id: StreamId, fields: StreamFields) {
+        self.last_id = id;
+        self.entries_added += 1;
+        self.entries.insert(id, fields);
+    }
+
+    pub fn remove(&mut self, id: StreamId) -> Option<StreamFields> {
+        let fields = self.entries.remove(&id)?;
+        self.max_deleted_id = self.max_deleted_id.max(id);
+        Some(fields)
+    }
+
+    /// Remove the oldest entry.
+    pub fn pop_first(&mut self) -> Option<(StreamId, StreamFields)> {
+        self.entries.pop_first()
+    }
+
+    fn first_id(&self) -> StreamId {
+        self.entries.keys().next().copied().unwrap_or_default()
+    }
+
+    fn counters(&self) -> Counters {
+        Counters {
+            length: self.entries.len(),
+            first_id: self.first_id(),
+            last_id: self.last_id,
+            max_deleted_id: self.max_deleted_id,
+            entries_added: self.entries_added,
+        }
+    }
+}
+
+impl Deref for Stream {
+    type Target = BTreeMap<StreamId, StreamFields>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.entries
+    }
+}
+
+impl ConsumerGroup {
+    fn new(last_id: StreamId, entries_read: Option<u64>) -> Self {
+        ConsumerGroup {
+            last_id,
+            entries_read,
+            pending: BTreeMap::new(),
+            consumers: BTreeMap::new(),
+        }
+    }
+
+    // the consumer, created if it doesn't exist, seen now
+    fn consumer(&mut self, name: &str, now: u64) -> &mut Consumer {
+        let consumer = self
+            .consumers
+            .entry(name.to_string())
+            .or_insert_with(|| Consumer::new(now));
+        consumer.seen_at = now;
+        consumer
+    }
+
+    // make the entry pending for the consumer, taking it from the consumer it was pending for.
+    // A new pending entry is not delivered yet.
+    fn assign(&mut self, id: StreamId, consumer: &str, now: u64) -> &mut PendingEntry {
+        let pending = self.pending.entry(id).or_insert_with(|| PendingEntry {
+            consumer: consumer.to_string(),
+            delivered_at: now,
+            deliveries: 0,
+        });
+        if pending.consumer != consumer {
+            if let Some(owner) = self.consumers.get_mut(&pending.consumer) {
+                owner.pending.remove(&id);
+            }
+            pending.consumer = consumer.to_string();
+        }
+        let owner = self
+            .consumers
+            .entry(consumer.to_string())
+            .or_insert_with(|| Consumer::new(now));
+        owner.pending.insert(id);
+        owner.active_at = Some(now);
+        pending
+    }
+
+    // drop the entry from the pending entries, returns whether it was pending
+    fn ack(&mut self, id: StreamId) -> bool {
+        match self.pending.remove(&id) {
+            Some(pending) => {
+                if let Some(owner) = self.consumers.get_mut(&pending.consumer) {
+                    owner.pending.remove(&id);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Consumer {
+    fn new(now: u64) -> Self {
+        Consumer {
+            seen_at: now,
+            active_at: None,
+            pending: BTreeSet::new(),
+        }
+    }
+}
+
+// what the read counters of the groups are worked out from. A group counts the entries it
+// read to report its lag, which only holds as long as no entry it has yet to read was deleted:
+// then the count is estimated from the first and last entries when it can be, as Redis does.
+#[derive(Debug, Clone, Copy)]
+struct Counters {
+    length: usize,
+    first_id: StreamId,
+    last_id: StreamId,
+    max_deleted_id: StreamId,
+    entries_added: u64,
+}
+
+impl Counters {
+    // whether an entry from `start` on was deleted
+    fn has_tombstones(&self, start: StreamId) -> bool {
+        self.length > 0 && self.max_deleted_id != StreamId::MIN && start <= self.max_deleted_id
+    }
+
+    // how many entries were added up to the ID included, None if it can't be told
+    fn entries_read_at(&self, id: StreamId) -> Option<u64> {
+        if self.entries_added == 0 {
+            return Some(0);
+        }
+        if id == self.last_id || (self.length == 0 && id < self.last_id) {
+            return Some(self.entries_added);
+        }
+        if id > self.last_id {
+            // the future entries are unknown
+            return None;
+        }
+        if self.max_deleted_id == StreamId::MIN || self.max_deleted_id < self.first_id {
+            // only entries before the first one were deleted
+            let before_first = self.entries_added - self.length as u64;
+            if id < self.first_id {
+                return Some(before_first);
+            }
+            if id == self.first_id {
+                return Some(before_first + 1);
+            }
+        }
+        None
+    }
+
+    // the counter of a group once it read the entry
+    fn read(&self, group: &ConsumerGroup, id: StreamId) -> Option<u64> {
+        match group.entries_read {
+            Some(read) if !self.has_tombstones(id) => Some(read + 1),
+            _ => self.entries_read_at(id),
+        }
+    }
+
+    fn lag(&self, group: &ConsumerGroup) -> Option<u64> {
+        if self.entries_added == 0 {
+            return Some(0);
+        }
+        let read = match group.entries_read {
+            Some(read) if !self.has_tombstones(group.last_id) => read,
+            _ => self.entries_read_at(group.last_id)?,
+        };
+        Some(self.entries_added.saturating_sub(read))
+    }
+}
+
+impl Backend {
+    /// XADD: add an entry, creating the stream unless `no_mkstream` is set, then trim it.
+    /// Returns the ID of the entry, None if the stream does not exist and was not created.
+    pub fn xadd(
+        &self,
+        key: &str,
+        id: XAddId,
+        fields: StreamFields,
+        no_mkstream: bool,
+        trim: Option<StreamTrim>,
+    ) -> Result<Option<StreamId>, BackendError> {
+        let id = {
+            let _guard = self.locks.write([key]);
+            let now = self.now_ms();
+            self.write_value::<Stream, _>(key, !no_mkstream, |stream| {
+                let id = next_id(stream.last_id(), id, now)?;
+                stream.insert(id, fields);
+                if let Some(trim) = trim {
+                    trim_stream(stream, &trim);
+                }
+                Ok(id)
+            })?
+        };
+        if id.is_some() {
+            self.signal_ready(key);
+        }
+        Ok(id)
+    }
+
+    pub fn xlen(&self, key: &str) -> Result<usize, BackendError> {
+        let _guard = self.locks.read([key]);
+        let len = self.read_stream(key, |stream| stream.len())?;
+        Ok(len.unwrap_or(0))
+    }
+
+    /// XRANGE / XREVRANGE: the entries between `start` and `end` included, up to `count` of
+    /// them, newest first with `rev`.
+    pub fn xrange(
+        &self,
+        key: &str,
+        start: StreamId,
+        end: StreamId,
+        rev: bool,
+        count: Option<usize>,
+    ) -> Result<Vec<(StreamId, StreamFields)>, BackendError> {
+        if start > end {
+            return Ok(Vec::new());
+        }
+        let _guard = self.locks.read([key]);
+        let entries = self.read_stream(key, |stream| {
+            let range = stream
+                .range(start..=end)
+                .map(|(id, fields)| (*id, fields.clone()));
+            let count = count.unwrap_or(usize::MAX);
+            if rev {
+                range.rev().take(count).collect()
+            } else {
+                range.take(count).collect()
+            }
+        })?;
+        Ok(entries.unwrap_or_default())
+    }
+
+    /// XTRIM: returns how many entries were removed.
+    pub fn xtrim(&self, key: &str, trim: &StreamTrim) -> Result<usize, BackendError> {
+        let _guard = self.locks.write([key]);
+        let removed =
+            self.write_value::<Stream, _>(key, false, |stream| Ok(trim_stream(stream, trim)))?;
+        Ok(removed.unwrap_or(0))
+    }
+
+    /// XDEL: returns how many of the entries existed.
+    pub fn xdel(&self, key: &str, ids: &[StreamId]) -> Result<usize, BackendError> {
+        let _guard = self.locks.write([key]);
+        let removed = self.write_value::<Stream, _>(key, false, |stream| {
+            Ok(ids
+                .iter()
+                .filter(|id| stream.remove(**id).is_some())
+                .count())
+        })?;
+        Ok(removed.unwrap_or(0))
+    }
+
+    /// XREAD: the entries after the given ID of each stream, up to `count` per stream. Only
+    /// the streams with such entries are in the reply.
+    pub fn xread(
+        &self,
+        streams: &[(String, StreamId)],
+        count: Option<usize>,
+    ) -> Result<StreamReads, BackendError> {
+        let _guard = self.locks.read(streams.iter().map(|(key, _)| key.as_str()));
+        let mut reply = Vec::new();
+        for (key, after) in streams {
+            let entries = self.read_stream(key, |stream| {
+                stream
+                    .range((Bound::Excluded(*after), Bound::Unbounded))
+                    .take(count.unwrap_or(usize::MAX))
+                    .map(|(id, fields)| (*id, fields.clone()))
+                    .collect::<Vec<_>>()
+            })?;
+            match entries {
+                Some(entries) if !entries.is_empty() => reply.push((key.clone(), entries)),
+                _ => {}
+            }
+        }
+        Ok(reply)
+    }
+
+    /// The last ID of the stream, what `$` stands for, 0-0 if the key doesn't exist.
+    pub fn xlast_id(&self, key: &str) -> Result<StreamId, BackendError> {
+        let _guard = self.locks.read([key]);
+        let id = self.read_stream(key, |stream| stream.last_id)?;
+        Ok(id.unwrap_or_default())
+    }
+
+    /// XREADGROUP: read as a consumer of the group.
+    ///
+    /// A stream without an ID (`>`) delivers the entries the group didn't deliver yet, which
+    /// become pending for the consumer unless `no_ack` is set, and is only in the reply if
+    /// there are some. A stream with an ID replays the entries pending for the consumer after
+    /// it, an entry deleted since coming without its fields.
+    pub fn xreadgroup(
+        &self,
+        group: &str,
+        consumer: &str,
+        streams: &[(String, Option<StreamId>)],
+        count: Option<usize>,
+        no_ack: bool,
+    ) -> Result<StreamReads<Option<StreamFields>>, BackendError> {
+        let _guard = self
+            .locks
+            .write(streams.iter().map(|(key, _)| key.as_str()));
+        // nothing is delivered unless every group exists
+        for (key, _) in streams {
+            if self.read_stream(key, |stream| stream.groups.contains_key(group))? != Some(true) {
+                return Err(no_group(key, group));
+            }
+        }
+        let now = self.now_ms();
+        let count = count.unwrap_or(usize::MAX);
+        let mut reply = Vec::new();
+        for (key, start) in streams {
+            let entries = self.write_value::<Stream, _>(key, false, |stream| {
+                let counters = stream.counters();
+                let group = stream
+                    .groups
+                    .get_mut(group)
+                    .ok_or_else(|| no_group(key, group))?;
+                group.consumer(consumer, now);
+                let mut entries = Vec::new();
+                match start {
+                    None => {
+                        let new = (Bound::Excluded(group.last_id), Bound::Unbounded);
+                        for (id, fields) in stream.entries.range(new).take(count) {
+                            group.entries_read = counters.read(group, *id);
+                            group.last_id = *id;
+                            if no_ack {
+                                group.consumer(consumer, now).active_at = Some(now);
+                            } else {
+                                let pending = group.assign(*id, consumer, now);
+                                pending.delivered_at = now;
+                                pending.deliveries = 1;
+                            }
+                            entries.push((*id, Some(fields.clone())));
+                        }
+                    }
+                    Some(start) => {
+                        let history = (Bound::Excluded(*start), Bound::Unbounded);
+                        let ids = group.consumer(consumer, now).pending.range(history);
+                        let ids = ids.take(count).copied().collect::<Vec<_>>();
+                        for id in ids {
+                            let fields = stream.entries.get(&id).cloned();
+                            if let (Some(_), Some(pending)) = (&fields, group.pending.get_mut(&id))
+                            {
+                                pending.delivered_at = now;
+                                pending.deliveries += 1;
+                            }
+                            entries.push((id, fields));
+                        }
+                    }
+                }
+                Ok(entries)
+            })?;
+            match entries {
+                Some(entries) if start.is_some() || !entries.is_empty() => {
+                    reply.push((key.clone(), entries))
+                }
+                _ => {}
+            }
+        }
+        Ok(reply)
+    }
+
+    /// XGROUP CREATE: the group starts after the ID, the last one of the stream for None
+    /// (`$`). A missing key is an error unless `mkstream` is set.
+    pub fn xgroup_create(
+        &self,
+        key: &str,
+        group: &str,
+        id: Option<StreamId>,
+        mkstream: bool,
+        entries_read: Option<u64>,
+    ) -> Result<(), BackendError> {
+        let _guard = self.locks.write([key]);
+        let created = self.write_value::<Stream, _>(key, mkstream, |stream| {
+            if stream.groups.contains_key(group) {
+                return Err(BackendError::BusyGroup);
+            }
+            let last_id = id.unwrap_or(stream.last_id);
+            let new = ConsumerGroup::new(last_id, entries_read);
+            stream.groups.insert(group.to_string(), new);
+            Ok(())
+        })?;
+        created.ok_or(BackendError::StreamKeyMissing)
+    }
+
+    /// XGROUP SETID: move the group to the ID, the last one of the stream for None (`$`).
+    pub fn xgroup_setid(
+        &self,
+        key: &str,
+        group: &str,
+        id: Option<StreamId>,
+        entries_read: Option<u64>,
+    ) -> Result<(), BackendError> {
+        let _guard = self.locks.write([key]);
+        let set = self.write_value::<Stream, _>(key, false, |stream| {
+            let last_id = id.unwrap_or(stream.last_id);
+            let group = stream
+                .groups
+                .get_mut(group)
+                .ok_or_else(|| no_such_group(key, group))?;
+            group.last_id = last_id;
+            group.entries_read = entries_read;
+            Ok(())
+        })?;
+        set.ok_or(BackendError::StreamKeyMissing)
+    }
+
+    /// XGROUP DESTROY: returns whether the group existed.
+    pub fn xgroup_destroy(&self, key: &str, group: &str) -> Result<bool, BackendError> {
+        let destroyed = {
+            let _guard = self.locks.write([key]);
+            self.write_value::<Stream, _>(key, false, |stream| {
+                Ok(stream.groups.remove(group).is_some())
+            })?
+        };
+        // the clients blocked in XREADGROUP on the group are told it is gone
+        if destroyed == Some(true) {
+            self.signal_ready(key);
+        }
+        destroyed.ok_or(BackendError::StreamKeyMissing)
+    }
+
+    /// XGROUP CREATECONSUMER: returns whether the consumer was created.
+    pub fn xgroup_create_consumer(
+        &self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+    ) -> Result<bool, BackendError> {
+        let _guard = self.locks.write([key]);
+        let now = self.now_ms();
+        let created = self.write_value::<Stream, _>(key, false, |stream| {
+            let group = stream
+                .groups
+                .get_mut(group)
+                .ok_or_else(|| no_such_group(key, group))?;
+            if group.consumers.contains_key(consumer) {
+                return Ok(false);
+            }
+            group.consumer(consumer, now);
+            Ok(true)
+        })?;
+        created.ok_or(BackendError::StreamKeyMissing)
+    }
+
+    /// XGROUP DELCONSUMER: returns how many entries were pending for the consumer, they are
+    /// not pending anymore.
+    pub fn xgroup_del_consumer(
+        &self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+    ) -> Result<usize, BackendError> {
+        let _guard = self.locks.write([key]);
+        let deleted = self.write_value::<Stream, _>(key, false, |stream| {
+            let group = stream
+                .groups
+                .get_mut(group)
+                .ok_or_else(|| no_such_group(key, group))?;
+            let Some(consumer) = group.consumers.remove(consumer) else {
+                return Ok(0);
+            };
+            for id in &consumer.pending {
+                group.pending.remove(id);
+            }
+            Ok(consumer.pending.len())
+        })?;
+        deleted.ok_or(BackendError::StreamKeyMissing)
+    }
+
+    /// XACK: returns how many of the entries were pending.
+    pub fn xack(&self, key: &str, group: &str, ids: &[StreamId]) -> Result<usize, BackendError> {
+        let _guard = self.locks.write([key]);
+        let acked = self.write_value::<Stream, _>(key, false, |stream| {
+            let acked = match stream.groups.get_mut(group) {
+                Some(group) => ids.iter().filter(|id| group.ack(**id)).count(),
+                None => 0,
+            };
+            Ok(acked)
+        })?;
+        Ok(acked.unwrap_or(0))
+    }
+
+    /// XPENDING without a range.
+    pub fn xpending_summary(&self, key: &str, group: &str) -> Result<PendingSummary, BackendError> {
+        let _guard = self.locks.read([key]);
+        self.read_group(key, group, |_, group| {
+            let first = group.pending.keys().next();
+            let last = group.pending.keys().next_back();
+            PendingSummary {
+                count: group.pending.len(),
+                range: first.zip(last).map(|(first, last)| (*first, *last)),
+                consumers: group
+                    .consumers
+                    .iter()
+                    .filter(|(_, consumer)| !consumer.pending.is_empty())
+                    .map(|(name, consumer)| (name.clone(), consumer.pending.len()))
+                    .collect(),
+            }
+        })
+    }
+
+    /// XPENDING with a range: the pending entries between the IDs included.
+    pub fn xpending(
+        &self,
+        key: &str,
+        group: &str,
+        range: &PendingRange,
+    ) -> Result<Vec<PendingInfo>, BackendError> {
+        let _guard = self.locks.read([key]);
+        let now = self.now_ms();
+        self.read_group(key, group, |_, group| {
+            if range.start > range.end {
+                return Vec::new();
+            }
+            group
+                .pending
+                .range(range.start..=range.end)
+                .filter(|(_, pending)| match &range.consumer {
+                    Some(consumer) => pending.consumer == *consumer,
+                    None => true,
+                })
+                .map(|(id, pending)| PendingInfo {
+                    id: *id,
+                    consumer: pending.consumer.clone(),
+                    idle: now.saturating_sub(pending.delivered_at),
+                    deliveries: pending.deliveries,
+                })
+                .filter(|info| info.idle >= range.min_idle)
+                .take(range.count)
+                .collect()
+        })
+    }
+
+    /// XCLAIM: make the entries idle for at least `min_idle` milliseconds pending for the
+    /// consumer. Entries deleted from the stream are dropped from the pending entries.
+    /// Returns the entries claimed, without their fields with `just_id`.
+    pub fn xclaim(
+        &self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+        min_idle: u64,
+        ids: &[StreamId],
+        options: &ClaimOptions,
+    ) -> Result<Vec<(StreamId, StreamFields)>, BackendError> {
+        let _guard = self.locks.write([key]);
+        let now = self.now_ms();
+        // a delivery time in the future is now
+        let delivered_at = options.delivered_at.map_or(now, |time| time.min(now));
+        self.write_group(key, group, |entries, group| {
+            group.consumer(consumer, now);
+            let mut claimed = Vec::new();
+            for id in ids {
+                let Some(fields) = entries.get(id) else {
+                    group.ack(*id);
+                    continue;
+                };
+                match group.pending.get(id) {
+                    Some(pending) if now.saturating_sub(pending.delivered_at) < min_idle => {
+                        continue
+                    }
+                    None if !options.force => continue,
+                    _ => {}
+                }
+                let pending = group.assign(*id, consumer, now);
+                pending.delivered_at = delivered_at;
+                match options.deliveries {
+                    Some(deliveries) => pending.deliveries = deliveries,
+                    None if !options.just_id => pending.deliveries += 1,
+                    None => {}
+                }
+                let fields = if options.just_id {
+                    Vec::new()
+                } else {
+                    fields.clone()
+                };
+                claimed.push((*id, fields));
+            }
+            if let Some(last_id) = options.last_id {
+                group.last_id = group.last_id.max(last_id);
+            }
+            claimed
+        })
+    }
+
+    /// XAUTOCLAIM: claim up to `count` of the entries idle for at least `min_idle`
+    /// milliseconds, scanning the pending entries from `start`. At most ten times `count`
+    /// entries are looked at, the scan goes on at the returned ID, 0-0 once it got to the end.
+    /// Also returns the entries deleted from the stream, which are dropped from the pending
+    /// entries.
+    #[allow(clippy::too_many_arguments)]
+    pub fn xautoclaim(
+        &self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+        min_idle: u64,
+        start: StreamId,
+        count: usize,
+        just_id: bool,
+    ) -> Result<AutoClaim, BackendError> {
+        let _guard = self.locks.write([key]);
+        let now = self.now_ms();
+        self.write_group(key, group, |entries, group| {
+            group.consumer(consumer, now);
+            let attempts = count.saturating_mul(10);
+            let ids = group
+                .pending
+                .range(start..)
+                .map(|(id, _)| *id)
+                .take(attempts.saturating_add(1))
+                .collect::<Vec<_>>();
+            let mut claim = AutoClaim::default();
+            let mut scanned = 0;
+            for id in &ids {
+                if scanned == attempts || claim.claimed.len() == count {
+                    break;
+                }
+                scanned += 1;
+                let Some(fields) = entries.get(id) else {
+                    group.ack(*id);
+                    claim.deleted.push(*id);
+                    continue;
+                };
+                if let Some(pending) = group.pending.get(id) {
+                    if now.saturating_sub(pending.delivered_at) < min_idle {
+                        continue;
+                    }
+                }
+                let pending = group.assign(*id, consumer, now);
+                pending.delivered_at = now;
+                if !just_id {
+                    pending.deliveries += 1;
+                }
+                let fields = if just_id { Vec::new() } else { fields.clone() };
+                claim.claimed.push((*id, fields));
+            }
+            claim.next = ids.get(scanned).copied().unwrap_or_default();
+            claim
+        })
+    }
+
+    /// XINFO STREAM.
+    pub fn xinfo_stream(&self, key: &str) -> Result<StreamInfo, BackendError> {
+        let _guard = self.locks.read([key]);
+        let info = self.read_stream(key, |stream| {
+            let entry = |(id, fields): (&StreamId, &StreamFields)| (*id, fields.clone());
+            StreamInfo {
+                length: stream.len(),
+                last_id: stream.last_id,
+                max_deleted_id: stream.max_deleted_id,
+                entries_added: stream.entries_added,
+                first_id: stream.first_id(),
+                groups: stream.groups.len(),
+                first_entry: stream.first_key_value().map(entry),
+                last_entry: stream.last_key_value().map(entry),
+            }
+        })?;
+        info.ok_or(BackendError::NoSuchKey)
+    }
+
+    /// XINFO GROUPS.
+    pub fn xinfo_groups(&self, key: &str) -> Result<Vec<GroupInfo>, BackendError> {
+        let _guard = self.locks.read([key]);
+        let groups = self.read_stream(key, |stream| {
+            let counters = stream.counters();
+            stream
+                .groups
+                .iter()
+                .map(|(name, group)| GroupInfo {
+                    name: name.clone(),
+                    consumers: group.consumers.len(),
+                    pending: group.pending.len(),
+                    last_id: group.last_id,
+                    entries_read: group.entries_read,
+                    lag: counters.lag(group),
+                })
+                .collect()
+        })?;
+        groups.ok_or(BackendError::NoSuchKey)
+    }
+
+    /// XINFO CONSUMERS.
+    pub fn xinfo_consumers(
+        &self,
+        key: &str,
+        group: &str,
+    ) -> Result<Vec<ConsumerInfo>, BackendError> {
+        let _guard = self.locks.read([key]);
+        let now = self.now_ms();
+        let consumers = self.read_stream(key, |stream| {
+            let group = stream
+                .groups
+                .get(group)
+                .ok_or_else(|| no_such_group(key, group))?;
+            Ok(group
+                .consumers
+                .iter()
+                .map(|(name, consumer)| ConsumerInfo {
+                    name: name.clone(),
+                    pending: consumer.pending.len(),
+                    idle: now.saturating_sub(consumer.seen_at),
+                    inactive: consumer.active_at.map(|at| now.saturating_sub(at)),
+                })
+                .collect())
+        })?;
+        consumers.ok_or(BackendError::NoSuchKey)?
+    }
+
+    // run `f` over the entries of the stream and the group, NOGROUP if either doesn't exist
+    fn read_group<T>(
+        &self,
+        key: &str,
+        group: &str,
+        f: impl FnOnce(&BTreeMap<StreamId, StreamFields>, &ConsumerGroup) -> T,
+    ) -> Result<T, BackendError> {
+        let ret = self.read_stream(key, |stream| {
+            let group = stream
+                .groups
+                .get(group)
+                .ok_or_else(|| no_group(key, group))?;
+            Ok(f(&stream.entries, group))
+        })?;
+        ret.ok_or_else(|| no_group(key, group))?
+    }
+
+    fn write_group<T>(
+        &self,
+        key: &str,
+        group: &str,
+        f: impl FnOnce(&BTreeMap<StreamId, StreamFields>, &mut ConsumerGroup) -> T,
+    ) -> Result<T, BackendError> {
+        let ret = self.write_value::<Stream, _>(key, false, |stream| {
+            let group = stream
+                .groups
+                .get_mut(group)
+                .ok_or_else(|| no_group(key, group))?;
+            Ok(f(&stream.entries, group))
+        })?;
+        ret.ok_or_else(|| no_group(key, group))
+    }
+
+    fn read_stream<T>(
+        &self,
+        key: &str,
+        f: impl FnOnce(&Stream) -> T,
+    ) -> Result<Option<T>, BackendError> {
+        match self.lookup(key).as_deref() {
+            Some(Entry {
+                value: RedisValue::Stream(stream),
+                ..
+            }) => Ok(Some(f(stream))),
+            Some(_) => Err(BackendError::WrongType),
+            None => Ok(None),
+        }
+    }
+}
+
+// the error of the commands reading or claiming through a group
+fn no_group(key: &str, group: &str) -> BackendError {
+    BackendError::NoGroup(key.to_string(), group.to_string())
+}
+
+// the error of the commands managing a group
+fn no_such_group(key: &str, group: &str) -> BackendError {
+    BackendError::NoSuchGroup(key.to_string(), group.to_string())
+}
+
+// the ID of a new entry, greater than the last one
+fn next_id(last: StreamId, id: XAddId, now: u64) -> Result<StreamId, BackendError> {
+    let id = match id {
+        // the clock going back reuses the last time
+        XAddId::Auto if now > last.ms => StreamId::new(now, 0),
+        XAddId::Auto => last.next().ok_or(BackendError::StreamExhausted)?,
+        XAddId::AutoSeq(ms) if ms > last.ms => StreamId::new(ms, 0),
+        XAddId::AutoSeq(ms) if ms == last.ms => match last.seq.checked_add(1) {
+            Some(seq) => StreamId::new(ms, seq),
+            None => return Err(BackendError::StreamIdTooSmall),
+        },
+        XAddId::AutoSeq(_) => return Err(BackendError::StreamIdTooSmall),
+        XAddId::Explicit(id) => id,
+    };
+    if id == StreamId::MIN {
+        return Err(BackendError::StreamIdZero);
+    }
+    if id <= last {
+        return Err(BackendError::StreamIdTooSmall);
+    }
+    Ok(id)
+}
+
+// remove the oldest entries past the threshold, returns how many
+fn trim_stream(stream: &mut Stream, trim: &StreamTrim) -> usize {
+    let excess = match trim.strategy {
+        TrimStrategy::MaxLen(max_len) => stream.len().saturating_sub(max_len),
+        TrimStrategy::MinId(min_id) => stream.range(..min_id).count(),
+    };
+    let count = if trim.approx {
+        let limit = match trim.limit {
+            Some(0) => usize::MAX,
+            Some(limit) => limit,
+            None => NODE_ENTRIES * 100,
+        };
+        excess.min(limit) / NODE_ENTRIES * NODE_ENTRIES
+    } else {
+        excess
+    };
+    for _ in 0..count {
+        stream.pop_first();
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{BulkString, ManualClock};
+
+    fn fields(n: usize) -> StreamFields {
+        vec![("n".to_string(), BulkString::from(n.to_string()).into())]
+    }
+
+    fn ids(entries: Vec<(StreamId, StreamFields)>) -> Vec<String> {
+        entries.into_iter().map(|(id, _)| id.to_string()).collect()
+    }
+
+    fn group_ids(reads: StreamReads<Option<StreamFields>>) -> Vec<String> {
+        reads
+            .into_iter()
+            .flat_map(|(_, entries)| entries)
+            .map(|(id, fields)| match fields {
+                Some(_) => id.to_string(),
+                None => format!("{id} deleted"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_stream_ids() {
+        let id = StreamId::new(5, u64::MAX);
+        assert_eq!(id.next(), Some(StreamId::new(6, 0)));
+        assert_eq!(StreamId::new(6, 0).prev(), Some(id));
+        assert_eq!(StreamId::MAX.next(), None);
+        assert_eq!(StreamId::MIN.prev(), None);
+        assert!(StreamId::new(1, 9) < StreamId::new(2, 0));
+        assert_eq!(id.to_string(), "5-18446744073709551615");
+
+        // the last ID survives the removal of its entry
+        let mut stream = Stream::default();
+        stream.insert(StreamId::new(1, 0), vec![("f".to_string(), b"v".into())]);
+        stream.insert(id, vec![]);
+        assert!(stream.remove(id).is_some());
+        assert_eq!(stream.last_id(), id);
+        assert_eq!(stream.len(), 1);
+        assert_eq!(stream.max_deleted_id, id);
+        assert_eq!(stream.entries_added, 2);
+    }
+
+    #[test]
+    fn test_xadd_ids() {
+        let backend = Backend::with_clock(Arc::new(ManualClock::new(1000)));
+        let add = |id| backend.xadd("s", id, fields(0), false, None);
+        assert_eq!(add(XAddId::Auto), Ok(Some(StreamId::new(1000, 0))));
+        assert_eq!(add(XAddId::Auto), Ok(Some(StreamId::new(1000, 1))));
+        assert_eq!(add(XAddId::AutoSeq(1000)), Ok(Some(StreamId::new(1000, 2))));
+        assert_eq!(add(XAddId::AutoSeq(2000)), Ok(Some(StreamId::new(2000, 0))));
+        // the clock is behind the last ID
+        assert_eq!(add(XAddId::Auto), Ok(Some(StreamId::new(2000, 1))));
+        assert_eq!(
+            add(XAddId::Explicit(StreamId::new(2000, 1))),
+            Err(BackendError::StreamIdTooSmall)
+        );
+        assert_eq!(add(XAddId::AutoSeq(5)), Err(BackendError::StreamIdTooSmall));
+        assert_eq!(
+            add(XAddId::Explicit(StreamId::new(3000, 7))),
+            Ok(Some(StreamId::new(3000, 7)))
+        );
+        assert_eq!(backend.xlen("s"), Ok(6));
+
+        assert_eq!(
+            backend.xadd("new", XAddId::AutoSeq(0), fields(0), false, None),
+            Ok(Some(StreamId::new(0, 1)))
+        );
+        assert_eq!(
+            backend.xadd(
+                "zero",
+                XAddId::Explicit(StreamId::MIN),
+                fields(0),
+                false,
+                None
+            ),
+            Err(BackendError::StreamIdZero)
+        );
+        assert_eq!(
+            backend.xadd("missing", XAddId::Auto, fields(0), true, None),
+            Ok(None)
+        );
+        assert_eq!(backend.key_type("missing"), "none");
+
+        let last = StreamId::new(u64::MAX, u64::MAX);
+        backend
+            .xadd("full", XAddId::Explicit(last), fields(0), false, None)
+            .unwrap();
+        assert_eq!(
+            backend.xadd("full", XAddId::Auto, fields(0), false, None),
+            Err(BackendError::StreamExhausted)
+        );
+    }
+
+    #[test]
+    fn test_xrange_and_xdel() {
+        let backend = Backend::new();
+        for seq in 1..=5 {
+            let id = XAddId::Explicit(StreamId::new(1, seq));
+            backend
+                .xadd("s", id, fields(seq as usize), false, None)
+                .unwrap();
+        }
+        let (start, end) = (StreamId::new(1, 2), StreamId::MAX);
+        assert_eq!(
+            ids(backend.xrange("s", start, end, false, Some(2)).unwrap()),
+            vec!["1-2", "1-3"]
+        );
+        assert_eq!(
+            ids(backend.xrange("s", start, end, true, Some(2)).unwrap()),
+            vec!["1-5", "1-4"]
+        );
+        assert_eq!(backend.xrange("s", end, start, false, None), Ok(vec![]));
+
+        let deleted = [StreamId::new(1, 5), StreamId::new(1, 9)];
+        assert_eq!(backend.xdel("s", &deleted), Ok(1));
+        // the stream keeps its last ID, and stays even once empty
+        assert_eq!(
+            backend.xadd("s", XAddId::AutoSeq(1), fields(0), false, None),
+            Ok(Some(StreamId::new(1, 6)))
+        );
+        let all = backend.xrange("s", StreamId::MIN, StreamId::MAX, false, None);
+        let all = all
+            .unwrap()
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+        assert_eq!(backend.xdel("s", &all), Ok(5));
+        assert_eq!(backend.key_type("s"), "stream");
+        assert_eq!(backend.xlen("s"), Ok(0));
+    }
+
+    #[test]
+    fn test_xtrim() {
+        let backend = Backend::new();
+        for seq in 1..=250 {
+            let id = XAddId::Explicit(StreamId::new(seq, 0));
+            backend.xadd("s", id, fields(0), false, None).unwrap();
+        }
+        let trim = |strategy, approx, limit| StreamTrim {
+            strategy,
+            approx,
+            limit,
+        };
+        // approximate trimming removes whole nodes only
+        assert_eq!(
+            backend.xtrim("s", &trim(TrimStrategy::MaxLen(60), true, None)),
+            Ok(100)
+        );
+        assert_eq!(
+            backend.xtrim("s", &trim(TrimStrategy::MaxLen(60), true, Some(50))),
+            Ok(0)
+        );
+        let min_id = TrimStrategy::MinId(StreamId::new(200, 0));
+        assert_eq!(backend.xtrim("s", &trim(min_id, false, None)), Ok(99));
+        assert_eq!(
+            backend.xtrim("s", &trim(TrimStrategy::MaxLen(10), false, None)),
+            Ok(41)
+        );
+        assert_eq!(backend.xlen("s"), Ok(10));
+
+        let capped = StreamTrim {
+            strategy: TrimStrategy::MaxLen(1),
+            approx: false,
+            limit: None,
+        };
+        backend
+            .xadd("t", XAddId::Auto, fields(0), false, Some(capped))
+            .unwrap();
+        backend
+            .xadd("t", XAddId::Auto, fields(1), false, Some(capped))
+            .unwrap();
+        assert_eq!(backend.xlen("t"), Ok(1));
+    }
+
+    #[test]
+    fn test_consumer_groups() {
+        let backend = Backend::new();
+        assert_eq!(
+            backend.xgroup_create("s", "g", None, false, None),
+            Err(BackendError::StreamKeyMissing)
+        );
+        assert_eq!(backend.xgroup_create("s", "g", None, true, None), Ok(()));
+        assert_eq!(
+            backend.xgroup_create("s", "g", None, true, None),
+            Err(BackendError::BusyGroup)
+        );
+        for seq in 1..=3 {
+            let id = XAddId::Explicit(StreamId::new(1, seq));
+            backend.xadd("s", id, fields(0), false, None).unwrap();
+        }
+
+        let new = [("s".to_string(), None)];
+        let read = |consumer, streams: &[(String, Option<StreamId>)], count| {
+            backend.xreadgroup("g", consumer, streams, count, false)
+        };
+        assert_eq!(
+            group_ids(read("alice", &new, Some(2)).unwrap()),
+            ["1-1", "1-2"]
+        );
+        assert_eq!(group_ids(read("bob", &new, None).unwrap()), ["1-3"]);
+        // nothing new: the stream is left out
+        assert_eq!(read("bob", &new, None), Ok(vec![]));
+
+        // the history of a consumer is what it has pending
+        let history = [("s".to_string(), Some(StreamId::MIN))];
+        assert_eq!(
+            group_ids(read("alice", &history, None).unwrap()),
+            ["1-1", "1-2"]
+        );
+        assert_eq!(backend.xack("s", "g", &[StreamId::new(1, 1)]), Ok(1));
+        assert_eq!(backend.xack("s", "g", &[StreamId::new(1, 1)]), Ok(0));
+        assert_eq!(backend.xack("s", "missing", &[StreamId::new(1, 2)]), Ok(0));
+        backend.xdel("s", &[StreamId::new(1, 2)]).unwrap();
+        assert_eq!(
+            group_ids(read("alice", &history, None).unwrap()),
+            ["1-2 deleted"]
+        );
+        // an empty history still names the stream
+        assert_eq!(
+            read("carol", &history, None),
+            Ok(vec![("s".to_string(), vec![])])
+        );
+
+        let summary = backend.xpending_summary("s", "g").unwrap();
+        assert_eq!(summary.count, 2);
+        assert_eq!(
+            summary.range,
+            Some((StreamId::new(1, 2), StreamId::new(1, 3)))
+        );
+        assert_eq!(
+            summary.consumers,
+            vec![("alice".to_string(), 1), ("bob".to_string(), 1)]
+        );
+        // reading 1-2 again counted as a delivery, not once it was deleted
+        let range = PendingRange {
+            start: StreamId::MIN,
+            end: StreamId::MAX,
+            count: 10,
+            consumer: Some("alice".to_string()),
+            min_idle: 0,
+        };
+        let pending = backend.xpending("s", "g", &range).unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].deliveries, 2);
+
+        assert_eq!(
+            read("alice", &[("t".to_string(), None)], None),
+            Err(BackendError::NoGroup("t".to_string(), "g".to_string()))
+        );
+        assert_eq!(backend.xgroup_create_consumer("s", "g", "dave"), Ok(true));
+        assert_eq!(backend.xgroup_create_consumer("s", "g", "dave"), Ok(false));
+        assert_eq!(backend.xgroup_del_consumer("s", "g", "bob"), Ok(1));
+        assert_eq!(backend.xpending_summary("s", "g").unwrap().count, 1);
+        assert_eq!(
+            backend.xgroup_del_consumer("s", "nope", "bob"),
+            Err(BackendError::NoSuchGroup(
+                "s".to_string(),
+                "nope".to_string()
+            ))
+        );
+        assert_eq!(backend.xgroup_destroy("s", "g"), Ok(true));
+        assert_eq!(backend.xgroup_destroy("s", "g"), Ok(false));
+    }
+
+    #[test]
+    fn test_group_lag() {
+        let backend = Backend::new();
+        for seq in 1..=5 {
+            let id = XAddId::Explicit(StreamId::new(1, seq));
+            backend.xadd("s", id, fields(0), false, None).unwrap();
+        }
+        backend
+            .xgroup_create("s", "g", Some(StreamId::MIN), false, None)
+            .unwrap();
+        let lag = || backend.xinfo_groups("s").unwrap()[0].clone();
+        // estimated from the first entry
+        assert_eq!(lag().lag, Some(5));
+
+        let new = [("s".to_string(), None)];
+        backend.xreadgroup("g", "c", &new, Some(2), true).unwrap();
+        assert_eq!((lag().entries_read, lag().lag), (Some(2), Some(3)));
+        // a deletion ahead of the group makes the lag unknown until it is read past
+        backend.xdel("s", &[StreamId::new(1, 4)]).unwrap();
+        assert_eq!(lag().lag, None);
+        backend.xreadgroup("g", "c", &new, None, true).unwrap();
+        assert_eq!((lag().entries_read, lag().lag), (Some(5), Some(0)));
+
+        let info = backend.xinfo_stream("s").unwrap();
+        assert_eq!(info.length, 4);
+        assert_eq!(info.entries_added, 5);
+        assert_eq!(info.max_deleted_id, StreamId::new(1, 4));
+        assert_eq!(info.first_id, StreamId::new(1, 1));
+        assert_eq!(info.groups, 1);
+        assert_eq!(
+            backend.xinfo_stream("missing"),
+            Err(BackendError::NoSuchKey)
+        );
+    }
+
+    #[test]
+    fn test_xclaim_and_xautoclaim() {
+        let clock = Arc::new(ManualClock::new(1000));
+        let backend = Backend::with_clock(clock.clone());
+        backend
+            .xgroup_create("s", "g", Some(StreamId::MIN), true, None)
+            .unwrap();
+        for seq in 1..=4 {
+            let id = XAddId::Explicit(StreamId::new(1, seq));
+            backend.xadd("s", id, fields(0), false, None).unwrap();
+        }
+        let new = [("s".to_string(), None)];
+        backend.xreadgroup("g", "alice", &new, None, false).unwrap();
+        clock.advance(Duration::from_millis(100));
+
+        let both = [StreamId::new(1, 1), StreamId::new(1, 2)];
+        let options = ClaimOptions::default();
+        // not idle for long enough
+        assert_eq!(
+            backend.xclaim("s", "g", "bob", 500, &both, &options),
+            Ok(vec![])
+        );
+        let claimed = backend
+            .xclaim("s", "g", "bob", 100, &both, &options)
+            .unwrap();
+        assert_eq!(claimed.len(), 2);
+        let consumers = backend.xinfo_consumers("s", "g").unwrap();
+        assert_eq!(
+            consumers
+                .iter()
+                .map(|c| (c.name.as_str(), c.pending, c.idle, c.inactive))
+                .collect::<Vec<_>>(),
+            vec![("alice", 2, 100, Some(100)), ("bob", 2, 0, Some(0))]
+        );
+
+        // FORCE claims an entry no one has pending, JUSTID leaves the delivery count alone
+        backend.xack("s", "g", &[StreamId::new(1, 3)]).unwrap();
+        let forced = ClaimOptions {
+            force: true,
+            just_id: true,
+            last_id: Some(StreamId::new(9, 0)),
+            ..ClaimOptions::default()
+        };
+        let claimed = backend.xclaim("s", "g", "bob", 0, &[StreamId::new(1, 3)], &forced);
+        assert_eq!(claimed.unwrap(), vec![(StreamId::new(1, 3), vec![])]);
+        assert_eq!(
+            backend.xinfo_groups("s").unwrap()[0].last_id,
+            StreamId::new(9, 0)
+        );
+
+        clock.advance(Duration::from_millis(100));
+        backend.xdel("s", &[StreamId::new(1, 2)]).unwrap();
+        let claim = backend
+            .xautoclaim("s", "g", "carol", 50, StreamId::MIN, 1, false)
+            .unwrap();
+        assert_eq!(ids(claim.claimed), vec!["1-1"]);
+        assert_eq!(claim.next, StreamId::new(1, 2));
+        let claim = backend
+            .xautoclaim("s", "g", "carol", 50, claim.next, 10, false)
+            .unwrap();
+        assert_eq!(ids(claim.claimed), vec!["1-3", "1-4"]);
+        assert_eq!(claim.deleted, vec![StreamId::new(1, 2)]);
+        assert_eq!(claim.next, StreamId::MIN);
+        assert_eq!(backend.xpending_summary("s", "g").unwrap().count, 3);
+
+        assert_eq!(
+            backend.xclaim("s", "nope", "bob", 0, &both, &options),
+            Err(BackendError::NoGroup("s".to_string(), "nope".to_string()))
+        );
+    }
+}
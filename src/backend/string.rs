@@ -0,0 +1,348 @@
+use dashmap::mapref::entry::Entry as MapEntry;
+
+use super::{Backend, BackendError, Entry, RedisValue};
+use crate::{BulkString, RespFrame};
+
+/// When SET is allowed to write: always, only for a new key (NX) or only over an existing one (XX).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SetCondition {
+    #[default]
+    Always,
+    IfNotExists,
+    IfExists,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SetOptions {
+    pub condition: SetCondition,
+    // absolute deadline in unix milliseconds
+    pub expire_at: Option<u64>,
+    pub keep_ttl: bool,
+    // fetch the previous string value, the write fails with WRONGTYPE if it is not a string
+    pub get: bool,
+}
+
+impl Backend {
+    /// SET with its options applied atomically under the key's lock. Returns whether the value
+    /// was written and, if `get` was requested, the previous value.
+    pub fn set_with_options(
+        &self,
+        key: String,
+        value: RespFrame,
+        opts: SetOptions,
+    ) -> Result<(bool, Option<RespFrame>), BackendError> {
+        let _guard = self.locks.write([key.as_str()]);
+        let entry = self.lookup_mut(key);
+        let (old_value, old_expire) = match &entry {
+            MapEntry::Occupied(e) => (Some(&e.get().value), e.get().expire_at),
+            MapEntry::Vacant(_) => (None, None),
+        };
+        let old = match old_value {
+            Some(RedisValue::String(v)) if opts.get => Some(v.clone()),
+            Some(_) if opts.get => return Err(BackendError::WrongType),
+            _ => None,
+        };
+        let apply = match opts.condition {
+            SetCondition::Always => true,
+            SetCondition::IfNotExists => old_value.is_none(),
+            SetCondition::IfExists => old_value.is_some(),
+        };
+        if apply {
+            let expire_at = if opts.keep_ttl {
+                old_expire
+            } else {
+                opts.expire_at
+            };
+            self.track_expire(entry.key(), old_expire, expire_at);
+            let old_fields = old_value.is_some_and(RedisValue::has_volatile_fields);
+            self.track_field_expire(entry.key(), old_fields, false);
+            if old_value.is_none() {
+                self.track_key(entry.key(), true);
+            }
+            entry.insert(Entry::new(value, expire_at));
+        }
+        Ok((apply, old))
+    }
+
+    /// MGET: the values of the keys, None for missing keys and keys holding another type.
+    pub fn mget(&self, keys: &[String]) -> Vec<Option<RespFrame>> {
+        let _guard = self.locks.read(keys.iter().map(String::as_str));
+        keys.iter()
+            .map(|key| match self.lookup(key).as_deref() {
+                Some(Entry {
+                    value: RedisValue::String(v),
+                    ..
+                }) => Some(v.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// MSET and MSETNX: write every pair at once, no reader sees only part of them. With `nx`
+    /// nothing is written if any of the keys exists. Returns whether the pairs were written.
+    pub fn mset(&self, pairs: Vec<(String, RespFrame)>, nx: bool) -> bool {
+        let _guard = self.locks.write(pairs.iter().map(|(k, _)| k.as_str()));
+        if nx && pairs.iter().any(|(k, _)| self.lookup(k).is_some()) {
+            return false;
+        }
+        for (key, value) in pairs {
+            self.insert_entry(key, Entry::new(value, None));
+        }
+        true
+    }
+
+    /// INCRBY: add `delta` to the integer stored at the key, a missing key counts as 0.
+    /// The key keeps its TTL.
+    pub fn incr_by(&self, key: String, delta: i64) -> Result<i64, BackendError> {
+        self.update_number(key, |current| {
+            let current = match current {
+                Some(v) => parse_i64(v).ok_or(BackendError::NotInteger)?,
+                None => 0,
+            };
+            let ret = current.checked_add(delta).ok_or(BackendError::Overflow)?;
+            Ok((ret, ret.to_string()))
+        })
+    }
+
+    /// INCRBYFLOAT: add `delta` to the number stored at the key, a missing key counts as 0.
+    /// Returns the new value formatted the way it is stored.
+    pub fn incr_by_float(&self, key: String, delta: f64) -> Result<String, BackendError> {
+        self.update_number(key, |current| {
+            let current = match current {
+                Some(v) => parse_f64(v).ok_or(BackendError::NotFloat)?,
+                None => 0.0,
+            };
+            let ret = current + delta;
+            if !ret.is_finite() {
+                return Err(BackendError::NanOrInfinity);
+            }
+            let ret = ret.to_string();
+            Ok((ret.clone(), ret))
+        })
+    }
+
+    // read-modify-write of a string value while holding the key's lock
+    fn update_number<T>(
+        &self,
+        key: String,
+        f: impl FnOnce(Option<&RespFrame>) -> Result<(T, String), BackendError>,
+    ) -> Result<T, BackendError> {
+        let _guard = self.locks.write([key.as_str()]);
+        match self.lookup_mut(key) {
+            MapEntry::Occupied(mut e) => match &mut e.get_mut().value {
+                RedisValue::String(v) => {
+                    let (ret, stored) = f(Some(v))?;
+                    *v = BulkString::from(stored).into();
+                    Ok(ret)
+                }
+                _ => Err(BackendError::WrongType),
+            },
+            MapEntry::Vacant(e) => {
+                let (ret, stored) = f(None)?;
+                self.track_key(e.key(), true);
+                e.insert(Entry::new(RespFrame::from(BulkString::from(stored)), None));
+                Ok(ret)
+            }
+        }
+    }
+}
+
+pub(super) fn parse_i64(frame: &RespFrame) -> Option<i64> {
+    match frame {
+        RespFrame::Integer(n) => Some(*n),
+        RespFrame::BulkString(s) => std::str::from_utf8(s).ok()?.parse().ok(),
+        _ => None,
+    }
+}
+
+pub(super) fn parse_f64(frame: &RespFrame) -> Option<f64> {
+    match frame {
+        RespFrame::Integer(n) => Some(*n as f64),
+        RespFrame::Double(n) => Some(*n),
+        RespFrame::BulkString(s) => std::str::from_utf8(s)
+            .ok()?
+            .parse::<f64>()
+            .ok()
+            .filter(|n| !n.is_nan()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_set_with_condition() {
+        let backend = Backend::new();
+        let nx = SetOptions {
+            condition: SetCondition::IfNotExists,
+            ..Default::default()
+        };
+        let xx = SetOptions {
+            condition: SetCondition::IfExists,
+            ..Default::default()
+        };
+        assert_eq!(
+            backend.set_with_options("key".to_string(), b"v1".into(), xx.clone()),
+            Ok((false, None))
+        );
+        assert_eq!(
+            backend.set_with_options("key".to_string(), b"v1".into(), nx.clone()),
+            Ok((true, None))
+        );
+        assert_eq!(
+            backend.set_with_options("key".to_string(), b"v2".into(), nx),
+            Ok((false, None))
+        );
+        assert_eq!(
+            backend.set_with_options("key".to_string(), b"v2".into(), xx),
+            Ok((true, None))
+        );
+        assert_eq!(backend.get("key"), Ok(Some(b"v2".into())));
+    }
+
+    #[test]
+    fn test_set_get_and_keep_ttl() {
+        let backend = Backend::new();
+        backend.set_with_expire("key".to_string(), b"v1".into(), Some(u64::MAX));
+        let opts = SetOptions {
+            keep_ttl: true,
+            get: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            backend.set_with_options("key".to_string(), b"v2".into(), opts.clone()),
+            Ok((true, Some(b"v1".into())))
+        );
+        assert_eq!(backend.expire_time("key"), Some(Some(u64::MAX)));
+
+        backend
+            .db
+            .insert("hash".to_string(), Entry::new(HashMap::new(), None));
+        assert_eq!(
+            backend.set_with_options("hash".to_string(), b"v2".into(), opts),
+            Err(BackendError::WrongType)
+        );
+        assert_eq!(backend.key_type("hash"), "hash");
+    }
+
+    #[test]
+    fn test_incr_by() {
+        let backend = Backend::new();
+        assert_eq!(backend.incr_by("counter".to_string(), 1), Ok(1));
+        assert_eq!(backend.incr_by("counter".to_string(), -11), Ok(-10));
+        assert_eq!(backend.get("counter"), Ok(Some(b"-10".into())));
+
+        backend.set("max".to_string(), RespFrame::Integer(i64::MAX));
+        assert_eq!(
+            backend.incr_by("max".to_string(), 1),
+            Err(BackendError::Overflow)
+        );
+        assert_eq!(backend.get("max"), Ok(Some(RespFrame::Integer(i64::MAX))));
+
+        backend.set("text".to_string(), b"abc".into());
+        assert_eq!(
+            backend.incr_by("text".to_string(), 1),
+            Err(BackendError::NotInteger)
+        );
+        backend.set("float".to_string(), b"1.5".into());
+        assert_eq!(
+            backend.incr_by("float".to_string(), 1),
+            Err(BackendError::NotInteger)
+        );
+    }
+
+    #[test]
+    fn test_incr_by_keeps_ttl() {
+        let backend = Backend::new();
+        backend.set_with_expire("counter".to_string(), b"41".into(), Some(u64::MAX));
+        assert_eq!(backend.incr_by("counter".to_string(), 1), Ok(42));
+        assert_eq!(backend.expire_time("counter"), Some(Some(u64::MAX)));
+    }
+
+    #[test]
+    fn test_incr_by_float() {
+        let backend = Backend::new();
+        backend.set("n".to_string(), b"10.50".into());
+        assert_eq!(
+            backend.incr_by_float("n".to_string(), 0.1),
+            Ok("10.6".to_string())
+        );
+        assert_eq!(
+            backend.incr_by_float("n".to_string(), -5.6),
+            Ok("5".to_string())
+        );
+        assert_eq!(backend.incr_by("n".to_string(), 1), Ok(6));
+        assert_eq!(
+            backend.incr_by_float("n".to_string(), f64::INFINITY),
+            Err(BackendError::NanOrInfinity)
+        );
+
+        backend.set("text".to_string(), b"abc".into());
+        assert_eq!(
+            backend.incr_by_float("text".to_string(), 1.0),
+            Err(BackendError::NotFloat)
+        );
+    }
+
+    #[test]
+    fn test_mset_mget() {
+        let backend = Backend::new();
+        backend
+            .hset(
+                "hash".to_string(),
+                vec![("field".to_string(), b"value".into())],
+            )
+            .unwrap();
+        assert!(backend.mset(
+            vec![
+                ("a".to_string(), b"1".into()),
+                ("b".to_string(), b"2".into()),
+                ("a".to_string(), b"3".into()),
+            ],
+            false
+        ));
+        let keys = ["a", "b", "c", "hash"].map(String::from);
+        assert_eq!(
+            backend.mget(&keys),
+            vec![Some(b"3".into()), Some(b"2".into()), None, None]
+        );
+
+        assert!(!backend.mset(
+            vec![
+                ("c".to_string(), b"1".into()),
+                ("a".to_string(), b"1".into())
+            ],
+            true
+        ));
+        assert_eq!(backend.get("c"), Ok(None));
+        assert!(backend.mset(vec![("c".to_string(), b"1".into())], true));
+        assert_eq!(backend.get("c"), Ok(Some(b"1".into())));
+    }
+
+    #[test]
+    fn test_mset_is_atomic_for_readers() {
+        let backend = Backend::new();
+        let keys = (0..16).map(|i| format!("key{i}")).collect::<Vec<_>>();
+        let writer = {
+            let backend = backend.clone();
+            let keys = keys.clone();
+            std::thread::spawn(move || {
+                for round in 0..200 {
+                    let value = RespFrame::Integer(round);
+                    backend.mset(
+                        keys.iter().map(|k| (k.clone(), value.clone())).collect(),
+                        false,
+                    );
+                }
+            })
+        };
+        while !writer.is_finished() {
+            let values = backend.mget(&keys);
+            assert!(values.windows(2).all(|w| w[0] == w[1]), "{values:?}");
+        }
+        writer.join().unwrap();
+    }
+}
@@ -0,0 +1,512 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::ops::Deref;
+
+use dashmap::mapref::entry::Entry as MapEntry;
+
+use super::ranked::RankedSet;
+use super::scan::{ScanIndex, ScanPage};
+use super::{Backend, BackendError, Stream};
+use crate::RespFrame;
+
+/// A value stored in the keyspace, tagged with its Redis data type.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RedisValue {
+    String(RespFrame),
+    Hash(HashValue),
+    List(VecDeque<RespFrame>),
+    Set(HashSet<String>),
+    ZSet(SortedSet),
+    Stream(Stream),
+}
+
+/// The fields of a hash, plus the deadlines of the ones set to expire with HEXPIRE.
+///
+/// Reads go through `Deref` to the fields. Writes go through the methods below so the deadlines
+/// and the scan index never refer to a missing field.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HashValue {
+    fields: HashMap<String, RespFrame>,
+    // the field names ordered by scan hash, so an HSCAN step does not walk the whole hash
+    scan: ScanIndex,
+    // deadline in unix milliseconds of each volatile field, and the same pairs ordered by
+    // deadline so expired fields are found without a scan
+    expires: HashMap<String, u64>,
+    deadlines: BTreeSet<(u64, String)>,
+}
+
+/// The members of a sorted set with their scores, also kept ordered by score then member.
+///
+/// Like `HashValue`, reads of the scores go through `Deref` and writes through the methods below
+/// so both views stay in sync.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SortedSet {
+    scores: HashMap<String, f64>,
+    order: RankedSet<(Score, String)>,
+}
+
+// a score ordered with `total_cmp`, which is a total order since scores are never NaN
+#[derive(Debug, Clone, Copy)]
+struct Score(f64);
+
+/// A keyspace slot: the value plus its optional deadline in unix milliseconds.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Entry {
+    pub(crate) value: RedisValue,
+    pub(crate) expire_at: Option<u64>,
+}
+
+impl RedisValue {
+    /// The name reported by the TYPE command.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            RedisValue::String(_) => "string",
+            RedisValue::Hash(_) => "hash",
+            RedisValue::List(_) => "list",
+            RedisValue::Set(_) => "set",
+            RedisValue::ZSet(_) => "zset",
+            RedisValue::Stream(_) => "stream",
+        }
+    }
+
+    /// Whether the value is a hash with fields set to expire.
+    pub fn has_volatile_fields(&self) -> bool {
+        matches!(self, RedisValue::Hash(hash) if hash.has_volatile())
+    }
+
+    /// Roughly how much work dropping the value takes: its number of elements.
+    pub fn free_effort(&self) -> usize {
+        match self {
+            RedisValue::String(_) => 1,
+            RedisValue::Hash(hash) => hash.len(),
+            RedisValue::List(list) => list.len(),
+            RedisValue::Set(set) => set.len(),
+            RedisValue::ZSet(zset) => zset.len(),
+            RedisValue::Stream(stream) => stream.len(),
+        }
+    }
+}
+
+impl HashValue {
+    /// Set a field, dropping its deadline like HSET does. Returns the previous value.
+    pub fn insert(&mut self, field: String, value: RespFrame) -> Option<RespFrame> {
+        self.persist(&field);
+        self.update(field, value)
+    }
+
+    /// Set a field, keeping its deadline like HINCRBY does.
+    pub fn update(&mut self, field: String, value: RespFrame) -> Option<RespFrame> {
+        if !self.fields.contains_key(&field) {
+            self.scan.insert(&field);
+        }
+        self.fields.insert(field, value)
+    }
+
+    pub fn remove(&mut self, field: &str) -> Option<RespFrame> {
+        self.persist(field);
+        let value = self.fields.remove(field)?;
+        self.scan.remove(field);
+        Some(value)
+    }
+
+    /// One HSCAN step over the fields, costs O(log n + count).
+    pub fn scan(&self, cursor: u64, count: usize) -> ScanPage<(&str, &RespFrame)> {
+        let (next, fields) = self.scan.page(cursor, count);
+        let page = fields
+            .into_iter()
+            .filter_map(|field| self.fields.get_key_value(field))
+            .map(|(field, value)| (field.as_str(), value))
+            .collect();
+        (next, page)
+    }
+
+    /// The deadline of a field, None if it does not expire.
+    pub fn expire_time(&self, field: &str) -> Option<u64> {
+        self.expires.get(field).copied()
+    }
+
+    /// Set the deadline of an existing field. Returns false if the field does not exist.
+    pub fn set_expire(&mut self, field: &str, at: u64) -> bool {
+        if !self.fields.contains_key(field) {
+            return false;
+        }
+        self.persist(field);
+        self.expires.insert(field.to_string(), at);
+        self.deadlines.insert((at, field.to_string()));
+        true
+    }
+
+    /// Drop the deadline of a field. Returns false if it had none.
+    pub fn persist(&mut self, field: &str) -> bool {
+        match self.expires.remove(field) {
+            Some(at) => {
+                self.deadlines.remove(&(at, field.to_string()));
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn has_volatile(&self) -> bool {
+        !self.expires.is_empty()
+    }
+
+    // same rule as keys: a field is still alive at the exact millisecond of its deadline
+    pub(crate) fn has_expired(&self, now: u64) -> bool {
+        self.deadlines.first().is_some_and(|(at, _)| now > *at)
+    }
+
+    /// Remove the fields whose deadline passed, returns how many.
+    pub(crate) fn remove_expired(&mut self, now: u64) -> usize {
+        let mut removed = 0;
+        while let Some((at, _)) = self.deadlines.first() {
+            if now <= *at {
+                break;
+            }
+            let (_, field) = self.deadlines.pop_first().expect("checked above");
+            self.expires.remove(&field);
+            self.fields.remove(&field);
+            self.scan.remove(&field);
+            removed += 1;
+        }
+        removed
+    }
+}
+
+impl Deref for HashValue {
+    type Target = HashMap<String, RespFrame>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.fields
+    }
+}
+
+impl From<HashMap<String, RespFrame>> for HashValue {
+    fn from(fields: HashMap<String, RespFrame>) -> Self {
+        let mut scan = ScanIndex::default();
+        for field in fields.keys() {
+            scan.insert(field);
+        }
+        HashValue {
+            fields,
+            scan,
+            ..Default::default()
+        }
+    }
+}
+
+impl SortedSet {
+    /// Set the score of a member. Returns its previous score.
+    pub fn insert(&mut self, member: String, score: f64) -> Option<f64> {
+        // -0 and 0 are the same score
+        let score = if score == 0.0 { 0.0 } else { score };
+        let old = self.remove(&member);
+        self.order.insert((Score(score), member.clone()));
+        self.scores.insert(member, score);
+        old
+    }
+
+    pub fn remove(&mut self, member: &str) -> Option<f64> {
+        let score = self.scores.remove(member)?;
+        self.order.remove(&(Score(score), member.to_string()));
+        Some(score)
+    }
+
+    /// The members and their scores, lowest score first and members with the same score in
+    /// lexicographical order.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&str, f64)> {
+        self.range(0, self.len())
+    }
+
+    /// The members ranked from `start` up to but not including `end`, in the order of `iter`.
+    /// Finding the first one is O(log n).
+    pub fn range(&self, start: usize, end: usize) -> impl DoubleEndedIterator<Item = (&str, f64)> {
+        self.order
+            .range(start, end)
+            .map(|(score, member)| (member.as_str(), score.0))
+    }
+
+    /// The number of members of the leading run for which the predicate holds, like
+    /// `slice::partition_point`. O(log n).
+    pub fn partition_point(&self, pred: impl Fn(&str, f64) -> bool) -> usize {
+        self.order
+            .partition_point(|(score, member)| pred(member, score.0))
+    }
+
+    /// Remove the member with the lowest score, or the highest with `max`.
+    pub fn pop(&mut self, max: bool) -> Option<(String, f64)> {
+        let (score, member) = if max {
+            self.order.pop_last()?
+        } else {
+            self.order.pop_first()?
+        };
+        self.scores.remove(&member);
+        Some((member, score.0))
+    }
+
+    /// The position of a member in score order, 0 for the lowest. O(log n).
+    pub fn rank(&self, member: &str) -> Option<usize> {
+        let score = *self.scores.get(member)?;
+        Some(self.order.rank(&(Score(score), member.to_string())))
+    }
+}
+
+impl Deref for SortedSet {
+    type Target = HashMap<String, f64>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.scores
+    }
+}
+
+impl PartialEq for Score {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Score {}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// A collection type stored under a key, as seen by `Backend::write_value`.
+pub(crate) trait Collection: Default + Into<RedisValue> {
+    /// The value if the slot holds this type.
+    fn project(value: &mut RedisValue) -> Option<&mut Self>;
+
+    /// Whether the key must go: an empty collection is never stored, except a stream whose
+    /// last ID still matters.
+    fn is_dead(&self) -> bool;
+}
+
+impl Collection for HashValue {
+    fn project(value: &mut RedisValue) -> Option<&mut Self> {
+        match value {
+            RedisValue::Hash(hash) => Some(hash),
+            _ => None,
+        }
+    }
+
+    fn is_dead(&self) -> bool {
+        self.is_empty()
+    }
+}
+
+impl Collection for VecDeque<RespFrame> {
+    fn project(value: &mut RedisValue) -> Option<&mut Self> {
+        match value {
+            RedisValue::List(list) => Some(list),
+            _ => None,
+        }
+    }
+
+    fn is_dead(&self) -> bool {
+        self.is_empty()
+    }
+}
+
+impl Collection for HashSet<String> {
+    fn project(value: &mut RedisValue) -> Option<&mut Self> {
+        match value {
+            RedisValue::Set(set) => Some(set),
+            _ => None,
+        }
+    }
+
+    fn is_dead(&self) -> bool {
+        self.is_empty()
+    }
+}
+
+impl Collection for SortedSet {
+    fn project(value: &mut RedisValue) -> Option<&mut Self> {
+        match value {
+            RedisValue::ZSet(zset) => Some(zset),
+            _ => None,
+        }
+    }
+
+    fn is_dead(&self) -> bool {
+        self.is_empty()
+    }
+}
+
+impl Collection for Stream {
+    fn project(value: &mut RedisValue) -> Option<&mut Self> {
+        match value {
+            RedisValue::Stream(stream) => Some(stream),
+            _ => None,
+        }
+    }
+
+    fn is_dead(&self) -> bool {
+        false
+    }
+}
+
+impl Backend {
+    // run `f` over the collection stored at the key, a missing key is created if `create` is
+    // set and skipped otherwise. A collection left empty is removed, so the keyspace never
+    // holds one.
+    pub(crate) fn write_value<V: Collection, T>(
+        &self,
+        key: &str,
+        create: bool,
+        f: impl FnOnce(&mut V) -> Result<T, BackendError>,
+    ) -> Result<Option<T>, BackendError> {
+        let (ret, dead) = match self.lookup_mut(key.to_string()) {
+            MapEntry::Occupied(mut e) => {
+                let entry = e.get_mut();
+                let volatile = entry.value.has_volatile_fields();
+                let Some(value) = V::project(&mut entry.value) else {
+                    return Err(BackendError::WrongType);
+                };
+                let ret = f(value);
+                let dead = value.is_dead();
+                self.track_field_expire(key, volatile, entry.value.has_volatile_fields());
+                (ret, dead)
+            }
+            MapEntry::Vacant(e) if create => {
+                let mut value = V::default();
+                let ret = f(&mut value);
+                if ret.is_ok() && !value.is_dead() {
+                    self.track_key(key, true);
+                    e.insert(Entry::new(value, None));
+                }
+                return ret.map(Some);
+            }
+            MapEntry::Vacant(_) => return Ok(None),
+        };
+        // the slot guard is released, removing the key can not deadlock on its shard
+        if dead {
+            self.remove_entry(key);
+        }
+        ret.map(Some)
+    }
+}
+
+impl Entry {
+    pub(crate) fn new(value: impl Into<RedisValue>, expire_at: Option<u64>) -> Self {
+        Entry {
+            value: value.into(),
+            expire_at,
+        }
+    }
+
+    // same rule as Redis: a key is still alive at the exact millisecond of its deadline
+    pub(crate) fn is_expired(&self, now: u64) -> bool {
+        matches!(self.expire_at, Some(at) if now > at)
+    }
+}
+
+impl From<RespFrame> for RedisValue {
+    fn from(frame: RespFrame) -> Self {
+        RedisValue::String(frame)
+    }
+}
+
+impl From<HashValue> for RedisValue {
+    fn from(hash: HashValue) -> Self {
+        RedisValue::Hash(hash)
+    }
+}
+
+impl From<HashMap<String, RespFrame>> for RedisValue {
+    fn from(fields: HashMap<String, RespFrame>) -> Self {
+        RedisValue::Hash(fields.into())
+    }
+}
+
+impl From<VecDeque<RespFrame>> for RedisValue {
+    fn from(list: VecDeque<RespFrame>) -> Self {
+        RedisValue::List(list)
+    }
+}
+
+impl From<SortedSet> for RedisValue {
+    fn from(zset: SortedSet) -> Self {
+        RedisValue::ZSet(zset)
+    }
+}
+
+impl From<Stream> for RedisValue {
+    fn from(stream: Stream) -> Self {
+        RedisValue::Stream(stream)
+    }
+}
+
+impl From<HashSet<String>> for RedisValue {
+    fn from(set: HashSet<String>) -> Self {
+        RedisValue::Set(set)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_value_field_deadlines() {
+        let mut hash = HashValue::default();
+        hash.insert("a".to_string(), b"1".into());
+        hash.insert("b".to_string(), b"2".into());
+        assert!(!hash.set_expire("missing", 100));
+        assert!(hash.set_expire("a", 100));
+        assert!(hash.set_expire("b", 300));
+        assert!(hash.set_expire("b", 200));
+        assert_eq!(hash.expire_time("b"), Some(200));
+
+        // HINCRBY keeps the deadline, HSET drops it
+        hash.update("a".to_string(), b"3".into());
+        assert_eq!(hash.expire_time("a"), Some(100));
+        hash.insert("b".to_string(), b"4".into());
+        assert_eq!(hash.expire_time("b"), None);
+        assert!(hash.has_volatile());
+
+        assert!(!hash.has_expired(100));
+        assert_eq!(hash.remove_expired(100), 0);
+        assert!(hash.has_expired(101));
+        assert_eq!(hash.remove_expired(101), 1);
+        assert_eq!(hash.len(), 1);
+        assert!(!hash.has_volatile());
+        assert!(!hash.persist("b"));
+
+        // the expired field left the scan index with the hash
+        let four = RespFrame::from(b"4");
+        assert_eq!(hash.scan(0, 10), (0, vec![("b", &four)]));
+        hash.remove("b");
+        assert_eq!(hash.scan(0, 10), (0, vec![]));
+    }
+
+    #[test]
+    fn test_sorted_set_order() {
+        let mut zset = SortedSet::default();
+        zset.insert("b".to_string(), 1.0);
+        zset.insert("a".to_string(), 1.0);
+        zset.insert("c".to_string(), -0.0);
+        assert_eq!(zset.insert("d".to_string(), f64::INFINITY), None);
+        assert_eq!(zset.insert("c".to_string(), 0.0), Some(0.0));
+        assert_eq!(
+            zset.iter().collect::<Vec<_>>(),
+            vec![("c", 0.0), ("a", 1.0), ("b", 1.0), ("d", f64::INFINITY)]
+        );
+        assert_eq!(zset.rank("b"), Some(2));
+        assert_eq!(zset.rank("missing"), None);
+
+        assert_eq!(zset.pop(true), Some(("d".to_string(), f64::INFINITY)));
+        assert_eq!(zset.remove("a"), Some(1.0));
+        assert_eq!(zset.rank("b"), Some(1));
+        assert_eq!(zset.len(), 2);
+        assert_eq!(zset.order.len(), 2);
+    }
+}
@@ -0,0 +1,670 @@
+use std::collections::HashMap;
+use std::ops::Bound;
+
+use rand::seq::{index, SliceRandom};
+
+use super::list::clamp_range;
+use super::{Backend, BackendError, Entry, RedisValue, SetCondition, SetOp, SortedSet};
+
+/// The NX / XX, GT / LT and CH options of ZADD.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ZAddOptions {
+    pub condition: SetCondition,
+    // update an existing member only if its new score is greater (GT) or lower (LT), new
+    // members are added either way
+    pub gt: bool,
+    pub lt: bool,
+    // count the members whose score changed along with the added ones
+    pub ch: bool,
+}
+
+/// The members ZRANGE selects, before REV and LIMIT apply.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ZRangeBy {
+    // inclusive ranks, negative ones counting from the highest score
+    Rank(i64, i64),
+    Score(Bound<f64>, Bound<f64>),
+    // only meaningful when all members have the same score, like in Redis
+    Lex(Bound<String>, Bound<String>),
+}
+
+/// The LIMIT option of ZRANGE: members to skip, then how many to return at most.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ZLimit {
+    pub offset: usize,
+    pub count: Option<usize>,
+}
+
+/// How ZUNION and ZINTER merge the scores of a member found in several inputs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Aggregate {
+    #[default]
+    Sum,
+    Min,
+    Max,
+}
+
+impl Aggregate {
+    fn apply(self, a: f64, b: f64) -> f64 {
+        match self {
+            // inf + -inf is 0 rather than NaN, like in Redis
+            Aggregate::Sum => zero_if_nan(a + b),
+            Aggregate::Min => a.min(b),
+            Aggregate::Max => a.max(b),
+        }
+    }
+}
+
+impl ZAddOptions {
+    // whether a member with the `old` score, None if it is new, may get the `new` one
+    fn allows(&self, old: Option<f64>, new: f64) -> bool {
+        match (self.condition, old) {
+            (SetCondition::IfNotExists, Some(_)) | (SetCondition::IfExists, None) => false,
+            (_, None) => true,
+            (_, Some(old)) => !(self.gt && new <= old || self.lt && new >= old),
+        }
+    }
+}
+
+impl Backend {
+    /// ZADD: set the scores of the members, creating the sorted set if needed. Returns how many
+    /// members were added, or also changed with CH.
+    pub fn zadd(
+        &self,
+        key: String,
+        members: Vec<(f64, String)>,
+        opts: ZAddOptions,
+    ) -> Result<usize, BackendError> {
+        let create = opts.condition != SetCondition::IfExists;
+        let count = {
+            let _guard = self.locks.write([key.as_str()]);
+            self.write_value::<SortedSet, _>(&key, create, |zset| {
+                let (mut added, mut changed) = (0, 0);
+                for (score, member) in members {
+                    let old = zset.get(&member).copied();
+                    if !opts.allows(old, score) {
+                        continue;
+                    }
+                    match old {
+                        None => added += 1,
+                        Some(old) if old != score => changed += 1,
+                        Some(_) => continue,
+                    }
+                    zset.insert(member, score);
+                }
+                Ok(if opts.ch { added + changed } else { added })
+            })?
+        };
+        if count.is_some() {
+            self.signal_ready(&key);
+        }
+        Ok(count.unwrap_or(0))
+    }
+
+    /// ZINCRBY, and ZADD with INCR: add `delta` to the score of the member, a new member
+    /// starting at 0. Returns the new score, None if the options prevented the update.
+    pub fn zincr_by(
+        &self,
+        key: String,
+        member: String,
+        delta: f64,
+        opts: ZAddOptions,
+    ) -> Result<Option<f64>, BackendError> {
+        let create = opts.condition != SetCondition::IfExists;
+        let ret = {
+            let _guard = self.locks.write([key.as_str()]);
+            self.write_value::<SortedSet, _>(&key, create, |zset| {
+                let old = zset.get(&member).copied();
+                let score = old.unwrap_or(0.0) + delta;
+                if score.is_nan() {
+                    return Err(BackendError::ScoreNan);
+                }
+                if !opts.allows(old, score) {
+                    return Ok(None);
+                }
+                zset.insert(member, score);
+                Ok(Some(score))
+            })?
+        };
+        if ret.is_some() {
+            self.signal_ready(&key);
+        }
+        Ok(ret.flatten())
+    }
+
+    pub fn zscore(&self, key: &str, member: &str) -> Result<Option<f64>, BackendError> {
+        let _guard = self.locks.read([key]);
+        Ok(self
+            .read_zset(key, |zset| zset.get(member).copied())?
+            .flatten())
+    }
+
+    /// ZMSCORE: the score of each member, None for the missing ones.
+    pub fn zmscore(&self, key: &str, members: &[String]) -> Result<Vec<Option<f64>>, BackendError> {
+        let _guard = self.locks.read([key]);
+        let ret = self.read_zset(key, |zset| {
+            members.iter().map(|m| zset.get(m).copied()).collect()
+        })?;
+        Ok(ret.unwrap_or_else(|| vec![None; members.len()]))
+    }
+
+    pub fn zcard(&self, key: &str) -> Result<usize, BackendError> {
+        let _guard = self.locks.read([key]);
+        Ok(self.read_zset(key, |zset| zset.len())?.unwrap_or(0))
+    }
+
+    /// ZRANK / ZREVRANK: the rank of the member from the lowest score, or from the highest
+    /// with `rev`, along with its score.
+    pub fn zrank(
+        &self,
+        key: &str,
+        member: &str,
+        rev: bool,
+    ) -> Result<Option<(usize, f64)>, BackendError> {
+        let _guard = self.locks.read([key]);
+        let ret = self.read_zset(key, |zset| {
+            let rank = zset.rank(member)?;
+            let rank = if rev { zset.len() - 1 - rank } else { rank };
+            Some((rank, zset[member]))
+        })?;
+        Ok(ret.flatten())
+    }
+
+    /// ZREM: remove the members. Returns how many were in the sorted set.
+    pub fn zrem(&self, key: &str, members: &[String]) -> Result<usize, BackendError> {
+        let _guard = self.locks.write([key]);
+        let removed = self.write_value::<SortedSet, _>(key, false, |zset| {
+            Ok(members.iter().filter(|m| zset.remove(m).is_some()).count())
+        })?;
+        Ok(removed.unwrap_or(0))
+    }
+
+    /// ZRANGE: the members in the range with their scores, walking from the highest score with
+    /// `rev`. Score and lex bounds are given as min and max whatever the direction.
+    pub fn zrange(
+        &self,
+        key: &str,
+        range: &ZRangeBy,
+        rev: bool,
+        limit: Option<ZLimit>,
+    ) -> Result<Vec<(String, f64)>, BackendError> {
+        let _guard = self.locks.read([key]);
+        let ret = self.read_zset(key, |zset| {
+            let members = select(zset, range, rev);
+            let members: Box<dyn Iterator<Item = (&str, f64)>> = match limit {
+                Some(ZLimit { offset, count }) => {
+                    Box::new(members.skip(offset).take(count.unwrap_or(usize::MAX)))
+                }
+                None => members,
+            };
+            members.map(|(m, score)| (m.to_string(), score)).collect()
+        })?;
+        Ok(ret.unwrap_or_default())
+    }
+
+    /// ZUNION / ZINTER / ZDIFF: combine the inputs, sorted sets or plain sets whose members
+    /// score 1, each score multiplied by the weight of its input. Missing keys count as empty.
+    /// Returns the members in score order.
+    pub fn zset_op(
+        &self,
+        op: SetOp,
+        keys: &[String],
+        weights: &[f64],
+        aggregate: Aggregate,
+    ) -> Result<Vec<(String, f64)>, BackendError> {
+        let _guard = self.locks.read(keys.iter().map(String::as_str));
+        let zset = self.combine_zsets(op, keys, weights, aggregate)?;
+        Ok(zset
+            .iter()
+            .map(|(m, score)| (m.to_string(), score))
+            .collect())
+    }
+
+    /// The STORE variants: write the result to `dst`, replacing whatever it held, or delete it
+    /// if the result is empty. Returns the size of the result.
+    pub fn zset_op_store(
+        &self,
+        op: SetOp,
+        dst: &str,
+        keys: &[String],
+        weights: &[f64],
+        aggregate: Aggregate,
+    ) -> Result<usize, BackendError> {
+        let len = {
+            let _guard = self.locks.lock(keys.iter().map(String::as_str), [dst]);
+            let zset = self.combine_zsets(op, keys, weights, aggregate)?;
+            let len = zset.len();
+            if zset.is_empty() {
+                self.remove_entry(dst);
+            } else {
+                self.insert_entry(dst.to_string(), Entry::new(zset, None));
+            }
+            len
+        };
+        if len > 0 {
+            self.signal_ready(dst);
+        }
+        Ok(len)
+    }
+
+    /// ZREMRANGEBYRANK / BYSCORE / BYLEX: remove the members in the range. Returns how many.
+    pub fn zremrange(&self, key: &str, range: &ZRangeBy) -> Result<usize, BackendError> {
+        let _guard = self.locks.write([key]);
+        let removed = self.write_value::<SortedSet, _>(key, false, |zset| {
+            let members = select(zset, range, false)
+                .map(|(m, _)| m.to_string())
+                .collect::<Vec<_>>();
+            for member in &members {
+                zset.remove(member);
+            }
+            Ok(members.len())
+        })?;
+        Ok(removed.unwrap_or(0))
+    }
+
+    /// ZCOUNT / ZLEXCOUNT: how many members are in the range.
+    pub fn zcount(&self, key: &str, range: &ZRangeBy) -> Result<usize, BackendError> {
+        let _guard = self.locks.read([key]);
+        let count = self.read_zset(key, |zset| {
+            let (start, end) = ranks(zset, range, false);
+            end.saturating_sub(start)
+        })?;
+        Ok(count.unwrap_or(0))
+    }
+
+    /// ZPOPMIN / ZPOPMAX: remove up to `count` members with the lowest scores, or the highest
+    /// with `max`. None if the key does not exist.
+    pub fn zpop(
+        &self,
+        key: &str,
+        max: bool,
+        count: usize,
+    ) -> Result<Option<Vec<(String, f64)>>, BackendError> {
+        let _guard = self.locks.write([key]);
+        self.write_value::<SortedSet, _>(key, false, |zset| {
+            Ok((0..count).map_while(|_| zset.pop(max)).collect())
+        })
+    }
+
+    /// ZRANDMEMBER: up to `count` distinct random members, or exactly `-count` members that
+    /// may repeat when `count` is negative.
+    pub fn zrandmember(&self, key: &str, count: i64) -> Result<Vec<(String, f64)>, BackendError> {
+        let _guard = self.locks.read([key]);
+        let members = self.read_zset(key, |zset| {
+            let mut rng = rand::thread_rng();
+            let all = zset.iter().collect::<Vec<_>>();
+            let picked: Vec<_> = if count >= 0 {
+                let n = (count as usize).min(all.len());
+                index::sample(&mut rng, all.len(), n)
+                    .into_iter()
+                    .map(|i| all[i])
+                    .collect()
+            } else {
+                (0..count.unsigned_abs())
+                    .filter_map(|_| all.choose(&mut rng).copied())
+                    .collect()
+            };
+            picked
+                .into_iter()
+                .map(|(m, score)| (m.to_string(), score))
+                .collect()
+        })?;
+        Ok(members.unwrap_or_default())
+    }
+
+    // the caller holds the locks of the keys, each input is copied out under its own slot
+    // guard like in `combine`
+    fn combine_zsets(
+        &self,
+        op: SetOp,
+        keys: &[String],
+        weights: &[f64],
+        aggregate: Aggregate,
+    ) -> Result<SortedSet, BackendError> {
+        let mut acc: Option<HashMap<String, f64>> = None;
+        for (i, key) in keys.iter().enumerate() {
+            let weight = weights.get(i).copied().unwrap_or(1.0);
+            let scores = self
+                .read_scores(key)?
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(m, score)| (m, zero_if_nan(score * weight)));
+            acc = Some(match (op, acc) {
+                (_, None) => scores.collect(),
+                (SetOp::Union, Some(mut acc)) => {
+                    for (member, score) in scores {
+                        acc.entry(member)
+                            .and_modify(|a| *a = aggregate.apply(*a, score))
+                            .or_insert(score);
+                    }
+                    acc
+                }
+                (SetOp::Inter, Some(acc)) => {
+                    let scores = scores.collect::<HashMap<_, _>>();
+                    acc.into_iter()
+                        .filter_map(|(m, a)| {
+                            let score = scores.get(&m)?;
+                            Some((m, aggregate.apply(a, *score)))
+                        })
+                        .collect()
+                }
+                (SetOp::Diff, Some(mut acc)) => {
+                    for (member, _) in scores {
+                        acc.remove(&member);
+                    }
+                    acc
+                }
+            });
+        }
+        let mut zset = SortedSet::default();
+        for (member, score) in acc.unwrap_or_default() {
+            zset.insert(member, score);
+        }
+        Ok(zset)
+    }
+
+    // the scores of a sorted set, or of a set whose members all score 1
+    fn read_scores(&self, key: &str) -> Result<Option<HashMap<String, f64>>, BackendError> {
+        match self.lookup(key).as_deref().map(|e| &e.value) {
+            Some(RedisValue::ZSet(zset)) => Ok(Some((**zset).clone())),
+            Some(RedisValue::Set(set)) => Ok(Some(set.iter().map(|m| (m.clone(), 1.0)).collect())),
+            Some(_) => Err(BackendError::WrongType),
+            None => Ok(None),
+        }
+    }
+
+    fn read_zset<T>(
+        &self,
+        key: &str,
+        f: impl FnOnce(&SortedSet) -> T,
+    ) -> Result<Option<T>, BackendError> {
+        match self.lookup(key).as_deref() {
+            Some(Entry {
+                value: RedisValue::ZSet(zset),
+                ..
+            }) => Ok(Some(f(zset))),
+            Some(_) => Err(BackendError::WrongType),
+            None => Ok(None),
+        }
+    }
+}
+
+// the members in the range, in the order of the walk
+fn select<'a>(
+    zset: &'a SortedSet,
+    range: &ZRangeBy,
+    rev: bool,
+) -> Box<dyn Iterator<Item = (&'a str, f64)> + 'a> {
+    let (start, end) = ranks(zset, range, rev);
+    let members = zset.range(start, end);
+    if rev {
+        Box::new(members.rev())
+    } else {
+        Box::new(members)
+    }
+}
+
+// the ranks of the range, from `start` up to but not including `end`, found in O(log n): the
+// members before the range, then those before or inside it
+fn ranks(zset: &SortedSet, range: &ZRangeBy, rev: bool) -> (usize, usize) {
+    match range {
+        ZRangeBy::Rank(start, stop) => match clamp_range(*start, *stop, zset.len()) {
+            Some((start, stop)) if rev => (zset.len() - 1 - stop, zset.len() - start),
+            Some((start, stop)) => (start, stop + 1),
+            None => (0, 0),
+        },
+        ZRangeBy::Score(min, max) => {
+            let (min, max) = (min.as_ref(), max.as_ref());
+            (
+                zset.partition_point(|_, score| position(min, max, &score) < 0),
+                zset.partition_point(|_, score| position(min, max, &score) <= 0),
+            )
+        }
+        ZRangeBy::Lex(min, max) => {
+            let (min, max) = (
+                min.as_ref().map(String::as_str),
+                max.as_ref().map(String::as_str),
+            );
+            (
+                zset.partition_point(|member, _| position(min, max, member) < 0),
+                zset.partition_point(|member, _| position(min, max, member) <= 0),
+            )
+        }
+    }
+}
+
+fn zero_if_nan(score: f64) -> f64 {
+    if score.is_nan() {
+        0.0
+    } else {
+        score
+    }
+}
+
+// where a value is relative to a range: -1 below, 0 inside and 1 above
+fn position<T: PartialOrd + ?Sized>(min: Bound<&T>, max: Bound<&T>, value: &T) -> i32 {
+    let below = match min {
+        Bound::Included(min) => value < min,
+        Bound::Excluded(min) => value <= min,
+        Bound::Unbounded => false,
+    };
+    let above = match max {
+        Bound::Included(max) => value > max,
+        Bound::Excluded(max) => value >= max,
+        Bound::Unbounded => false,
+    };
+    match (below, above) {
+        (true, _) => -1,
+        (_, true) => 1,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zset(backend: &Backend, key: &str, members: &[(f64, &str)]) {
+        let members = members.iter().map(|(s, m)| (*s, m.to_string())).collect();
+        backend
+            .zadd(key.to_string(), members, ZAddOptions::default())
+            .unwrap();
+    }
+
+    fn names(members: Vec<(String, f64)>) -> Vec<String> {
+        members.into_iter().map(|(m, _)| m).collect()
+    }
+
+    #[test]
+    fn test_zadd_options() {
+        let backend = Backend::new();
+        zset(&backend, "z", &[(1.0, "a"), (2.0, "b")]);
+        let add = |score: f64, member: &str, opts| {
+            backend.zadd("z".to_string(), vec![(score, member.to_string())], opts)
+        };
+        let nx = ZAddOptions {
+            condition: SetCondition::IfNotExists,
+            ..Default::default()
+        };
+        assert_eq!(add(5.0, "a", nx), Ok(0));
+        assert_eq!(add(5.0, "c", nx), Ok(1));
+        let xx_ch = ZAddOptions {
+            condition: SetCondition::IfExists,
+            ch: true,
+            ..Default::default()
+        };
+        assert_eq!(add(3.0, "a", xx_ch), Ok(1));
+        assert_eq!(add(3.0, "d", xx_ch), Ok(0));
+        let gt = ZAddOptions {
+            gt: true,
+            ch: true,
+            ..Default::default()
+        };
+        assert_eq!(add(1.0, "a", gt), Ok(0));
+        assert_eq!(add(4.0, "a", gt), Ok(1));
+        assert_eq!(backend.zscore("z", "a"), Ok(Some(4.0)));
+        assert_eq!(
+            backend.zmscore("z", &["b".to_string(), "d".to_string()]),
+            Ok(vec![Some(2.0), None])
+        );
+        assert_eq!(backend.zcard("z"), Ok(3));
+    }
+
+    #[test]
+    fn test_zincr_by() {
+        let backend = Backend::new();
+        let opts = ZAddOptions::default();
+        let incr = |delta, opts| backend.zincr_by("z".to_string(), "a".to_string(), delta, opts);
+        assert_eq!(incr(2.5, opts), Ok(Some(2.5)));
+        assert_eq!(incr(-1.0, opts), Ok(Some(1.5)));
+        let lt = ZAddOptions {
+            lt: true,
+            ..Default::default()
+        };
+        assert_eq!(incr(1.0, lt), Ok(None));
+        assert_eq!(incr(f64::INFINITY, opts), Ok(Some(f64::INFINITY)));
+        assert_eq!(incr(f64::NEG_INFINITY, opts), Err(BackendError::ScoreNan));
+    }
+
+    #[test]
+    fn test_zrank_zrem() {
+        let backend = Backend::new();
+        zset(&backend, "z", &[(1.0, "a"), (2.0, "b"), (3.0, "c")]);
+        assert_eq!(backend.zrank("z", "b", false), Ok(Some((1, 2.0))));
+        assert_eq!(backend.zrank("z", "c", true), Ok(Some((0, 3.0))));
+        assert_eq!(backend.zrank("z", "x", false), Ok(None));
+        assert_eq!(
+            backend.zrem("z", &["a".to_string(), "x".to_string()]),
+            Ok(1)
+        );
+        assert_eq!(
+            backend.zrem("z", &["b".to_string(), "c".to_string()]),
+            Ok(2)
+        );
+        assert_eq!(backend.key_type("z"), "none");
+    }
+
+    #[test]
+    fn test_zrange() {
+        let backend = Backend::new();
+        zset(
+            &backend,
+            "z",
+            &[(1.0, "a"), (2.0, "b"), (2.0, "c"), (3.0, "d")],
+        );
+        let range = |range, rev, limit| names(backend.zrange("z", &range, rev, limit).unwrap());
+        assert_eq!(range(ZRangeBy::Rank(1, -2), false, None), vec!["b", "c"]);
+        assert_eq!(range(ZRangeBy::Rank(0, 1), true, None), vec!["d", "c"]);
+        assert_eq!(
+            range(ZRangeBy::Rank(5, 10), false, None),
+            Vec::<String>::new()
+        );
+
+        let score = ZRangeBy::Score(Bound::Excluded(1.0), Bound::Included(3.0));
+        assert_eq!(range(score.clone(), false, None), vec!["b", "c", "d"]);
+        assert_eq!(range(score.clone(), true, None), vec!["d", "c", "b"]);
+        let limit = ZLimit {
+            offset: 1,
+            count: Some(1),
+        };
+        assert_eq!(range(score, true, Some(limit)), vec!["c"]);
+        let all = ZRangeBy::Score(Bound::Unbounded, Bound::Excluded(2.0));
+        assert_eq!(range(all, false, None), vec!["a"]);
+
+        zset(&backend, "lex", &[(0.0, "a"), (0.0, "b"), (0.0, "c")]);
+        let lex = ZRangeBy::Lex(Bound::Excluded("a".to_string()), Bound::Unbounded);
+        assert_eq!(
+            names(backend.zrange("lex", &lex, true, None).unwrap()),
+            vec!["c", "b"]
+        );
+    }
+
+    #[test]
+    fn test_zset_op() {
+        let backend = Backend::new();
+        zset(&backend, "a", &[(1.0, "x"), (2.0, "y")]);
+        zset(&backend, "b", &[(10.0, "y"), (20.0, "z")]);
+        backend
+            .sadd("s".to_string(), vec!["z".to_string()])
+            .unwrap();
+        let keys = ["a".to_string(), "b".to_string()];
+        let op = |op, keys: &[String], weights: &[f64], aggregate| {
+            backend.zset_op(op, keys, weights, aggregate).unwrap()
+        };
+        assert_eq!(
+            op(SetOp::Union, &keys, &[], Aggregate::Sum),
+            vec![
+                ("x".to_string(), 1.0),
+                ("y".to_string(), 12.0),
+                ("z".to_string(), 20.0)
+            ]
+        );
+        assert_eq!(
+            op(SetOp::Inter, &keys, &[2.0, 1.0], Aggregate::Min),
+            vec![("y".to_string(), 4.0)]
+        );
+        assert_eq!(
+            op(SetOp::Diff, &keys, &[], Aggregate::Sum),
+            vec![("x".to_string(), 1.0)]
+        );
+        // a plain set scores its members 1
+        let with_set = ["b".to_string(), "s".to_string()];
+        assert_eq!(
+            op(SetOp::Inter, &with_set, &[], Aggregate::Max),
+            vec![("z".to_string(), 20.0)]
+        );
+
+        assert_eq!(
+            backend.zset_op_store(SetOp::Union, "a", &keys, &[], Aggregate::Max),
+            Ok(3)
+        );
+        assert_eq!(backend.zscore("a", "y"), Ok(Some(10.0)));
+        assert_eq!(
+            backend.zset_op_store(SetOp::Inter, "a", &keys, &[], Aggregate::Sum),
+            Ok(2)
+        );
+        assert_eq!(
+            backend.zset_op_store(SetOp::Diff, "a", &keys, &[], Aggregate::Sum),
+            Ok(0)
+        );
+        assert_eq!(backend.key_type("a"), "none");
+    }
+
+    #[test]
+    fn test_zremrange_zcount() {
+        let backend = Backend::new();
+        zset(
+            &backend,
+            "z",
+            &[(1.0, "a"), (2.0, "b"), (3.0, "c"), (4.0, "d")],
+        );
+        let score = ZRangeBy::Score(Bound::Included(2.0), Bound::Unbounded);
+        assert_eq!(backend.zcount("z", &score), Ok(3));
+        let lex = ZRangeBy::Lex(Bound::Unbounded, Bound::Excluded("c".to_string()));
+        assert_eq!(backend.zcount("z", &lex), Ok(2));
+        assert_eq!(backend.zremrange("z", &ZRangeBy::Rank(-1, -1)), Ok(1));
+        assert_eq!(backend.zremrange("z", &score), Ok(2));
+        assert_eq!(backend.zremrange("z", &lex), Ok(1));
+        assert_eq!(backend.key_type("z"), "none");
+    }
+
+    #[test]
+    fn test_zpop_zrandmember() {
+        let backend = Backend::new();
+        zset(&backend, "z", &[(1.0, "a"), (2.0, "b"), (3.0, "c")]);
+        assert_eq!(
+            backend.zpop("z", true, 2),
+            Ok(Some(vec![("c".to_string(), 3.0), ("b".to_string(), 2.0)]))
+        );
+        assert_eq!(backend.zrandmember("z", -3).unwrap().len(), 3);
+        assert_eq!(
+            backend.zrandmember("z", 3),
+            Ok(vec![("a".to_string(), 1.0)])
+        );
+        assert_eq!(
+            backend.zpop("z", false, 5),
+            Ok(Some(vec![("a".to_string(), 1.0)]))
+        );
+        assert_eq!(backend.zpop("z", false, 1), Ok(None));
+    }
+}
@@ -0,0 +1,282 @@
+use std::time::Duration;
+
+use super::list::parse_end;
+use super::zset::parse_min_max;
+use super::{
+    command_name, extract_args, extract_string, parse_float, parse_int, validate_command, Arity,
+    PopOp,
+};
+use crate::{
+    Backend, BackendError, BlockingPop, BulkString, CommandError, CommandExecutor, ListEnd,
+    RespArray, RespFrame, RespNull, RespNullArray,
+};
+use std::convert::TryFrom;
+
+const ONE_ARGS: usize = 1;
+const TWO_ARGS: usize = 2;
+const THREE_ARGS: usize = 3;
+const FOUR_ARGS: usize = 4;
+
+// without a client to block, as inside a transaction, the blocking commands reply at once
+impl CommandExecutor for BlockingPop {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.pop.execute(backend)
+    }
+}
+
+impl BlockingPop {
+    /// Pop right away if one of the keys has elements, else wait for a write until the
+    /// timeout. A timeout replies nil.
+    pub async fn wait(self, backend: &Backend) -> RespFrame {
+        match self.pop.pop(backend, false) {
+            Ok(None) => {}
+            ret => return self.pop.reply(ret),
+        }
+        let keys = self.pop.keys();
+        let nil = self.pop.nil();
+        let pop = self.pop;
+        let op = Box::new(move |backend: &Backend| match pop.pop(backend, true) {
+            Ok(ret) => ret,
+            Err(e) => Some(e.into()),
+        });
+        backend
+            .block_on(keys, self.timeout, op)
+            .await
+            .unwrap_or(nil)
+    }
+}
+
+impl PopOp {
+    // pop without blocking, nil if every key is empty
+    pub(super) fn execute(&self, backend: &Backend) -> RespFrame {
+        self.reply(self.pop(backend, false))
+    }
+
+    fn reply(&self, ret: Result<Option<RespFrame>, BackendError>) -> RespFrame {
+        match ret {
+            Ok(value) => value.unwrap_or_else(|| self.nil()),
+            Err(e) => e.into(),
+        }
+    }
+
+    // like Redis, the commands replying an array reply a null array when nothing was popped
+    // and BLMOVE the null bulk string it replies in place of the element
+    fn nil(&self) -> RespFrame {
+        match self {
+            PopOp::Move { .. } => RespFrame::Null(RespNull),
+            _ => RespFrame::NullArray(RespNullArray),
+        }
+    }
+
+    // the reply of the pop, None if every key is empty. A blocked client skips the keys
+    // holding another type, they may hold the right one later.
+    fn pop(
+        &self,
+        backend: &Backend,
+        skip_wrong_type: bool,
+    ) -> Result<Option<RespFrame>, BackendError> {
+        match self {
+            PopOp::List { keys, end } => {
+                first_of(keys, skip_wrong_type, |key| backend.pop(key, *end, 1))
+            }
+            PopOp::ListMulti { keys, end, count } => first_of(keys, skip_wrong_type, |key| {
+                let values = backend.pop(key, *end, *count)?;
+                Ok(values.map(|values| vec![RespArray::new(values).into()]))
+            }),
+            PopOp::Move { src, dst, from, to } => match backend.lmove(src, dst, *from, *to) {
+                // the destination holding another type is reported to the client, the source
+                // is waited on
+                Err(BackendError::WrongType)
+                    if skip_wrong_type && backend.key_type(src) != "list" =>
+                {
+                    Ok(None)
+                }
+                ret => ret,
+            },
+            PopOp::ZSet { keys, max } => first_of(keys, skip_wrong_type, |key| {
+                let members = backend.zpop(key, *max, 1)?;
+                Ok(members
+                    .and_then(|mut members| members.pop())
+                    .map(|(member, score)| {
+                        vec![BulkString::from(member).into(), RespFrame::Double(score)]
+                    }))
+            }),
+            PopOp::ZSetMulti { keys, max, count } => first_of(keys, skip_wrong_type, |key| {
+                let members = backend.zpop(key, *max, *count)?;
+                Ok(members.map(|members| {
+                    let members = members
+                        .into_iter()
+                        .map(|(member, score)| {
+                            let pair =
+                                vec![BulkString::from(member).into(), RespFrame::Double(score)];
+                            RespArray::new(pair).into()
+                        })
+                        .collect::<Vec<_>>();
+                    vec![RespArray::new(members).into()]
+                }))
+            }),
+        }
+    }
+
+    fn keys(&self) -> Vec<String> {
+        match self {
+            PopOp::List { keys, .. }
+            | PopOp::ListMulti { keys, .. }
+            | PopOp::ZSet { keys, .. }
+            | PopOp::ZSetMulti { keys, .. } => keys.clone(),
+            PopOp::Move { src, .. } => vec![src.clone()],
+        }
+    }
+}
+
+// the key followed by what `pop` replies for the first key it takes something from
+fn first_of(
+    keys: &[String],
+    skip_wrong_type: bool,
+    mut pop: impl FnMut(&str) -> Result<Option<Vec<RespFrame>>, BackendError>,
+) -> Result<Option<RespFrame>, BackendError> {
+    for key in keys {
+        match pop(key) {
+            Ok(Some(popped)) => {
+                let mut reply = vec![BulkString::from(key.as_str()).into()];
+                reply.extend(popped);
+                return Ok(Some(RespArray::new(reply).into()));
+            }
+            Ok(None) => {}
+            Err(BackendError::WrongType) if skip_wrong_type => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(None)
+}
+
+impl TryFrom<RespArray> for BlockingPop {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let name = command_name(&value)?;
+        match name.as_str() {
+            "blpop" | "brpop" | "bzpopmin" | "bzpopmax" => {
+                let (name, pop): (_, fn(Vec<String>) -> PopOp) = match name.as_str() {
+                    "blpop" => ("blpop", |keys| PopOp::List {
+                        keys,
+                        end: ListEnd::Left,
+                    }),
+                    "brpop" => ("brpop", |keys| PopOp::List {
+                        keys,
+                        end: ListEnd::Right,
+                    }),
+                    "bzpopmin" => ("bzpopmin", |keys| PopOp::ZSet { keys, max: false }),
+                    _ => ("bzpopmax", |keys| PopOp::ZSet { keys, max: true }),
+                };
+                validate_command(&value, &[name], Arity::AtLeast(TWO_ARGS))?;
+                let mut args = extract_args(value, ONE_ARGS)?;
+                let timeout = parse_timeout(&args.pop().ok_or(CommandError::SyntaxError)?)?;
+                let keys = args
+                    .into_iter()
+                    .map(extract_string)
+                    .collect::<Result<Vec<_>, _>>()?;
+                let pop = pop(keys);
+                Ok(BlockingPop { pop, timeout })
+            }
+            "blmove" | "brpoplpush" => {
+                let blmove = name == "blmove";
+                if blmove {
+                    validate_command(&value, &["blmove"], Arity::Exact(5))?;
+                } else {
+                    validate_command(&value, &["brpoplpush"], THREE_ARGS)?;
+                }
+                let mut args = extract_args(value, ONE_ARGS)?.into_iter();
+                let src = extract_string(args.next().ok_or(CommandError::SyntaxError)?)?;
+                let dst = extract_string(args.next().ok_or(CommandError::SyntaxError)?)?;
+                let (from, to) = if blmove {
+                    (parse_end(args.next())?, parse_end(args.next())?)
+                } else {
+                    (ListEnd::Right, ListEnd::Left)
+                };
+                let timeout = parse_timeout(&args.next().ok_or(CommandError::SyntaxError)?)?;
+                Ok(BlockingPop {
+                    pop: PopOp::Move { src, dst, from, to },
+                    timeout,
+                })
+            }
+            "bzmpop" => {
+                validate_command(&value, &["bzmpop"], Arity::AtLeast(FOUR_ARGS))?;
+                let mut args = extract_args(value, ONE_ARGS)?;
+                let timeout = parse_timeout(&args.remove(0))?;
+                let (keys, max, count) = parse_multi_pop(args, parse_min_max)?;
+                Ok(BlockingPop {
+                    pop: PopOp::ZSetMulti { keys, max, count },
+                    timeout,
+                })
+            }
+            _ => {
+                validate_command(&value, &["blmpop"], Arity::AtLeast(FOUR_ARGS))?;
+                let mut args = extract_args(value, ONE_ARGS)?;
+                let timeout = parse_timeout(&args.remove(0))?;
+                let (keys, end, count) = parse_multi_pop(args, parse_end)?;
+                Ok(BlockingPop {
+                    pop: PopOp::ListMulti { keys, end, count },
+                    timeout,
+                })
+            }
+        }
+    }
+}
+
+// the `numkeys key [key ...] <where> [COUNT count]` arguments of LMPOP, ZMPOP and their
+// blocking forms, `<where>` being parsed by `parse_where`
+pub(super) fn parse_multi_pop<T>(
+    args: Vec<RespFrame>,
+    parse_where: fn(Option<RespFrame>) -> Result<T, CommandError>,
+) -> Result<(Vec<String>, T, usize), CommandError> {
+    let mut args = args.into_iter();
+    let numkeys = parse_int(&args.next().ok_or(CommandError::SyntaxError)?)?;
+    if numkeys <= 0 {
+        return Err(CommandError::InvalidArgument(
+            "numkeys should be greater than 0".to_string(),
+        ));
+    }
+    let keys = args
+        .by_ref()
+        .take(numkeys as usize)
+        .map(extract_string)
+        .collect::<Result<Vec<_>, _>>()?;
+    if keys.len() < numkeys as usize {
+        return Err(CommandError::SyntaxError);
+    }
+    let at = parse_where(args.next())?;
+    let option = args.next().map(extract_string).transpose()?;
+    let count = match (option, args.next(), args.next()) {
+        (None, _, _) => 1,
+        (Some(option), Some(ref count), None) if option.eq_ignore_ascii_case("count") => {
+            match parse_int(count)? {
+                n if n <= 0 => {
+                    return Err(CommandError::InvalidArgument(
+                        "count should be greater than 0".to_string(),
+                    ))
+                }
+                n => n as usize,
+            }
+        }
+        _ => return Err(CommandError::SyntaxError),
+    };
+    Ok((keys, at, count))
+}
+
+// a timeout in seconds, 0 blocks forever
+fn parse_timeout(frame: &RespFrame) -> Result<Option<Duration>, CommandError> {
+    let secs = parse_float(frame).map_err(|_| {
+        CommandError::InvalidArgument("timeout is not a float or out of range".to_string())
+    })?;
+    if secs < 0.0 {
+        return Err(CommandError::InvalidArgument(
+            "timeout is negative".to_string(),
+        ));
+    }
+    if secs == 0.0 {
+        return Ok(None);
+    }
+    Duration::try_from_secs_f64(secs)
+        .map(Some)
+        .map_err(|_| CommandError::InvalidArgument("timeout is out of range".to_string()))
+}
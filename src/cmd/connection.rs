@@ -0,0 +1,439 @@
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{
+    subscriber, Backend, BulkString, Command, CommandError, CommandSpec, Echo, Hello, Inbox, Ping,
+    Quit, Reset, RespArray, RespFrame, RespMap, RespVersion, SimpleString, Subscriber, RESP_OK,
+};
+
+use super::{extract_args, extract_string, parse_int, validate_command, Arity, CommandExecutor};
+
+// the Redis version announced to clients, which some of them use to pick features
+const REDIS_VERSION: &str = "7.2.0";
+
+static NEXT_CLIENT_ID: AtomicU64 = AtomicU64::new(1);
+
+// what a RESP2 client can still run once subscribed, the replies of the others could not be
+// told from its messages
+const SUBSCRIBED_MODE_COMMANDS: &[&str] = &[
+    "subscribe",
+    "psubscribe",
+    "unsubscribe",
+    "punsubscribe",
+    "ping",
+    "quit",
+    "reset",
+];
+
+/// What the server knows about one client connection.
+#[derive(Debug)]
+pub struct ConnectionState {
+    pub id: u64,
+    pub protocol: RespVersion,
+    pub name: Option<String>,
+    pub channels: HashSet<String>,
+    pub patterns: HashSet<String>,
+    // set by QUIT, the connection is closed once the reply is flushed
+    pub closing: bool,
+    // where the broker sends the messages of the subscriptions, and the receiving end until
+    // the connection takes it to write them out
+    pub(super) messages: Subscriber,
+    inbox: Option<Inbox>,
+}
+
+impl ConnectionState {
+    pub fn new() -> Self {
+        Self::with_id(NEXT_CLIENT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+
+    fn with_id(id: u64) -> Self {
+        let (messages, inbox) = subscriber();
+        Self {
+            id,
+            protocol: RespVersion::default(),
+            name: None,
+            channels: HashSet::new(),
+            patterns: HashSet::new(),
+            closing: false,
+            messages,
+            inbox: Some(inbox),
+        }
+    }
+
+    pub fn is_subscribed(&self) -> bool {
+        !self.channels.is_empty() || !self.patterns.is_empty()
+    }
+
+    // the count replied by the (un)subscribe commands
+    pub(super) fn subscriptions(&self) -> usize {
+        self.channels.len() + self.patterns.len()
+    }
+
+    /// The messages sent to this client, for the task writing to it. Only the first call
+    /// gets them.
+    pub fn take_inbox(&mut self) -> Option<Inbox> {
+        self.inbox.take()
+    }
+
+    /// Leave every channel and pattern without telling the client, as when it disconnects.
+    pub fn unsubscribe_all(&mut self, backend: &Backend) {
+        for channel in self.channels.drain() {
+            backend.unsubscribe(&channel, self.id);
+        }
+        for pattern in self.patterns.drain() {
+            backend.punsubscribe(&pattern, self.id);
+        }
+    }
+
+    /// Back to the state of a new connection, only the client id and where its messages go
+    /// are kept. The subscriptions must have been left first.
+    pub fn reset(&mut self) {
+        *self = Self {
+            messages: self.messages.clone(),
+            inbox: self.inbox.take(),
+            ..Self::with_id(self.id)
+        };
+    }
+}
+
+impl Default for ConnectionState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Command {
+    /// Parse a command received on the connection. A RESP2 client can't tell replies from
+    /// messages, so once subscribed it is limited to the commands replying like messages.
+    pub fn parse_in(frame: RespFrame, conn: &ConnectionState) -> Result<Command, CommandError> {
+        if conn.is_subscribed() && conn.protocol == RespVersion::Resp2 {
+            if let RespFrame::Array(array) = &frame {
+                if let Some(RespFrame::BulkString(name)) = array.first() {
+                    if let Some(spec) = CommandSpec::lookup(name) {
+                        if !SUBSCRIBED_MODE_COMMANDS.contains(&spec.name) {
+                            return Err(CommandError::SubscribedMode(spec.name.to_string()));
+                        }
+                    }
+                }
+            }
+        }
+        Command::try_from(frame)
+    }
+
+    /// Like `execute_in`, but the blocking commands may wait for their keys.
+    pub async fn execute_async(self, backend: &Backend, conn: &mut ConnectionState) -> RespFrame {
+        match self {
+            Command::BlockingPop(cmd) => cmd.wait(backend).await,
+            Command::XRead(cmd) => cmd.wait(backend, conn.protocol).await,
+            cmd => cmd.execute_in(backend, conn),
+        }
+    }
+
+    /// Execute a command on behalf of a connection, connection-level commands update its
+    /// state.
+    pub fn execute_in(self, backend: &Backend, conn: &mut ConnectionState) -> RespFrame {
+        match self {
+            Command::Hello(hello) => hello.apply(conn),
+            Command::HGetAll(cmd) => cmd.reply(backend, conn.protocol),
+            Command::HRandField(cmd) => cmd.reply(backend, conn.protocol),
+            Command::ZRange(cmd) => cmd.reply(backend, conn.protocol),
+            Command::ZSetAlgebra(cmd) => cmd.reply(backend, conn.protocol),
+            Command::ZPop(cmd) => cmd.reply(backend, conn.protocol),
+            Command::ZRandMember(cmd) => cmd.reply(backend, conn.protocol),
+            Command::XRead(cmd) => cmd.reply(backend, conn.protocol),
+            Command::Subscribe(cmd) => cmd.apply(backend, conn),
+            Command::Unsubscribe(cmd) => cmd.apply(backend, conn),
+            // a RESP2 connection in subscribed mode can only receive pushes shaped as arrays
+            Command::Ping(ping) if conn.is_subscribed() && conn.protocol == RespVersion::Resp2 => {
+                let message = ping.message.unwrap_or_else(|| BulkString::from("").into());
+                RespArray::new(vec![BulkString::from("pong").into(), message]).into()
+            }
+            Command::Quit(_) => {
+                conn.closing = true;
+                RESP_OK.clone()
+            }
+            Command::Reset(_) => {
+                conn.unsubscribe_all(backend);
+                conn.reset();
+                SimpleString::new("RESET").into()
+            }
+            cmd => cmd.execute(backend),
+        }
+    }
+}
+
+impl Hello {
+    fn apply(self, conn: &mut ConnectionState) -> RespFrame {
+        if let Some((user, _)) = self.auth {
+            // there is no ACL: the default user has no password, and is the only user
+            if user != "default" {
+                return CommandError::WrongPass.into();
+            }
+        }
+        if let Some(protocol) = self.protocol {
+            conn.protocol = protocol;
+        }
+        if self.name.is_some() {
+            conn.name = self.name;
+        }
+
+        let proto = match conn.protocol {
+            RespVersion::Resp2 => 2,
+            RespVersion::Resp3 => 3,
+        };
+        let mut map = RespMap::new();
+        map.insert("server".to_string(), BulkString::from("redis").into());
+        map.insert(
+            "version".to_string(),
+            BulkString::from(REDIS_VERSION).into(),
+        );
+        map.insert("proto".to_string(), RespFrame::Integer(proto));
+        map.insert("id".to_string(), RespFrame::Integer(conn.id as i64));
+        map.insert("mode".to_string(), BulkString::from("standalone").into());
+        map.insert("role".to_string(), BulkString::from("master").into());
+        map.insert("modules".to_string(), RespArray::new(vec![]).into());
+        map.into()
+    }
+}
+
+impl CommandExecutor for Hello {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        self.apply(&mut ConnectionState::new())
+    }
+}
+
+impl TryFrom<RespArray> for Hello {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["hello"], Arity::AtLeast(0))?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let mut hello = Hello {
+            protocol: None,
+            auth: None,
+            name: None,
+        };
+        let Some(protover) = args.next() else {
+            return Ok(hello);
+        };
+        hello.protocol = match parse_int(&protover) {
+            Ok(2) => Some(RespVersion::Resp2),
+            Ok(3) => Some(RespVersion::Resp3),
+            Ok(_) => return Err(CommandError::NoProto),
+            Err(_) => {
+                return Err(CommandError::InvalidArgument(
+                    "Protocol version is not an integer or out of range".to_string(),
+                ))
+            }
+        };
+
+        while let Some(option) = args.next() {
+            let option = extract_string(option)?;
+            match option.to_ascii_lowercase().as_str() {
+                "auth" => match (args.next(), args.next()) {
+                    (Some(user), Some(pass)) => {
+                        hello.auth = Some((extract_string(user)?, extract_string(pass)?))
+                    }
+                    _ => return Err(hello_syntax_error(&option)),
+                },
+                "setname" => match args.next() {
+                    Some(name) => {
+                        let name = extract_string(name)?;
+                        if name.chars().any(|c| !('!'..='~').contains(&c)) {
+                            return Err(CommandError::InvalidArgument(
+                                "Client names cannot contain spaces, newlines or special characters."
+                                    .to_string(),
+                            ));
+                        }
+                        hello.name = Some(name);
+                    }
+                    None => return Err(hello_syntax_error(&option)),
+                },
+                _ => return Err(hello_syntax_error(&option)),
+            }
+        }
+        Ok(hello)
+    }
+}
+
+impl CommandExecutor for Ping {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        self.message
+            .unwrap_or_else(|| SimpleString::new("PONG").into())
+    }
+}
+
+impl CommandExecutor for Echo {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        self.message
+    }
+}
+
+// QUIT and RESET only act on the connection, see `Command::execute_in`
+impl CommandExecutor for Quit {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        RESP_OK.clone()
+    }
+}
+
+impl CommandExecutor for Reset {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        SimpleString::new("RESET").into()
+    }
+}
+
+impl TryFrom<RespArray> for Ping {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["ping"], Arity::AtLeast(0))?;
+        if value.len() > 2 {
+            return Err(CommandError::WrongArity("ping".to_string()));
+        }
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(Ping {
+            message: args.next(),
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Echo {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["echo"], 1)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        match args.next() {
+            Some(message @ RespFrame::BulkString(_)) => Ok(Echo { message }),
+            _ => Err(CommandError::InvalidArgument(
+                "ECHO command requires a string message".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for Quit {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        // like Redis, QUIT ignores its arguments
+        validate_command(&value, &["quit"], Arity::AtLeast(0))?;
+        Ok(Quit)
+    }
+}
+
+impl TryFrom<RespArray> for Reset {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["reset"], 0)?;
+        Ok(Reset)
+    }
+}
+
+fn hello_syntax_error(option: &str) -> CommandError {
+    CommandError::InvalidArgument(format!("Syntax error in HELLO option '{option}'"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::test_helpers::command;
+
+    #[test]
+    fn test_hello_switches_protocol() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        let mut conn = ConnectionState::new();
+        assert_eq!(conn.protocol, RespVersion::Resp2);
+
+        let cmd = command(&["HELLO", "3", "AUTH", "default", "pw", "SETNAME", "app"])?;
+        let RespFrame::Map(reply) = cmd.execute_in(&backend, &mut conn) else {
+            panic!("HELLO should reply with a map");
+        };
+        assert_eq!(reply["proto"], RespFrame::Integer(3));
+        assert_eq!(reply["id"], RespFrame::Integer(conn.id as i64));
+        assert_eq!(conn.protocol, RespVersion::Resp3);
+        assert_eq!(conn.name.as_deref(), Some("app"));
+
+        // without arguments HELLO only reports the current state
+        let RespFrame::Map(reply) = command(&["hello"])?.execute_in(&backend, &mut conn) else {
+            panic!("HELLO should reply with a map");
+        };
+        assert_eq!(reply["proto"], RespFrame::Integer(3));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ping_and_echo() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        let mut conn = ConnectionState::new();
+        let pong = command(&["PING"])?.execute_in(&backend, &mut conn);
+        assert_eq!(pong, SimpleString::new("PONG").into());
+        let hi = command(&["ping", "hi"])?.execute_in(&backend, &mut conn);
+        assert_eq!(hi, BulkString::from("hi").into());
+        let echo = command(&["echo", "hello"])?.execute_in(&backend, &mut conn);
+        assert_eq!(echo, BulkString::from("hello").into());
+        assert_eq!(
+            command(&["ping", "a", "b"]).unwrap_err().to_string(),
+            "ERR wrong number of arguments for 'ping' command"
+        );
+
+        conn.channels.insert("news".to_string());
+        let pong = command(&["ping"])?.execute_in(&backend, &mut conn);
+        assert_eq!(
+            pong,
+            RespArray::new(vec![
+                BulkString::from("pong").into(),
+                BulkString::from("").into()
+            ])
+            .into()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_quit_and_reset() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        let mut conn = ConnectionState::new();
+        let id = conn.id;
+        command(&["hello", "3", "setname", "app"])?.execute_in(&backend, &mut conn);
+        conn.channels.insert("news".to_string());
+
+        let ret = command(&["RESET"])?.execute_in(&backend, &mut conn);
+        assert_eq!(ret, SimpleString::new("RESET").into());
+        assert_eq!(conn.id, id);
+        assert_eq!(conn.protocol, RespVersion::Resp2);
+        assert_eq!(conn.name, None);
+        assert!(!conn.is_subscribed());
+
+        assert!(!conn.closing);
+        let ret = command(&["quit"])?.execute_in(&backend, &mut conn);
+        assert_eq!(ret, RESP_OK.clone());
+        assert!(conn.closing);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_hello_errors() -> anyhow::Result<()> {
+        assert!(matches!(
+            command(&["hello", "4"]),
+            Err(CommandError::NoProto)
+        ));
+        assert_eq!(
+            command(&["hello", "x"]).unwrap_err().to_string(),
+            "ERR Protocol version is not an integer or out of range"
+        );
+        assert_eq!(
+            command(&["hello", "3", "auth", "default"])
+                .unwrap_err()
+                .to_string(),
+            "ERR Syntax error in HELLO option 'auth'"
+        );
+
+        let mut conn = ConnectionState::new();
+        let ret =
+            command(&["hello", "3", "auth", "bob", "pw"])?.execute_in(&Backend::new(), &mut conn);
+        assert_eq!(ret, CommandError::WrongPass.into());
+        assert_eq!(conn.protocol, RespVersion::Resp2);
+
+        Ok(())
+    }
+}
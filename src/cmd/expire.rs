@@ -0,0 +1,496 @@
+use crate::{
+    Backend, CommandError, Expire, ExpireCondition, HExpire, HPersist, HTtl, Persist, RespArray,
+    RespFrame, Ttl,
+};
+use std::convert::TryFrom;
+
+use super::{
+    command_name, extract_args, extract_string, parse_int, validate_command, Arity,
+    CommandExecutor, TimeUnit,
+};
+
+const ONE_ARGS: usize = 1;
+const TWO_ARGS: usize = 2;
+const FOUR_ARGS: usize = 4;
+const FIVE_ARGS: usize = 5;
+
+// field deadlines are stored on 48 bits by Redis, later ones are refused
+const HASH_FIELD_MAX_EXPIRE_MS: i64 = (1 << 48) - 1;
+
+impl Expire {
+    fn name(&self) -> &'static str {
+        match (self.unit, self.absolute) {
+            (TimeUnit::Seconds, false) => "expire",
+            (TimeUnit::Milliseconds, false) => "pexpire",
+            (TimeUnit::Seconds, true) => "expireat",
+            (TimeUnit::Milliseconds, true) => "pexpireat",
+        }
+    }
+}
+
+// the deadline in unix milliseconds, None on overflow
+fn deadline(time: i64, unit: TimeUnit, absolute: bool, now: u64) -> Option<i64> {
+    let ms = match unit {
+        TimeUnit::Seconds => time.checked_mul(1000)?,
+        TimeUnit::Milliseconds => time,
+    };
+    if absolute {
+        Some(ms)
+    } else {
+        ms.checked_add(now as i64)
+    }
+}
+
+// the TTL family reply for a deadline: what is left of it, or the deadline itself
+fn ttl_reply(at: u64, now: u64, unit: TimeUnit, absolute: bool) -> i64 {
+    let ms = if absolute { at } else { at.saturating_sub(now) };
+    let ret = match unit {
+        // TTL rounds to the nearest second, EXPIRETIME truncates
+        TimeUnit::Seconds if !absolute => (ms + 500) / 1000,
+        TimeUnit::Seconds => ms / 1000,
+        TimeUnit::Milliseconds => ms,
+    };
+    ret as i64
+}
+
+impl HExpire {
+    fn name(&self) -> &'static str {
+        match (self.unit, self.absolute) {
+            (TimeUnit::Seconds, false) => "hexpire",
+            (TimeUnit::Milliseconds, false) => "hpexpire",
+            (TimeUnit::Seconds, true) => "hexpireat",
+            (TimeUnit::Milliseconds, true) => "hpexpireat",
+        }
+    }
+}
+
+impl CommandExecutor for Expire {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match deadline(self.time, self.unit, self.absolute, backend.now_ms()) {
+            Some(at) => RespFrame::Integer(backend.expire_at(&self.key, at.max(0) as u64) as i64),
+            None => CommandError::InvalidExpireTime(self.name().to_string()).into(),
+        }
+    }
+}
+
+impl CommandExecutor for Ttl {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let ret: i64 = match backend.expire_time(&self.key) {
+            None => -2,
+            Some(None) => -1,
+            Some(Some(at)) => ttl_reply(at, backend.now_ms(), self.unit, self.absolute),
+        };
+        RespFrame::Integer(ret)
+    }
+}
+
+impl CommandExecutor for Persist {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.persist(&self.key) as i64)
+    }
+}
+
+impl CommandExecutor for HExpire {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let at = match deadline(self.time, self.unit, self.absolute, backend.now_ms()) {
+            Some(at) if self.time >= 0 && at <= HASH_FIELD_MAX_EXPIRE_MS => at as u64,
+            _ => return CommandError::InvalidExpireTime(self.name().to_string()).into(),
+        };
+        match backend.hexpire_at(&self.key, &self.fields, at, self.condition) {
+            Ok(codes) => integers(codes),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for HTtl {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let now = backend.now_ms();
+        match backend.hexpire_time(&self.key, &self.fields) {
+            Ok(deadlines) => integers(deadlines.into_iter().map(|at| match at {
+                None => -2,
+                Some(None) => -1,
+                Some(Some(at)) => ttl_reply(at, now, self.unit, self.absolute),
+            })),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for HPersist {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.hpersist(&self.key, &self.fields) {
+            Ok(codes) => integers(codes),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+// the per-field reply of the hash field expiration commands
+fn integers(values: impl IntoIterator<Item = i64>) -> RespFrame {
+    RespArray::new(
+        values
+            .into_iter()
+            .map(RespFrame::Integer)
+            .collect::<Vec<_>>(),
+    )
+    .into()
+}
+
+impl TryFrom<RespArray> for Expire {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, unit, absolute) = match command_name(&value)?.as_str() {
+            "expire" => ("expire", TimeUnit::Seconds, false),
+            "pexpire" => ("pexpire", TimeUnit::Milliseconds, false),
+            "expireat" => ("expireat", TimeUnit::Seconds, true),
+            "pexpireat" => ("pexpireat", TimeUnit::Milliseconds, true),
+            name => {
+                return Err(CommandError::InvalidCommand(format!(
+                    "Invalid command: expected expire, got {name}"
+                )))
+            }
+        };
+        validate_command(&value, &[name], TWO_ARGS)?;
+        let mut args = extract_args(value, ONE_ARGS)?.into_iter();
+        match (args.next(), args.next()) {
+            (Some(RespFrame::BulkString(ref key)), Some(ref time)) => Ok(Expire {
+                key: String::from_utf8_lossy(key).to_string(),
+                time: parse_int(time)?,
+                unit,
+                absolute,
+            }),
+            _ => Err(CommandError::InvalidArgument(format!(
+                "{} command requires a string key and a time",
+                name.to_uppercase()
+            ))),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for Ttl {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, unit, absolute) = match command_name(&value)?.as_str() {
+            "ttl" => ("ttl", TimeUnit::Seconds, false),
+            "pttl" => ("pttl", TimeUnit::Milliseconds, false),
+            "expiretime" => ("expiretime", TimeUnit::Seconds, true),
+            "pexpiretime" => ("pexpiretime", TimeUnit::Milliseconds, true),
+            name => {
+                return Err(CommandError::InvalidCommand(format!(
+                    "Invalid command: expected ttl, got {name}"
+                )))
+            }
+        };
+        validate_command(&value, &[name], ONE_ARGS)?;
+        let args = extract_args(value, ONE_ARGS)?;
+        match args[0] {
+            RespFrame::BulkString(ref key) => Ok(Ttl {
+                key: String::from_utf8_lossy(key).to_string(),
+                unit,
+                absolute,
+            }),
+            _ => Err(CommandError::InvalidArgument(format!(
+                "{} command requires a string key",
+                name.to_uppercase()
+            ))),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for Persist {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["persist"], ONE_ARGS)?;
+        let args = extract_args(value, ONE_ARGS)?;
+        match args[0] {
+            RespFrame::BulkString(ref key) => Ok(Persist {
+                key: String::from_utf8_lossy(key).to_string(),
+            }),
+            _ => Err(CommandError::InvalidArgument(
+                "PERSIST command requires a string key".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for HExpire {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, unit, absolute) = match command_name(&value)?.as_str() {
+            "hexpire" => ("hexpire", TimeUnit::Seconds, false),
+            "hpexpire" => ("hpexpire", TimeUnit::Milliseconds, false),
+            "hexpireat" => ("hexpireat", TimeUnit::Seconds, true),
+            "hpexpireat" => ("hpexpireat", TimeUnit::Milliseconds, true),
+            name => {
+                return Err(CommandError::InvalidCommand(format!(
+                    "Invalid command: expected hexpire, got {name}"
+                )))
+            }
+        };
+        validate_command(&value, &[name], Arity::AtLeast(FIVE_ARGS))?;
+        let mut args = extract_args(value, ONE_ARGS)?.into_iter().peekable();
+        let key = extract_string(args.next().ok_or(CommandError::SyntaxError)?)?;
+        let time = parse_int(&args.next().ok_or(CommandError::SyntaxError)?)?;
+        let condition = match args
+            .peek()
+            .map(|arg| extract_string(arg.clone()))
+            .transpose()?
+        {
+            Some(arg) if !arg.eq_ignore_ascii_case("fields") => {
+                args.next();
+                match arg.to_ascii_lowercase().as_str() {
+                    "nx" => ExpireCondition::Nx,
+                    "xx" => ExpireCondition::Xx,
+                    "gt" => ExpireCondition::Gt,
+                    "lt" => ExpireCondition::Lt,
+                    _ => return Err(CommandError::SyntaxError),
+                }
+            }
+            _ => ExpireCondition::Always,
+        };
+        Ok(HExpire {
+            key,
+            time,
+            unit,
+            absolute,
+            condition,
+            fields: parse_fields(args)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HTtl {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, unit, absolute) = match command_name(&value)?.as_str() {
+            "httl" => ("httl", TimeUnit::Seconds, false),
+            "hpttl" => ("hpttl", TimeUnit::Milliseconds, false),
+            "hexpiretime" => ("hexpiretime", TimeUnit::Seconds, true),
+            "hpexpiretime" => ("hpexpiretime", TimeUnit::Milliseconds, true),
+            name => {
+                return Err(CommandError::InvalidCommand(format!(
+                    "Invalid command: expected httl, got {name}"
+                )))
+            }
+        };
+        validate_command(&value, &[name], Arity::AtLeast(FOUR_ARGS))?;
+        let mut args = extract_args(value, ONE_ARGS)?.into_iter();
+        let key = extract_string(args.next().ok_or(CommandError::SyntaxError)?)?;
+        Ok(HTtl {
+            key,
+            unit,
+            absolute,
+            fields: parse_fields(args)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HPersist {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["hpersist"], Arity::AtLeast(FOUR_ARGS))?;
+        let mut args = extract_args(value, ONE_ARGS)?.into_iter();
+        let key = extract_string(args.next().ok_or(CommandError::SyntaxError)?)?;
+        Ok(HPersist {
+            key,
+            fields: parse_fields(args)?,
+        })
+    }
+}
+
+// the trailing `FIELDS numfields field [field ...]` block, with Redis' error messages
+fn parse_fields(mut args: impl Iterator<Item = RespFrame>) -> Result<Vec<String>, CommandError> {
+    let keyword = args.next().map(extract_string).transpose()?;
+    if !keyword.is_some_and(|k| k.eq_ignore_ascii_case("fields")) {
+        return Err(CommandError::InvalidArgument(
+            "Mandatory argument FIELDS is missing or not at the right position".to_string(),
+        ));
+    }
+    let count = parse_int(&args.next().ok_or(CommandError::SyntaxError)?)?;
+    if count <= 0 {
+        return Err(CommandError::InvalidArgument(
+            "Parameter `numFields` should be greater than 0".to_string(),
+        ));
+    }
+    let fields = args.map(extract_string).collect::<Result<Vec<_>, _>>()?;
+    if fields.len() != count as usize {
+        return Err(CommandError::InvalidArgument(
+            "The `numfields` parameter must match the number of arguments".to_string(),
+        ));
+    }
+    Ok(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::test_helpers::run;
+    use crate::ManualClock;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_expire_and_ttl() {
+        let clock = Arc::new(ManualClock::new(1_000_000));
+        let backend = Backend::with_clock(clock.clone());
+        backend.set("key".to_string(), b"value".into());
+
+        let ttl = &["ttl", "key"];
+        let pttl = &["pttl", "key"];
+        assert_eq!(run(&backend, ttl), RespFrame::Integer(-1));
+        assert_eq!(run(&backend, &["ttl", "missing"]), RespFrame::Integer(-2));
+
+        let expire = &["EXPIRE", "key", "10"];
+        assert_eq!(run(&backend, expire), RespFrame::Integer(1));
+        assert_eq!(run(&backend, ttl), RespFrame::Integer(10));
+
+        clock.advance(Duration::from_millis(2_400));
+        assert_eq!(run(&backend, ttl), RespFrame::Integer(8));
+        assert_eq!(run(&backend, pttl), RespFrame::Integer(7_600));
+        assert_eq!(
+            run(&backend, &["expiretime", "key"]),
+            RespFrame::Integer(1_010)
+        );
+
+        clock.advance(Duration::from_secs(8));
+        assert_eq!(run(&backend, ttl), RespFrame::Integer(-2));
+        assert_eq!(backend.get("key"), Ok(None));
+    }
+
+    #[test]
+    fn test_pexpireat_and_persist() {
+        let clock = Arc::new(ManualClock::new(1_000_000));
+        let backend = Backend::with_clock(clock.clone());
+        backend.set("key".to_string(), b"value".into());
+
+        let persist = &["persist", "key"];
+        assert_eq!(run(&backend, persist), RespFrame::Integer(0));
+        assert_eq!(
+            run(&backend, &["pexpireat", "key", "1005000"]),
+            RespFrame::Integer(1)
+        );
+        assert_eq!(run(&backend, &["pttl", "key"]), RespFrame::Integer(5_000));
+        assert_eq!(run(&backend, persist), RespFrame::Integer(1));
+
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(backend.get("key"), Ok(Some(b"value".into())));
+    }
+
+    #[test]
+    fn test_set_with_ex_expires() {
+        let clock = Arc::new(ManualClock::new(1_000_000));
+        let backend = Backend::with_clock(clock.clone());
+        run(&backend, &["set", "key", "value", "px", "100"]);
+        assert_eq!(backend.expire_time("key"), Some(Some(1_000_100)));
+
+        clock.advance(Duration::from_millis(101));
+        assert_eq!(backend.get("key"), Ok(None));
+    }
+
+    #[test]
+    fn test_expire_overflow() {
+        let backend = Backend::new();
+        backend.set("key".to_string(), b"value".into());
+        let ret = run(&backend, &["expire", "key", "9223372036854775807"]);
+        assert_eq!(
+            ret,
+            CommandError::InvalidExpireTime("expire".to_string()).into()
+        );
+    }
+
+    fn integers(values: &[i64]) -> RespFrame {
+        RespArray::new(
+            values
+                .iter()
+                .map(|v| RespFrame::Integer(*v))
+                .collect::<Vec<_>>(),
+        )
+        .into()
+    }
+
+    #[test]
+    fn test_hexpire_and_httl() {
+        let clock = Arc::new(ManualClock::new(1_000_000));
+        let backend = Backend::with_clock(clock.clone());
+        run(&backend, &["hset", "h", "a", "1", "b", "2"]);
+
+        assert_eq!(
+            run(&backend, &["hexpire", "h", "10", "FIELDS", "2", "a", "c"]),
+            integers(&[1, -2])
+        );
+        assert_eq!(
+            run(
+                &backend,
+                &["hexpire", "h", "20", "nx", "fields", "2", "a", "b"]
+            ),
+            integers(&[0, 1])
+        );
+        assert_eq!(
+            run(&backend, &["httl", "h", "FIELDS", "3", "a", "b", "c"]),
+            integers(&[10, 20, -2])
+        );
+        assert_eq!(
+            run(
+                &backend,
+                &["hpexpireat", "h", "1005000", "lt", "fields", "1", "b"]
+            ),
+            integers(&[1])
+        );
+        assert_eq!(
+            run(&backend, &["hpexpiretime", "h", "FIELDS", "1", "b"]),
+            integers(&[1_005_000])
+        );
+        assert_eq!(
+            run(&backend, &["hpersist", "h", "FIELDS", "2", "a", "a"]),
+            integers(&[1, -1])
+        );
+
+        clock.advance(Duration::from_millis(5_001));
+        assert_eq!(backend.hget("h", "b"), Ok(None));
+        assert_eq!(
+            run(&backend, &["hexpire", "h", "0", "fields", "1", "a"]),
+            integers(&[2])
+        );
+        assert_eq!(
+            run(&backend, &["httl", "h", "fields", "1", "a"]),
+            integers(&[-2])
+        );
+        assert_eq!(backend.key_type("h"), "none");
+    }
+
+    #[test]
+    fn test_hexpire_errors() {
+        let backend = Backend::new();
+        run(&backend, &["hset", "h", "a", "1"]);
+        assert_eq!(
+            run(&backend, &["hexpire", "h", "10", "fields", "2", "a"]),
+            CommandError::InvalidArgument(
+                "The `numfields` parameter must match the number of arguments".to_string()
+            )
+            .into()
+        );
+        assert_eq!(
+            run(&backend, &["httl", "h", "fields", "0", "a"]),
+            CommandError::InvalidArgument(
+                "Parameter `numFields` should be greater than 0".to_string()
+            )
+            .into()
+        );
+        assert_eq!(
+            run(&backend, &["hpersist", "h", "a", "1", "a"]),
+            CommandError::InvalidArgument(
+                "Mandatory argument FIELDS is missing or not at the right position".to_string()
+            )
+            .into()
+        );
+        assert_eq!(
+            run(&backend, &["hexpire", "h", "10", "ZZ", "fields", "1", "a"]),
+            CommandError::SyntaxError.into()
+        );
+        assert_eq!(
+            run(&backend, &["hexpire", "h", "-1", "fields", "1", "a"]),
+            CommandError::InvalidExpireTime("hexpire".to_string()).into()
+        );
+    }
+}
@@ -1,54 +1,212 @@
-use super::{extract_args, validate_command, HSet};
+use super::keys::{parse_cursor, scan_reply};
+use super::{
+    command_name, extract_args, extract_string, parse_float, parse_int, parse_random_count,
+    validate_command, Arity, HSet, ScanOptions,
+};
 use crate::{
-    BulkString, CommandError, CommandExecutor, HGet, HGetAll, RespArray, RespFrame, RESP_OK,
+    Backend, BulkString, CommandError, CommandExecutor, HDel, HExists, HGet, HGetAll, HIncrBy,
+    HIncrByFloat, HKeys, HLen, HMGet, HRandField, HScan, HSetNx, HStrLen, RespArray, RespFrame,
+    RespMap, RespNull, RespVersion,
 };
 use std::convert::TryFrom;
 
 const ONE_ARGS: usize = 1;
 const TWO_ARGS: usize = 2;
+const THREE_ARGS: usize = 3;
 
 impl CommandExecutor for HGet {
     fn execute(self, backend: &crate::Backend) -> RespFrame {
         match backend.hget(&self.key, &self.field) {
-            Some(value) => value,
-            None => RespFrame::Null(crate::RespNull),
+            Ok(Some(value)) => value,
+            Ok(None) => RespFrame::Null(crate::RespNull),
+            Err(e) => e.into(),
         }
     }
 }
 
 impl CommandExecutor for HSet {
     fn execute(self, backend: &crate::Backend) -> RespFrame {
-        backend.hset(self.key, self.field, self.value);
-        RESP_OK.clone()
+        match backend.hset(self.key, self.fields) {
+            Ok(added) => RespFrame::Integer(added as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl HGetAll {
+    // RESP3 gets a map of the fields, RESP2 the same map as a flat array of field value pairs
+    pub(super) fn reply(self, backend: &Backend, protocol: RespVersion) -> RespFrame {
+        let fields = match backend.hgetall(&self.key) {
+            Ok(fields) => fields.unwrap_or_default(),
+            Err(e) => return e.into(),
+        };
+        let map = RespFrame::Map(RespMap(fields.into_iter().collect()));
+        match protocol {
+            RespVersion::Resp2 => map.into_resp2(),
+            RespVersion::Resp3 => map,
+        }
     }
 }
 
 impl CommandExecutor for HGetAll {
-    fn execute(self, backend: &crate::Backend) -> RespFrame {
-        let hmap = backend.hmap.get(&self.key);
-        match hmap {
-            Some(hmap) => {
-                let mut arr = Vec::with_capacity(hmap.len());
-                for v in hmap.iter() {
-                    let key = v.key().to_owned();
-                    arr.push((key, v.value().clone()));
-                }
-                if self.sort {
-                    arr.sort_by(|a, b| a.0.cmp(&b.0));
-                }
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.reply(backend, RespVersion::Resp2)
+    }
+}
 
-                let ret = arr
-                    .into_iter()
-                    .flat_map(|(k, v)| vec![BulkString::from(k).into(), v])
-                    .collect::<Vec<RespFrame>>();
+impl CommandExecutor for HSetNx {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.hsetnx(self.key, self.field, self.value) {
+            Ok(added) => RespFrame::Integer(added as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
 
+impl CommandExecutor for HMGet {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.hmget(&self.key, &self.fields) {
+            Ok(values) => {
+                let ret = values
+                    .into_iter()
+                    .map(|v| v.unwrap_or(RespFrame::Null(RespNull)))
+                    .collect::<Vec<_>>();
                 RespArray::new(ret).into()
             }
-            None => RespFrame::Null(crate::RespNull),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for HDel {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.hdel(&self.key, &self.fields) {
+            Ok(removed) => RespFrame::Integer(removed as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for HExists {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.hexists(&self.key, &self.field) {
+            Ok(exists) => RespFrame::Integer(exists as i64),
+            Err(e) => e.into(),
         }
     }
 }
 
+impl CommandExecutor for HLen {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.hlen(&self.key) {
+            Ok(len) => RespFrame::Integer(len as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for HKeys {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let ret = if self.values {
+            backend.hvals(&self.key)
+        } else {
+            backend.hkeys(&self.key).map(|keys| {
+                keys.into_iter()
+                    .map(|k| BulkString::from(k).into())
+                    .collect()
+            })
+        };
+        match ret {
+            Ok(frames) => RespArray::new(frames).into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for HIncrBy {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.hincr_by(self.key, self.field, self.delta) {
+            Ok(n) => RespFrame::Integer(n),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for HIncrByFloat {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.hincr_by_float(self.key, self.field, self.delta) {
+            Ok(n) => BulkString::from(n).into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for HStrLen {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.hstrlen(&self.key, &self.field) {
+            Ok(len) => RespFrame::Integer(len as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl HRandField {
+    // with WITHVALUES, RESP3 gets an array of field value pairs and RESP2 a flat array
+    pub(super) fn reply(self, backend: &Backend, protocol: RespVersion) -> RespFrame {
+        let fields = match backend.hrandfield(&self.key, self.count.unwrap_or(1)) {
+            Ok(fields) => fields,
+            Err(e) => return e.into(),
+        };
+        let Some(_) = self.count else {
+            return match fields.into_iter().next() {
+                Some((field, _)) => BulkString::from(field).into(),
+                None => RespFrame::Null(RespNull),
+            };
+        };
+        let ret = fields
+            .into_iter()
+            .flat_map(|(field, value)| {
+                let field = BulkString::from(field).into();
+                match (self.with_values, protocol) {
+                    (false, _) => vec![field],
+                    (true, RespVersion::Resp2) => vec![field, value],
+                    (true, RespVersion::Resp3) => vec![RespArray::new(vec![field, value]).into()],
+                }
+            })
+            .collect::<Vec<_>>();
+        RespArray::new(ret).into()
+    }
+}
+
+impl CommandExecutor for HRandField {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.reply(backend, RespVersion::Resp2)
+    }
+}
+
+impl CommandExecutor for HScan {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let (cursor, page) = match backend.hscan(&self.key, self.cursor, self.opts.count) {
+            Ok(page) => page,
+            Err(e) => return e.into(),
+        };
+        // MATCH filters the page after it was taken, like SCAN
+        let items = page
+            .into_iter()
+            .filter(|(field, _)| self.opts.matches(field))
+            .flat_map(|(field, value)| {
+                let field = BulkString::from(field).into();
+                if self.no_values {
+                    vec![field]
+                } else {
+                    vec![field, value]
+                }
+            })
+            .collect::<Vec<_>>();
+        scan_reply(cursor, items)
+    }
+}
+
 impl TryFrom<RespArray> for HGet {
     type Error = CommandError;
 
@@ -77,7 +235,6 @@ impl TryFrom<RespArray> for HGetAll {
         match args.next() {
             Some(RespFrame::BulkString(ref key)) => Ok(HGetAll {
                 key: String::from_utf8_lossy(key).to_string(),
-                sort: false,
             }),
             _ => Err(CommandError::InvalidArgument(
                 "HGetAll command requires a string key and bool".to_string(),
@@ -90,28 +247,198 @@ impl TryFrom<RespArray> for HSet {
     type Error = CommandError;
 
     fn try_from(array: RespArray) -> Result<Self, Self::Error> {
-        validate_command(&array, &["hset"], 3)?;
+        validate_command(&array, &["hset"], Arity::Pairs(ONE_ARGS))?;
+        let mut args = extract_args(array, ONE_ARGS)?.into_iter();
+        let key = extract_string(args.next().ok_or(CommandError::SyntaxError)?)?;
+        let mut fields = Vec::with_capacity(args.len() / 2);
+        while let (Some(field), Some(value)) = (args.next(), args.next()) {
+            fields.push((extract_string(field)?, value));
+        }
+        Ok(HSet { key, fields })
+    }
+}
+
+impl TryFrom<RespArray> for HSetNx {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&array, &["hsetnx"], THREE_ARGS)?;
         let mut args = extract_args(array, ONE_ARGS)?.into_iter();
         match (args.next(), args.next(), args.next()) {
-            (
-                Some(RespFrame::BulkString(ref key)),
-                Some(RespFrame::BulkString(ref field)),
-                Some(value),
-            ) => Ok(HSet {
-                key: String::from_utf8_lossy(key).to_string(),
-                field: String::from_utf8_lossy(field).to_string(),
+            (Some(key), Some(field), Some(value)) => Ok(HSetNx {
+                key: extract_string(key)?,
+                field: extract_string(field)?,
                 value,
             }),
-            _ => Err(CommandError::InvalidArgument(
-                "HSet command requires a string key, field and value".to_string(),
-            )),
+            _ => Err(CommandError::WrongArity("hsetnx".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for HMGet {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&array, &["hmget"], Arity::AtLeast(TWO_ARGS))?;
+        let (key, fields) = key_and_fields(array)?;
+        Ok(HMGet { key, fields })
+    }
+}
+
+impl TryFrom<RespArray> for HDel {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&array, &["hdel"], Arity::AtLeast(TWO_ARGS))?;
+        let (key, fields) = key_and_fields(array)?;
+        Ok(HDel { key, fields })
+    }
+}
+
+impl TryFrom<RespArray> for HExists {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&array, &["hexists"], TWO_ARGS)?;
+        let (key, mut fields) = key_and_fields(array)?;
+        Ok(HExists {
+            key,
+            field: fields.remove(0),
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HLen {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&array, &["hlen"], ONE_ARGS)?;
+        let (key, _) = key_and_fields(array)?;
+        Ok(HLen { key })
+    }
+}
+
+impl TryFrom<RespArray> for HKeys {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        let (name, values) = match command_name(&array)?.as_str() {
+            "hkeys" => ("hkeys", false),
+            "hvals" => ("hvals", true),
+            name => {
+                return Err(CommandError::InvalidCommand(format!(
+                    "Invalid command: expected hkeys, got {name}"
+                )))
+            }
+        };
+        validate_command(&array, &[name], ONE_ARGS)?;
+        let (key, _) = key_and_fields(array)?;
+        Ok(HKeys { key, values })
+    }
+}
+
+impl TryFrom<RespArray> for HIncrBy {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&array, &["hincrby"], THREE_ARGS)?;
+        let mut args = extract_args(array, ONE_ARGS)?.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(key), Some(field), Some(ref delta)) => Ok(HIncrBy {
+                key: extract_string(key)?,
+                field: extract_string(field)?,
+                delta: parse_int(delta)?,
+            }),
+            _ => Err(CommandError::WrongArity("hincrby".to_string())),
         }
     }
 }
 
+impl TryFrom<RespArray> for HIncrByFloat {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&array, &["hincrbyfloat"], THREE_ARGS)?;
+        let mut args = extract_args(array, ONE_ARGS)?.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(key), Some(field), Some(ref delta)) => Ok(HIncrByFloat {
+                key: extract_string(key)?,
+                field: extract_string(field)?,
+                delta: parse_float(delta)?,
+            }),
+            _ => Err(CommandError::WrongArity("hincrbyfloat".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for HStrLen {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&array, &["hstrlen"], TWO_ARGS)?;
+        let (key, mut fields) = key_and_fields(array)?;
+        Ok(HStrLen {
+            key,
+            field: fields.remove(0),
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HRandField {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&array, &["hrandfield"], Arity::AtLeast(ONE_ARGS))?;
+        if array.len() > 4 {
+            return Err(CommandError::SyntaxError);
+        }
+        let mut args = extract_args(array, ONE_ARGS)?.into_iter();
+        let key = extract_string(args.next().ok_or(CommandError::SyntaxError)?)?;
+        let count = args.next().map(|c| parse_random_count(&c)).transpose()?;
+        let with_values = match args.next().map(extract_string).transpose()? {
+            Some(arg) if arg.eq_ignore_ascii_case("withvalues") => true,
+            Some(_) => return Err(CommandError::SyntaxError),
+            None => false,
+        };
+        Ok(HRandField {
+            key,
+            count,
+            with_values,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HScan {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&array, &["hscan"], Arity::AtLeast(TWO_ARGS))?;
+        let mut args = extract_args(array, ONE_ARGS)?.into_iter();
+        let key = extract_string(args.next().ok_or(CommandError::SyntaxError)?)?;
+        let cursor = parse_cursor(&args.next().ok_or(CommandError::SyntaxError)?)?;
+        let mut no_values = false;
+        let opts = ScanOptions::parse(&mut args, |option, _| match option {
+            "novalues" => {
+                no_values = true;
+                Ok(())
+            }
+            _ => Err(CommandError::SyntaxError),
+        })?;
+        Ok(HScan {
+            key,
+            cursor,
+            opts,
+            no_values,
+        })
+    }
+}
+
+// the key followed by field names, or set members
+pub(super) fn key_and_fields(array: RespArray) -> Result<(String, Vec<String>), CommandError> {
+    let mut args = extract_args(array, ONE_ARGS)?
+        .into_iter()
+        .map(extract_string)
+        .collect::<Result<Vec<_>, _>>()?;
+    if args.is_empty() {
+        return Err(CommandError::SyntaxError);
+    }
+    let key = args.remove(0);
+    Ok((key, args))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cmd::test_helpers::run;
     use crate::resp::RespDecode;
     use anyhow::Result;
     use bytes::BytesMut;
@@ -152,9 +479,152 @@ mod tests {
 
         let result: HSet = frame.try_into()?;
         assert_eq!(result.key, "map");
-        assert_eq!(result.field, "hello");
-        assert_eq!(result.value, RespFrame::BulkString(b"world".into()));
+        assert_eq!(
+            result.fields,
+            vec![("hello".to_string(), RespFrame::BulkString(b"world".into()))]
+        );
 
         Ok(())
     }
+
+    #[test]
+    fn test_hash_commands() {
+        let backend = Backend::new();
+        assert_eq!(
+            run(&backend, &["HSET", "h", "a", "1", "b", "2"]),
+            RespFrame::Integer(2)
+        );
+        assert_eq!(
+            run(&backend, &["hset", "h", "a"]),
+            CommandError::WrongArity("hset".to_string()).into()
+        );
+        assert_eq!(
+            run(&backend, &["hsetnx", "h", "a", "x"]),
+            RespFrame::Integer(0)
+        );
+        assert_eq!(run(&backend, &["hlen", "h"]), RespFrame::Integer(2));
+        assert_eq!(run(&backend, &["hexists", "h", "b"]), RespFrame::Integer(1));
+        assert_eq!(
+            run(&backend, &["hmget", "h", "a", "x"]),
+            RespArray::new(vec![b"1".into(), RespFrame::Null(RespNull)]).into()
+        );
+        assert_eq!(run(&backend, &["hstrlen", "h", "a"]), RespFrame::Integer(1));
+        assert_eq!(
+            run(&backend, &["hincrby", "h", "a", "41"]),
+            RespFrame::Integer(42)
+        );
+        assert_eq!(
+            run(&backend, &["hincrbyfloat", "h", "b", "0.5"]),
+            RespFrame::from(b"2.5")
+        );
+
+        let RespFrame::Array(keys) = run(&backend, &["hkeys", "h"]) else {
+            panic!("HKEYS should reply with an array");
+        };
+        assert_eq!(keys.len(), 2);
+        let RespFrame::Array(vals) = run(&backend, &["hvals", "h"]) else {
+            panic!("HVALS should reply with an array");
+        };
+        assert!(vals.contains(&b"42".into()));
+
+        assert_eq!(
+            run(&backend, &["hdel", "h", "a", "b", "c"]),
+            RespFrame::Integer(2)
+        );
+        assert_eq!(run(&backend, &["exists", "h"]), RespFrame::Integer(0));
+        assert_eq!(
+            run(&backend, &["hgetall", "h"]),
+            RespArray::new(vec![]).into()
+        );
+    }
+
+    #[test]
+    fn test_hgetall_command() {
+        let backend = Backend::new();
+        run(&backend, &["hset", "h", "b", "2", "a", "1"]);
+        assert_eq!(
+            run(&backend, &["hgetall", "h"]),
+            RespArray::new(vec![b"a".into(), b"1".into(), b"b".into(), b"2".into()]).into()
+        );
+
+        let hgetall = |key: &str| {
+            let frames = ["hgetall", key]
+                .map(|s| BulkString::from(s).into())
+                .to_vec();
+            HGetAll::try_from(RespArray::new(frames)).unwrap()
+        };
+        let mut map = RespMap::new();
+        map.insert("a".to_string(), b"1".into());
+        map.insert("b".to_string(), b"2".into());
+        assert_eq!(hgetall("h").reply(&backend, RespVersion::Resp3), map.into());
+        assert_eq!(
+            hgetall("missing").reply(&backend, RespVersion::Resp3),
+            RespMap::new().into()
+        );
+    }
+
+    #[test]
+    fn test_hrandfield_command() {
+        let backend = Backend::new();
+        assert_eq!(
+            run(&backend, &["hrandfield", "h"]),
+            RespFrame::Null(RespNull)
+        );
+        run(&backend, &["hset", "h", "a", "1"]);
+        assert_eq!(run(&backend, &["hrandfield", "h"]), RespFrame::from(b"a"));
+        assert_eq!(
+            run(&backend, &["hrandfield", "h", "-2", "withvalues"]),
+            RespArray::new(vec![b"a".into(), b"1".into(), b"a".into(), b"1".into()]).into()
+        );
+
+        let frames = ["hrandfield", "h", "1", "WITHVALUES"]
+            .map(|s| BulkString::from(s).into())
+            .to_vec();
+        let cmd = HRandField::try_from(RespArray::new(frames)).unwrap();
+        assert_eq!(
+            cmd.reply(&backend, RespVersion::Resp3),
+            RespArray::new(vec![RespArray::new(vec![b"a".into(), b"1".into()]).into()]).into()
+        );
+        assert_eq!(
+            run(&backend, &["hrandfield", "h", "1", "bogus"]),
+            CommandError::SyntaxError.into()
+        );
+        assert_eq!(
+            run(&backend, &["hrandfield", "h", &i64::MIN.to_string()]),
+            CommandError::InvalidArgument("value is out of range".to_string()).into()
+        );
+    }
+
+    #[test]
+    fn test_hscan_command() {
+        let backend = Backend::new();
+        run(&backend, &["hset", "h", "a1", "1", "a2", "2", "b1", "3"]);
+
+        let RespFrame::Array(reply) = run(&backend, &["hscan", "h", "0", "COUNT", "100"]) else {
+            panic!("HSCAN should reply with an array");
+        };
+        assert_eq!(reply[0], RespFrame::from(b"0"));
+        let RespFrame::Array(ref items) = reply[1] else {
+            panic!("HSCAN should reply with a page");
+        };
+        assert_eq!(items.len(), 6);
+
+        let reply = run(&backend, &["hscan", "h", "0", "match", "b*", "novalues"]);
+        assert_eq!(
+            reply,
+            RespArray::new(vec![b"0".into(), RespArray::new(vec![b"b1".into()]).into()]).into()
+        );
+        assert_eq!(
+            run(&backend, &["hscan", "missing", "0"]),
+            RespArray::new(vec![b"0".into(), RespArray::new(vec![]).into()]).into()
+        );
+        assert_eq!(
+            run(&backend, &["hscan", "h", "x"]),
+            CommandError::InvalidArgument("invalid cursor".to_string()).into()
+        );
+        assert_eq!(
+            run(&backend, &["hscan", "h", "0", "type", "hash"]),
+            CommandError::SyntaxError.into()
+        );
+    }
 }
@@ -0,0 +1,479 @@
+use crate::{
+    glob_match, BulkString, CommandError, CopyKey, DbSize, Del, Exists, Keys, RandomKey, Rename,
+    RespArray, RespFrame, RespNull, Scan, SimpleString, Type, RESP_OK,
+};
+use std::convert::TryFrom;
+
+use super::{
+    command_name, extract_args, extract_string, parse_int, validate_command, Arity,
+    CommandExecutor, ScanOptions,
+};
+
+const SCAN_DEFAULT_COUNT: usize = 10;
+
+const ONE_ARGS: usize = 1;
+const TWO_ARGS: usize = 2;
+
+impl CommandExecutor for Type {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        SimpleString::new(backend.key_type(&self.key)).into()
+    }
+}
+
+impl CommandExecutor for Del {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let count = if self.lazy {
+            backend.unlink(&self.keys)
+        } else {
+            backend.del(&self.keys)
+        };
+        RespFrame::Integer(count as i64)
+    }
+}
+
+impl CommandExecutor for Exists {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        RespFrame::Integer(backend.exists(&self.keys) as i64)
+    }
+}
+
+impl CommandExecutor for Rename {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.rename(&self.src, &self.dst, self.nx) {
+            Ok(renamed) if self.nx => RespFrame::Integer(renamed as i64),
+            Ok(_) => RESP_OK.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for CopyKey {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.copy(&self.src, &self.dst, self.replace) {
+            Ok(copied) => RespFrame::Integer(copied as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for Keys {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let keys = backend
+            .keys(&self.pattern)
+            .into_iter()
+            .map(|key| BulkString::from(key).into())
+            .collect::<Vec<RespFrame>>();
+        RespArray::new(keys).into()
+    }
+}
+
+impl CommandExecutor for Scan {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let (cursor, page) = backend.scan(self.cursor, self.opts.count);
+        // like Redis, MATCH and TYPE filter the page after it was taken, so a page can be empty
+        // while the iteration is not over
+        let keys = page
+            .into_iter()
+            .filter(|(key, type_name)| {
+                self.opts.matches(key) && self.type_name.as_deref().is_none_or(|t| t == *type_name)
+            })
+            .map(|(key, _)| BulkString::from(key).into())
+            .collect::<Vec<RespFrame>>();
+        scan_reply(cursor, keys)
+    }
+}
+
+impl CommandExecutor for DbSize {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        RespFrame::Integer(backend.dbsize() as i64)
+    }
+}
+
+impl CommandExecutor for RandomKey {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.random_key() {
+            Some(key) => BulkString::from(key).into(),
+            None => RespFrame::Null(RespNull),
+        }
+    }
+}
+
+impl ScanOptions {
+    // parse MATCH and COUNT, any other option is handed to `other` along with the remaining
+    // arguments
+    pub(super) fn parse(
+        args: &mut impl Iterator<Item = RespFrame>,
+        mut other: impl FnMut(&str, &mut dyn Iterator<Item = RespFrame>) -> Result<(), CommandError>,
+    ) -> Result<Self, CommandError> {
+        let mut opts = ScanOptions {
+            pattern: None,
+            count: SCAN_DEFAULT_COUNT,
+        };
+        while let Some(arg) = args.next() {
+            let option = extract_string(arg)?.to_ascii_lowercase();
+            match option.as_str() {
+                "match" => {
+                    let pattern = args.next().ok_or(CommandError::SyntaxError)?;
+                    opts.pattern = Some(extract_string(pattern)?);
+                }
+                "count" => {
+                    let count = parse_int(&args.next().ok_or(CommandError::SyntaxError)?)?;
+                    if count < 1 {
+                        return Err(CommandError::SyntaxError);
+                    }
+                    opts.count = count as usize;
+                }
+                _ => other(&option, args)?,
+            }
+        }
+        Ok(opts)
+    }
+
+    pub(super) fn matches(&self, name: &str) -> bool {
+        self.pattern
+            .as_deref()
+            .is_none_or(|p| p == "*" || glob_match(p.as_bytes(), name.as_bytes()))
+    }
+}
+
+pub(super) fn parse_cursor(frame: &RespFrame) -> Result<u64, CommandError> {
+    match frame {
+        RespFrame::BulkString(ref s) => std::str::from_utf8(s)
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .ok_or_else(|| CommandError::InvalidArgument("invalid cursor".to_string())),
+        _ => Err(CommandError::InvalidArgument("invalid cursor".to_string())),
+    }
+}
+
+// the two element reply of the SCAN family: the next cursor as a string and the page
+pub(super) fn scan_reply(cursor: u64, items: Vec<RespFrame>) -> RespFrame {
+    RespArray::new(vec![
+        BulkString::from(cursor.to_string()).into(),
+        RespArray::new(items).into(),
+    ])
+    .into()
+}
+
+impl TryFrom<RespArray> for Type {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["type"], ONE_ARGS)?;
+        let args = extract_args(value, ONE_ARGS)?;
+        match args[0] {
+            RespFrame::BulkString(ref key) => Ok(Type {
+                key: String::from_utf8_lossy(key).to_string(),
+            }),
+            _ => Err(CommandError::InvalidArgument(
+                "TYPE command requires a string key".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for Del {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, lazy) = match command_name(&value)?.as_str() {
+            "del" => ("del", false),
+            "unlink" => ("unlink", true),
+            name => {
+                return Err(CommandError::InvalidCommand(format!(
+                    "Invalid command: expected del, got {name}"
+                )))
+            }
+        };
+        validate_command(&value, &[name], Arity::AtLeast(ONE_ARGS))?;
+        let keys = extract_args(value, ONE_ARGS)?
+            .into_iter()
+            .map(extract_string)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Del { keys, lazy })
+    }
+}
+
+impl TryFrom<RespArray> for Exists {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["exists"], Arity::AtLeast(ONE_ARGS))?;
+        let keys = extract_args(value, ONE_ARGS)?
+            .into_iter()
+            .map(extract_string)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Exists { keys })
+    }
+}
+
+impl TryFrom<RespArray> for Rename {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, nx) = match command_name(&value)?.as_str() {
+            "rename" => ("rename", false),
+            "renamenx" => ("renamenx", true),
+            name => {
+                return Err(CommandError::InvalidCommand(format!(
+                    "Invalid command: expected rename, got {name}"
+                )))
+            }
+        };
+        validate_command(&value, &[name], TWO_ARGS)?;
+        let mut args = extract_args(value, ONE_ARGS)?.into_iter();
+        match (args.next(), args.next()) {
+            (Some(src), Some(dst)) => Ok(Rename {
+                src: extract_string(src)?,
+                dst: extract_string(dst)?,
+                nx,
+            }),
+            _ => Err(CommandError::WrongArity(name.to_string())),
+        }
+    }
+}
+
+// COPY source destination [DB destination-db] [REPLACE]
+impl TryFrom<RespArray> for CopyKey {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["copy"], Arity::AtLeast(TWO_ARGS))?;
+        let mut args = extract_args(value, ONE_ARGS)?.into_iter();
+        let (Some(src), Some(dst)) = (args.next(), args.next()) else {
+            return Err(CommandError::WrongArity("copy".to_string()));
+        };
+        let mut cmd = CopyKey {
+            src: extract_string(src)?,
+            dst: extract_string(dst)?,
+            replace: false,
+        };
+        while let Some(arg) = args.next() {
+            match extract_string(arg)?.to_ascii_lowercase().as_str() {
+                "replace" => cmd.replace = true,
+                // there is a single database
+                "db" => match args.next() {
+                    Some(db) if parse_int(&db)? == 0 => {}
+                    Some(_) => {
+                        return Err(CommandError::InvalidArgument(
+                            "DB index is out of range".to_string(),
+                        ))
+                    }
+                    None => return Err(CommandError::SyntaxError),
+                },
+                _ => return Err(CommandError::SyntaxError),
+            }
+        }
+        Ok(cmd)
+    }
+}
+
+impl TryFrom<RespArray> for Keys {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["keys"], ONE_ARGS)?;
+        let mut args = extract_args(value, ONE_ARGS)?.into_iter();
+        match args.next() {
+            Some(pattern) => Ok(Keys {
+                pattern: extract_string(pattern)?,
+            }),
+            None => Err(CommandError::WrongArity("keys".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for Scan {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["scan"], Arity::AtLeast(ONE_ARGS))?;
+        let mut args = extract_args(value, ONE_ARGS)?.into_iter();
+        let cursor = parse_cursor(&args.next().ok_or(CommandError::SyntaxError)?)?;
+        let mut type_name = None;
+        let opts = ScanOptions::parse(&mut args, |option, args| match option {
+            "type" => {
+                let name = args.next().ok_or(CommandError::SyntaxError)?;
+                type_name = Some(extract_string(name)?.to_ascii_lowercase());
+                Ok(())
+            }
+            _ => Err(CommandError::SyntaxError),
+        })?;
+        Ok(Scan {
+            cursor,
+            opts,
+            type_name,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for DbSize {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["dbsize"], 0)?;
+        Ok(DbSize)
+    }
+}
+
+impl TryFrom<RespArray> for RandomKey {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["randomkey"], 0)?;
+        Ok(RandomKey)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::test_helpers::run;
+    use crate::{Backend, RespDecode};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_type_command() -> Result<()> {
+        let backend = Backend::new();
+        backend.set("str".to_string(), b"value".into());
+        backend.hset(
+            "hash".to_string(),
+            vec![("field".to_string(), b"value".into())],
+        )?;
+
+        for (key, expected) in [("str", "string"), ("hash", "hash"), ("none", "none")] {
+            let cmd = Type {
+                key: key.to_string(),
+            };
+            assert_eq!(cmd.execute(&backend), SimpleString::new(expected).into());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_del_unlink_exists() {
+        let backend = Backend::new();
+        run(&backend, &["mset", "a", "1", "b", "2", "c", "3"]);
+        assert_eq!(
+            run(&backend, &["EXISTS", "a", "a", "x"]),
+            RespFrame::Integer(2)
+        );
+        assert_eq!(run(&backend, &["DEL", "a", "x"]), RespFrame::Integer(1));
+        assert_eq!(run(&backend, &["UNLINK", "b", "c"]), RespFrame::Integer(2));
+        assert_eq!(
+            run(&backend, &["exists", "a", "b", "c"]),
+            RespFrame::Integer(0)
+        );
+        assert_eq!(
+            run(&backend, &["del"]),
+            CommandError::WrongArity("del".to_string()).into()
+        );
+    }
+
+    #[test]
+    fn test_rename_commands() {
+        let backend = Backend::new();
+        run(&backend, &["mset", "a", "1", "b", "2"]);
+        assert_eq!(
+            run(&backend, &["renamenx", "a", "b"]),
+            RespFrame::Integer(0)
+        );
+        assert_eq!(run(&backend, &["rename", "a", "c"]), RESP_OK.clone());
+        assert_eq!(
+            run(&backend, &["rename", "a", "c"]),
+            crate::BackendError::NoSuchKey.into()
+        );
+        assert_eq!(run(&backend, &["get", "c"]), RespFrame::from(b"1"));
+    }
+
+    #[test]
+    fn test_copy_command() {
+        let backend = Backend::new();
+        run(&backend, &["mset", "a", "1", "b", "2"]);
+        assert_eq!(run(&backend, &["copy", "a", "b"]), RespFrame::Integer(0));
+        assert_eq!(
+            run(&backend, &["copy", "a", "b", "db", "0", "replace"]),
+            RespFrame::Integer(1)
+        );
+        assert_eq!(run(&backend, &["get", "b"]), RespFrame::from(b"1"));
+        assert_eq!(
+            run(&backend, &["copy", "a", "b", "db", "1"]),
+            CommandError::InvalidArgument("DB index is out of range".to_string()).into()
+        );
+        assert_eq!(
+            run(&backend, &["copy", "a", "b", "bogus"]),
+            CommandError::SyntaxError.into()
+        );
+    }
+
+    #[test]
+    fn test_scan_command() {
+        let backend = Backend::new();
+        for i in 0..30 {
+            run(&backend, &["set", &format!("user:{i}"), "v"]);
+        }
+        backend
+            .hset(
+                "user:hash".to_string(),
+                vec![("f".to_string(), b"v".into())],
+            )
+            .unwrap();
+        run(&backend, &["set", "other", "v"]);
+
+        let mut cursor = "0".to_string();
+        let mut seen = vec![];
+        loop {
+            let reply = run(
+                &backend,
+                &[
+                    "SCAN", &cursor, "MATCH", "user:*", "COUNT", "7", "TYPE", "string",
+                ],
+            );
+            let RespFrame::Array(reply) = reply else {
+                panic!("SCAN should reply with an array");
+            };
+            let (RespFrame::BulkString(next), RespFrame::Array(keys)) = (&reply[0], &reply[1])
+            else {
+                panic!("SCAN should reply with a cursor and an array of keys");
+            };
+            seen.extend(keys.iter().cloned());
+            cursor = String::from_utf8_lossy(next).to_string();
+            if cursor == "0" {
+                break;
+            }
+        }
+        assert_eq!(seen.len(), 30);
+        assert!(!seen.contains(&RespFrame::from(b"user:hash")));
+
+        assert_eq!(
+            run(&backend, &["scan", "x"]),
+            CommandError::InvalidArgument("invalid cursor".to_string()).into()
+        );
+        assert_eq!(
+            run(&backend, &["scan", "0", "count", "0"]),
+            CommandError::SyntaxError.into()
+        );
+    }
+
+    #[test]
+    fn test_keys_dbsize_randomkey_commands() {
+        let backend = Backend::new();
+        assert_eq!(run(&backend, &["randomkey"]), RespFrame::Null(RespNull));
+        run(&backend, &["mset", "h1", "1", "h2", "2", "x", "3"]);
+        let RespFrame::Array(keys) = run(&backend, &["KEYS", "h[0-9]"]) else {
+            panic!("KEYS should reply with an array");
+        };
+        assert_eq!(keys.len(), 2);
+        assert_eq!(run(&backend, &["DBSIZE"]), RespFrame::Integer(3));
+        assert!(matches!(
+            run(&backend, &["randomkey"]),
+            RespFrame::BulkString(_)
+        ));
+    }
+
+    #[test]
+    fn test_type_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*2\r\n$4\r\ntype\r\n$5\r\nhello\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: Type = frame.try_into()?;
+        assert_eq!(result.key, "hello");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,583 @@
+use crate::{
+    Backend, CommandError, CommandExecutor, LIndex, LInsert, LLen, LMPop, LMove, LPos, LRange,
+    LRem, LSet, LTrim, ListEnd, LposOptions, Pop, Push, RespArray, RespFrame, RespNull,
+    RespNullArray, RESP_OK,
+};
+use std::convert::TryFrom;
+
+use super::blocking::parse_multi_pop;
+use super::{
+    command_name, extract_args, extract_string, parse_int, validate_command, Arity, PopOp,
+};
+
+const ONE_ARGS: usize = 1;
+const TWO_ARGS: usize = 2;
+const THREE_ARGS: usize = 3;
+const FOUR_ARGS: usize = 4;
+
+impl CommandExecutor for Push {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.push(self.key, self.values, self.end, self.only_existing) {
+            Ok(len) => RespFrame::Integer(len as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for Pop {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.pop(&self.key, self.end, self.count.unwrap_or(1)) {
+            Ok(None) if self.count.is_some() => RespFrame::NullArray(RespNullArray),
+            Ok(None) => RespFrame::Null(RespNull),
+            Ok(Some(values)) if self.count.is_some() => RespArray::new(values).into(),
+            Ok(Some(mut values)) => values.pop().unwrap_or(RespFrame::Null(RespNull)),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for LLen {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.llen(&self.key) {
+            Ok(len) => RespFrame::Integer(len as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for LRange {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.lrange(&self.key, self.start, self.stop) {
+            Ok(values) => RespArray::new(values).into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for LIndex {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.lindex(&self.key, self.index) {
+            Ok(value) => value.unwrap_or(RespFrame::Null(RespNull)),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for LSet {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.lset(&self.key, self.index, self.value) {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for LRem {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.lrem(&self.key, self.count, &self.value) {
+            Ok(removed) => RespFrame::Integer(removed as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for LTrim {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.ltrim(&self.key, self.start, self.stop) {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for LInsert {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.linsert(&self.key, self.before, &self.pivot, self.value) {
+            Ok(len) => RespFrame::Integer(len),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for LPos {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let indexes = match backend.lpos(&self.key, &self.element, self.opts) {
+            Ok(indexes) => indexes,
+            Err(e) => return e.into(),
+        };
+        if self.multiple {
+            let indexes = indexes
+                .into_iter()
+                .map(|i| RespFrame::Integer(i as i64))
+                .collect::<Vec<_>>();
+            return RespArray::new(indexes).into();
+        }
+        match indexes.first() {
+            Some(&i) => RespFrame::Integer(i as i64),
+            None => RespFrame::Null(RespNull),
+        }
+    }
+}
+
+impl CommandExecutor for LMove {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.lmove(&self.src, &self.dst, self.from, self.to) {
+            Ok(value) => value.unwrap_or(RespFrame::Null(RespNull)),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for LMPop {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.pop.execute(backend)
+    }
+}
+
+impl TryFrom<RespArray> for Push {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, end, only_existing) = match command_name(&value)?.as_str() {
+            "lpush" => ("lpush", ListEnd::Left, false),
+            "rpush" => ("rpush", ListEnd::Right, false),
+            "lpushx" => ("lpushx", ListEnd::Left, true),
+            "rpushx" => ("rpushx", ListEnd::Right, true),
+            name => {
+                return Err(CommandError::InvalidCommand(format!(
+                    "Invalid command: expected lpush, got {name}"
+                )))
+            }
+        };
+        validate_command(&value, &[name], Arity::AtLeast(TWO_ARGS))?;
+        let mut args = extract_args(value, ONE_ARGS)?.into_iter();
+        let key = extract_string(args.next().ok_or(CommandError::SyntaxError)?)?;
+        Ok(Push {
+            key,
+            values: args.collect(),
+            end,
+            only_existing,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Pop {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, end) = match command_name(&value)?.as_str() {
+            "lpop" => ("lpop", ListEnd::Left),
+            "rpop" => ("rpop", ListEnd::Right),
+            name => {
+                return Err(CommandError::InvalidCommand(format!(
+                    "Invalid command: expected lpop, got {name}"
+                )))
+            }
+        };
+        validate_command(&value, &[name], Arity::AtLeast(ONE_ARGS))?;
+        if value.len() > 3 {
+            return Err(CommandError::WrongArity(name.to_string()));
+        }
+        let mut args = extract_args(value, ONE_ARGS)?.into_iter();
+        let key = extract_string(args.next().ok_or(CommandError::SyntaxError)?)?;
+        let count = match args.next() {
+            Some(ref count) => Some(parse_positive(count)?),
+            None => None,
+        };
+        Ok(Pop { key, end, count })
+    }
+}
+
+impl TryFrom<RespArray> for LLen {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["llen"], ONE_ARGS)?;
+        let mut args = extract_args(value, ONE_ARGS)?.into_iter();
+        let key = extract_string(args.next().ok_or(CommandError::SyntaxError)?)?;
+        Ok(LLen { key })
+    }
+}
+
+impl TryFrom<RespArray> for LRange {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["lrange"], THREE_ARGS)?;
+        let (key, start, stop) = key_and_range(value)?;
+        Ok(LRange { key, start, stop })
+    }
+}
+
+impl TryFrom<RespArray> for LTrim {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["ltrim"], THREE_ARGS)?;
+        let (key, start, stop) = key_and_range(value)?;
+        Ok(LTrim { key, start, stop })
+    }
+}
+
+impl TryFrom<RespArray> for LIndex {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["lindex"], TWO_ARGS)?;
+        let mut args = extract_args(value, ONE_ARGS)?.into_iter();
+        match (args.next(), args.next()) {
+            (Some(key), Some(ref index)) => Ok(LIndex {
+                key: extract_string(key)?,
+                index: parse_int(index)?,
+            }),
+            _ => Err(CommandError::WrongArity("lindex".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for LSet {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["lset"], THREE_ARGS)?;
+        let mut args = extract_args(value, ONE_ARGS)?.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(key), Some(ref index), Some(value)) => Ok(LSet {
+                key: extract_string(key)?,
+                index: parse_int(index)?,
+                value,
+            }),
+            _ => Err(CommandError::WrongArity("lset".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for LRem {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["lrem"], THREE_ARGS)?;
+        let mut args = extract_args(value, ONE_ARGS)?.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(key), Some(ref count), Some(value)) => Ok(LRem {
+                key: extract_string(key)?,
+                count: parse_int(count)?,
+                value,
+            }),
+            _ => Err(CommandError::WrongArity("lrem".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for LInsert {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["linsert"], FOUR_ARGS)?;
+        let mut args = extract_args(value, ONE_ARGS)?.into_iter();
+        match (args.next(), args.next(), args.next(), args.next()) {
+            (Some(key), Some(where_), Some(pivot), Some(value)) => {
+                let before = match extract_string(where_)?.to_ascii_lowercase().as_str() {
+                    "before" => true,
+                    "after" => false,
+                    _ => return Err(CommandError::SyntaxError),
+                };
+                Ok(LInsert {
+                    key: extract_string(key)?,
+                    before,
+                    pivot,
+                    value,
+                })
+            }
+            _ => Err(CommandError::WrongArity("linsert".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for LPos {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["lpos"], Arity::AtLeast(TWO_ARGS))?;
+        let mut args = extract_args(value, ONE_ARGS)?.into_iter();
+        let key = extract_string(args.next().ok_or(CommandError::SyntaxError)?)?;
+        let element = args.next().ok_or(CommandError::SyntaxError)?;
+        let mut opts = LposOptions::default();
+        let mut multiple = false;
+        while let Some(arg) = args.next() {
+            let option = extract_string(arg)?.to_ascii_lowercase();
+            let value = parse_int(&args.next().ok_or(CommandError::SyntaxError)?)?;
+            match option.as_str() {
+                "rank" if value == 0 || value == i64::MIN => {
+                    return Err(CommandError::InvalidArgument(
+                        "RANK can't be zero: use 1 to start from the first match, 2 from the second ... or use negative to start from the end of the list".to_string(),
+                    ))
+                }
+                "rank" => opts.rank = value,
+                "count" if value < 0 => {
+                    return Err(CommandError::InvalidArgument(
+                        "COUNT can't be negative".to_string(),
+                    ))
+                }
+                "count" => {
+                    opts.count = value as usize;
+                    multiple = true;
+                }
+                "maxlen" if value < 0 => {
+                    return Err(CommandError::InvalidArgument(
+                        "MAXLEN can't be negative".to_string(),
+                    ))
+                }
+                "maxlen" => opts.max_len = value as usize,
+                _ => return Err(CommandError::SyntaxError),
+            }
+        }
+        Ok(LPos {
+            key,
+            element,
+            opts,
+            multiple,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for LMove {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let name = command_name(&value)?;
+        let lmove = name == "lmove";
+        if lmove {
+            validate_command(&value, &["lmove"], FOUR_ARGS)?;
+        } else {
+            validate_command(&value, &["rpoplpush"], TWO_ARGS)?;
+        }
+        let mut args = extract_args(value, ONE_ARGS)?.into_iter();
+        let src = extract_string(args.next().ok_or(CommandError::SyntaxError)?)?;
+        let dst = extract_string(args.next().ok_or(CommandError::SyntaxError)?)?;
+        let (from, to) = if lmove {
+            (parse_end(args.next())?, parse_end(args.next())?)
+        } else {
+            (ListEnd::Right, ListEnd::Left)
+        };
+        Ok(LMove { src, dst, from, to })
+    }
+}
+
+impl TryFrom<RespArray> for LMPop {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["lmpop"], Arity::AtLeast(THREE_ARGS))?;
+        let args = extract_args(value, ONE_ARGS)?;
+        let (keys, end, count) = parse_multi_pop(args, parse_end)?;
+        Ok(LMPop {
+            pop: PopOp::ListMulti { keys, end, count },
+        })
+    }
+}
+
+// LEFT or RIGHT
+pub(super) fn parse_end(frame: Option<RespFrame>) -> Result<ListEnd, CommandError> {
+    let end = extract_string(frame.ok_or(CommandError::SyntaxError)?)?;
+    match end.to_ascii_lowercase().as_str() {
+        "left" => Ok(ListEnd::Left),
+        "right" => Ok(ListEnd::Right),
+        _ => Err(CommandError::SyntaxError),
+    }
+}
+
+// a COUNT argument, Redis refuses negative ones
+pub(super) fn parse_positive(frame: &RespFrame) -> Result<usize, CommandError> {
+    match parse_int(frame)? {
+        n if n < 0 => Err(CommandError::InvalidArgument(
+            "value is out of range, must be positive".to_string(),
+        )),
+        n => Ok(n as usize),
+    }
+}
+
+// the `key start stop` arguments of LRANGE and LTRIM
+fn key_and_range(value: RespArray) -> Result<(String, i64, i64), CommandError> {
+    let mut args = extract_args(value, ONE_ARGS)?.into_iter();
+    match (args.next(), args.next(), args.next()) {
+        (Some(key), Some(ref start), Some(ref stop)) => {
+            Ok((extract_string(key)?, parse_int(start)?, parse_int(stop)?))
+        }
+        _ => Err(CommandError::SyntaxError),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::test_helpers::{array, bulk, run, run_async};
+    use crate::BackendError;
+
+    #[test]
+    fn test_push_pop_commands() {
+        let backend = Backend::new();
+        assert_eq!(run(&backend, &["lpushx", "l", "a"]), RespFrame::Integer(0));
+        assert_eq!(
+            run(&backend, &["RPUSH", "l", "a", "b", "c"]),
+            RespFrame::Integer(3)
+        );
+        assert_eq!(run(&backend, &["lpush", "l", "z"]), RespFrame::Integer(4));
+        assert_eq!(run(&backend, &["lpop", "l"]), bulk("z"));
+        assert_eq!(run(&backend, &["rpop", "l", "2"]), array(&["c", "b"]));
+        assert_eq!(
+            run(&backend, &["rpop", "l", "-1"]),
+            CommandError::InvalidArgument("value is out of range, must be positive".to_string())
+                .into()
+        );
+        assert_eq!(run(&backend, &["rpop", "l", "5"]), array(&["a"]));
+        assert_eq!(run(&backend, &["lpop", "l"]), RespFrame::Null(RespNull));
+        assert_eq!(
+            run(&backend, &["lpop", "l", "1"]),
+            RespFrame::NullArray(RespNullArray)
+        );
+        assert_eq!(run(&backend, &["exists", "l"]), RespFrame::Integer(0));
+    }
+
+    #[test]
+    fn test_list_commands() {
+        let backend = Backend::new();
+        run(&backend, &["rpush", "l", "a", "b", "c", "b"]);
+        assert_eq!(run(&backend, &["llen", "l"]), RespFrame::Integer(4));
+        assert_eq!(
+            run(&backend, &["lrange", "l", "0", "-2"]),
+            array(&["a", "b", "c"])
+        );
+        assert_eq!(run(&backend, &["lindex", "l", "-1"]), bulk("b"));
+        assert_eq!(
+            run(&backend, &["lindex", "l", "9"]),
+            RespFrame::Null(RespNull)
+        );
+        assert_eq!(run(&backend, &["lset", "l", "0", "x"]), RESP_OK.clone());
+        assert_eq!(
+            run(&backend, &["lset", "l", "9", "x"]),
+            crate::BackendError::IndexOutOfRange.into()
+        );
+        assert_eq!(run(&backend, &["lpos", "l", "b"]), RespFrame::Integer(1));
+        assert_eq!(
+            run(&backend, &["lpos", "l", "b", "rank", "-1", "count", "0"]),
+            RespArray::new(vec![RespFrame::Integer(3), RespFrame::Integer(1)]).into()
+        );
+        assert_eq!(
+            run(&backend, &["lpos", "l", "q"]),
+            RespFrame::Null(RespNull)
+        );
+        assert!(matches!(
+            run(&backend, &["lpos", "l", "b", "rank", "0"]),
+            RespFrame::Error(_)
+        ));
+        assert_eq!(
+            run(&backend, &["linsert", "l", "AFTER", "c", "d"]),
+            RespFrame::Integer(5)
+        );
+        assert_eq!(
+            run(&backend, &["linsert", "l", "around", "c", "d"]),
+            CommandError::SyntaxError.into()
+        );
+        assert_eq!(
+            run(&backend, &["lrem", "l", "0", "b"]),
+            RespFrame::Integer(2)
+        );
+        assert_eq!(run(&backend, &["ltrim", "l", "1", "-1"]), RESP_OK.clone());
+        assert_eq!(
+            run(&backend, &["lrange", "l", "0", "-1"]),
+            array(&["c", "d"])
+        );
+    }
+
+    #[test]
+    fn test_lmove_commands() {
+        let backend = Backend::new();
+        run(&backend, &["rpush", "src", "a", "b"]);
+        assert_eq!(run(&backend, &["rpoplpush", "src", "dst"]), bulk("b"));
+        assert_eq!(
+            run(&backend, &["lmove", "src", "dst", "LEFT", "RIGHT"]),
+            bulk("a")
+        );
+        assert_eq!(
+            run(&backend, &["lrange", "dst", "0", "-1"]),
+            array(&["b", "a"])
+        );
+        assert_eq!(
+            run(&backend, &["lmove", "src", "dst", "left", "right"]),
+            RespFrame::Null(RespNull)
+        );
+        assert_eq!(
+            run(&backend, &["lmove", "dst", "src", "up", "right"]),
+            CommandError::SyntaxError.into()
+        );
+    }
+
+    #[test]
+    fn test_lmpop_command() {
+        let backend = Backend::new();
+        run(&backend, &["rpush", "b", "1", "2", "3"]);
+        assert_eq!(
+            run(&backend, &["lmpop", "2", "a", "b", "RIGHT", "COUNT", "2"]),
+            RespArray::new(vec![bulk("b"), array(&["3", "2"])]).into()
+        );
+        assert_eq!(
+            run(&backend, &["lmpop", "1", "a", "left"]),
+            RespFrame::NullArray(RespNullArray)
+        );
+        assert_eq!(
+            run(&backend, &["lmpop", "0", "a", "left"]),
+            CommandError::InvalidArgument("numkeys should be greater than 0".to_string()).into()
+        );
+        assert_eq!(
+            run(&backend, &["lmpop", "1", "b", "left", "count", "0"]),
+            CommandError::InvalidArgument("count should be greater than 0".to_string()).into()
+        );
+        assert_eq!(
+            run(&backend, &["lmpop", "3", "a", "b", "left"]),
+            CommandError::SyntaxError.into()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_blocking_pop_commands() {
+        let backend = Backend::new();
+        run(&backend, &["rpush", "b", "x"]);
+        assert_eq!(
+            run_async(&backend, &["blpop", "a", "b", "0"]).await,
+            array(&["b", "x"])
+        );
+        assert_eq!(
+            run_async(&backend, &["brpop", "a", "0.01"]).await,
+            RespFrame::NullArray(RespNullArray)
+        );
+        assert_eq!(
+            run_async(&backend, &["blpop", "a", "-1"]).await,
+            CommandError::InvalidArgument("timeout is negative".to_string()).into()
+        );
+        assert_eq!(
+            run_async(&backend, &["blpop", "a", "soon"]).await,
+            CommandError::InvalidArgument("timeout is not a float or out of range".to_string())
+                .into()
+        );
+        run(&backend, &["set", "s", "v"]);
+        assert_eq!(
+            run_async(&backend, &["blpop", "s", "0"]).await,
+            BackendError::WrongType.into()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_blocking_pop_wakes_up_on_push() {
+        let backend = Backend::new();
+        let client = backend.clone();
+        let waiter = tokio::spawn(async move {
+            run_async(&client, &["blmpop", "0", "1", "l", "LEFT", "COUNT", "5"]).await
+        });
+        let mover = {
+            let client = backend.clone();
+            tokio::spawn(async move {
+                run_async(&client, &["blmove", "src", "l", "LEFT", "RIGHT", "0"]).await
+            })
+        };
+        while backend.blocked_clients() < 2 {
+            tokio::task::yield_now().await;
+        }
+        // the moved element wakes up the client blocked on the destination
+        run(&backend, &["rpush", "src", "a"]);
+        assert_eq!(mover.await.unwrap(), bulk("a"));
+        assert_eq!(
+            waiter.await.unwrap(),
+            RespArray::new(vec![bulk("l"), array(&["a"])]).into()
+        );
+        assert_eq!(run(&backend, &["llen", "l"]), RespFrame::Integer(0));
+    }
+}
@@ -1,7 +1,13 @@
-use crate::{CommandError, Get, RespArray, RespFrame, RESP_OK};
+use crate::{
+    BulkString, CommandError, Get, IncrBy, IncrByFloat, MGet, MSet, RespArray, RespFrame,
+    SetCondition, SetOptions, RESP_OK,
+};
 use std::convert::TryFrom;
 
-use super::{extract_args, validate_command, CommandExecutor, Set};
+use super::{
+    command_name, extract_args, extract_string, parse_float, parse_int, validate_command, Arity,
+    CommandExecutor, Set, SetExpire,
+};
 
 const ONE_ARGS: usize = 1;
 const TWO_ARGS: usize = 2;
@@ -9,16 +15,77 @@ const TWO_ARGS: usize = 2;
 impl CommandExecutor for Get {
     fn execute(self, backend: &crate::Backend) -> RespFrame {
         match backend.get(&self.key) {
-            Some(value) => value,
-            None => RespFrame::Null(crate::RespNull),
+            Ok(Some(value)) => value,
+            Ok(None) => RespFrame::Null(crate::RespNull),
+            Err(e) => e.into(),
         }
     }
 }
 
 impl CommandExecutor for Set {
     fn execute(self, backend: &crate::Backend) -> RespFrame {
-        backend.set(self.key, self.value);
-        RESP_OK.clone()
+        let mut opts = SetOptions {
+            condition: self.condition,
+            get: self.get,
+            ..Default::default()
+        };
+        match self.expire {
+            Some(SetExpire::Relative(ttl)) => match backend.now_ms().checked_add(ttl) {
+                Some(at) => opts.expire_at = Some(at),
+                None => return CommandError::InvalidExpireTime("set".to_string()).into(),
+            },
+            Some(SetExpire::At(at)) => opts.expire_at = Some(at),
+            Some(SetExpire::KeepTtl) => opts.keep_ttl = true,
+            None => {}
+        }
+        match backend.set_with_options(self.key, self.value, opts) {
+            // with GET the reply is the old value, whether or not the write happened
+            Ok((_, Some(old))) => old,
+            Ok((_, None)) if self.get => RespFrame::Null(crate::RespNull),
+            Ok((true, None)) => RESP_OK.clone(),
+            Ok((false, None)) => RespFrame::Null(crate::RespNull),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for MGet {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let values = backend
+            .mget(&self.keys)
+            .into_iter()
+            .map(|v| v.unwrap_or(RespFrame::Null(crate::RespNull)))
+            .collect::<Vec<_>>();
+        RespArray::new(values).into()
+    }
+}
+
+impl CommandExecutor for MSet {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let written = backend.mset(self.pairs, self.nx);
+        if self.nx {
+            RespFrame::Integer(written as i64)
+        } else {
+            RESP_OK.clone()
+        }
+    }
+}
+
+impl CommandExecutor for IncrBy {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.incr_by(self.key, self.delta) {
+            Ok(n) => RespFrame::Integer(n),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for IncrByFloat {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.incr_by_float(self.key, self.delta) {
+            Ok(n) => BulkString::from(n).into(),
+            Err(e) => e.into(),
+        }
     }
 }
 
@@ -42,17 +109,147 @@ impl TryFrom<RespArray> for Get {
 impl TryFrom<RespArray> for Set {
     type Error = CommandError;
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
-        // validate the command
-        validate_command(&value, &["set"], TWO_ARGS)?;
+        // validate the command,
+        // SET key value [NX | XX] [GET] [EX seconds | PX ms | EXAT ts | PXAT ts-ms | KEEPTTL]
+        validate_command(&value, &["set"], Arity::AtLeast(TWO_ARGS))?;
 
         let mut args = extract_args(value, ONE_ARGS)?.into_iter();
-        match (args.next(), args.next()) {
-            (Some(RespFrame::BulkString(ref key)), Some(value)) => Ok(Set {
+        let mut cmd = match (args.next(), args.next()) {
+            (Some(RespFrame::BulkString(ref key)), Some(value)) => Set {
                 key: String::from_utf8_lossy(key).to_string(),
                 value,
+                condition: SetCondition::Always,
+                expire: None,
+                get: false,
+            },
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "SET command requires a string key and value".to_string(),
+                ))
+            }
+        };
+        while let Some(arg) = args.next() {
+            let opt = match arg {
+                RespFrame::BulkString(ref opt) => opt.to_ascii_uppercase(),
+                _ => return Err(CommandError::SyntaxError),
+            };
+            match opt.as_slice() {
+                b"NX" if cmd.condition == SetCondition::Always => {
+                    cmd.condition = SetCondition::IfNotExists
+                }
+                b"XX" if cmd.condition == SetCondition::Always => {
+                    cmd.condition = SetCondition::IfExists
+                }
+                b"GET" => cmd.get = true,
+                b"KEEPTTL" if cmd.expire.is_none() => cmd.expire = Some(SetExpire::KeepTtl),
+                b"EX" | b"PX" | b"EXAT" | b"PXAT" if cmd.expire.is_none() => {
+                    let time = parse_int(&args.next().ok_or(CommandError::SyntaxError)?)?;
+                    let ms = match opt.as_slice() {
+                        b"EX" | b"EXAT" => time.checked_mul(1000),
+                        _ => Some(time),
+                    };
+                    let ms = match ms {
+                        Some(ms) if ms > 0 => ms as u64,
+                        _ => return Err(CommandError::InvalidExpireTime("set".to_string())),
+                    };
+                    cmd.expire = match opt.as_slice() {
+                        b"EX" | b"PX" => Some(SetExpire::Relative(ms)),
+                        _ => Some(SetExpire::At(ms)),
+                    };
+                }
+                _ => return Err(CommandError::SyntaxError),
+            }
+        }
+        Ok(cmd)
+    }
+}
+
+impl TryFrom<RespArray> for MGet {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["mget"], Arity::AtLeast(ONE_ARGS))?;
+        let keys = extract_args(value, ONE_ARGS)?
+            .into_iter()
+            .map(extract_string)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(MGet { keys })
+    }
+}
+
+impl TryFrom<RespArray> for MSet {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, nx) = match command_name(&value)?.as_str() {
+            "mset" => ("mset", false),
+            "msetnx" => ("msetnx", true),
+            name => {
+                return Err(CommandError::InvalidCommand(format!(
+                    "Invalid command: expected mset, got {name}"
+                )))
+            }
+        };
+        validate_command(&value, &[name], Arity::Pairs(0))?;
+        let mut args = extract_args(value, ONE_ARGS)?.into_iter();
+        let mut pairs = Vec::with_capacity(args.len() / 2);
+        while let (Some(key), Some(value)) = (args.next(), args.next()) {
+            pairs.push((extract_string(key)?, value));
+        }
+        Ok(MSet { pairs, nx })
+    }
+}
+
+impl TryFrom<RespArray> for IncrBy {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let name = command_name(&value)?;
+        let (name, n_args) = match name.as_str() {
+            "incr" => ("incr", ONE_ARGS),
+            "decr" => ("decr", ONE_ARGS),
+            "incrby" => ("incrby", TWO_ARGS),
+            "decrby" => ("decrby", TWO_ARGS),
+            _ => {
+                return Err(CommandError::InvalidCommand(format!(
+                    "Invalid command: expected incr, got {name}"
+                )))
+            }
+        };
+        validate_command(&value, &[name], n_args)?;
+        let mut args = extract_args(value, ONE_ARGS)?.into_iter();
+        let key = match args.next() {
+            Some(RespFrame::BulkString(ref key)) => String::from_utf8_lossy(key).to_string(),
+            _ => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "{} command requires a string key",
+                    name.to_uppercase()
+                )))
+            }
+        };
+        let delta = match args.next() {
+            Some(ref delta) => parse_int(delta)?,
+            None => 1,
+        };
+        let delta = match name {
+            "decr" | "decrby" => delta.checked_neg().ok_or_else(|| {
+                CommandError::InvalidArgument("decrement would overflow".to_string())
+            })?,
+            _ => delta,
+        };
+        Ok(IncrBy { key, delta })
+    }
+}
+
+impl TryFrom<RespArray> for IncrByFloat {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["incrbyfloat"], TWO_ARGS)?;
+        let mut args = extract_args(value, ONE_ARGS)?.into_iter();
+        match (args.next(), args.next()) {
+            (Some(RespFrame::BulkString(ref key)), Some(ref delta)) => Ok(IncrByFloat {
+                key: String::from_utf8_lossy(key).to_string(),
+                delta: parse_float(delta)?,
             }),
             _ => Err(CommandError::InvalidArgument(
-                "SET command requires a string key and value".to_string(),
+                "INCRBYFLOAT command requires a string key and increment".to_string(),
             )),
         }
     }
@@ -61,7 +258,8 @@ impl TryFrom<RespArray> for Set {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{Backend, CommandExecutor, RespArray, RespDecode};
+    use crate::cmd::test_helpers::run;
+    use crate::{Backend, CommandExecutor, RespArray, RespDecode, RespEncode};
     use anyhow::Result;
     use bytes::BytesMut;
 
@@ -71,6 +269,9 @@ mod tests {
         let cmd = Set {
             key: "hello".to_string(),
             value: RespFrame::BulkString(b"world".into()),
+            condition: SetCondition::Always,
+            expire: None,
+            get: false,
         };
         let result = cmd.execute(&backend);
         assert_eq!(result, RESP_OK.clone());
@@ -84,6 +285,25 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_get_wrong_type() -> Result<()> {
+        let backend = Backend::new();
+        backend.hset(
+            "hash".to_string(),
+            vec![("field".to_string(), b"value".into())],
+        )?;
+
+        let cmd = Get {
+            key: "hash".to_string(),
+        };
+        assert_eq!(
+            cmd.execute(&backend).encode(),
+            b"-WRONGTYPE Operation against a key holding the wrong kind of value\r\n"
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_get_from_resp_array() -> Result<()> {
         let mut buf = BytesMut::new();
@@ -97,6 +317,27 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_get_wrong_arity() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$3\r\nGET\r\n$5\r\nhello\r\n$5\r\nworld\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+        let err = Get::try_from(frame).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "ERR wrong number of arguments for 'get' command"
+        );
+
+        let frame: RespFrame = err.into();
+        assert_eq!(
+            frame,
+            crate::SimpleError::new("ERR wrong number of arguments for 'get' command").into()
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_set_from_resp_array() -> Result<()> {
         let mut buf = BytesMut::new();
@@ -110,4 +351,188 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_set_with_expire_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$3\r\nset\r\n$5\r\nhello\r\n$5\r\nworld\r\n$2\r\nEX\r\n$2\r\n10\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Set = frame.try_into()?;
+        assert_eq!(result.expire, Some(SetExpire::Relative(10_000)));
+
+        buf.extend_from_slice(
+            b"*5\r\n$3\r\nset\r\n$5\r\nhello\r\n$5\r\nworld\r\n$2\r\npx\r\n$1\r\n0\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let err = Set::try_from(frame).unwrap_err();
+        assert_eq!(err.to_string(), "ERR invalid expire time in 'set' command");
+
+        Ok(())
+    }
+
+    fn set_cmd(args: &[&str]) -> Result<Set, CommandError> {
+        let frames = std::iter::once("set")
+            .chain(args.iter().copied())
+            .map(|s| crate::BulkString::from(s).into())
+            .collect::<Vec<RespFrame>>();
+        Set::try_from(RespArray::new(frames))
+    }
+
+    #[test]
+    fn test_set_options_grammar() -> Result<()> {
+        let cmd = set_cmd(&["lock", "token", "nx", "PX", "30000"])?;
+        assert_eq!(cmd.condition, SetCondition::IfNotExists);
+        assert_eq!(cmd.expire, Some(SetExpire::Relative(30_000)));
+        assert!(!cmd.get);
+
+        let cmd = set_cmd(&["key", "value", "XX", "GET", "EXAT", "100"])?;
+        assert_eq!(cmd.condition, SetCondition::IfExists);
+        assert_eq!(cmd.expire, Some(SetExpire::At(100_000)));
+        assert!(cmd.get);
+
+        let cmd = set_cmd(&["key", "value", "keepttl"])?;
+        assert_eq!(cmd.expire, Some(SetExpire::KeepTtl));
+
+        for args in [
+            &["key", "value", "NX", "XX"][..],
+            &["key", "value", "EX", "10", "PX", "10"],
+            &["key", "value", "KEEPTTL", "EX", "10"],
+            &["key", "value", "PXAT", "10", "KEEPTTL"],
+            &["key", "value", "EX"],
+            &["key", "value", "FOO"],
+        ] {
+            let err = set_cmd(args).unwrap_err();
+            assert_eq!(err.to_string(), "ERR syntax error", "{args:?}");
+        }
+
+        let err = set_cmd(&["key", "value", "EX", "ten"]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "ERR value is not an integer or out of range"
+        );
+        let err = set_cmd(&["key", "value", "EX", "-1"]).unwrap_err();
+        assert_eq!(err.to_string(), "ERR invalid expire time in 'set' command");
+        let err = set_cmd(&["key"]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "ERR wrong number of arguments for 'set' command"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_options_execute() -> Result<()> {
+        let backend = Backend::new();
+        let lock = || set_cmd(&["lock", "token", "NX", "PX", "30000"]);
+        assert_eq!(lock()?.execute(&backend), RESP_OK.clone());
+        assert_eq!(lock()?.execute(&backend), RespFrame::Null(crate::RespNull));
+        assert!(backend.expire_time("lock").unwrap().is_some());
+
+        let ret = set_cmd(&["lock", "other", "XX", "GET"])?.execute(&backend);
+        assert_eq!(ret, RespFrame::BulkString(b"token".into()));
+        // a plain XX write drops the TTL
+        assert_eq!(backend.expire_time("lock"), Some(None));
+
+        let ret = set_cmd(&["missing", "value", "XX", "GET"])?.execute(&backend);
+        assert_eq!(ret, RespFrame::Null(crate::RespNull));
+        assert_eq!(backend.get("missing"), Ok(None));
+
+        let ret = set_cmd(&["fresh", "value", "NX", "GET"])?.execute(&backend);
+        assert_eq!(ret, RespFrame::Null(crate::RespNull));
+        assert_eq!(backend.get("fresh"), Ok(Some(b"value".into())));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_incr_decr_commands() {
+        let backend = Backend::new();
+        assert_eq!(run(&backend, &["incr", "n"]), RespFrame::Integer(1));
+        assert_eq!(
+            run(&backend, &["incrby", "n", "41"]),
+            RespFrame::Integer(42)
+        );
+        assert_eq!(run(&backend, &["decr", "n"]), RespFrame::Integer(41));
+        assert_eq!(
+            run(&backend, &["decrby", "n", "50"]),
+            RespFrame::Integer(-9)
+        );
+        assert_eq!(
+            run(&backend, &["get", "n"]),
+            RespFrame::BulkString(b"-9".into())
+        );
+
+        assert_eq!(
+            run(&backend, &["incrby", "n", "abc"]).encode(),
+            b"-ERR value is not an integer or out of range\r\n"
+        );
+        assert_eq!(
+            run(&backend, &["decrby", "n", "-9223372036854775808"]).encode(),
+            b"-ERR decrement would overflow\r\n"
+        );
+        run(&backend, &["set", "n", "9223372036854775807"]);
+        assert_eq!(
+            run(&backend, &["incr", "n"]).encode(),
+            b"-ERR increment or decrement would overflow\r\n"
+        );
+        assert_eq!(
+            run(&backend, &["incr", "n", "1"]).encode(),
+            b"-ERR wrong number of arguments for 'incr' command\r\n"
+        );
+    }
+
+    #[test]
+    fn test_incrbyfloat_command() {
+        let backend = Backend::new();
+        assert_eq!(
+            run(&backend, &["incrbyfloat", "f", "10.5"]),
+            RespFrame::BulkString(b"10.5".into())
+        );
+        assert_eq!(
+            run(&backend, &["incrbyfloat", "f", "-0.5"]),
+            RespFrame::BulkString(b"10".into())
+        );
+        assert_eq!(
+            run(&backend, &["incrbyfloat", "f", "5.0e3"]),
+            RespFrame::BulkString(b"5010".into())
+        );
+        assert_eq!(
+            run(&backend, &["incrbyfloat", "f", "nan"]).encode(),
+            b"-ERR value is not a valid float\r\n"
+        );
+    }
+
+    #[test]
+    fn test_mset_mget_commands() {
+        let backend = Backend::new();
+        assert_eq!(
+            run(&backend, &["mset", "a", "1", "b", "2"]),
+            RESP_OK.clone()
+        );
+        assert_eq!(
+            run(&backend, &["mget", "a", "missing", "b"]),
+            RespArray::new(vec![
+                RespFrame::BulkString(b"1".into()),
+                RespFrame::Null(crate::RespNull),
+                RespFrame::BulkString(b"2".into()),
+            ])
+            .into()
+        );
+        assert_eq!(
+            run(&backend, &["msetnx", "c", "3", "a", "4"]),
+            RespFrame::Integer(0)
+        );
+        assert_eq!(run(&backend, &["msetnx", "c", "3"]), RespFrame::Integer(1));
+        assert_eq!(
+            run(&backend, &["mset", "a", "1", "b"]).encode(),
+            b"-ERR wrong number of arguments for 'mset' command\r\n"
+        );
+        assert_eq!(
+            run(&backend, &["mget"]).encode(),
+            b"-ERR wrong number of arguments for 'mget' command\r\n"
+        );
+    }
 }
@@ -1,11 +1,30 @@
+mod blocking;
+mod connection;
+mod expire;
 mod hmap;
+mod keys;
+mod list;
 mod map;
+mod pubsub;
+mod server;
+mod set;
+mod stream;
+mod table;
+mod zset;
 
-use crate::{Backend, RespArray, RespError, RespFrame, SimpleString};
+use crate::{
+    Aggregate, Backend, ClaimOptions, ExpireCondition, ListEnd, LposOptions, PendingRange,
+    RespArray, RespError, RespFrame, RespVersion, SetCondition, SetOp, SimpleError, SimpleString,
+    StreamFields, StreamId, StreamTrim, XAddId, ZAddOptions, ZLimit, ZRangeBy,
+};
 use enum_dispatch::enum_dispatch;
 use lazy_static::lazy_static;
+use std::time::Duration;
 use thiserror::Error;
 
+pub use connection::ConnectionState;
+pub use table::{CommandFlag, CommandGroup, CommandSpec, KeySpec};
+
 lazy_static! {
     pub static ref RESP_OK: RespFrame = SimpleString::new("OK").into();
 }
@@ -15,30 +34,146 @@ pub trait CommandExecutor {
     fn execute(self, backend: &Backend) -> RespFrame;
 }
 
+// the Display output of each variant is the exact text sent back to the client, so every
+// message starts with a Redis error prefix (ERR, WRONGTYPE, ...)
 #[derive(Error, Debug)]
 pub enum CommandError {
-    #[error("Invalid command: {0}")]
+    #[error("ERR {0}")]
     InvalidCommand(String),
-    #[error("Invalid argument: {0}")]
+    #[error("ERR {0}")]
     InvalidArgument(String),
+    #[error("ERR unknown command '{name}', with args beginning with: {args}")]
+    UnknownCommand { name: String, args: String },
+    #[error("ERR unknown subcommand '{name}'. Try {command} HELP.")]
+    UnknownSubcommand { command: String, name: String },
+    #[error("ERR wrong number of arguments for '{0}' command")]
+    WrongArity(String),
+    #[error("ERR syntax error")]
+    SyntaxError,
+    #[error("ERR value is not an integer or out of range")]
+    NotAnInteger,
+    #[error("ERR value is not a valid float")]
+    NotAFloat,
+    #[error("ERR invalid expire time in '{0}' command")]
+    InvalidExpireTime(String),
+    #[error("NOPROTO unsupported protocol version")]
+    NoProto,
+    #[error("ERR Can't execute '{0}': only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING / QUIT / RESET are allowed in this context")]
+    SubscribedMode(String),
+    #[error("WRONGPASS invalid username-password pair or user is disabled.")]
+    WrongPass,
 
-    #[error("{0}")]
+    #[error("ERR Protocol error: {0}")]
     RespError(#[from] RespError),
-    #[error("Utf8 error: {0}")]
+    #[error("ERR invalid utf8 string: {0}")]
     Utf8Error(#[from] std::string::FromUtf8Error),
 }
 
+impl From<CommandError> for RespFrame {
+    fn from(e: CommandError) -> Self {
+        SimpleError::new(e.to_string()).into()
+    }
+}
+
 #[derive(Debug)]
 #[enum_dispatch(CommandExecutor)]
 pub enum Command {
     Get(Get),
     Set(Set),
+    MGet(MGet),
+    MSet(MSet),
+    IncrBy(IncrBy),
+    IncrByFloat(IncrByFloat),
     HGet(HGet),
     HSet(HSet),
     HGetAll(HGetAll),
-
-    // unrecognized command
-    Unrecognized(Unrecognized),
+    HSetNx(HSetNx),
+    HMGet(HMGet),
+    HDel(HDel),
+    HExists(HExists),
+    HLen(HLen),
+    HKeys(HKeys),
+    HIncrBy(HIncrBy),
+    HIncrByFloat(HIncrByFloat),
+    HStrLen(HStrLen),
+    HRandField(HRandField),
+    HScan(HScan),
+    Push(Push),
+    Pop(Pop),
+    LLen(LLen),
+    LRange(LRange),
+    LIndex(LIndex),
+    LSet(LSet),
+    LRem(LRem),
+    LTrim(LTrim),
+    LInsert(LInsert),
+    LPos(LPos),
+    LMove(LMove),
+    LMPop(LMPop),
+    BlockingPop(BlockingPop),
+    SAdd(SAdd),
+    SRem(SRem),
+    SMembers(SMembers),
+    SIsMember(SIsMember),
+    SMIsMember(SMIsMember),
+    SCard(SCard),
+    SPop(SPop),
+    SRandMember(SRandMember),
+    SScan(SScan),
+    SetAlgebra(SetAlgebra),
+    SInterCard(SInterCard),
+    SMove(SMove),
+    ZAdd(ZAdd),
+    ZScore(ZScore),
+    ZMScore(ZMScore),
+    ZIncrBy(ZIncrBy),
+    ZCard(ZCard),
+    ZRank(ZRank),
+    ZRem(ZRem),
+    ZRange(ZRange),
+    ZSetAlgebra(ZSetAlgebra),
+    ZRemRange(ZRemRange),
+    ZCount(ZCount),
+    ZPop(ZPop),
+    ZMPop(ZMPop),
+    ZRandMember(ZRandMember),
+    XAdd(XAdd),
+    XRange(XRange),
+    XLen(XLen),
+    XTrim(XTrim),
+    XDel(XDel),
+    XRead(XRead),
+    XGroup(XGroup),
+    XAck(XAck),
+    XPending(XPending),
+    XClaim(XClaim),
+    XAutoClaim(XAutoClaim),
+    XInfo(XInfo),
+    Type(Type),
+    Expire(Expire),
+    Ttl(Ttl),
+    Persist(Persist),
+    HExpire(HExpire),
+    HTtl(HTtl),
+    HPersist(HPersist),
+    CommandInfo(CommandInfo),
+    Hello(Hello),
+    Ping(Ping),
+    Echo(Echo),
+    Quit(Quit),
+    Reset(Reset),
+    Subscribe(Subscribe),
+    Unsubscribe(Unsubscribe),
+    Publish(Publish),
+    PubSub(PubSub),
+    Del(Del),
+    Exists(Exists),
+    Rename(Rename),
+    Copy(CopyKey),
+    Keys(Keys),
+    Scan(Scan),
+    DbSize(DbSize),
+    RandomKey(RandomKey),
 }
 
 #[derive(Debug)]
@@ -50,6 +185,34 @@ pub struct Get {
 pub struct Set {
     key: String,
     value: RespFrame,
+    condition: SetCondition,
+    expire: Option<SetExpire>,
+    get: bool,
+}
+
+#[derive(Debug)]
+pub struct MGet {
+    keys: Vec<String>,
+}
+
+// MSET and MSETNX
+#[derive(Debug)]
+pub struct MSet {
+    pairs: Vec<(String, RespFrame)>,
+    nx: bool,
+}
+
+// INCR, DECR, INCRBY and DECRBY
+#[derive(Debug)]
+pub struct IncrBy {
+    key: String,
+    delta: i64,
+}
+
+#[derive(Debug)]
+pub struct IncrByFloat {
+    key: String,
+    delta: f64,
 }
 
 #[derive(Debug)]
@@ -60,37 +223,761 @@ pub struct HGet {
 
 #[derive(Debug)]
 pub struct HSet {
+    key: String,
+    fields: Vec<(String, RespFrame)>,
+}
+
+#[derive(Debug)]
+pub struct HGetAll {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct HSetNx {
     key: String,
     field: String,
     value: RespFrame,
 }
 
 #[derive(Debug)]
-pub struct HGetAll {
+pub struct HMGet {
     key: String,
-    sort: bool,
+    fields: Vec<String>,
 }
 
 #[derive(Debug)]
-pub struct Unrecognized;
+pub struct HDel {
+    key: String,
+    fields: Vec<String>,
+}
 
-impl CommandExecutor for Unrecognized {
-    fn execute(self, _: &Backend) -> RespFrame {
-        RESP_OK.clone()
-    }
+#[derive(Debug)]
+pub struct HExists {
+    key: String,
+    field: String,
+}
+
+#[derive(Debug)]
+pub struct HLen {
+    key: String,
+}
+
+// HKEYS and HVALS
+#[derive(Debug)]
+pub struct HKeys {
+    key: String,
+    values: bool,
+}
+
+#[derive(Debug)]
+pub struct HIncrBy {
+    key: String,
+    field: String,
+    delta: i64,
+}
+
+#[derive(Debug)]
+pub struct HIncrByFloat {
+    key: String,
+    field: String,
+    delta: f64,
+}
+
+#[derive(Debug)]
+pub struct HStrLen {
+    key: String,
+    field: String,
+}
+
+// HRANDFIELD key [count [WITHVALUES]]
+#[derive(Debug)]
+pub struct HRandField {
+    key: String,
+    count: Option<i64>,
+    with_values: bool,
+}
+
+// HSCAN key cursor [MATCH pattern] [COUNT count] [NOVALUES]
+#[derive(Debug)]
+pub struct HScan {
+    key: String,
+    cursor: u64,
+    opts: ScanOptions,
+    no_values: bool,
+}
+
+#[derive(Debug)]
+pub struct Type {
+    key: String,
+}
+
+// DEL and UNLINK
+#[derive(Debug)]
+pub struct Del {
+    keys: Vec<String>,
+    lazy: bool,
+}
+
+#[derive(Debug)]
+pub struct Exists {
+    keys: Vec<String>,
+}
+
+// RENAME and RENAMENX
+#[derive(Debug)]
+pub struct Rename {
+    src: String,
+    dst: String,
+    nx: bool,
+}
+
+#[derive(Debug)]
+pub struct CopyKey {
+    src: String,
+    dst: String,
+    replace: bool,
+}
+
+#[derive(Debug)]
+pub struct Keys {
+    pattern: String,
+}
+
+// SCAN cursor [MATCH pattern] [COUNT count] [TYPE type]
+#[derive(Debug)]
+pub struct Scan {
+    cursor: u64,
+    opts: ScanOptions,
+    type_name: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct DbSize;
+
+#[derive(Debug)]
+pub struct RandomKey;
+
+// EXPIRE, PEXPIRE, EXPIREAT and PEXPIREAT
+#[derive(Debug)]
+pub struct Expire {
+    key: String,
+    time: i64,
+    unit: TimeUnit,
+    absolute: bool,
+}
+
+// TTL, PTTL, EXPIRETIME and PEXPIRETIME
+#[derive(Debug)]
+pub struct Ttl {
+    key: String,
+    unit: TimeUnit,
+    absolute: bool,
+}
+
+#[derive(Debug)]
+pub struct Persist {
+    key: String,
+}
+
+// LPUSH, RPUSH, LPUSHX and RPUSHX
+#[derive(Debug)]
+pub struct Push {
+    key: String,
+    values: Vec<RespFrame>,
+    end: ListEnd,
+    only_existing: bool,
+}
+
+// LPOP and RPOP, with an optional count
+#[derive(Debug)]
+pub struct Pop {
+    key: String,
+    end: ListEnd,
+    count: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct LLen {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct LRange {
+    key: String,
+    start: i64,
+    stop: i64,
+}
+
+#[derive(Debug)]
+pub struct LIndex {
+    key: String,
+    index: i64,
+}
+
+#[derive(Debug)]
+pub struct LSet {
+    key: String,
+    index: i64,
+    value: RespFrame,
+}
+
+#[derive(Debug)]
+pub struct LRem {
+    key: String,
+    count: i64,
+    value: RespFrame,
+}
+
+#[derive(Debug)]
+pub struct LTrim {
+    key: String,
+    start: i64,
+    stop: i64,
+}
+
+// LINSERT key BEFORE | AFTER pivot element
+#[derive(Debug)]
+pub struct LInsert {
+    key: String,
+    before: bool,
+    pivot: RespFrame,
+    value: RespFrame,
+}
+
+// LPOS key element [RANK rank] [COUNT num-matches] [MAXLEN len]
+#[derive(Debug)]
+pub struct LPos {
+    key: String,
+    element: RespFrame,
+    opts: LposOptions,
+    // with COUNT the reply is an array
+    multiple: bool,
+}
+
+// LMOVE and RPOPLPUSH
+#[derive(Debug)]
+pub struct LMove {
+    src: String,
+    dst: String,
+    from: ListEnd,
+    to: ListEnd,
+}
+
+// LMPOP numkeys key [key ...] LEFT | RIGHT [COUNT count]
+#[derive(Debug)]
+pub struct LMPop {
+    pop: PopOp,
+}
+
+// BLPOP, BRPOP, BLMOVE, BRPOPLPUSH, BLMPOP, BZPOPMIN, BZPOPMAX and BZMPOP: a pop waiting for
+// an element, forever without a timeout
+#[derive(Debug)]
+pub struct BlockingPop {
+    pop: PopOp,
+    timeout: Option<Duration>,
+}
+
+// what the pop commands take from the first of their keys holding elements, the reply starting
+// with that key
+#[derive(Debug, Clone)]
+enum PopOp {
+    // one element of a list
+    List {
+        keys: Vec<String>,
+        end: ListEnd,
+    },
+    // up to `count` elements of a list
+    ListMulti {
+        keys: Vec<String>,
+        end: ListEnd,
+        count: usize,
+    },
+    Move {
+        src: String,
+        dst: String,
+        from: ListEnd,
+        to: ListEnd,
+    },
+    // the member with the lowest score of a sorted set, or the highest with `max`
+    ZSet {
+        keys: Vec<String>,
+        max: bool,
+    },
+    // up to `count` members of a sorted set
+    ZSetMulti {
+        keys: Vec<String>,
+        max: bool,
+        count: usize,
+    },
+}
+
+#[derive(Debug)]
+pub struct SAdd {
+    key: String,
+    members: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct SRem {
+    key: String,
+    members: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct SMembers {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct SIsMember {
+    key: String,
+    member: String,
+}
+
+#[derive(Debug)]
+pub struct SMIsMember {
+    key: String,
+    members: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct SCard {
+    key: String,
+}
+
+// SPOP key [count]: without a count the reply is a single member
+#[derive(Debug)]
+pub struct SPop {
+    key: String,
+    count: Option<usize>,
+}
+
+// SRANDMEMBER key [count]: a negative count allows the same member several times
+#[derive(Debug)]
+pub struct SRandMember {
+    key: String,
+    count: Option<i64>,
+}
+
+// SSCAN key cursor [MATCH pattern] [COUNT count]
+#[derive(Debug)]
+pub struct SScan {
+    key: String,
+    cursor: u64,
+    opts: ScanOptions,
+}
+
+// SINTER, SUNION and SDIFF, and their STORE variants writing to `dst`
+#[derive(Debug)]
+pub struct SetAlgebra {
+    op: SetOp,
+    dst: Option<String>,
+    keys: Vec<String>,
+}
+
+// SINTERCARD numkeys key [key ...] [LIMIT limit]
+#[derive(Debug)]
+pub struct SInterCard {
+    keys: Vec<String>,
+    limit: usize,
+}
+
+#[derive(Debug)]
+pub struct SMove {
+    src: String,
+    dst: String,
+    member: String,
+}
+
+// ZADD key [NX | XX] [GT | LT] [CH] [INCR] score member [score member ...]
+#[derive(Debug)]
+pub struct ZAdd {
+    key: String,
+    opts: ZAddOptions,
+    // INCR: a single pair whose score is added, the reply is the new score
+    incr: bool,
+    members: Vec<(f64, String)>,
+}
+
+#[derive(Debug)]
+pub struct ZScore {
+    key: String,
+    member: String,
+}
+
+#[derive(Debug)]
+pub struct ZMScore {
+    key: String,
+    members: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct ZIncrBy {
+    key: String,
+    delta: f64,
+    member: String,
+}
+
+#[derive(Debug)]
+pub struct ZCard {
+    key: String,
+}
+
+// ZRANK and ZREVRANK key member [WITHSCORE]
+#[derive(Debug)]
+pub struct ZRank {
+    key: String,
+    member: String,
+    rev: bool,
+    with_score: bool,
+}
+
+#[derive(Debug)]
+pub struct ZRem {
+    key: String,
+    members: Vec<String>,
+}
+
+// ZRANGE key start stop [BYSCORE | BYLEX] [REV] [LIMIT offset count] [WITHSCORES]
+#[derive(Debug)]
+pub struct ZRange {
+    key: String,
+    range: ZRangeBy,
+    rev: bool,
+    limit: Option<ZLimit>,
+    with_scores: bool,
+}
+
+// ZUNION, ZINTER and ZDIFF numkeys key [key ...] [WEIGHTS weight [weight ...]]
+// [AGGREGATE SUM | MIN | MAX] [WITHSCORES], and their STORE variants writing to `dst`
+#[derive(Debug)]
+pub struct ZSetAlgebra {
+    op: SetOp,
+    dst: Option<String>,
+    keys: Vec<String>,
+    // one per key, 1 by default
+    weights: Vec<f64>,
+    aggregate: Aggregate,
+    with_scores: bool,
+}
+
+// ZREMRANGEBYRANK, ZREMRANGEBYSCORE and ZREMRANGEBYLEX
+#[derive(Debug)]
+pub struct ZRemRange {
+    key: String,
+    range: ZRangeBy,
+}
+
+// ZCOUNT and ZLEXCOUNT
+#[derive(Debug)]
+pub struct ZCount {
+    key: String,
+    range: ZRangeBy,
+}
+
+// ZPOPMIN and ZPOPMAX key [count]
+#[derive(Debug)]
+pub struct ZPop {
+    key: String,
+    max: bool,
+    count: Option<usize>,
+}
+
+// ZMPOP numkeys key [key ...] MIN | MAX [COUNT count]
+#[derive(Debug)]
+pub struct ZMPop {
+    pop: PopOp,
+}
+
+// ZRANDMEMBER key [count [WITHSCORES]]
+#[derive(Debug)]
+pub struct ZRandMember {
+    key: String,
+    count: Option<i64>,
+    with_scores: bool,
+}
+
+// XADD key [NOMKSTREAM] [MAXLEN | MINID [= | ~] threshold [LIMIT count]] * | id field value
+// [field value ...]
+#[derive(Debug)]
+pub struct XAdd {
+    key: String,
+    no_mkstream: bool,
+    trim: Option<StreamTrim>,
+    id: XAddId,
+    fields: StreamFields,
+}
+
+// XRANGE key start end [COUNT count] and XREVRANGE key end start [COUNT count], the bounds
+// made inclusive
+#[derive(Debug)]
+pub struct XRange {
+    key: String,
+    start: StreamId,
+    end: StreamId,
+    rev: bool,
+    count: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct XLen {
+    key: String,
+}
+
+// XTRIM key MAXLEN | MINID [= | ~] threshold [LIMIT count]
+#[derive(Debug)]
+pub struct XTrim {
+    key: String,
+    trim: StreamTrim,
+}
+
+#[derive(Debug)]
+pub struct XDel {
+    key: String,
+    ids: Vec<StreamId>,
+}
+
+// XREAD [COUNT count] [BLOCK milliseconds] STREAMS key [key ...] id [id ...] and
+// XREADGROUP GROUP group consumer [COUNT count] [BLOCK milliseconds] [NOACK] STREAMS key
+// [key ...] id [id ...]
+#[derive(Debug)]
+pub struct XRead {
+    group: Option<ReadGroup>,
+    count: Option<usize>,
+    // BLOCK, a timeout of None waits forever
+    block: bool,
+    timeout: Option<Duration>,
+    // a None ID is `$` for XREAD, the last ID of the stream, and `>` for XREADGROUP, the
+    // entries the group didn't deliver yet
+    streams: Vec<(String, Option<StreamId>)>,
+}
+
+// XGROUP CREATE | SETID | DESTROY | CREATECONSUMER | DELCONSUMER | HELP
+#[derive(Debug)]
+pub struct XGroup {
+    subcommand: GroupSubcommand,
+    key: String,
+    group: String,
+}
+
+// XACK key group id [id ...]
+#[derive(Debug)]
+pub struct XAck {
+    key: String,
+    group: String,
+    ids: Vec<StreamId>,
+}
+
+// XPENDING key group [[IDLE min-idle-time] start end count [consumer]]
+#[derive(Debug)]
+pub struct XPending {
+    key: String,
+    group: String,
+    range: Option<PendingRange>,
+}
+
+// XCLAIM key group consumer min-idle-time id [id ...] [IDLE ms] [TIME unix-time-milliseconds]
+// [RETRYCOUNT count] [FORCE] [JUSTID] [LASTID lastid]
+#[derive(Debug)]
+pub struct XClaim {
+    key: String,
+    group: String,
+    consumer: String,
+    min_idle: u64,
+    ids: Vec<StreamId>,
+    options: ClaimOptions,
+    // IDLE, made the delivery time when the command runs
+    idle: Option<u64>,
+}
+
+// XAUTOCLAIM key group consumer min-idle-time start [COUNT count] [JUSTID]
+#[derive(Debug)]
+pub struct XAutoClaim {
+    key: String,
+    group: String,
+    consumer: String,
+    min_idle: u64,
+    start: StreamId,
+    count: usize,
+    just_id: bool,
+}
+
+// XINFO STREAM | GROUPS | CONSUMERS | HELP
+#[derive(Debug)]
+pub struct XInfo {
+    subcommand: XInfoSubcommand,
+    key: String,
+}
+
+// HEXPIRE, HPEXPIRE, HEXPIREAT and HPEXPIREAT
+// key time [NX | XX | GT | LT] FIELDS numfields field [field ...]
+#[derive(Debug)]
+pub struct HExpire {
+    key: String,
+    time: i64,
+    unit: TimeUnit,
+    absolute: bool,
+    condition: ExpireCondition,
+    fields: Vec<String>,
+}
+
+// HTTL, HPTTL, HEXPIRETIME and HPEXPIRETIME
+#[derive(Debug)]
+pub struct HTtl {
+    key: String,
+    unit: TimeUnit,
+    absolute: bool,
+    fields: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct HPersist {
+    key: String,
+    fields: Vec<String>,
+}
+
+// COMMAND and its COUNT, INFO, DOCS and HELP subcommands
+#[derive(Debug)]
+pub struct CommandInfo {
+    subcommand: InfoSubcommand,
+    names: Vec<String>,
+}
+
+// HELLO [protover [AUTH username password] [SETNAME clientname]]
+#[derive(Debug)]
+pub struct Hello {
+    protocol: Option<RespVersion>,
+    auth: Option<(String, String)>,
+    name: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct Ping {
+    message: Option<RespFrame>,
+}
+
+#[derive(Debug)]
+pub struct Echo {
+    message: RespFrame,
+}
+
+#[derive(Debug)]
+pub struct Quit;
+
+#[derive(Debug)]
+pub struct Reset;
+
+// SUBSCRIBE channel [channel ...] and PSUBSCRIBE pattern [pattern ...]
+#[derive(Debug)]
+pub struct Subscribe {
+    channels: Vec<String>,
+    pattern: bool,
+}
+
+// UNSUBSCRIBE [channel ...] and PUNSUBSCRIBE [pattern ...], from all of them without
+// arguments
+#[derive(Debug)]
+pub struct Unsubscribe {
+    channels: Vec<String>,
+    pattern: bool,
+}
+
+#[derive(Debug)]
+pub struct Publish {
+    channel: String,
+    message: RespFrame,
+}
+
+// PUBSUB CHANNELS | NUMSUB | NUMPAT | HELP
+#[derive(Debug)]
+pub struct PubSub {
+    subcommand: PubSubSubcommand,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SetExpire {
+    // relative TTL in milliseconds, from EX / PX
+    Relative(u64),
+    // absolute deadline in unix milliseconds, from EXAT / PXAT
+    At(u64),
+    KeepTtl,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TimeUnit {
+    Seconds,
+    Milliseconds,
+}
+
+// the MATCH and COUNT options shared by the SCAN family
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ScanOptions {
+    pattern: Option<String>,
+    count: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InfoSubcommand {
+    Count,
+    // a bare COMMAND is COMMAND INFO without names
+    Info,
+    Docs,
+    Help,
+}
+
+// the GROUP option of XREADGROUP
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ReadGroup {
+    name: String,
+    consumer: String,
+    no_ack: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum GroupSubcommand {
+    // a None ID is `$`, the last ID of the stream
+    Create {
+        id: Option<StreamId>,
+        mkstream: bool,
+        entries_read: Option<u64>,
+    },
+    SetId {
+        id: Option<StreamId>,
+        entries_read: Option<u64>,
+    },
+    Destroy,
+    CreateConsumer(String),
+    DelConsumer(String),
+    Help,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PubSubSubcommand {
+    // the optional pattern the channels must match
+    Channels(Option<String>),
+    NumSub(Vec<String>),
+    NumPat,
+    Help,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum XInfoSubcommand {
+    Stream,
+    Groups,
+    Consumers(String),
+    Help,
 }
 
 impl TryFrom<RespArray> for Command {
     type Error = CommandError;
     fn try_from(v: RespArray) -> Result<Self, Self::Error> {
         match v.first() {
-            Some(RespFrame::BulkString(ref cmd)) => match cmd.as_ref() {
-                b"get" => Ok(Get::try_from(v)?.into()),
-                b"set" => Ok(Set::try_from(v)?.into()),
-                b"hget" => Ok(HGet::try_from(v)?.into()),
-                b"hset" => Ok(HSet::try_from(v)?.into()),
-                b"hgetall" => Ok(HGetAll::try_from(v)?.into()),
-                _ => Ok(Unrecognized.into()),
+            Some(RespFrame::BulkString(ref cmd)) => match CommandSpec::lookup(cmd) {
+                Some(spec) => spec.parse(v),
+                None => Err(CommandError::UnknownCommand {
+                    name: String::from_utf8_lossy(cmd).chars().take(128).collect(),
+                    args: table::format_args_preview(&v[1..]),
+                }),
             },
             _ => Err(CommandError::InvalidCommand(
                 "Command must have a BulkString as the first argument".to_string(),
@@ -111,17 +998,39 @@ impl TryFrom<RespFrame> for Command {
     }
 }
 
+/// How many arguments a command takes after its name (and subcommand names).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Arity {
+    Exact(usize),
+    AtLeast(usize),
+    // n arguments followed by one or more pairs, like `MSET key value [key value ...]`
+    Pairs(usize),
+}
+
+impl Arity {
+    fn accepts(&self, n_args: usize) -> bool {
+        match *self {
+            Arity::Exact(n) => n_args == n,
+            Arity::AtLeast(n) => n_args >= n,
+            Arity::Pairs(n) => n_args >= n + 2 && (n_args - n).is_multiple_of(2),
+        }
+    }
+}
+
+impl From<usize> for Arity {
+    fn from(n: usize) -> Self {
+        Arity::Exact(n)
+    }
+}
+
 fn validate_command(
     value: &RespArray,
     names: &[&'static str],
-    n_args: usize,
+    arity: impl Into<Arity>,
 ) -> Result<(), CommandError> {
-    // test if the array has exactly two elements
-    if value.len() != n_args + 1 {
-        return Err(CommandError::InvalidArgument(format!(
-            "{} command must have exactly {n_args} argument",
-            names.join("")
-        )));
+    // test if the array has the expected number of elements
+    if value.len() < names.len() || !arity.into().accepts(value.len() - names.len()) {
+        return Err(CommandError::WrongArity(names.join("|")));
     }
 
     for (i, name) in names.iter().enumerate() {
@@ -137,7 +1046,7 @@ fn validate_command(
             }
             _ => {
                 return Err(CommandError::InvalidCommand(
-                    "GET command requires a string key".to_string(),
+                    "command name must be a BulkString".to_string(),
                 ));
             }
         }
@@ -148,3 +1057,155 @@ fn validate_command(
 fn extract_args(value: RespArray, start: usize) -> Result<Vec<RespFrame>, CommandError> {
     Ok(value.0.into_iter().skip(start).collect::<Vec<RespFrame>>())
 }
+
+// the lowercased command name, for structs shared by several commands
+fn command_name(value: &RespArray) -> Result<String, CommandError> {
+    match value.first() {
+        Some(RespFrame::BulkString(ref cmd)) => {
+            Ok(String::from_utf8_lossy(cmd).to_ascii_lowercase())
+        }
+        _ => Err(CommandError::InvalidCommand(
+            "command name must be a BulkString".to_string(),
+        )),
+    }
+}
+
+// a key, field or member argument
+fn extract_string(frame: RespFrame) -> Result<String, CommandError> {
+    match frame {
+        RespFrame::BulkString(ref s) => Ok(String::from_utf8_lossy(s).to_string()),
+        _ => Err(CommandError::InvalidArgument(
+            "argument must be a BulkString".to_string(),
+        )),
+    }
+}
+
+fn parse_int(frame: &RespFrame) -> Result<i64, CommandError> {
+    match frame {
+        RespFrame::Integer(n) => Ok(*n),
+        RespFrame::BulkString(ref s) => std::str::from_utf8(s)
+            .ok()
+            .and_then(|s| s.parse::<i64>().ok())
+            .ok_or(CommandError::NotAnInteger),
+        _ => Err(CommandError::NotAnInteger),
+    }
+}
+
+// the count of SRANDMEMBER, HRANDFIELD and ZRANDMEMBER, where a negative count allows repeats.
+// Bounded like Redis so a negative count can't ask for a reply of any size.
+fn parse_random_count(frame: &RespFrame) -> Result<i64, CommandError> {
+    match parse_int(frame)? {
+        n if n < -(i64::MAX / 2) => Err(CommandError::InvalidArgument(
+            "value is out of range".to_string(),
+        )),
+        n => Ok(n),
+    }
+}
+
+fn parse_float(frame: &RespFrame) -> Result<f64, CommandError> {
+    match frame {
+        RespFrame::Integer(n) => Ok(*n as f64),
+        RespFrame::Double(n) if !n.is_nan() => Ok(*n),
+        RespFrame::BulkString(ref s) => std::str::from_utf8(s)
+            .ok()
+            .and_then(|s| s.parse::<f64>().ok())
+            .filter(|n| !n.is_nan())
+            .ok_or(CommandError::NotAFloat),
+        _ => Err(CommandError::NotAFloat),
+    }
+}
+
+// what the command tests of every module build their requests and replies with
+#[cfg(test)]
+pub(crate) mod test_helpers {
+    use super::{Command, CommandError, CommandExecutor, ConnectionState};
+    use crate::{Backend, BulkString, RespArray, RespFrame};
+
+    pub(crate) fn bulk(s: &str) -> RespFrame {
+        BulkString::from(s).into()
+    }
+
+    pub(crate) fn array(values: &[&str]) -> RespFrame {
+        RespArray::new(values.iter().map(|v| bulk(v)).collect::<Vec<_>>()).into()
+    }
+
+    pub(crate) fn command(args: &[&str]) -> Result<Command, CommandError> {
+        let frames = args.iter().map(|s| bulk(s)).collect::<Vec<RespFrame>>();
+        Command::try_from(RespArray::new(frames))
+    }
+
+    // execute the command, a parse error is the reply like on a connection
+    pub(crate) fn run(backend: &Backend, args: &[&str]) -> RespFrame {
+        match command(args) {
+            Ok(cmd) => cmd.execute(backend),
+            Err(e) => e.into(),
+        }
+    }
+
+    // execute the command on a fresh connection, blocking commands wait like on a socket
+    pub(crate) async fn run_async(backend: &Backend, args: &[&str]) -> RespFrame {
+        match command(args) {
+            Ok(cmd) => {
+                let mut conn = ConnectionState::new();
+                cmd.execute_async(backend, &mut conn).await
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_helpers::command;
+
+    #[test]
+    fn test_command_dispatch_is_case_insensitive() {
+        assert!(matches!(command(&["GET", "key"]), Ok(Command::Get(_))));
+        assert!(matches!(
+            command(&["hGetAll", "key"]),
+            Ok(Command::HGetAll(_))
+        ));
+        assert!(matches!(
+            command(&["MSETNX", "k", "v"]),
+            Ok(Command::MSet(_))
+        ));
+    }
+
+    #[test]
+    fn test_unknown_command() {
+        let err = command(&["xyz", "a", "b"]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "ERR unknown command 'xyz', with args beginning with: 'a' 'b' "
+        );
+        let err = command(&["xyz"]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "ERR unknown command 'xyz', with args beginning with: "
+        );
+    }
+
+    #[test]
+    fn test_table_arity_check() {
+        let err = command(&["SET", "key"]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "ERR wrong number of arguments for 'set' command"
+        );
+    }
+
+    #[test]
+    fn test_arity() {
+        assert!(Arity::Exact(2).accepts(2));
+        assert!(!Arity::Exact(2).accepts(3));
+        assert!(Arity::AtLeast(1).accepts(5));
+        assert!(!Arity::AtLeast(1).accepts(0));
+        assert!(Arity::Pairs(0).accepts(2));
+        assert!(Arity::Pairs(0).accepts(4));
+        assert!(!Arity::Pairs(0).accepts(0));
+        assert!(!Arity::Pairs(0).accepts(3));
+        assert!(Arity::Pairs(1).accepts(3));
+        assert!(!Arity::Pairs(1).accepts(1));
+    }
+}
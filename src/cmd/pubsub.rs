@@ -0,0 +1,381 @@
+use super::stream::help_reply;
+use super::{
+    command_name, extract_args, extract_string, validate_command, Arity, ConnectionState,
+    PubSubSubcommand,
+};
+use crate::{
+    pubsub_message, Backend, BulkString, CommandError, CommandExecutor, PubSub, Publish, RespArray,
+    RespFrame, RespNull, Subscribe, Unsubscribe,
+};
+use std::convert::TryFrom;
+
+const ONE_ARGS: usize = 1;
+const TWO_ARGS: usize = 2;
+
+const PUBSUB_HELP: &[&str] = &[
+    "PUBSUB <subcommand> [<arg> [value] [opt] ...]. Subcommands are:",
+    "CHANNELS [<pattern>]",
+    "    Return the currently active channels matching a <pattern> (default: '*').",
+    "NUMPAT",
+    "    Return number of subscriptions to patterns.",
+    "NUMSUB [<channel> ...]",
+    "    Return the number of subscribers for the specified channels, excluding",
+    "    pattern subscriptions(default: no channels).",
+    "HELP",
+    "    Print this help.",
+];
+
+impl Subscribe {
+    pub(super) fn apply(self, backend: &Backend, conn: &mut ConnectionState) -> RespFrame {
+        let kind = if self.pattern {
+            "psubscribe"
+        } else {
+            "subscribe"
+        };
+        let mut replies = Vec::with_capacity(self.channels.len());
+        for channel in self.channels {
+            if self.pattern {
+                if conn.patterns.insert(channel.clone()) {
+                    backend.psubscribe(&channel, conn.id, &conn.messages);
+                }
+            } else if conn.channels.insert(channel.clone()) {
+                backend.subscribe(&channel, conn.id, &conn.messages);
+            }
+            replies.push(confirmation(kind, Some(channel), conn));
+        }
+        reply_each(replies, conn)
+    }
+}
+
+impl Unsubscribe {
+    pub(super) fn apply(self, backend: &Backend, conn: &mut ConnectionState) -> RespFrame {
+        let kind = if self.pattern {
+            "punsubscribe"
+        } else {
+            "unsubscribe"
+        };
+        let mut channels = self.channels;
+        if channels.is_empty() {
+            let subscribed = if self.pattern {
+                &conn.patterns
+            } else {
+                &conn.channels
+            };
+            channels = subscribed.iter().cloned().collect();
+            channels.sort();
+        }
+        if channels.is_empty() {
+            return confirmation(kind, None, conn);
+        }
+        let mut replies = Vec::with_capacity(channels.len());
+        for channel in channels {
+            if self.pattern {
+                if conn.patterns.remove(&channel) {
+                    backend.punsubscribe(&channel, conn.id);
+                }
+            } else if conn.channels.remove(&channel) {
+                backend.unsubscribe(&channel, conn.id);
+            }
+            replies.push(confirmation(kind, Some(channel), conn));
+        }
+        reply_each(replies, conn)
+    }
+}
+
+// without a connection to deliver to, the subscriptions end with the reply
+impl CommandExecutor for Subscribe {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let mut conn = ConnectionState::new();
+        let reply = self.apply(backend, &mut conn);
+        conn.unsubscribe_all(backend);
+        reply
+    }
+}
+
+impl CommandExecutor for Unsubscribe {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.apply(backend, &mut ConnectionState::new())
+    }
+}
+
+impl CommandExecutor for Publish {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.publish(&self.channel, &self.message) as i64)
+    }
+}
+
+impl CommandExecutor for PubSub {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match self.subcommand {
+            PubSubSubcommand::Channels(pattern) => {
+                let channels = backend
+                    .pubsub_channels(pattern.as_deref())
+                    .into_iter()
+                    .map(|channel| BulkString::from(channel).into())
+                    .collect::<Vec<RespFrame>>();
+                RespArray::new(channels).into()
+            }
+            PubSubSubcommand::NumSub(channels) => {
+                let counts = backend.pubsub_numsub(&channels);
+                let reply = channels
+                    .into_iter()
+                    .zip(counts)
+                    .flat_map(|(channel, n)| {
+                        [
+                            BulkString::from(channel).into(),
+                            RespFrame::Integer(n as i64),
+                        ]
+                    })
+                    .collect::<Vec<RespFrame>>();
+                RespArray::new(reply).into()
+            }
+            PubSubSubcommand::NumPat => RespFrame::Integer(backend.pubsub_numpat() as i64),
+            PubSubSubcommand::Help => help_reply(PUBSUB_HELP),
+        }
+    }
+}
+
+// the `[kind, channel, count]` sent for each channel (un)subscribed, the channel is nil when
+// UNSUBSCRIBE had nothing to leave
+fn confirmation(kind: &str, channel: Option<String>, conn: &ConnectionState) -> RespFrame {
+    let channel = channel.map_or(RespFrame::Null(RespNull), |c| BulkString::from(c).into());
+    pubsub_message(vec![
+        BulkString::from(kind).into(),
+        channel,
+        RespFrame::Integer(conn.subscriptions() as i64),
+    ])
+}
+
+// a command replying once per channel: all but the last reply are queued with the messages,
+// which the connection writes out first
+fn reply_each(mut replies: Vec<RespFrame>, conn: &ConnectionState) -> RespFrame {
+    let last = replies.pop().unwrap_or(RespFrame::Null(RespNull));
+    for reply in replies {
+        // only fails once the connection is gone
+        conn.messages.send(reply);
+    }
+    last
+}
+
+impl TryFrom<RespArray> for Subscribe {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let pattern = command_name(&value)? == "psubscribe";
+        let name = if pattern { "psubscribe" } else { "subscribe" };
+        validate_command(&value, &[name], Arity::AtLeast(ONE_ARGS))?;
+        let channels = extract_args(value, ONE_ARGS)?
+            .into_iter()
+            .map(extract_string)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Subscribe { channels, pattern })
+    }
+}
+
+impl TryFrom<RespArray> for Unsubscribe {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let pattern = command_name(&value)? == "punsubscribe";
+        let name = if pattern {
+            "punsubscribe"
+        } else {
+            "unsubscribe"
+        };
+        validate_command(&value, &[name], Arity::AtLeast(0))?;
+        let channels = extract_args(value, ONE_ARGS)?
+            .into_iter()
+            .map(extract_string)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Unsubscribe { channels, pattern })
+    }
+}
+
+impl TryFrom<RespArray> for Publish {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["publish"], TWO_ARGS)?;
+        let mut args = extract_args(value, ONE_ARGS)?.into_iter();
+        match (args.next(), args.next()) {
+            (Some(channel), Some(message @ RespFrame::BulkString(_))) => Ok(Publish {
+                channel: extract_string(channel)?,
+                message,
+            }),
+            _ => Err(CommandError::InvalidArgument(
+                "argument must be a BulkString".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for PubSub {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["pubsub"], Arity::AtLeast(ONE_ARGS))?;
+        let mut args = extract_args(value, ONE_ARGS)?.into_iter();
+        let name = extract_string(args.next().ok_or(CommandError::SyntaxError)?)?;
+        let name = name.to_ascii_lowercase();
+        let mut args = args.map(extract_string).collect::<Result<Vec<_>, _>>()?;
+        let subcommand = match (name.as_str(), args.len()) {
+            ("channels", 0 | 1) => PubSubSubcommand::Channels(args.pop()),
+            ("numsub", _) => PubSubSubcommand::NumSub(args),
+            ("numpat", 0) => PubSubSubcommand::NumPat,
+            ("help", 0) => PubSubSubcommand::Help,
+            ("channels" | "numpat" | "help", _) => {
+                return Err(CommandError::WrongArity(format!("pubsub|{name}")))
+            }
+            _ => {
+                return Err(CommandError::UnknownSubcommand {
+                    command: "PUBSUB".to_string(),
+                    name,
+                })
+            }
+        };
+        Ok(PubSub { subcommand })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::test_helpers::command;
+    use crate::{Command, RespPush, RespVersion};
+
+    fn message(kind: &str, channel: &str, count: i64) -> RespFrame {
+        RespPush::new(vec![
+            BulkString::from(kind).into(),
+            BulkString::from(channel).into(),
+            RespFrame::Integer(count),
+        ])
+        .into()
+    }
+
+    #[test]
+    fn test_subscribe_and_unsubscribe() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        let mut conn = ConnectionState::new();
+        let mut inbox = conn.take_inbox().unwrap();
+
+        // one confirmation per channel, all but the last queued before the reply
+        let ret = command(&["subscribe", "a", "b"])?.execute_in(&backend, &mut conn);
+        assert_eq!(ret, message("subscribe", "b", 2));
+        assert_eq!(inbox.try_recv()?, message("subscribe", "a", 1));
+        let ret = command(&["psubscribe", "a*"])?.execute_in(&backend, &mut conn);
+        assert_eq!(ret, message("psubscribe", "a*", 3));
+        // subscribing twice changes nothing
+        let ret = command(&["subscribe", "a"])?.execute_in(&backend, &mut conn);
+        assert_eq!(ret, message("subscribe", "a", 3));
+
+        let ret = command(&["publish", "a", "hi"])?.execute(&backend);
+        assert_eq!(ret, RespFrame::Integer(2));
+        assert_eq!(
+            inbox.try_recv()?,
+            RespPush::new(vec![b"message".into(), b"a".into(), b"hi".into()]).into()
+        );
+        assert!(matches!(inbox.try_recv()?, RespFrame::Push(m) if m.len() == 4));
+
+        let ret = command(&["unsubscribe"])?.execute_in(&backend, &mut conn);
+        assert_eq!(ret, message("unsubscribe", "b", 1));
+        assert_eq!(inbox.try_recv()?, message("unsubscribe", "a", 2));
+        let ret = command(&["punsubscribe", "a*", "x"])?.execute_in(&backend, &mut conn);
+        assert_eq!(ret, message("punsubscribe", "x", 0));
+        assert_eq!(inbox.try_recv()?, message("punsubscribe", "a*", 0));
+        assert!(!conn.is_subscribed());
+
+        let ret = command(&["unsubscribe"])?.execute_in(&backend, &mut conn);
+        assert_eq!(
+            ret,
+            RespPush::new(vec![
+                b"unsubscribe".into(),
+                RespFrame::Null(RespNull),
+                RespFrame::Integer(0)
+            ])
+            .into()
+        );
+        let ret = command(&["publish", "a", "hi"])?.execute(&backend);
+        assert_eq!(ret, RespFrame::Integer(0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_subscribed_mode_restrictions() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        let mut conn = ConnectionState::new();
+        let frame = |args: &[&str]| -> RespFrame {
+            let args = args
+                .iter()
+                .map(|s| BulkString::from(*s).into())
+                .collect::<Vec<RespFrame>>();
+            RespArray::new(args).into()
+        };
+        assert!(Command::parse_in(frame(&["get", "k"]), &conn).is_ok());
+
+        command(&["subscribe", "a"])?.execute_in(&backend, &mut conn);
+        assert_eq!(
+            Command::parse_in(frame(&["GET", "k"]), &conn)
+                .unwrap_err()
+                .to_string(),
+            "ERR Can't execute 'get': only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING / QUIT / RESET are allowed in this context"
+        );
+        assert!(Command::parse_in(frame(&["ping"]), &conn).is_ok());
+        assert!(Command::parse_in(frame(&["PSUBSCRIBE", "b*"]), &conn).is_ok());
+        assert!(matches!(
+            Command::parse_in(frame(&["nosuchcommand"]), &conn),
+            Err(CommandError::UnknownCommand { .. })
+        ));
+
+        // RESP3 tells messages apart, and RESET leaves the channels
+        conn.protocol = RespVersion::Resp3;
+        assert!(Command::parse_in(frame(&["get", "k"]), &conn).is_ok());
+        command(&["reset"])?.execute_in(&backend, &mut conn);
+        assert!(!conn.is_subscribed());
+        assert_eq!(backend.pubsub_numsub(&["a".to_string()]), vec![0]);
+        assert!(conn.take_inbox().is_some());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pubsub_commands() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        let mut conn = ConnectionState::new();
+        command(&["subscribe", "news.tech", "weather"])?.execute_in(&backend, &mut conn);
+        command(&["psubscribe", "news.*"])?.execute_in(&backend, &mut conn);
+
+        let ret = command(&["pubsub", "channels", "news.*"])?.execute(&backend);
+        assert_eq!(ret, RespArray::new(vec![b"news.tech".into()]).into());
+        let ret = command(&["PUBSUB", "NUMSUB", "weather", "x"])?.execute(&backend);
+        assert_eq!(
+            ret,
+            RespArray::new(vec![
+                b"weather".into(),
+                RespFrame::Integer(1),
+                b"x".into(),
+                RespFrame::Integer(0)
+            ])
+            .into()
+        );
+        let ret = command(&["pubsub", "numpat"])?.execute(&backend);
+        assert_eq!(ret, RespFrame::Integer(1));
+        assert!(matches!(
+            command(&["pubsub", "help"])?.execute(&backend),
+            RespFrame::Array(lines) if lines.len() == PUBSUB_HELP.len()
+        ));
+
+        assert_eq!(
+            command(&["pubsub", "channels", "a", "b"])
+                .unwrap_err()
+                .to_string(),
+            "ERR wrong number of arguments for 'pubsub|channels' command"
+        );
+        assert!(matches!(
+            command(&["pubsub", "nope"]),
+            Err(CommandError::UnknownSubcommand { .. })
+        ));
+        assert_eq!(
+            command(&["subscribe"]).unwrap_err().to_string(),
+            "ERR wrong number of arguments for 'subscribe' command"
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,320 @@
+use crate::{
+    BulkString, CommandError, CommandGroup, CommandInfo, CommandSpec, KeySpec, RespArray,
+    RespFrame, RespMap, RespNull, RespSet, SimpleString,
+};
+use std::convert::TryFrom;
+
+use super::{
+    extract_args, extract_string, validate_command, Arity, CommandExecutor, CommandFlag,
+    InfoSubcommand,
+};
+
+const COMMAND_HELP: &[&str] = &[
+    "COMMAND <subcommand> [<arg> [value] [opt] ...]. Subcommands are:",
+    "(no subcommand)",
+    "    Return details about all commands.",
+    "COUNT",
+    "    Return the total number of commands in this server.",
+    "INFO [<command-name> ...]",
+    "    Return details about multiple commands.",
+    "    If no command names are given, documentation details for all",
+    "    commands are returned.",
+    "DOCS [<command-name> ...]",
+    "    Return documentation details about multiple commands.",
+    "    If no command names are given, documentation details for all",
+    "    commands are returned.",
+    "HELP",
+    "    Print this help.",
+];
+
+impl CommandExecutor for CommandInfo {
+    fn execute(self, _backend: &crate::Backend) -> RespFrame {
+        match self.subcommand {
+            InfoSubcommand::Count => RespFrame::Integer(CommandSpec::all().len() as i64),
+            InfoSubcommand::Info if self.names.is_empty() => RespArray::new(
+                CommandSpec::all()
+                    .iter()
+                    .map(info_frame)
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            InfoSubcommand::Info => {
+                let ret = self
+                    .names
+                    .iter()
+                    .map(|name| match CommandSpec::resolve(name.as_bytes()) {
+                        Some(spec) => info_frame(spec),
+                        None => RespFrame::Null(RespNull),
+                    })
+                    .collect::<Vec<_>>();
+                RespArray::new(ret).into()
+            }
+            InfoSubcommand::Docs => {
+                let mut map = RespMap::new();
+                if self.names.is_empty() {
+                    for spec in CommandSpec::all() {
+                        map.insert(spec.name.to_string(), docs_frame(spec));
+                    }
+                }
+                // unknown names are silently left out, like Redis does
+                for spec in self
+                    .names
+                    .iter()
+                    .filter_map(|name| CommandSpec::resolve(name.as_bytes()))
+                {
+                    map.insert(spec.name.to_string(), docs_frame(spec));
+                }
+                map.into()
+            }
+            InfoSubcommand::Help => {
+                let ret = COMMAND_HELP
+                    .iter()
+                    .map(|line| SimpleString::new(*line).into())
+                    .collect::<Vec<RespFrame>>();
+                RespArray::new(ret).into()
+            }
+        }
+    }
+}
+
+impl TryFrom<RespArray> for CommandInfo {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["command"], Arity::AtLeast(0))?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let subcommand = match args.next() {
+            None => {
+                return Ok(CommandInfo {
+                    subcommand: InfoSubcommand::Info,
+                    names: vec![],
+                })
+            }
+            Some(sub) => extract_string(sub)?,
+        };
+        let names = args.map(extract_string).collect::<Result<Vec<_>, _>>()?;
+        let subcommand = match subcommand.to_ascii_lowercase().as_str() {
+            "count" if names.is_empty() => InfoSubcommand::Count,
+            "help" if names.is_empty() => InfoSubcommand::Help,
+            "info" => InfoSubcommand::Info,
+            "docs" => InfoSubcommand::Docs,
+            sub @ ("count" | "help") => {
+                return Err(CommandError::WrongArity(format!("command|{sub}")))
+            }
+            _ => {
+                return Err(CommandError::UnknownSubcommand {
+                    command: "COMMAND".to_string(),
+                    name: subcommand,
+                })
+            }
+        };
+        Ok(CommandInfo { subcommand, names })
+    }
+}
+
+fn status(s: &str) -> RespFrame {
+    SimpleString::new(s).into()
+}
+
+fn bulk(s: &str) -> RespFrame {
+    BulkString::from(s).into()
+}
+
+fn map<const N: usize>(entries: [(&str, RespFrame); N]) -> RespFrame {
+    let mut map = RespMap::new();
+    for (key, value) in entries {
+        map.insert(key.to_string(), value);
+    }
+    map.into()
+}
+
+// the 10 element reply of COMMAND INFO: name, arity, flags, first key, last key, key step,
+// ACL categories, tips, key specifications and subcommands
+fn info_frame(spec: &CommandSpec) -> RespFrame {
+    let flags = spec
+        .flags
+        .iter()
+        .map(|f| status(f.as_str()))
+        .collect::<Vec<_>>();
+    RespArray::new(vec![
+        bulk(spec.name),
+        RespFrame::Integer(spec.redis_arity()),
+        RespSet::new(flags).into(),
+        RespFrame::Integer(spec.keys.first),
+        RespFrame::Integer(spec.keys.last),
+        RespFrame::Integer(spec.keys.step),
+        RespSet::new(acl_categories(spec)).into(),
+        RespArray::new(vec![]).into(),
+        RespArray::new(key_specs(spec)).into(),
+        RespArray::new(spec.subcommands.iter().map(info_frame).collect::<Vec<_>>()).into(),
+    ])
+    .into()
+}
+
+fn acl_categories(spec: &CommandSpec) -> Vec<RespFrame> {
+    let mut categories = vec![];
+    if spec.has_flag(CommandFlag::Write) {
+        categories.push("@write");
+    }
+    if spec.has_flag(CommandFlag::ReadOnly) {
+        categories.push("@read");
+    }
+    categories.push(match spec.group {
+        CommandGroup::Generic => "@keyspace",
+        CommandGroup::String => "@string",
+        CommandGroup::Hash => "@hash",
+        CommandGroup::List => "@list",
+        CommandGroup::Set => "@set",
+        CommandGroup::SortedSet => "@sortedset",
+        CommandGroup::Stream => "@stream",
+        CommandGroup::PubSub => "@pubsub",
+        CommandGroup::Connection | CommandGroup::Server => "@connection",
+    });
+    if spec.has_flag(CommandFlag::Fast) {
+        categories.push("@fast");
+    } else {
+        categories.push("@slow");
+    }
+    categories.into_iter().map(status).collect()
+}
+
+// the legacy first/last/step triple expressed as a single index + range key specification
+fn key_specs(spec: &CommandSpec) -> Vec<RespFrame> {
+    let KeySpec { first, last, step } = spec.keys;
+    if step == 0 {
+        return vec![];
+    }
+    let flags = if spec.has_flag(CommandFlag::Write) {
+        ["RW", "UPDATE"]
+    } else {
+        ["RO", "ACCESS"]
+    };
+    let lastkey = if last < 0 { last } else { last - first };
+    vec![map([
+        (
+            "flags",
+            RespSet::new(flags.into_iter().map(status).collect::<Vec<_>>()).into(),
+        ),
+        (
+            "begin_search",
+            map([
+                ("type", bulk("index")),
+                ("spec", map([("index", RespFrame::Integer(first))])),
+            ]),
+        ),
+        (
+            "find_keys",
+            map([
+                ("type", bulk("range")),
+                (
+                    "spec",
+                    map([
+                        ("lastkey", RespFrame::Integer(lastkey)),
+                        ("keystep", RespFrame::Integer(step)),
+                        ("limit", RespFrame::Integer(0)),
+                    ]),
+                ),
+            ]),
+        ),
+    ])]
+}
+
+fn docs_frame(spec: &CommandSpec) -> RespFrame {
+    let mut docs = RespMap::new();
+    docs.insert("summary".to_string(), bulk(spec.summary));
+    docs.insert("since".to_string(), bulk(spec.since));
+    docs.insert("group".to_string(), bulk(spec.group.as_str()));
+    if !spec.subcommands.is_empty() {
+        let mut subcommands = RespMap::new();
+        for sub in spec.subcommands {
+            subcommands.insert(sub.name.to_string(), docs_frame(sub));
+        }
+        docs.insert("subcommands".to_string(), subcommands.into());
+    }
+    docs.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::test_helpers::run;
+    use crate::Backend;
+
+    #[test]
+    fn test_command_count() {
+        let backend = Backend::new();
+        assert_eq!(
+            run(&backend, &["COMMAND", "COUNT"]),
+            RespFrame::Integer(CommandSpec::all().len() as i64)
+        );
+        let RespFrame::Array(all) = run(&backend, &["command"]) else {
+            panic!("COMMAND should reply with an array");
+        };
+        assert_eq!(all.len(), CommandSpec::all().len());
+    }
+
+    #[test]
+    fn test_command_info() {
+        let backend = Backend::new();
+        let RespFrame::Array(ret) = run(
+            &backend,
+            &["command", "info", "GET", "nope", "command|count"],
+        ) else {
+            panic!("COMMAND INFO should reply with an array");
+        };
+        assert_eq!(ret.len(), 3);
+        assert_eq!(ret[1], RespFrame::Null(RespNull));
+
+        let RespFrame::Array(ref get) = ret[0] else {
+            panic!("command info should be an array");
+        };
+        assert_eq!(get.len(), 10);
+        assert_eq!(get[0], bulk("get"));
+        assert_eq!(get[1], RespFrame::Integer(2));
+        assert_eq!(
+            get[2],
+            RespSet::new(vec![status("readonly"), status("fast")]).into()
+        );
+        assert_eq!(get[3..6], [1.into(), 1.into(), 1.into()]);
+        assert_eq!(
+            get[6],
+            RespSet::new(vec![status("@read"), status("@string"), status("@fast")]).into()
+        );
+
+        let RespFrame::Array(ref count) = ret[2] else {
+            panic!("command info should be an array");
+        };
+        assert_eq!(count[0], bulk("command|count"));
+        assert_eq!(count[1], RespFrame::Integer(2));
+    }
+
+    #[test]
+    fn test_command_docs() {
+        let backend = Backend::new();
+        let RespFrame::Map(docs) = run(&backend, &["command", "docs", "set", "nope"]) else {
+            panic!("COMMAND DOCS should reply with a map");
+        };
+        assert_eq!(docs.len(), 1);
+        let RespFrame::Map(ref set) = docs["set"] else {
+            panic!("command docs should be a map");
+        };
+        assert_eq!(set["since"], bulk("1.0.0"));
+        assert_eq!(set["group"], bulk("string"));
+    }
+
+    #[test]
+    fn test_command_errors() {
+        let backend = Backend::new();
+        assert_eq!(
+            run(&backend, &["command", "foo"]),
+            CommandError::UnknownSubcommand {
+                command: "COMMAND".to_string(),
+                name: "foo".to_string()
+            }
+            .into()
+        );
+        assert_eq!(
+            run(&backend, &["command", "count", "x"]),
+            CommandError::WrongArity("command|count".to_string()).into()
+        );
+    }
+}
@@ -0,0 +1,535 @@
+use super::hmap::key_and_fields;
+use super::keys::{parse_cursor, scan_reply};
+use super::list::parse_positive;
+use super::{
+    command_name, extract_args, extract_string, parse_int, parse_random_count, validate_command,
+    Arity, ScanOptions,
+};
+use crate::{
+    Backend, BulkString, CommandError, CommandExecutor, RespArray, RespFrame, RespNull, RespSet,
+    SAdd, SCard, SInterCard, SIsMember, SMIsMember, SMembers, SMove, SPop, SRandMember, SRem,
+    SScan, SetAlgebra, SetOp,
+};
+use std::convert::TryFrom;
+
+const ONE_ARGS: usize = 1;
+const TWO_ARGS: usize = 2;
+const THREE_ARGS: usize = 3;
+
+impl CommandExecutor for SAdd {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.sadd(self.key, self.members) {
+            Ok(added) => RespFrame::Integer(added as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for SRem {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.srem(&self.key, &self.members) {
+            Ok(removed) => RespFrame::Integer(removed as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+// a RESP3 set, sent as an array to RESP2 clients by the codec
+impl CommandExecutor for SMembers {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.smembers(&self.key) {
+            Ok(members) => set_reply(members),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for SIsMember {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.sismember(&self.key, &self.member) {
+            Ok(found) => RespFrame::Integer(found as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for SMIsMember {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.smismember(&self.key, &self.members) {
+            Ok(found) => {
+                let found = found.into_iter().map(|f| RespFrame::Integer(f as i64));
+                RespArray::new(found.collect::<Vec<_>>()).into()
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for SCard {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.scard(&self.key) {
+            Ok(len) => RespFrame::Integer(len as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for SPop {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let members = match backend.spop(&self.key, self.count.unwrap_or(1)) {
+            Ok(members) => members,
+            Err(e) => return e.into(),
+        };
+        match self.count {
+            Some(_) => set_reply(members),
+            None => single_member(members),
+        }
+    }
+}
+
+// with a count the members may repeat, so the reply is an array even on RESP3
+impl CommandExecutor for SRandMember {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let members = match backend.srandmember(&self.key, self.count.unwrap_or(1)) {
+            Ok(members) => members,
+            Err(e) => return e.into(),
+        };
+        match self.count {
+            Some(_) => RespArray::new(bulk_strings(members)).into(),
+            None => single_member(members),
+        }
+    }
+}
+
+impl CommandExecutor for SScan {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let (cursor, page) = match backend.sscan(&self.key, self.cursor, self.opts.count) {
+            Ok(page) => page,
+            Err(e) => return e.into(),
+        };
+        let members = page.into_iter().filter(|m| self.opts.matches(m)).collect();
+        scan_reply(cursor, bulk_strings(members))
+    }
+}
+
+impl CommandExecutor for SetAlgebra {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let ret = match self.dst {
+            Some(dst) => backend
+                .set_op_store(self.op, &dst, &self.keys)
+                .map(|len| RespFrame::Integer(len as i64)),
+            None => backend.set_op(self.op, &self.keys).map(set_reply),
+        };
+        ret.unwrap_or_else(|e| e.into())
+    }
+}
+
+impl CommandExecutor for SInterCard {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.sintercard(&self.keys, self.limit) {
+            Ok(len) => RespFrame::Integer(len as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for SMove {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.smove(&self.src, &self.dst, &self.member) {
+            Ok(moved) => RespFrame::Integer(moved as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for SAdd {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&array, &["sadd"], Arity::AtLeast(TWO_ARGS))?;
+        let (key, members) = key_and_fields(array)?;
+        Ok(SAdd { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for SRem {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&array, &["srem"], Arity::AtLeast(TWO_ARGS))?;
+        let (key, members) = key_and_fields(array)?;
+        Ok(SRem { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for SMembers {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&array, &["smembers"], ONE_ARGS)?;
+        let (key, _) = key_and_fields(array)?;
+        Ok(SMembers { key })
+    }
+}
+
+impl TryFrom<RespArray> for SIsMember {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&array, &["sismember"], TWO_ARGS)?;
+        let (key, mut members) = key_and_fields(array)?;
+        Ok(SIsMember {
+            key,
+            member: members.remove(0),
+        })
+    }
+}
+
+impl TryFrom<RespArray> for SMIsMember {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&array, &["smismember"], Arity::AtLeast(TWO_ARGS))?;
+        let (key, members) = key_and_fields(array)?;
+        Ok(SMIsMember { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for SCard {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&array, &["scard"], ONE_ARGS)?;
+        let (key, _) = key_and_fields(array)?;
+        Ok(SCard { key })
+    }
+}
+
+impl TryFrom<RespArray> for SPop {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&array, &["spop"], Arity::AtLeast(ONE_ARGS))?;
+        let (key, count) = key_and_count(array)?;
+        let count = count.as_ref().map(parse_positive).transpose()?;
+        Ok(SPop { key, count })
+    }
+}
+
+impl TryFrom<RespArray> for SRandMember {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&array, &["srandmember"], Arity::AtLeast(ONE_ARGS))?;
+        let (key, count) = key_and_count(array)?;
+        let count = count.as_ref().map(parse_random_count).transpose()?;
+        Ok(SRandMember { key, count })
+    }
+}
+
+impl TryFrom<RespArray> for SScan {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&array, &["sscan"], Arity::AtLeast(TWO_ARGS))?;
+        let mut args = extract_args(array, ONE_ARGS)?.into_iter();
+        let key = extract_string(args.next().ok_or(CommandError::SyntaxError)?)?;
+        let cursor = parse_cursor(&args.next().ok_or(CommandError::SyntaxError)?)?;
+        let opts = ScanOptions::parse(&mut args, |_, _| Err(CommandError::SyntaxError))?;
+        Ok(SScan { key, cursor, opts })
+    }
+}
+
+impl TryFrom<RespArray> for SetAlgebra {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        let (name, op, store) = match command_name(&array)?.as_str() {
+            "sinter" => ("sinter", SetOp::Inter, false),
+            "sunion" => ("sunion", SetOp::Union, false),
+            "sdiff" => ("sdiff", SetOp::Diff, false),
+            "sinterstore" => ("sinterstore", SetOp::Inter, true),
+            "sunionstore" => ("sunionstore", SetOp::Union, true),
+            "sdiffstore" => ("sdiffstore", SetOp::Diff, true),
+            name => {
+                return Err(CommandError::InvalidCommand(format!(
+                    "Invalid command: expected sinter, got {name}"
+                )))
+            }
+        };
+        let min_args = if store { TWO_ARGS } else { ONE_ARGS };
+        validate_command(&array, &[name], Arity::AtLeast(min_args))?;
+        let (first, mut keys) = key_and_fields(array)?;
+        let dst = if store {
+            Some(first)
+        } else {
+            keys.insert(0, first);
+            None
+        };
+        Ok(SetAlgebra { op, dst, keys })
+    }
+}
+
+impl TryFrom<RespArray> for SInterCard {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&array, &["sintercard"], Arity::AtLeast(TWO_ARGS))?;
+        let mut args = extract_args(array, ONE_ARGS)?.into_iter();
+        let numkeys = parse_int(&args.next().ok_or(CommandError::SyntaxError)?)?;
+        if numkeys <= 0 {
+            return Err(CommandError::InvalidArgument(
+                "numkeys should be greater than 0".to_string(),
+            ));
+        }
+        let keys = args
+            .by_ref()
+            .take(numkeys as usize)
+            .map(extract_string)
+            .collect::<Result<Vec<_>, _>>()?;
+        if keys.len() < numkeys as usize {
+            return Err(CommandError::InvalidArgument(
+                "Number of keys can't be greater than number of args".to_string(),
+            ));
+        }
+        let option = args.next().map(extract_string).transpose()?;
+        let limit = match (option, args.next(), args.next()) {
+            (None, _, _) => 0,
+            (Some(option), Some(ref limit), None) if option.eq_ignore_ascii_case("limit") => {
+                match parse_int(limit)? {
+                    n if n < 0 => {
+                        return Err(CommandError::InvalidArgument(
+                            "LIMIT can't be negative".to_string(),
+                        ))
+                    }
+                    n => n as usize,
+                }
+            }
+            _ => return Err(CommandError::SyntaxError),
+        };
+        Ok(SInterCard { keys, limit })
+    }
+}
+
+impl TryFrom<RespArray> for SMove {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&array, &["smove"], THREE_ARGS)?;
+        let (src, mut args) = key_and_fields(array)?;
+        let member = args.pop().ok_or(CommandError::SyntaxError)?;
+        let dst = args.pop().ok_or(CommandError::SyntaxError)?;
+        Ok(SMove { src, dst, member })
+    }
+}
+
+// the `key [count]` arguments of SPOP and SRANDMEMBER
+fn key_and_count(array: RespArray) -> Result<(String, Option<RespFrame>), CommandError> {
+    if array.len() > 3 {
+        return Err(CommandError::SyntaxError);
+    }
+    let mut args = extract_args(array, ONE_ARGS)?.into_iter();
+    let key = extract_string(args.next().ok_or(CommandError::SyntaxError)?)?;
+    Ok((key, args.next()))
+}
+
+fn set_reply(members: Vec<String>) -> RespFrame {
+    RespSet::new(bulk_strings(members)).into()
+}
+
+fn single_member(members: Vec<String>) -> RespFrame {
+    match members.into_iter().next() {
+        Some(member) => BulkString::from(member).into(),
+        None => RespFrame::Null(RespNull),
+    }
+}
+
+fn bulk_strings(members: Vec<String>) -> Vec<RespFrame> {
+    members
+        .into_iter()
+        .map(|m| BulkString::from(m).into())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::test_helpers::{bulk, run};
+    use crate::{BackendError, RespVersion};
+
+    // the members of a set or array reply, sorted since sets have no order
+    fn members(frame: RespFrame) -> Vec<RespFrame> {
+        let mut members = match frame {
+            RespFrame::Set(set) => set.0,
+            RespFrame::Array(array) => array.0,
+            other => panic!("not a collection: {other:?}"),
+        };
+        members.sort_by_key(|m| format!("{m:?}"));
+        members
+    }
+
+    #[test]
+    fn test_set_commands() {
+        let backend = Backend::new();
+        assert_eq!(
+            run(&backend, &["sadd", "s", "a", "b", "a"]),
+            RespFrame::Integer(2)
+        );
+        assert_eq!(run(&backend, &["scard", "s"]), RespFrame::Integer(2));
+        assert_eq!(
+            members(run(&backend, &["smembers", "s"])),
+            vec![bulk("a"), bulk("b")]
+        );
+        assert_eq!(
+            run(&backend, &["sismember", "s", "a"]),
+            RespFrame::Integer(1)
+        );
+        assert_eq!(
+            run(&backend, &["smismember", "s", "a", "c"]),
+            RespArray::new(vec![RespFrame::Integer(1), RespFrame::Integer(0)]).into()
+        );
+        assert_eq!(
+            run(&backend, &["srem", "s", "a", "c"]),
+            RespFrame::Integer(1)
+        );
+        assert_eq!(
+            run(&backend, &["smembers", "missing"]),
+            RespSet::new(vec![]).into()
+        );
+
+        run(&backend, &["set", "str", "v"]);
+        assert_eq!(
+            run(&backend, &["sadd", "str", "a"]),
+            BackendError::WrongType.into()
+        );
+    }
+
+    #[test]
+    fn test_smembers_reply_per_protocol() {
+        let backend = Backend::new();
+        run(&backend, &["sadd", "s", "a"]);
+        let reply = run(&backend, &["smembers", "s"]);
+        assert_eq!(
+            reply.clone().encode_as(RespVersion::Resp3),
+            b"~1\r\n$1\r\na\r\n"
+        );
+        assert_eq!(reply.encode_as(RespVersion::Resp2), b"*1\r\n$1\r\na\r\n");
+    }
+
+    #[test]
+    fn test_spop_srandmember_commands() {
+        let backend = Backend::new();
+        run(&backend, &["sadd", "s", "a", "b", "c"]);
+        assert_eq!(
+            members(run(&backend, &["srandmember", "s", "10"])),
+            vec![bulk("a"), bulk("b"), bulk("c")]
+        );
+        assert_eq!(members(run(&backend, &["srandmember", "s", "-7"])).len(), 7);
+        assert_eq!(
+            run(&backend, &["srandmember", "s", &i64::MIN.to_string()]),
+            CommandError::InvalidArgument("value is out of range".to_string()).into()
+        );
+        assert!(matches!(
+            run(&backend, &["srandmember", "s"]),
+            RespFrame::BulkString(_)
+        ));
+        assert_eq!(
+            run(&backend, &["spop", "s", "-1"]),
+            CommandError::InvalidArgument("value is out of range, must be positive".to_string())
+                .into()
+        );
+        assert_eq!(
+            run(&backend, &["spop", "s", "1", "2"]),
+            CommandError::SyntaxError.into()
+        );
+
+        assert_eq!(members(run(&backend, &["spop", "s", "2"])).len(), 2);
+        assert!(matches!(
+            run(&backend, &["spop", "s"]),
+            RespFrame::BulkString(_)
+        ));
+        assert_eq!(run(&backend, &["spop", "s"]), RespFrame::Null(RespNull));
+        assert_eq!(
+            run(&backend, &["srandmember", "s", "-3"]),
+            RespArray::new(vec![]).into()
+        );
+    }
+
+    #[test]
+    fn test_sscan_command() {
+        let backend = Backend::new();
+        run(&backend, &["sadd", "s", "a1", "a2", "b1"]);
+        let reply = run(&backend, &["sscan", "s", "0", "MATCH", "a*", "COUNT", "10"]);
+        let RespFrame::Array(reply) = reply else {
+            panic!("not an array");
+        };
+        assert_eq!(reply[0], bulk("0"));
+        assert_eq!(members(reply[1].clone()), vec![bulk("a1"), bulk("a2")]);
+        assert_eq!(
+            run(&backend, &["sscan", "s", "0", "NOVALUES"]),
+            CommandError::SyntaxError.into()
+        );
+    }
+
+    #[test]
+    fn test_set_algebra_commands() {
+        let backend = Backend::new();
+        run(&backend, &["sadd", "a", "1", "2", "3"]);
+        run(&backend, &["sadd", "b", "2", "3", "4"]);
+        assert_eq!(
+            members(run(&backend, &["sinter", "a", "b"])),
+            vec![bulk("2"), bulk("3")]
+        );
+        assert_eq!(members(run(&backend, &["sunion", "a", "b"])).len(), 4);
+        assert_eq!(
+            run(&backend, &["sdiff", "a", "b"]),
+            RespSet::new(vec![bulk("1")]).into()
+        );
+        assert_eq!(
+            run(&backend, &["sunionstore", "dst", "a", "b"]),
+            RespFrame::Integer(4)
+        );
+        assert_eq!(run(&backend, &["scard", "dst"]), RespFrame::Integer(4));
+        assert_eq!(
+            run(&backend, &["sinterstore", "dst", "a", "missing"]),
+            RespFrame::Integer(0)
+        );
+        assert_eq!(run(&backend, &["exists", "dst"]), RespFrame::Integer(0));
+        assert_eq!(
+            run(&backend, &["sdiffstore", "dst"]),
+            CommandError::WrongArity("sdiffstore".to_string()).into()
+        );
+    }
+
+    #[test]
+    fn test_sintercard_command() {
+        let backend = Backend::new();
+        run(&backend, &["sadd", "a", "1", "2", "3"]);
+        run(&backend, &["sadd", "b", "1", "2", "3"]);
+        assert_eq!(
+            run(&backend, &["sintercard", "2", "a", "b"]),
+            RespFrame::Integer(3)
+        );
+        assert_eq!(
+            run(&backend, &["sintercard", "2", "a", "b", "LIMIT", "2"]),
+            RespFrame::Integer(2)
+        );
+        assert_eq!(
+            run(&backend, &["sintercard", "3", "a", "b"]),
+            CommandError::InvalidArgument(
+                "Number of keys can't be greater than number of args".to_string()
+            )
+            .into()
+        );
+        assert_eq!(
+            run(&backend, &["sintercard", "1", "a", "limit", "-1"]),
+            CommandError::InvalidArgument("LIMIT can't be negative".to_string()).into()
+        );
+    }
+
+    #[test]
+    fn test_smove_command() {
+        let backend = Backend::new();
+        run(&backend, &["sadd", "a", "1"]);
+        assert_eq!(
+            run(&backend, &["smove", "a", "b", "1"]),
+            RespFrame::Integer(1)
+        );
+        assert_eq!(
+            run(&backend, &["smove", "a", "b", "1"]),
+            RespFrame::Integer(0)
+        );
+        assert_eq!(
+            run(&backend, &["sismember", "b", "1"]),
+            RespFrame::Integer(1)
+        );
+    }
+}
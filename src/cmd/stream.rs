@@ -0,0 +1,1647 @@
+use std::time::Duration;
+
+use super::{
+    command_name, extract_args, extract_string, parse_int, validate_command, Arity,
+    GroupSubcommand, ReadGroup, XInfoSubcommand,
+};
+use crate::{
+    Backend, BackendError, BulkString, ClaimOptions, CommandError, CommandExecutor, PendingRange,
+    RespArray, RespFrame, RespMap, RespNull, RespNullArray, RespVersion, SimpleString,
+    StreamFields, StreamId, StreamTrim, TrimStrategy, XAck, XAdd, XAddId, XAutoClaim, XClaim, XDel,
+    XGroup, XInfo, XLen, XPending, XRange, XRead, XTrim, RESP_OK,
+};
+use std::convert::TryFrom;
+
+const ONE_ARGS: usize = 1;
+const TWO_ARGS: usize = 2;
+const THREE_ARGS: usize = 3;
+
+const XGROUP_HELP: &[&str] = &[
+    "XGROUP <subcommand> [<arg> [value] [opt] ...]. Subcommands are:",
+    "CREATE <key> <groupname> <id|$> [option]",
+    "    Create a new consumer group. Options are:",
+    "    * MKSTREAM",
+    "      Create the empty stream if it does not exist.",
+    "    * ENTRIESREAD entries_read",
+    "      Set the group's entries_read counter (internal use).",
+    "CREATECONSUMER <key> <groupname> <consumer>",
+    "    Create a new consumer in the specified group.",
+    "DELCONSUMER <key> <groupname> <consumer>",
+    "    Remove the specified consumer.",
+    "DESTROY <key> <groupname>",
+    "    Remove the specified group.",
+    "SETID <key> <groupname> <id|$> [ENTRIESREAD entries_read]",
+    "    Set the current group ID and entries_read counter.",
+    "HELP",
+    "    Print this help.",
+];
+
+const XINFO_HELP: &[&str] = &[
+    "XINFO <subcommand> [<arg> [value] [opt] ...]. Subcommands are:",
+    "CONSUMERS <key> <groupname>",
+    "    Show consumers of <groupname>.",
+    "GROUPS <key>",
+    "    Show the stream consumer groups.",
+    "STREAM <key>",
+    "    Show information about the stream.",
+    "HELP",
+    "    Print this help.",
+];
+
+impl CommandExecutor for XAdd {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.xadd(&self.key, self.id, self.fields, self.no_mkstream, self.trim) {
+            Ok(Some(id)) => BulkString::from(id.to_string()).into(),
+            Ok(None) => RespFrame::Null(RespNull),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for XRange {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        // like Redis, COUNT 0 replies nil rather than an empty array
+        if self.count == Some(0) {
+            return RespFrame::Null(RespNull);
+        }
+        match backend.xrange(&self.key, self.start, self.end, self.rev, self.count) {
+            Ok(entries) => entries_reply(entries),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for XLen {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.xlen(&self.key) {
+            Ok(len) => RespFrame::Integer(len as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for XTrim {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.xtrim(&self.key, &self.trim) {
+            Ok(removed) => RespFrame::Integer(removed as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for XDel {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.xdel(&self.key, &self.ids) {
+            Ok(removed) => RespFrame::Integer(removed as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl XRead {
+    // the entries of each stream as [key, entries] pairs on RESP2 and a map on RESP3, a null
+    // array if no stream has any
+    pub(super) fn reply(mut self, backend: &Backend, protocol: RespVersion) -> RespFrame {
+        let ret = self
+            .resolve(backend)
+            .and_then(|_| self.read(backend, protocol));
+        match ret {
+            Ok(reply) => reply.unwrap_or(RespFrame::NullArray(RespNullArray)),
+            Err(e) => e.into(),
+        }
+    }
+
+    /// With BLOCK and nothing to read, wait for an XADD to one of the streams until the
+    /// timeout. A timeout replies nil.
+    pub async fn wait(mut self, backend: &Backend, protocol: RespVersion) -> RespFrame {
+        if !self.block {
+            return self.reply(backend, protocol);
+        }
+        match self
+            .resolve(backend)
+            .and_then(|_| self.read(backend, protocol))
+        {
+            Ok(None) => {}
+            Ok(Some(reply)) => return reply,
+            Err(e) => return e.into(),
+        }
+        let keys = self.streams.iter().map(|(key, _)| key.clone()).collect();
+        let timeout = self.timeout;
+        let op = Box::new(
+            move |backend: &Backend| match self.read(backend, protocol) {
+                Ok(reply) => reply,
+                Err(e) => Some(e.into()),
+            },
+        );
+        backend
+            .block_on(keys, timeout, op)
+            .await
+            .unwrap_or(RespFrame::NullArray(RespNullArray))
+    }
+
+    // XREAD reads after the last IDs the streams have when it runs for `$`
+    fn resolve(&mut self, backend: &Backend) -> Result<(), BackendError> {
+        if self.group.is_none() {
+            for (key, id) in &mut self.streams {
+                if id.is_none() {
+                    *id = Some(backend.xlast_id(key)?);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn read(
+        &self,
+        backend: &Backend,
+        protocol: RespVersion,
+    ) -> Result<Option<RespFrame>, BackendError> {
+        let streams = match &self.group {
+            None => {
+                // `resolve` replaced the `$` IDs
+                let streams = self
+                    .streams
+                    .iter()
+                    .map(|(key, id)| (key.clone(), id.unwrap_or(StreamId::MAX)))
+                    .collect::<Vec<_>>();
+                backend
+                    .xread(&streams, self.count)?
+                    .into_iter()
+                    .map(|(key, entries)| (key, entries_reply(entries)))
+                    .collect::<Vec<_>>()
+            }
+            Some(group) => backend
+                .xreadgroup(
+                    &group.name,
+                    &group.consumer,
+                    &self.streams,
+                    self.count,
+                    group.no_ack,
+                )?
+                .into_iter()
+                .map(|(key, entries)| (key, history_reply(entries)))
+                .collect(),
+        };
+        if streams.is_empty() {
+            return Ok(None);
+        }
+        let reply = match protocol {
+            RespVersion::Resp2 => {
+                let streams = streams
+                    .into_iter()
+                    .map(|(key, entries)| {
+                        RespArray::new(vec![BulkString::from(key).into(), entries]).into()
+                    })
+                    .collect::<Vec<RespFrame>>();
+                RespArray::new(streams).into()
+            }
+            RespVersion::Resp3 => {
+                let mut map = RespMap::new();
+                for (key, entries) in streams {
+                    map.insert(key, entries);
+                }
+                map.into()
+            }
+        };
+        Ok(Some(reply))
+    }
+}
+
+impl CommandExecutor for XRead {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.reply(backend, RespVersion::Resp2)
+    }
+}
+
+impl CommandExecutor for XGroup {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let (key, group) = (&self.key, &self.group);
+        let ret = match self.subcommand {
+            GroupSubcommand::Create {
+                id,
+                mkstream,
+                entries_read,
+            } => backend
+                .xgroup_create(key, group, id, mkstream, entries_read)
+                .map(|_| RESP_OK.clone()),
+            GroupSubcommand::SetId { id, entries_read } => backend
+                .xgroup_setid(key, group, id, entries_read)
+                .map(|_| RESP_OK.clone()),
+            GroupSubcommand::Destroy => backend
+                .xgroup_destroy(key, group)
+                .map(|destroyed| RespFrame::Integer(destroyed as i64)),
+            GroupSubcommand::CreateConsumer(consumer) => backend
+                .xgroup_create_consumer(key, group, &consumer)
+                .map(|created| RespFrame::Integer(created as i64)),
+            GroupSubcommand::DelConsumer(consumer) => backend
+                .xgroup_del_consumer(key, group, &consumer)
+                .map(|pending| RespFrame::Integer(pending as i64)),
+            GroupSubcommand::Help => Ok(help_reply(XGROUP_HELP)),
+        };
+        ret.unwrap_or_else(|e| e.into())
+    }
+}
+
+impl CommandExecutor for XAck {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.xack(&self.key, &self.group, &self.ids) {
+            Ok(acked) => RespFrame::Integer(acked as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for XPending {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let Some(range) = self.range else {
+            return match backend.xpending_summary(&self.key, &self.group) {
+                Ok(summary) => {
+                    let (first, last) = match summary.range {
+                        Some((first, last)) => (id_reply(first), id_reply(last)),
+                        None => (RespFrame::Null(RespNull), RespFrame::Null(RespNull)),
+                    };
+                    let consumers = if summary.consumers.is_empty() {
+                        RespFrame::Null(RespNull)
+                    } else {
+                        let consumers = summary
+                            .consumers
+                            .into_iter()
+                            .map(|(name, count)| {
+                                // the count comes as a string
+                                let pair = vec![
+                                    BulkString::from(name).into(),
+                                    BulkString::from(count.to_string()).into(),
+                                ];
+                                RespArray::new(pair).into()
+                            })
+                            .collect::<Vec<RespFrame>>();
+                        RespArray::new(consumers).into()
+                    };
+                    let count = RespFrame::Integer(summary.count as i64);
+                    RespArray::new(vec![count, first, last, consumers]).into()
+                }
+                Err(e) => e.into(),
+            };
+        };
+        match backend.xpending(&self.key, &self.group, &range) {
+            Ok(entries) => {
+                let entries = entries
+                    .into_iter()
+                    .map(|info| {
+                        let entry = vec![
+                            id_reply(info.id),
+                            BulkString::from(info.consumer).into(),
+                            RespFrame::Integer(info.idle as i64),
+                            RespFrame::Integer(info.deliveries as i64),
+                        ];
+                        RespArray::new(entry).into()
+                    })
+                    .collect::<Vec<RespFrame>>();
+                RespArray::new(entries).into()
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for XClaim {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let mut options = self.options;
+        if let Some(idle) = self.idle {
+            options.delivered_at = Some(backend.now_ms().saturating_sub(idle));
+        }
+        let claimed = backend.xclaim(
+            &self.key,
+            &self.group,
+            &self.consumer,
+            self.min_idle,
+            &self.ids,
+            &options,
+        );
+        match claimed {
+            Ok(claimed) if options.just_id => ids_reply(claimed.into_iter().map(|(id, _)| id)),
+            Ok(claimed) => entries_reply(claimed),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for XAutoClaim {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let claim = backend.xautoclaim(
+            &self.key,
+            &self.group,
+            &self.consumer,
+            self.min_idle,
+            self.start,
+            self.count,
+            self.just_id,
+        );
+        match claim {
+            Ok(claim) => {
+                let claimed = if self.just_id {
+                    ids_reply(claim.claimed.into_iter().map(|(id, _)| id))
+                } else {
+                    entries_reply(claim.claimed)
+                };
+                let reply = vec![id_reply(claim.next), claimed, ids_reply(claim.deleted)];
+                RespArray::new(reply).into()
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
+// the XINFO replies are maps, which RESP2 connections receive as flat arrays
+impl CommandExecutor for XInfo {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let ret = match self.subcommand {
+            XInfoSubcommand::Stream => backend.xinfo_stream(&self.key).map(|info| {
+                let entry = |entry: Option<(StreamId, StreamFields)>| match entry {
+                    Some((id, fields)) => entry_reply(id, fields),
+                    None => RespFrame::Null(RespNull),
+                };
+                let mut map = RespMap::new();
+                map.insert("length".to_string(), int_reply(info.length as u64));
+                map.insert("last-generated-id".to_string(), id_reply(info.last_id));
+                map.insert(
+                    "max-deleted-entry-id".to_string(),
+                    id_reply(info.max_deleted_id),
+                );
+                map.insert("entries-added".to_string(), int_reply(info.entries_added));
+                map.insert(
+                    "recorded-first-entry-id".to_string(),
+                    id_reply(info.first_id),
+                );
+                map.insert("groups".to_string(), int_reply(info.groups as u64));
+                map.insert("first-entry".to_string(), entry(info.first_entry));
+                map.insert("last-entry".to_string(), entry(info.last_entry));
+                map.into()
+            }),
+            XInfoSubcommand::Groups => backend.xinfo_groups(&self.key).map(|groups| {
+                let groups = groups
+                    .into_iter()
+                    .map(|group| {
+                        let mut map = RespMap::new();
+                        map.insert("name".to_string(), BulkString::from(group.name).into());
+                        map.insert("consumers".to_string(), int_reply(group.consumers as u64));
+                        map.insert("pending".to_string(), int_reply(group.pending as u64));
+                        map.insert("last-delivered-id".to_string(), id_reply(group.last_id));
+                        map.insert(
+                            "entries-read".to_string(),
+                            opt_int_reply(group.entries_read),
+                        );
+                        map.insert("lag".to_string(), opt_int_reply(group.lag));
+                        map.into()
+                    })
+                    .collect::<Vec<RespFrame>>();
+                RespArray::new(groups).into()
+            }),
+            XInfoSubcommand::Consumers(group) => {
+                backend.xinfo_consumers(&self.key, &group).map(|consumers| {
+                    let consumers = consumers
+                        .into_iter()
+                        .map(|consumer| {
+                            let mut map = RespMap::new();
+                            map.insert("name".to_string(), BulkString::from(consumer.name).into());
+                            map.insert("pending".to_string(), int_reply(consumer.pending as u64));
+                            map.insert("idle".to_string(), int_reply(consumer.idle));
+                            // -1 for a consumer that never read anything
+                            let inactive = consumer.inactive.map_or(-1, |ms| ms as i64);
+                            map.insert("inactive".to_string(), RespFrame::Integer(inactive));
+                            map.into()
+                        })
+                        .collect::<Vec<RespFrame>>();
+                    RespArray::new(consumers).into()
+                })
+            }
+            XInfoSubcommand::Help => Ok(help_reply(XINFO_HELP)),
+        };
+        ret.unwrap_or_else(|e| e.into())
+    }
+}
+
+impl TryFrom<RespArray> for XAdd {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&array, &["xadd"], Arity::AtLeast(THREE_ARGS))?;
+        let mut args = extract_args(array, ONE_ARGS)?.into_iter();
+        let key = extract_string(args.next().ok_or(CommandError::SyntaxError)?)?;
+        let (mut no_mkstream, mut trim) = (false, TrimArgs::default());
+        // the options come first, the first argument that is not one is the ID
+        let id = loop {
+            let arg = extract_string(args.next().ok_or(CommandError::SyntaxError)?)?;
+            if arg.eq_ignore_ascii_case("nomkstream") {
+                no_mkstream = true;
+            } else if !trim.parse(&arg, &mut args)? {
+                break parse_xadd_id(&arg)?;
+            }
+        };
+        if args.len() == 0 || args.len() % 2 != 0 {
+            return Err(CommandError::WrongArity("xadd".to_string()));
+        }
+        let mut fields = StreamFields::with_capacity(args.len() / 2);
+        while let (Some(field), Some(value)) = (args.next(), args.next()) {
+            fields.push((extract_string(field)?, value));
+        }
+        Ok(XAdd {
+            key,
+            no_mkstream,
+            trim: trim.finish()?,
+            id,
+            fields,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XRange {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        let (name, rev) = match command_name(&array)?.as_str() {
+            "xrange" => ("xrange", false),
+            "xrevrange" => ("xrevrange", true),
+            name => {
+                return Err(CommandError::InvalidCommand(format!(
+                    "Invalid command: expected xrange, got {name}"
+                )))
+            }
+        };
+        validate_command(&array, &[name], Arity::AtLeast(THREE_ARGS))?;
+        let mut args = extract_args(array, ONE_ARGS)?.into_iter();
+        let key = extract_string(args.next().ok_or(CommandError::SyntaxError)?)?;
+        let first = extract_string(args.next().ok_or(CommandError::SyntaxError)?)?;
+        let second = extract_string(args.next().ok_or(CommandError::SyntaxError)?)?;
+        // XREVRANGE takes the end first
+        let (start, end) = if rev {
+            (second, first)
+        } else {
+            (first, second)
+        };
+        let option = args.next().map(extract_string).transpose()?;
+        let count = match (option, args.next(), args.next()) {
+            (None, _, _) => None,
+            (Some(option), Some(ref count), None) if option.eq_ignore_ascii_case("count") => {
+                // a negative count selects nothing, like 0
+                Some(parse_int(count)?.max(0) as usize)
+            }
+            _ => return Err(CommandError::SyntaxError),
+        };
+        Ok(XRange {
+            key,
+            start: parse_interval_id(&start, true)?,
+            end: parse_interval_id(&end, false)?,
+            rev,
+            count,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XLen {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&array, &["xlen"], ONE_ARGS)?;
+        let mut args = extract_args(array, ONE_ARGS)?.into_iter();
+        let key = extract_string(args.next().ok_or(CommandError::SyntaxError)?)?;
+        Ok(XLen { key })
+    }
+}
+
+impl TryFrom<RespArray> for XTrim {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&array, &["xtrim"], Arity::AtLeast(THREE_ARGS))?;
+        let mut args = extract_args(array, ONE_ARGS)?.into_iter();
+        let key = extract_string(args.next().ok_or(CommandError::SyntaxError)?)?;
+        let mut trim = TrimArgs::default();
+        while let Some(arg) = args.next() {
+            if !trim.parse(&extract_string(arg)?, &mut args)? {
+                return Err(CommandError::SyntaxError);
+            }
+        }
+        let trim = trim.finish()?.ok_or_else(|| {
+            CommandError::InvalidArgument(
+                "syntax error, XTRIM must be called with a trimming strategy".to_string(),
+            )
+        })?;
+        Ok(XTrim { key, trim })
+    }
+}
+
+impl TryFrom<RespArray> for XDel {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&array, &["xdel"], Arity::AtLeast(TWO_ARGS))?;
+        let mut args = extract_args(array, ONE_ARGS)?.into_iter();
+        let key = extract_string(args.next().ok_or(CommandError::SyntaxError)?)?;
+        let ids = args
+            .map(|id| parse_stream_id(&extract_string(id)?, 0))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(XDel { key, ids })
+    }
+}
+
+impl TryFrom<RespArray> for XRead {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        let (name, xreadgroup) = match command_name(&array)?.as_str() {
+            "xread" => ("xread", false),
+            "xreadgroup" => ("xreadgroup", true),
+            name => {
+                return Err(CommandError::InvalidCommand(format!(
+                    "Invalid command: expected xread, got {name}"
+                )))
+            }
+        };
+        validate_command(&array, &[name], Arity::AtLeast(THREE_ARGS))?;
+        let mut args = extract_args(array, ONE_ARGS)?.into_iter();
+        let (mut group, mut no_ack, mut count, mut block, mut timeout) =
+            (None, false, None, false, None);
+        // the options come first, up to STREAMS
+        loop {
+            let option = extract_string(next_arg(&mut args)?)?;
+            match option.to_ascii_lowercase().as_str() {
+                "count" => {
+                    // 0 or less reads everything
+                    let n = parse_int(&next_arg(&mut args)?)?;
+                    count = (n > 0).then_some(n as usize);
+                }
+                "block" => {
+                    block = true;
+                    timeout = parse_block(&next_arg(&mut args)?)?;
+                }
+                "group" if xreadgroup => {
+                    let name = extract_string(next_arg(&mut args)?)?;
+                    let consumer = extract_string(next_arg(&mut args)?)?;
+                    group = Some((name, consumer));
+                }
+                "group" => return Err(CommandError::InvalidArgument(
+                    "The GROUP option is only supported by XREADGROUP. You called XREAD instead."
+                        .to_string(),
+                )),
+                "noack" if xreadgroup => no_ack = true,
+                "streams" => break,
+                _ => return Err(CommandError::SyntaxError),
+            }
+        }
+        let group = match group {
+            Some((name, consumer)) => Some(ReadGroup {
+                name,
+                consumer,
+                no_ack,
+            }),
+            None if xreadgroup => {
+                return Err(CommandError::InvalidArgument(
+                    "Missing GROUP option for XREADGROUP".to_string(),
+                ))
+            }
+            None => None,
+        };
+        let args = args.map(extract_string).collect::<Result<Vec<_>, _>>()?;
+        if args.is_empty() || args.len() % 2 != 0 {
+            let last = if xreadgroup { ">" } else { "$" };
+            return Err(CommandError::InvalidArgument(format!(
+                "Unbalanced '{name}' list of streams: for each stream key an ID or '{last}' must be specified."
+            )));
+        }
+        let (keys, ids) = args.split_at(args.len() / 2);
+        let streams = keys
+            .iter()
+            .zip(ids)
+            .map(|(key, id)| {
+                let id = match id.as_str() {
+                    ">" if xreadgroup => None,
+                    "$" if !xreadgroup => None,
+                    ">" => {
+                        return Err(CommandError::InvalidArgument(
+                            "The > ID can be specified only when calling XREADGROUP using the GROUP <group> <consumer> option."
+                                .to_string(),
+                        ))
+                    }
+                    "$" => {
+                        return Err(CommandError::InvalidArgument(
+                            "The $ ID is meaningless in the context of XREADGROUP: you want to read the history of this consumer by specifying a proper ID, or use the > ID to get new messages. The $ ID would just return an empty result set."
+                                .to_string(),
+                        ))
+                    }
+                    id => Some(parse_stream_id(id, 0)?),
+                };
+                Ok((key.clone(), id))
+            })
+            .collect::<Result<Vec<_>, CommandError>>()?;
+        Ok(XRead {
+            group,
+            count,
+            block,
+            timeout,
+            streams,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XGroup {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&array, &["xgroup"], Arity::AtLeast(ONE_ARGS))?;
+        let mut args = extract_args(array, ONE_ARGS)?.into_iter();
+        let name = extract_string(args.next().ok_or(CommandError::SyntaxError)?)?;
+        let name = name.to_ascii_lowercase();
+        if name == "help" {
+            return Ok(XGroup {
+                subcommand: GroupSubcommand::Help,
+                key: String::new(),
+                group: String::new(),
+            });
+        }
+        let mut next = || extract_string(args.next().ok_or(CommandError::SyntaxError)?);
+        let (key, group) = (next()?, next()?);
+        let subcommand = match name.as_str() {
+            "create" | "setid" => {
+                let id = match next()?.as_str() {
+                    "$" => None,
+                    id => Some(parse_stream_id(id, 0)?),
+                };
+                let (mut mkstream, mut entries_read) = (false, None);
+                while let Some(option) = args.next() {
+                    match extract_string(option)?.to_ascii_lowercase().as_str() {
+                        "mkstream" if name == "create" => mkstream = true,
+                        "entriesread" => {
+                            let n = parse_int(&args.next().ok_or(CommandError::SyntaxError)?)?;
+                            entries_read = match n {
+                                -1 => None,
+                                n if n < 0 => {
+                                    return Err(CommandError::InvalidArgument(
+                                        "value for ENTRIESREAD must be positive or -1".to_string(),
+                                    ))
+                                }
+                                n => Some(n as u64),
+                            };
+                        }
+                        _ => return Err(CommandError::SyntaxError),
+                    }
+                }
+                if name == "create" {
+                    GroupSubcommand::Create {
+                        id,
+                        mkstream,
+                        entries_read,
+                    }
+                } else {
+                    GroupSubcommand::SetId { id, entries_read }
+                }
+            }
+            "destroy" => GroupSubcommand::Destroy,
+            "createconsumer" => GroupSubcommand::CreateConsumer(next()?),
+            "delconsumer" => GroupSubcommand::DelConsumer(next()?),
+            _ => {
+                return Err(CommandError::UnknownSubcommand {
+                    command: "XGROUP".to_string(),
+                    name,
+                })
+            }
+        };
+        if args.len() > 0 {
+            return Err(CommandError::WrongArity(format!("xgroup|{name}")));
+        }
+        Ok(XGroup {
+            subcommand,
+            key,
+            group,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XAck {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&array, &["xack"], Arity::AtLeast(THREE_ARGS))?;
+        let mut args = extract_args(array, ONE_ARGS)?.into_iter();
+        let key = extract_string(args.next().ok_or(CommandError::SyntaxError)?)?;
+        let group = extract_string(args.next().ok_or(CommandError::SyntaxError)?)?;
+        let ids = args
+            .map(|id| parse_stream_id(&extract_string(id)?, 0))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(XAck { key, group, ids })
+    }
+}
+
+impl TryFrom<RespArray> for XPending {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&array, &["xpending"], Arity::AtLeast(TWO_ARGS))?;
+        let mut args = extract_args(array, ONE_ARGS)?.into_iter();
+        let key = extract_string(args.next().ok_or(CommandError::SyntaxError)?)?;
+        let group = extract_string(args.next().ok_or(CommandError::SyntaxError)?)?;
+        let mut args = args.map(extract_string).collect::<Result<Vec<_>, _>>()?;
+        if args.is_empty() {
+            return Ok(XPending {
+                key,
+                group,
+                range: None,
+            });
+        }
+        let mut min_idle = 0;
+        if args.len() >= 5 && args[0].eq_ignore_ascii_case("idle") {
+            // a negative time is no minimum
+            min_idle = args[1]
+                .parse::<i64>()
+                .map_err(|_| CommandError::NotAnInteger)?;
+            args.drain(..2);
+        }
+        if args.len() < 3 || args.len() > 4 {
+            return Err(CommandError::SyntaxError);
+        }
+        let count = args[2]
+            .parse::<i64>()
+            .map_err(|_| CommandError::NotAnInteger)?;
+        let range = PendingRange {
+            start: parse_interval_id(&args[0], true)?,
+            end: parse_interval_id(&args[1], false)?,
+            count: count.max(0) as usize,
+            consumer: args.get(3).cloned(),
+            min_idle: min_idle.max(0) as u64,
+        };
+        Ok(XPending {
+            key,
+            group,
+            range: Some(range),
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XClaim {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&array, &["xclaim"], Arity::AtLeast(5))?;
+        let mut args = extract_args(array, ONE_ARGS)?.into_iter().peekable();
+        let mut next = || extract_string(next_arg(&mut args)?);
+        let (key, group, consumer) = (next()?, next()?, next()?);
+        let min_idle = parse_millis(&next()?, "min-idle-time argument for XCLAIM")?;
+        // the IDs come first, the first argument that is not one is an option
+        let mut ids = Vec::new();
+        while let Some(id) = args.peek().and_then(|frame| match frame {
+            RespFrame::BulkString(s) => std::str::from_utf8(s).ok(),
+            _ => None,
+        }) {
+            match parse_stream_id(id, 0) {
+                Ok(id) => ids.push(id),
+                Err(_) => break,
+            }
+            args.next();
+        }
+        let (mut options, mut idle) = (ClaimOptions::default(), None);
+        while let Some(option) = args.next() {
+            let option = extract_string(option)?;
+            let value = match option.to_ascii_lowercase().as_str() {
+                "force" => {
+                    options.force = true;
+                    continue;
+                }
+                "justid" => {
+                    options.just_id = true;
+                    continue;
+                }
+                "idle" | "time" | "retrycount" | "lastid" if args.peek().is_some() => {
+                    extract_string(next_arg(&mut args)?)?
+                }
+                _ => {
+                    return Err(CommandError::InvalidArgument(format!(
+                        "Unrecognized XCLAIM option '{option}'"
+                    )))
+                }
+            };
+            match option.to_ascii_lowercase().as_str() {
+                "idle" => {
+                    idle = Some(parse_millis(&value, "IDLE option argument for XCLAIM")?);
+                    options.delivered_at = None;
+                }
+                "time" => {
+                    let time = parse_millis(&value, "TIME option argument for XCLAIM")?;
+                    options.delivered_at = Some(time);
+                    idle = None;
+                }
+                "retrycount" => {
+                    let n = parse_millis(&value, "RETRYCOUNT option argument for XCLAIM")?;
+                    options.deliveries = Some(n);
+                }
+                _ => options.last_id = Some(parse_stream_id(&value, 0)?),
+            }
+        }
+        Ok(XClaim {
+            key,
+            group,
+            consumer,
+            min_idle,
+            ids,
+            options,
+            idle,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XAutoClaim {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&array, &["xautoclaim"], Arity::AtLeast(5))?;
+        let mut args = extract_args(array, ONE_ARGS)?.into_iter();
+        let mut next = || extract_string(args.next().ok_or(CommandError::SyntaxError)?);
+        let (key, group, consumer) = (next()?, next()?, next()?);
+        let min_idle = parse_millis(&next()?, "min-idle-time argument for XAUTOCLAIM")?;
+        let start = parse_interval_id(&next()?, true)?;
+        let (mut count, mut just_id) = (100, false);
+        while let Some(option) = args.next() {
+            match extract_string(option)?.to_ascii_lowercase().as_str() {
+                "count" => {
+                    let n = args.next().ok_or(CommandError::SyntaxError)?;
+                    // the count is multiplied to bound the scan
+                    count = match parse_int(&n) {
+                        Ok(n) if n > 0 && n <= i64::MAX / 10 => n as usize,
+                        _ => {
+                            return Err(CommandError::InvalidArgument(
+                                "COUNT must be > 0".to_string(),
+                            ))
+                        }
+                    };
+                }
+                "justid" => just_id = true,
+                _ => return Err(CommandError::SyntaxError),
+            }
+        }
+        Ok(XAutoClaim {
+            key,
+            group,
+            consumer,
+            min_idle,
+            start,
+            count,
+            just_id,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XInfo {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&array, &["xinfo"], Arity::AtLeast(ONE_ARGS))?;
+        let mut args = extract_args(array, ONE_ARGS)?.into_iter();
+        let name = extract_string(args.next().ok_or(CommandError::SyntaxError)?)?;
+        let name = name.to_ascii_lowercase();
+        let mut next = || args.next().map(extract_string).transpose();
+        let (key, subcommand) = match (name.as_str(), next()?, next()?, next()?) {
+            ("help", None, _, _) => (String::new(), XInfoSubcommand::Help),
+            ("stream", Some(key), None, _) => (key, XInfoSubcommand::Stream),
+            ("groups", Some(key), None, _) => (key, XInfoSubcommand::Groups),
+            ("consumers", Some(key), Some(group), None) => (key, XInfoSubcommand::Consumers(group)),
+            ("help" | "stream" | "groups" | "consumers", _, _, _) => {
+                return Err(CommandError::WrongArity(format!("xinfo|{name}")))
+            }
+            _ => {
+                return Err(CommandError::UnknownSubcommand {
+                    command: "XINFO".to_string(),
+                    name,
+                })
+            }
+        };
+        Ok(XInfo { subcommand, key })
+    }
+}
+
+// the MAXLEN / MINID [= | ~] threshold and LIMIT count options of XADD and XTRIM
+#[derive(Debug, Default)]
+struct TrimArgs {
+    strategy: Option<TrimStrategy>,
+    approx: bool,
+    limit: Option<usize>,
+}
+
+impl TrimArgs {
+    // consume `arg` and its values if it is a trim option, else return false
+    fn parse(
+        &mut self,
+        arg: &str,
+        args: &mut impl Iterator<Item = RespFrame>,
+    ) -> Result<bool, CommandError> {
+        let mut next = || extract_string(args.next().ok_or(CommandError::SyntaxError)?);
+        match arg.to_ascii_lowercase().as_str() {
+            option @ ("maxlen" | "minid") => {
+                let mut threshold = next()?;
+                if threshold == "~" || threshold == "=" {
+                    self.approx = threshold == "~";
+                    threshold = next()?;
+                }
+                let strategy = if option == "maxlen" {
+                    match threshold.parse::<i64>() {
+                        Ok(n) if n < 0 => {
+                            return Err(CommandError::InvalidArgument(
+                                "The MAXLEN argument must be >= 0.".to_string(),
+                            ))
+                        }
+                        Ok(n) => TrimStrategy::MaxLen(n as usize),
+                        Err(_) => return Err(CommandError::NotAnInteger),
+                    }
+                } else {
+                    TrimStrategy::MinId(parse_stream_id(&threshold, 0)?)
+                };
+                match (self.strategy, strategy) {
+                    (Some(TrimStrategy::MaxLen(_)), TrimStrategy::MinId(_))
+                    | (Some(TrimStrategy::MinId(_)), TrimStrategy::MaxLen(_)) => {
+                        return Err(CommandError::InvalidArgument(
+                            "syntax error, MAXLEN and MINID options at the same time are not compatible"
+                                .to_string(),
+                        ))
+                    }
+                    _ => self.strategy = Some(strategy),
+                }
+            }
+            "limit" => match next()?.parse::<i64>() {
+                Ok(n) if n < 0 => {
+                    return Err(CommandError::InvalidArgument(
+                        "The LIMIT argument must be >= 0.".to_string(),
+                    ))
+                }
+                Ok(n) => self.limit = Some(n as usize),
+                Err(_) => return Err(CommandError::NotAnInteger),
+            },
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    fn finish(self) -> Result<Option<StreamTrim>, CommandError> {
+        if self.limit.is_some() && self.strategy.is_none() {
+            return Err(CommandError::InvalidArgument(
+                "syntax error, LIMIT cannot be used without specifying a trimming strategy"
+                    .to_string(),
+            ));
+        }
+        if self.limit.is_some() && !self.approx {
+            return Err(CommandError::InvalidArgument(
+                "syntax error, LIMIT cannot be used without the special ~ option".to_string(),
+            ));
+        }
+        Ok(self.strategy.map(|strategy| StreamTrim {
+            strategy,
+            approx: self.approx,
+            limit: self.limit,
+        }))
+    }
+}
+
+fn invalid_id() -> CommandError {
+    CommandError::InvalidArgument(
+        "Invalid stream ID specified as stream command argument".to_string(),
+    )
+}
+
+// `<ms>-<seq>`, or `<ms>` alone with `missing_seq` as the sequence number
+fn parse_stream_id(s: &str, missing_seq: u64) -> Result<StreamId, CommandError> {
+    let (ms, seq) = match s.split_once('-') {
+        Some((ms, seq)) => (ms, seq.parse().map_err(|_| invalid_id())?),
+        None => (s, missing_seq),
+    };
+    Ok(StreamId::new(ms.parse().map_err(|_| invalid_id())?, seq))
+}
+
+// `*`, `<ms>-*` or an explicit ID
+fn parse_xadd_id(s: &str) -> Result<XAddId, CommandError> {
+    if s == "*" {
+        return Ok(XAddId::Auto);
+    }
+    match s.strip_suffix("-*") {
+        Some(ms) => Ok(XAddId::AutoSeq(ms.parse().map_err(|_| invalid_id())?)),
+        None => Ok(XAddId::Explicit(parse_stream_id(s, 0)?)),
+    }
+}
+
+// a bound of XRANGE made inclusive: `-` and `+` for the first and last possible IDs, a missing
+// sequence number includes the whole millisecond and `(` excludes the ID
+fn parse_interval_id(s: &str, start: bool) -> Result<StreamId, CommandError> {
+    let (exclusive, s) = match s.strip_prefix('(') {
+        Some(rest) if !rest.is_empty() => (true, rest),
+        _ => (false, s),
+    };
+    let id = match s {
+        "-" => StreamId::MIN,
+        "+" => StreamId::MAX,
+        s => parse_stream_id(s, if start { 0 } else { u64::MAX })?,
+    };
+    match (exclusive, start) {
+        (false, _) => Ok(id),
+        (true, true) => id.next().ok_or_else(|| {
+            CommandError::InvalidArgument("invalid start ID for the interval".to_string())
+        }),
+        (true, false) => id.prev().ok_or_else(|| {
+            CommandError::InvalidArgument("invalid end ID for the interval".to_string())
+        }),
+    }
+}
+
+fn next_arg(args: &mut impl Iterator<Item = RespFrame>) -> Result<RespFrame, CommandError> {
+    args.next().ok_or(CommandError::SyntaxError)
+}
+
+// the BLOCK milliseconds of XREAD and XREADGROUP, 0 blocks forever
+fn parse_block(frame: &RespFrame) -> Result<Option<Duration>, CommandError> {
+    match parse_int(frame) {
+        Ok(ms) if ms < 0 => Err(CommandError::InvalidArgument(
+            "timeout is negative".to_string(),
+        )),
+        Ok(0) => Ok(None),
+        Ok(ms) => Ok(Some(Duration::from_millis(ms as u64))),
+        Err(_) => Err(CommandError::InvalidArgument(
+            "timeout is not an integer or out of range".to_string(),
+        )),
+    }
+}
+
+// a time in milliseconds of XCLAIM and XAUTOCLAIM, negative ones are 0
+fn parse_millis(s: &str, what: &str) -> Result<u64, CommandError> {
+    match s.parse::<i64>() {
+        Ok(ms) => Ok(ms.max(0) as u64),
+        Err(_) => Err(CommandError::InvalidArgument(format!("Invalid {what}"))),
+    }
+}
+
+fn id_reply(id: StreamId) -> RespFrame {
+    BulkString::from(id.to_string()).into()
+}
+
+fn ids_reply(ids: impl IntoIterator<Item = StreamId>) -> RespFrame {
+    RespArray::new(ids.into_iter().map(id_reply).collect::<Vec<_>>()).into()
+}
+
+fn int_reply(n: u64) -> RespFrame {
+    RespFrame::Integer(n as i64)
+}
+
+// nil for a value that is not known
+fn opt_int_reply(n: Option<u64>) -> RespFrame {
+    n.map_or(RespFrame::Null(RespNull), int_reply)
+}
+
+// an entry as its ID followed by the flat field value pairs
+fn entry_reply(id: StreamId, fields: StreamFields) -> RespFrame {
+    let fields = fields
+        .into_iter()
+        .flat_map(|(field, value)| [BulkString::from(field).into(), value])
+        .collect::<Vec<_>>();
+    RespArray::new(vec![id_reply(id), RespArray::new(fields).into()]).into()
+}
+
+fn entries_reply(entries: Vec<(StreamId, StreamFields)>) -> RespFrame {
+    let entries = entries
+        .into_iter()
+        .map(|(id, fields)| entry_reply(id, fields))
+        .collect::<Vec<RespFrame>>();
+    RespArray::new(entries).into()
+}
+
+// the entries of XREADGROUP, those deleted since they were delivered come with nil fields
+fn history_reply(entries: Vec<(StreamId, Option<StreamFields>)>) -> RespFrame {
+    let entries = entries
+        .into_iter()
+        .map(|(id, fields)| match fields {
+            Some(fields) => entry_reply(id, fields),
+            None => RespArray::new(vec![id_reply(id), RespFrame::Null(RespNull)]).into(),
+        })
+        .collect::<Vec<RespFrame>>();
+    RespArray::new(entries).into()
+}
+
+pub(super) fn help_reply(lines: &[&str]) -> RespFrame {
+    let lines = lines
+        .iter()
+        .map(|line| SimpleString::new(*line).into())
+        .collect::<Vec<RespFrame>>();
+    RespArray::new(lines).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::test_helpers::{array, bulk, run, run_async};
+
+    fn entry(id: &str, fields: &[&str]) -> RespFrame {
+        RespArray::new(vec![bulk(id), array(fields)]).into()
+    }
+
+    fn invalid(msg: &str) -> RespFrame {
+        CommandError::InvalidArgument(msg.to_string()).into()
+    }
+
+    #[test]
+    fn test_xadd_command() {
+        let backend = Backend::new();
+        assert_eq!(run(&backend, &["xadd", "s", "1-1", "a", "1"]), bulk("1-1"));
+        assert_eq!(run(&backend, &["xadd", "s", "1-*", "b", "2"]), bulk("1-2"));
+        assert_eq!(run(&backend, &["xadd", "s", "5", "c", "3"]), bulk("5-0"));
+        assert_eq!(
+            run(&backend, &["xadd", "s", "5-0", "c", "3"]),
+            BackendError::StreamIdTooSmall.into()
+        );
+        assert_eq!(
+            run(
+                &backend,
+                &["xadd", "s", "MAXLEN", "=", "2", "6-0", "d", "4"]
+            ),
+            bulk("6-0")
+        );
+        assert_eq!(run(&backend, &["xlen", "s"]), RespFrame::Integer(2));
+        assert_eq!(
+            run(&backend, &["xadd", "missing", "NOMKSTREAM", "*", "a", "1"]),
+            RespFrame::Null(RespNull)
+        );
+        assert_eq!(
+            run(&backend, &["type", "s"]),
+            SimpleString::new("stream").into()
+        );
+
+        assert_eq!(
+            run(&backend, &["xadd", "s", "*", "a"]),
+            CommandError::WrongArity("xadd".to_string()).into()
+        );
+        assert_eq!(
+            run(&backend, &["xadd", "s", "1-x", "a", "1"]),
+            invalid("Invalid stream ID specified as stream command argument")
+        );
+        assert_eq!(
+            run(&backend, &["xadd", "s", "MAXLEN", "-1", "*", "a", "1"]),
+            invalid("The MAXLEN argument must be >= 0.")
+        );
+        assert_eq!(
+            run(
+                &backend,
+                &["xadd", "s", "MAXLEN", "1", "LIMIT", "5", "*", "a", "1"]
+            ),
+            invalid("syntax error, LIMIT cannot be used without the special ~ option")
+        );
+        assert_eq!(
+            run(
+                &backend,
+                &["xadd", "s", "MAXLEN", "1", "MINID", "0", "*", "a", "1"]
+            ),
+            invalid("syntax error, MAXLEN and MINID options at the same time are not compatible")
+        );
+        run(&backend, &["set", "str", "v"]);
+        assert_eq!(
+            run(&backend, &["xadd", "str", "*", "a", "1"]),
+            BackendError::WrongType.into()
+        );
+    }
+
+    #[test]
+    fn test_xrange_command() {
+        let backend = Backend::new();
+        run(&backend, &["xadd", "s", "1-1", "a", "1", "b", "2"]);
+        run(&backend, &["xadd", "s", "1-2", "c", "3"]);
+        run(&backend, &["xadd", "s", "2-0", "d", "4"]);
+        let all = RespArray::new(vec![
+            entry("1-1", &["a", "1", "b", "2"]),
+            entry("1-2", &["c", "3"]),
+            entry("2-0", &["d", "4"]),
+        ]);
+        assert_eq!(run(&backend, &["xrange", "s", "-", "+"]), all.into());
+        assert_eq!(
+            run(&backend, &["xrange", "s", "(1-1", "1"]),
+            RespArray::new(vec![entry("1-2", &["c", "3"])]).into()
+        );
+        assert_eq!(
+            run(&backend, &["xrevrange", "s", "+", "-", "COUNT", "1"]),
+            RespArray::new(vec![entry("2-0", &["d", "4"])]).into()
+        );
+        assert_eq!(
+            run(&backend, &["xrange", "s", "-", "+", "COUNT", "0"]),
+            RespFrame::Null(RespNull)
+        );
+        assert_eq!(
+            run(&backend, &["xrange", "s", "2", "1"]),
+            RespArray::new(vec![]).into()
+        );
+        assert_eq!(
+            run(&backend, &["xrange", "missing", "-", "+"]),
+            RespArray::new(vec![]).into()
+        );
+        assert_eq!(
+            run(&backend, &["xrange", "s", "(-", "+", "COUNT", "1"]),
+            RespArray::new(vec![entry("1-1", &["a", "1", "b", "2"])]).into()
+        );
+        assert_eq!(
+            run(&backend, &["xrange", "s", "(+", "+"]),
+            invalid("invalid start ID for the interval")
+        );
+        assert_eq!(
+            run(&backend, &["xrange", "s", "-", "+", "LIMIT", "1"]),
+            CommandError::SyntaxError.into()
+        );
+    }
+
+    #[test]
+    fn test_xtrim_and_xdel_commands() {
+        let backend = Backend::new();
+        for id in ["1-0", "2-0", "3-0", "4-0"] {
+            run(&backend, &["xadd", "s", id, "f", "v"]);
+        }
+        assert_eq!(
+            run(&backend, &["xtrim", "s", "MAXLEN", "~", "1"]),
+            RespFrame::Integer(0)
+        );
+        assert_eq!(
+            run(&backend, &["xtrim", "s", "MINID", "2"]),
+            RespFrame::Integer(1)
+        );
+        assert_eq!(
+            run(&backend, &["xdel", "s", "3-0", "9-0"]),
+            RespFrame::Integer(1)
+        );
+        assert_eq!(
+            run(&backend, &["xtrim", "s", "MAXLEN", "0"]),
+            RespFrame::Integer(2)
+        );
+        assert_eq!(run(&backend, &["xlen", "s"]), RespFrame::Integer(0));
+        // the last ID outlives the entries
+        assert_eq!(
+            run(&backend, &["xadd", "s", "4-0", "f", "v"]),
+            BackendError::StreamIdTooSmall.into()
+        );
+        assert_eq!(
+            run(&backend, &["xtrim", "s", "LIMIT", "10"]),
+            invalid("syntax error, LIMIT cannot be used without specifying a trimming strategy")
+        );
+        assert_eq!(
+            run(&backend, &["xtrim", "s", "NOMKSTREAM", "1"]),
+            CommandError::SyntaxError.into()
+        );
+        assert_eq!(
+            run(&backend, &["xdel", "s", "x"]),
+            invalid("Invalid stream ID specified as stream command argument")
+        );
+    }
+
+    #[test]
+    fn test_xreadgroup_commands() {
+        let backend = Backend::new();
+        assert_eq!(
+            run(&backend, &["xgroup", "CREATE", "s", "g", "$"]),
+            BackendError::StreamKeyMissing.into()
+        );
+        assert_eq!(
+            run(&backend, &["xgroup", "CREATE", "s", "g", "$", "MKSTREAM"]),
+            RESP_OK.clone()
+        );
+        assert_eq!(
+            run(&backend, &["xgroup", "create", "s", "g", "0"]),
+            BackendError::BusyGroup.into()
+        );
+        run(&backend, &["xadd", "s", "1-1", "a", "1"]);
+        run(&backend, &["xadd", "s", "1-2", "b", "2"]);
+
+        let read = |consumer, id| {
+            let args = ["xreadgroup", "GROUP", "g", consumer, "STREAMS", "s", id];
+            run(&backend, &args)
+        };
+        let stream = |entries: Vec<RespFrame>| {
+            let pair = vec![bulk("s"), RespArray::new(entries).into()];
+            RespArray::new(vec![RespArray::new(pair).into()]).into()
+        };
+        assert_eq!(
+            read("alice", ">"),
+            stream(vec![entry("1-1", &["a", "1"]), entry("1-2", &["b", "2"])])
+        );
+        assert_eq!(read("bob", ">"), RespFrame::NullArray(RespNullArray));
+        assert_eq!(
+            run(&backend, &["xack", "s", "g", "1-1", "5-0"]),
+            RespFrame::Integer(1)
+        );
+        run(&backend, &["xdel", "s", "1-2"]);
+        let deleted = RespArray::new(vec![bulk("1-2"), RespFrame::Null(RespNull)]);
+        assert_eq!(read("alice", "0"), stream(vec![deleted.into()]));
+        assert_eq!(read("bob", "0"), stream(vec![]));
+
+        // XREAD ignores the groups
+        assert_eq!(
+            run(
+                &backend,
+                &["xread", "COUNT", "1", "STREAMS", "s", "missing", "0", "0"]
+            ),
+            stream(vec![entry("1-1", &["a", "1"])])
+        );
+        assert_eq!(
+            run(&backend, &["xread", "STREAMS", "s", "$"]),
+            RespFrame::NullArray(RespNullArray)
+        );
+
+        assert_eq!(
+            run(
+                &backend,
+                &["xreadgroup", "GROUP", "nope", "c", "STREAMS", "s", ">"]
+            ),
+            BackendError::NoGroup("s".to_string(), "nope".to_string()).into()
+        );
+        assert_eq!(
+            run(&backend, &["xread", "STREAMS", "s", "t", "0"]),
+            invalid(
+                "Unbalanced 'xread' list of streams: for each stream key an ID or '$' must be specified."
+            )
+        );
+        assert_eq!(
+            run(&backend, &["xread", "STREAMS", "s", ">"]),
+            invalid("The > ID can be specified only when calling XREADGROUP using the GROUP <group> <consumer> option.")
+        );
+        assert_eq!(
+            run(
+                &backend,
+                &["xreadgroup", "COUNT", "1", "NOACK", "STREAMS", "s", ">"]
+            ),
+            invalid("Missing GROUP option for XREADGROUP")
+        );
+        assert_eq!(
+            run(&backend, &["xread", "BLOCK", "-1", "STREAMS", "s", "0"]),
+            invalid("timeout is negative")
+        );
+
+        assert_eq!(
+            run(&backend, &["xgroup", "CREATECONSUMER", "s", "g", "carol"]),
+            RespFrame::Integer(1)
+        );
+        assert_eq!(
+            run(&backend, &["xgroup", "DELCONSUMER", "s", "g", "alice"]),
+            RespFrame::Integer(1)
+        );
+        assert_eq!(
+            run(&backend, &["xgroup", "SETID", "s", "nope", "0"]),
+            BackendError::NoSuchGroup("s".to_string(), "nope".to_string()).into()
+        );
+        assert_eq!(
+            run(
+                &backend,
+                &["xgroup", "SETID", "s", "g", "0", "ENTRIESREAD", "-2"]
+            ),
+            invalid("value for ENTRIESREAD must be positive or -1")
+        );
+        assert_eq!(
+            run(&backend, &["xgroup", "DESTROY", "s", "g"]),
+            RespFrame::Integer(1)
+        );
+        assert_eq!(
+            run(&backend, &["xgroup", "DESTROY", "s", "g", "x"]),
+            CommandError::WrongArity("xgroup|destroy".to_string()).into()
+        );
+        assert_eq!(
+            run(&backend, &["xgroup", "NOPE"]),
+            CommandError::UnknownSubcommand {
+                command: "XGROUP".to_string(),
+                name: "NOPE".to_string()
+            }
+            .into()
+        );
+    }
+
+    #[test]
+    fn test_xpending_and_xclaim_commands() {
+        let clock = std::sync::Arc::new(crate::ManualClock::new(1000));
+        let backend = Backend::with_clock(clock.clone());
+        run(&backend, &["xgroup", "CREATE", "s", "g", "0", "MKSTREAM"]);
+        run(&backend, &["xadd", "s", "1-1", "a", "1"]);
+        run(&backend, &["xadd", "s", "1-2", "b", "2"]);
+        run(&backend, &["xadd", "s", "1-3", "c", "3"]);
+        run(
+            &backend,
+            &["xreadgroup", "GROUP", "g", "alice", "STREAMS", "s", ">"],
+        );
+        clock.advance(Duration::from_millis(50));
+
+        let summary = RespArray::new(vec![
+            RespFrame::Integer(3),
+            bulk("1-1"),
+            bulk("1-3"),
+            RespArray::new(vec![array(&["alice", "3"])]).into(),
+        ]);
+        assert_eq!(run(&backend, &["xpending", "s", "g"]), summary.into());
+        let pending = |id: &str, consumer: &str, idle, deliveries| -> RespFrame {
+            let entry = vec![
+                bulk(id),
+                bulk(consumer),
+                RespFrame::Integer(idle),
+                RespFrame::Integer(deliveries),
+            ];
+            RespArray::new(entry).into()
+        };
+        assert_eq!(
+            run(&backend, &["xpending", "s", "g", "(1-1", "+", "1"]),
+            RespArray::new(vec![pending("1-2", "alice", 50, 1)]).into()
+        );
+
+        assert_eq!(
+            run(
+                &backend,
+                &[
+                    "xclaim",
+                    "s",
+                    "g",
+                    "bob",
+                    "10",
+                    "1-1",
+                    "1-2",
+                    "RETRYCOUNT",
+                    "5",
+                    "IDLE",
+                    "20"
+                ]
+            ),
+            RespArray::new(vec![entry("1-1", &["a", "1"]), entry("1-2", &["b", "2"])]).into()
+        );
+        assert_eq!(
+            run(
+                &backend,
+                &["xpending", "s", "g", "IDLE", "30", "-", "+", "10"]
+            ),
+            RespArray::new(vec![pending("1-3", "alice", 50, 1)]).into()
+        );
+        assert_eq!(
+            run(&backend, &["xpending", "s", "g", "-", "+", "10", "bob"]),
+            RespArray::new(vec![
+                pending("1-1", "bob", 20, 5),
+                pending("1-2", "bob", 20, 5)
+            ])
+            .into()
+        );
+        assert_eq!(
+            run(&backend, &["xclaim", "s", "g", "bob", "0", "1-1", "NOPE"]),
+            invalid("Unrecognized XCLAIM option 'NOPE'")
+        );
+        assert_eq!(
+            run(&backend, &["xclaim", "s", "g", "bob", "x", "1-1"]),
+            invalid("Invalid min-idle-time argument for XCLAIM")
+        );
+
+        run(&backend, &["xdel", "s", "1-2"]);
+        let claimed = RespArray::new(vec![bulk("0-0"), array(&["1-1", "1-3"]), array(&["1-2"])]);
+        assert_eq!(
+            run(
+                &backend,
+                &["xautoclaim", "s", "g", "carol", "20", "-", "JUSTID"]
+            ),
+            claimed.into()
+        );
+        assert_eq!(
+            run(
+                &backend,
+                &["xautoclaim", "s", "g", "carol", "0", "0", "COUNT", "0"]
+            ),
+            invalid("COUNT must be > 0")
+        );
+        assert_eq!(
+            run(&backend, &["xpending", "missing", "g"]),
+            BackendError::NoGroup("missing".to_string(), "g".to_string()).into()
+        );
+    }
+
+    #[test]
+    fn test_xinfo_commands() {
+        let backend = Backend::with_clock(std::sync::Arc::new(crate::ManualClock::new(1000)));
+        run(&backend, &["xadd", "s", "1-1", "a", "1"]);
+        run(&backend, &["xadd", "s", "1-2", "b", "2"]);
+        run(&backend, &["xgroup", "CREATE", "s", "g", "0"]);
+        run(&backend, &["xgroup", "CREATECONSUMER", "s", "g", "idle"]);
+        run(
+            &backend,
+            &[
+                "xreadgroup",
+                "GROUP",
+                "g",
+                "c",
+                "COUNT",
+                "1",
+                "STREAMS",
+                "s",
+                ">",
+            ],
+        );
+
+        let mut stream = RespMap::new();
+        let int = RespFrame::Integer;
+        stream.insert("length".to_string(), int(2));
+        stream.insert("last-generated-id".to_string(), bulk("1-2"));
+        stream.insert("max-deleted-entry-id".to_string(), bulk("0-0"));
+        stream.insert("entries-added".to_string(), int(2));
+        stream.insert("recorded-first-entry-id".to_string(), bulk("1-1"));
+        stream.insert("groups".to_string(), int(1));
+        stream.insert("first-entry".to_string(), entry("1-1", &["a", "1"]));
+        stream.insert("last-entry".to_string(), entry("1-2", &["b", "2"]));
+        assert_eq!(run(&backend, &["xinfo", "STREAM", "s"]), stream.into());
+
+        let mut group = RespMap::new();
+        group.insert("name".to_string(), bulk("g"));
+        group.insert("consumers".to_string(), int(2));
+        group.insert("pending".to_string(), int(1));
+        group.insert("last-delivered-id".to_string(), bulk("1-1"));
+        group.insert("entries-read".to_string(), int(1));
+        group.insert("lag".to_string(), int(1));
+        assert_eq!(
+            run(&backend, &["xinfo", "GROUPS", "s"]),
+            RespArray::new(vec![group.into()]).into()
+        );
+
+        let consumer = |name: &str, pending, inactive| -> RespFrame {
+            let mut map = RespMap::new();
+            map.insert("name".to_string(), bulk(name));
+            map.insert("pending".to_string(), int(pending));
+            map.insert("idle".to_string(), int(0));
+            map.insert("inactive".to_string(), int(inactive));
+            map.into()
+        };
+        assert_eq!(
+            run(&backend, &["xinfo", "CONSUMERS", "s", "g"]),
+            RespArray::new(vec![consumer("c", 1, 0), consumer("idle", 0, -1)]).into()
+        );
+
+        assert_eq!(
+            run(&backend, &["xinfo", "STREAM", "missing"]),
+            BackendError::NoSuchKey.into()
+        );
+        assert_eq!(
+            run(&backend, &["xinfo", "CONSUMERS", "s", "nope"]),
+            BackendError::NoSuchGroup("s".to_string(), "nope".to_string()).into()
+        );
+        assert!(matches!(
+            run(&backend, &["xinfo", "HELP"]),
+            RespFrame::Array(lines) if lines.len() == XINFO_HELP.len()
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_blocking_xread_commands() {
+        let backend = Backend::new();
+        run(&backend, &["xgroup", "CREATE", "s", "g", "$", "MKSTREAM"]);
+        assert_eq!(
+            run_async(&backend, &["xread", "BLOCK", "10", "STREAMS", "s", "$"]).await,
+            RespFrame::NullArray(RespNullArray)
+        );
+
+        let client = backend.clone();
+        let reader = tokio::spawn(async move {
+            run_async(&client, &["xread", "BLOCK", "0", "STREAMS", "s", "$"]).await
+        });
+        let client = backend.clone();
+        let consumer = tokio::spawn(async move {
+            let args = [
+                "xreadgroup",
+                "GROUP",
+                "g",
+                "c",
+                "BLOCK",
+                "0",
+                "STREAMS",
+                "s",
+                ">",
+            ];
+            run_async(&client, &args).await
+        });
+        while backend.blocked_clients() < 2 {
+            tokio::task::yield_now().await;
+        }
+        run(&backend, &["xadd", "s", "1-1", "a", "1"]);
+        let stream = RespArray::new(vec![
+            bulk("s"),
+            RespArray::new(vec![entry("1-1", &["a", "1"])]).into(),
+        ]);
+        let reply = RespArray::new(vec![stream.into()]).into();
+        assert_eq!(reader.await.unwrap(), reply);
+        assert_eq!(consumer.await.unwrap(), reply);
+        // the entry was delivered to the blocked consumer
+        let summary = RespArray::new(vec![
+            RespFrame::Integer(1),
+            bulk("1-1"),
+            bulk("1-1"),
+            RespArray::new(vec![array(&["c", "1"])]).into(),
+        ]);
+        assert_eq!(run(&backend, &["xpending", "s", "g"]), summary.into());
+
+        // destroying the group or deleting the stream wakes its blocked consumers
+        for removal in [&["xgroup", "DESTROY", "s", "g"][..], &["del", "s"]] {
+            run(&backend, &["xgroup", "CREATE", "s", "g", "$", "MKSTREAM"]);
+            let client = backend.clone();
+            let consumer = tokio::spawn(async move {
+                let args = [
+                    "xreadgroup",
+                    "GROUP",
+                    "g",
+                    "c",
+                    "BLOCK",
+                    "0",
+                    "STREAMS",
+                    "s",
+                    ">",
+                ];
+                run_async(&client, &args).await
+            });
+            while backend.blocked_clients() < 1 {
+                tokio::task::yield_now().await;
+            }
+            run(&backend, removal);
+            assert_eq!(
+                consumer.await.unwrap(),
+                BackendError::NoGroup("s".to_string(), "g".to_string()).into()
+            );
+        }
+    }
+}
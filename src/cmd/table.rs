@@ -0,0 +1,641 @@
+use std::collections::HashMap;
+
+use lazy_static::lazy_static;
+
+use super::{Arity, Command, CommandError};
+use crate::{
+    BlockingPop, CommandInfo, CopyKey, DbSize, Del, Echo, Exists, Expire, Get, HDel, HExists,
+    HExpire, HGet, HGetAll, HIncrBy, HIncrByFloat, HKeys, HLen, HMGet, HPersist, HRandField, HScan,
+    HSet, HSetNx, HStrLen, HTtl, Hello, IncrBy, IncrByFloat, Keys, LIndex, LInsert, LLen, LMPop,
+    LMove, LPos, LRange, LRem, LSet, LTrim, MGet, MSet, Persist, Ping, Pop, PubSub, Publish, Push,
+    Quit, RandomKey, Rename, Reset, RespArray, RespFrame, SAdd, SCard, SInterCard, SIsMember,
+    SMIsMember, SMembers, SMove, SPop, SRandMember, SRem, SScan, Scan, Set, SetAlgebra, Subscribe,
+    Ttl, Type, Unsubscribe, XAck, XAdd, XAutoClaim, XClaim, XDel, XGroup, XInfo, XLen, XPending,
+    XRange, XRead, XTrim, ZAdd, ZCard, ZCount, ZIncrBy, ZMPop, ZMScore, ZPop, ZRandMember, ZRange,
+    ZRank, ZRem, ZRemRange, ZScore, ZSetAlgebra,
+};
+
+use CommandFlag::*;
+use CommandGroup as Group;
+
+const ONE_ARGS: usize = 1;
+const TWO_ARGS: usize = 2;
+
+// key positions for commands working on a single key as their first argument
+const FIRST_KEY: KeySpec = KeySpec::new(1, 1, 1);
+const ALL_KEYS: KeySpec = KeySpec::new(1, -1, 1);
+const NO_KEYS: KeySpec = KeySpec::new(0, 0, 0);
+
+/// Command flags, as reported by COMMAND INFO.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandFlag {
+    Write,
+    ReadOnly,
+    DenyOom,
+    Fast,
+    Blocking,
+}
+
+/// The documentation group of a command, as reported by COMMAND DOCS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandGroup {
+    Generic,
+    String,
+    Hash,
+    List,
+    Set,
+    SortedSet,
+    Stream,
+    PubSub,
+    Connection,
+    Server,
+}
+
+/// Where the keys are among the arguments: the first and last key position (negative counts
+/// from the end) and the step between two keys, 0 for a command without keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeySpec {
+    pub first: i64,
+    pub last: i64,
+    pub step: i64,
+}
+
+/// A row of the command table.
+#[derive(Debug)]
+pub struct CommandSpec {
+    pub name: &'static str,
+    arity: Arity,
+    pub flags: &'static [CommandFlag],
+    pub keys: KeySpec,
+    pub group: CommandGroup,
+    pub since: &'static str,
+    pub summary: &'static str,
+    pub subcommands: &'static [CommandSpec],
+    parse: fn(RespArray) -> Result<Command, CommandError>,
+}
+
+lazy_static! {
+    static ref COMMAND_INDEX: HashMap<&'static str, &'static CommandSpec> =
+        COMMAND_TABLE.iter().map(|spec| (spec.name, spec)).collect();
+}
+
+#[rustfmt::skip]
+static COMMAND_TABLE: &[CommandSpec] = &[
+    // strings
+    CommandSpec::new("get", Arity::Exact(ONE_ARGS), &[ReadOnly, Fast], FIRST_KEY, |v| Ok(Get::try_from(v)?.into()))
+        .doc(Group::String, "1.0.0", "Returns the string value of a key."),
+    CommandSpec::new("set", Arity::AtLeast(TWO_ARGS), &[Write, DenyOom], FIRST_KEY, |v| Ok(Set::try_from(v)?.into()))
+        .doc(Group::String, "1.0.0", "Sets the string value of a key, ignoring its type. The key is created if it doesn't exist."),
+    CommandSpec::new("mget", Arity::AtLeast(ONE_ARGS), &[ReadOnly, Fast], ALL_KEYS, |v| Ok(MGet::try_from(v)?.into()))
+        .doc(Group::String, "1.0.0", "Atomically returns the string values of one or more keys."),
+    CommandSpec::new("mset", Arity::Pairs(0), &[Write, DenyOom], KeySpec::new(1, -1, 2), |v| Ok(MSet::try_from(v)?.into()))
+        .doc(Group::String, "1.0.1", "Atomically creates or modifies the string values of one or more keys."),
+    CommandSpec::new("msetnx", Arity::Pairs(0), &[Write, DenyOom], KeySpec::new(1, -1, 2), |v| Ok(MSet::try_from(v)?.into()))
+        .doc(Group::String, "1.0.1", "Atomically modifies the string values of one or more keys only when all keys don't exist."),
+    CommandSpec::new("incr", Arity::Exact(ONE_ARGS), &[Write, DenyOom, Fast], FIRST_KEY, |v| Ok(IncrBy::try_from(v)?.into()))
+        .doc(Group::String, "1.0.0", "Increments the integer value of a key by one. Uses 0 as initial value if the key doesn't exist."),
+    CommandSpec::new("decr", Arity::Exact(ONE_ARGS), &[Write, DenyOom, Fast], FIRST_KEY, |v| Ok(IncrBy::try_from(v)?.into()))
+        .doc(Group::String, "1.0.0", "Decrements the integer value of a key by one. Uses 0 as initial value if the key doesn't exist."),
+    CommandSpec::new("incrby", Arity::Exact(TWO_ARGS), &[Write, DenyOom, Fast], FIRST_KEY, |v| Ok(IncrBy::try_from(v)?.into()))
+        .doc(Group::String, "1.0.0", "Increments the integer value of a key by a number. Uses 0 as initial value if the key doesn't exist."),
+    CommandSpec::new("decrby", Arity::Exact(TWO_ARGS), &[Write, DenyOom, Fast], FIRST_KEY, |v| Ok(IncrBy::try_from(v)?.into()))
+        .doc(Group::String, "1.0.0", "Decrements a number from the integer value of a key. Uses 0 as initial value if the key doesn't exist."),
+    CommandSpec::new("incrbyfloat", Arity::Exact(TWO_ARGS), &[Write, DenyOom, Fast], FIRST_KEY, |v| Ok(IncrByFloat::try_from(v)?.into()))
+        .doc(Group::String, "2.6.0", "Increment the floating point value of a key by a number. Uses 0 as initial value if the key doesn't exist."),
+    // hashes
+    CommandSpec::new("hget", Arity::Exact(TWO_ARGS), &[ReadOnly, Fast], FIRST_KEY, |v| Ok(HGet::try_from(v)?.into()))
+        .doc(Group::Hash, "2.0.0", "Returns the value of a field in a hash."),
+    CommandSpec::new("hset", Arity::Pairs(ONE_ARGS), &[Write, DenyOom, Fast], FIRST_KEY, |v| Ok(HSet::try_from(v)?.into()))
+        .doc(Group::Hash, "2.0.0", "Creates or modifies the value of a field in a hash."),
+    CommandSpec::new("hsetnx", Arity::Exact(3), &[Write, DenyOom, Fast], FIRST_KEY, |v| Ok(HSetNx::try_from(v)?.into()))
+        .doc(Group::Hash, "2.0.0", "Sets the value of a field in a hash only when the field doesn't exist."),
+    CommandSpec::new("hmget", Arity::AtLeast(TWO_ARGS), &[ReadOnly, Fast], FIRST_KEY, |v| Ok(HMGet::try_from(v)?.into()))
+        .doc(Group::Hash, "2.0.0", "Returns the values of all fields in a hash."),
+    CommandSpec::new("hdel", Arity::AtLeast(TWO_ARGS), &[Write, Fast], FIRST_KEY, |v| Ok(HDel::try_from(v)?.into()))
+        .doc(Group::Hash, "2.0.0", "Deletes one or more fields and their values from a hash. Deletes the hash if no fields remain."),
+    CommandSpec::new("hexists", Arity::Exact(TWO_ARGS), &[ReadOnly, Fast], FIRST_KEY, |v| Ok(HExists::try_from(v)?.into()))
+        .doc(Group::Hash, "2.0.0", "Determines whether a field exists in a hash."),
+    CommandSpec::new("hlen", Arity::Exact(ONE_ARGS), &[ReadOnly, Fast], FIRST_KEY, |v| Ok(HLen::try_from(v)?.into()))
+        .doc(Group::Hash, "2.0.0", "Returns the number of fields in a hash."),
+    CommandSpec::new("hkeys", Arity::Exact(ONE_ARGS), &[ReadOnly], FIRST_KEY, |v| Ok(HKeys::try_from(v)?.into()))
+        .doc(Group::Hash, "2.0.0", "Returns all fields in a hash."),
+    CommandSpec::new("hvals", Arity::Exact(ONE_ARGS), &[ReadOnly], FIRST_KEY, |v| Ok(HKeys::try_from(v)?.into()))
+        .doc(Group::Hash, "2.0.0", "Returns all values in a hash."),
+    CommandSpec::new("hincrby", Arity::Exact(3), &[Write, DenyOom, Fast], FIRST_KEY, |v| Ok(HIncrBy::try_from(v)?.into()))
+        .doc(Group::Hash, "2.0.0", "Increments the integer value of a field in a hash by a number. Uses 0 as initial value if the field doesn't exist."),
+    CommandSpec::new("hincrbyfloat", Arity::Exact(3), &[Write, DenyOom, Fast], FIRST_KEY, |v| Ok(HIncrByFloat::try_from(v)?.into()))
+        .doc(Group::Hash, "2.6.0", "Increments the floating point value of a field by a number. Uses 0 as initial value if the field doesn't exist."),
+    CommandSpec::new("hstrlen", Arity::Exact(TWO_ARGS), &[ReadOnly, Fast], FIRST_KEY, |v| Ok(HStrLen::try_from(v)?.into()))
+        .doc(Group::Hash, "3.2.0", "Returns the length of the value of a field."),
+    CommandSpec::new("hrandfield", Arity::AtLeast(ONE_ARGS), &[ReadOnly], FIRST_KEY, |v| Ok(HRandField::try_from(v)?.into()))
+        .doc(Group::Hash, "6.2.0", "Returns one or more random fields from a hash."),
+    CommandSpec::new("hscan", Arity::AtLeast(TWO_ARGS), &[ReadOnly], FIRST_KEY, |v| Ok(HScan::try_from(v)?.into()))
+        .doc(Group::Hash, "2.8.0", "Iterates over fields and values of a hash."),
+    CommandSpec::new("hexpire", Arity::AtLeast(5), &[Write, Fast], FIRST_KEY, |v| Ok(HExpire::try_from(v)?.into()))
+        .doc(Group::Hash, "7.4.0", "Set expiry for hash field using relative time to expire (seconds)."),
+    CommandSpec::new("hpexpire", Arity::AtLeast(5), &[Write, Fast], FIRST_KEY, |v| Ok(HExpire::try_from(v)?.into()))
+        .doc(Group::Hash, "7.4.0", "Set expiry for hash field using relative time to expire (milliseconds)."),
+    CommandSpec::new("hexpireat", Arity::AtLeast(5), &[Write, Fast], FIRST_KEY, |v| Ok(HExpire::try_from(v)?.into()))
+        .doc(Group::Hash, "7.4.0", "Set expiry for hash field using an absolute Unix timestamp (seconds)."),
+    CommandSpec::new("hpexpireat", Arity::AtLeast(5), &[Write, Fast], FIRST_KEY, |v| Ok(HExpire::try_from(v)?.into()))
+        .doc(Group::Hash, "7.4.0", "Set expiry for hash field using an absolute Unix timestamp (milliseconds)."),
+    CommandSpec::new("httl", Arity::AtLeast(4), &[ReadOnly, Fast], FIRST_KEY, |v| Ok(HTtl::try_from(v)?.into()))
+        .doc(Group::Hash, "7.4.0", "Returns the TTL in seconds of a hash field."),
+    CommandSpec::new("hpttl", Arity::AtLeast(4), &[ReadOnly, Fast], FIRST_KEY, |v| Ok(HTtl::try_from(v)?.into()))
+        .doc(Group::Hash, "7.4.0", "Returns the TTL in milliseconds of a hash field."),
+    CommandSpec::new("hexpiretime", Arity::AtLeast(4), &[ReadOnly, Fast], FIRST_KEY, |v| Ok(HTtl::try_from(v)?.into()))
+        .doc(Group::Hash, "7.4.0", "Returns the expiration time of a hash field as a Unix timestamp, in seconds."),
+    CommandSpec::new("hpexpiretime", Arity::AtLeast(4), &[ReadOnly, Fast], FIRST_KEY, |v| Ok(HTtl::try_from(v)?.into()))
+        .doc(Group::Hash, "7.4.0", "Returns the expiration time of a hash field as a Unix timestamp, in msec."),
+    CommandSpec::new("hpersist", Arity::AtLeast(4), &[Write, Fast], FIRST_KEY, |v| Ok(HPersist::try_from(v)?.into()))
+        .doc(Group::Hash, "7.4.0", "Removes the expiration time for each specified field."),
+    CommandSpec::new("hgetall", Arity::Exact(ONE_ARGS), &[ReadOnly], FIRST_KEY, |v| Ok(HGetAll::try_from(v)?.into()))
+        .doc(Group::Hash, "2.0.0", "Returns all fields and values in a hash."),
+    // lists
+    CommandSpec::new("lpush", Arity::AtLeast(TWO_ARGS), &[Write, DenyOom, Fast], FIRST_KEY, |v| Ok(Push::try_from(v)?.into()))
+        .doc(Group::List, "1.0.0", "Prepends one or more elements to a list. Creates the key if it doesn't exist."),
+    CommandSpec::new("rpush", Arity::AtLeast(TWO_ARGS), &[Write, DenyOom, Fast], FIRST_KEY, |v| Ok(Push::try_from(v)?.into()))
+        .doc(Group::List, "1.0.0", "Appends one or more elements to a list. Creates the key if it doesn't exist."),
+    CommandSpec::new("lpushx", Arity::AtLeast(TWO_ARGS), &[Write, DenyOom, Fast], FIRST_KEY, |v| Ok(Push::try_from(v)?.into()))
+        .doc(Group::List, "2.2.0", "Prepends one or more elements to a list only when the list exists."),
+    CommandSpec::new("rpushx", Arity::AtLeast(TWO_ARGS), &[Write, DenyOom, Fast], FIRST_KEY, |v| Ok(Push::try_from(v)?.into()))
+        .doc(Group::List, "2.2.0", "Appends an element to a list only when the list exists."),
+    CommandSpec::new("lpop", Arity::AtLeast(ONE_ARGS), &[Write, Fast], FIRST_KEY, |v| Ok(Pop::try_from(v)?.into()))
+        .doc(Group::List, "1.0.0", "Returns the first elements in a list after removing it. Deletes the list if the last element was popped."),
+    CommandSpec::new("rpop", Arity::AtLeast(ONE_ARGS), &[Write, Fast], FIRST_KEY, |v| Ok(Pop::try_from(v)?.into()))
+        .doc(Group::List, "1.0.0", "Returns and removes the last elements of a list. Deletes the list if the last element was popped."),
+    CommandSpec::new("llen", Arity::Exact(ONE_ARGS), &[ReadOnly, Fast], FIRST_KEY, |v| Ok(LLen::try_from(v)?.into()))
+        .doc(Group::List, "1.0.0", "Returns the length of a list."),
+    CommandSpec::new("lrange", Arity::Exact(3), &[ReadOnly], FIRST_KEY, |v| Ok(LRange::try_from(v)?.into()))
+        .doc(Group::List, "1.0.0", "Returns a range of elements from a list."),
+    CommandSpec::new("lindex", Arity::Exact(TWO_ARGS), &[ReadOnly], FIRST_KEY, |v| Ok(LIndex::try_from(v)?.into()))
+        .doc(Group::List, "1.0.0", "Returns an element from a list by its index."),
+    CommandSpec::new("lset", Arity::Exact(3), &[Write, DenyOom], FIRST_KEY, |v| Ok(LSet::try_from(v)?.into()))
+        .doc(Group::List, "1.0.0", "Sets the value of an element in a list by its index."),
+    CommandSpec::new("lrem", Arity::Exact(3), &[Write], FIRST_KEY, |v| Ok(LRem::try_from(v)?.into()))
+        .doc(Group::List, "1.0.0", "Removes elements from a list. Deletes the list if the last element was removed."),
+    CommandSpec::new("ltrim", Arity::Exact(3), &[Write], FIRST_KEY, |v| Ok(LTrim::try_from(v)?.into()))
+        .doc(Group::List, "1.0.0", "Removes elements from both ends of a list. Deletes the list if all elements were trimmed."),
+    CommandSpec::new("linsert", Arity::Exact(4), &[Write, DenyOom], FIRST_KEY, |v| Ok(LInsert::try_from(v)?.into()))
+        .doc(Group::List, "2.2.0", "Inserts an element before or after another element in a list."),
+    CommandSpec::new("lpos", Arity::AtLeast(TWO_ARGS), &[ReadOnly], FIRST_KEY, |v| Ok(LPos::try_from(v)?.into()))
+        .doc(Group::List, "6.0.6", "Returns the index of matching elements in a list."),
+    CommandSpec::new("lmove", Arity::Exact(4), &[Write, DenyOom], KeySpec::new(1, 2, 1), |v| Ok(LMove::try_from(v)?.into()))
+        .doc(Group::List, "6.2.0", "Returns an element after popping it from one list and pushing it to another. Deletes the list if the last element was moved."),
+    CommandSpec::new("rpoplpush", Arity::Exact(TWO_ARGS), &[Write, DenyOom], KeySpec::new(1, 2, 1), |v| Ok(LMove::try_from(v)?.into()))
+        .doc(Group::List, "1.2.0", "Returns the last element of a list after removing and pushing it to another list. Deletes the list if the last element was popped."),
+    CommandSpec::new("lmpop", Arity::AtLeast(3), &[Write], NO_KEYS, |v| Ok(LMPop::try_from(v)?.into()))
+        .doc(Group::List, "7.0.0", "Returns multiple elements from a list after removing them. Deletes the list if the last element was popped."),
+    CommandSpec::new("blpop", Arity::AtLeast(TWO_ARGS), &[Write, Blocking], KeySpec::new(1, -2, 1), |v| Ok(BlockingPop::try_from(v)?.into()))
+        .doc(Group::List, "2.0.0", "Removes and returns the first element in a list. Blocks until an element is available otherwise. Deletes the list if the last element was popped."),
+    CommandSpec::new("brpop", Arity::AtLeast(TWO_ARGS), &[Write, Blocking], KeySpec::new(1, -2, 1), |v| Ok(BlockingPop::try_from(v)?.into()))
+        .doc(Group::List, "2.0.0", "Removes and returns the last element in a list. Blocks until an element is available otherwise. Deletes the list if the last element was popped."),
+    CommandSpec::new("blmove", Arity::Exact(5), &[Write, DenyOom, Blocking], KeySpec::new(1, 2, 1), |v| Ok(BlockingPop::try_from(v)?.into()))
+        .doc(Group::List, "6.2.0", "Pops an element from a list, pushes it to another list and returns it. Blocks until an element is available otherwise. Deletes the list if the last element was moved."),
+    CommandSpec::new("brpoplpush", Arity::Exact(3), &[Write, DenyOom, Blocking], KeySpec::new(1, 2, 1), |v| Ok(BlockingPop::try_from(v)?.into()))
+        .doc(Group::List, "2.2.0", "Pops an element from a list, pushes it to another list and returns it. Block until an element is available otherwise. Deletes the list if the last element was popped."),
+    CommandSpec::new("blmpop", Arity::AtLeast(4), &[Write, Blocking], NO_KEYS, |v| Ok(BlockingPop::try_from(v)?.into()))
+        .doc(Group::List, "7.0.0", "Pops the first element from one of multiple lists. Blocks until an element is available otherwise. Deletes the list if the last element was popped."),
+    // sets
+    CommandSpec::new("sadd", Arity::AtLeast(TWO_ARGS), &[Write, DenyOom, Fast], FIRST_KEY, |v| Ok(SAdd::try_from(v)?.into()))
+        .doc(Group::Set, "1.0.0", "Adds one or more members to a set. Creates the key if it doesn't exist."),
+    CommandSpec::new("srem", Arity::AtLeast(TWO_ARGS), &[Write, Fast], FIRST_KEY, |v| Ok(SRem::try_from(v)?.into()))
+        .doc(Group::Set, "1.0.0", "Removes one or more members from a set. Deletes the set if the last member was removed."),
+    CommandSpec::new("smembers", Arity::Exact(ONE_ARGS), &[ReadOnly], FIRST_KEY, |v| Ok(SMembers::try_from(v)?.into()))
+        .doc(Group::Set, "1.0.0", "Returns all members of a set."),
+    CommandSpec::new("sismember", Arity::Exact(TWO_ARGS), &[ReadOnly, Fast], FIRST_KEY, |v| Ok(SIsMember::try_from(v)?.into()))
+        .doc(Group::Set, "1.0.0", "Determines whether a member belongs to a set."),
+    CommandSpec::new("smismember", Arity::AtLeast(TWO_ARGS), &[ReadOnly, Fast], FIRST_KEY, |v| Ok(SMIsMember::try_from(v)?.into()))
+        .doc(Group::Set, "6.2.0", "Determines whether multiple members belong to a set."),
+    CommandSpec::new("scard", Arity::Exact(ONE_ARGS), &[ReadOnly, Fast], FIRST_KEY, |v| Ok(SCard::try_from(v)?.into()))
+        .doc(Group::Set, "1.0.0", "Returns the number of members in a set."),
+    CommandSpec::new("spop", Arity::AtLeast(ONE_ARGS), &[Write, Fast], FIRST_KEY, |v| Ok(SPop::try_from(v)?.into()))
+        .doc(Group::Set, "1.0.0", "Returns one or more random members from a set after removing them. Deletes the set if the last member was popped."),
+    CommandSpec::new("srandmember", Arity::AtLeast(ONE_ARGS), &[ReadOnly], FIRST_KEY, |v| Ok(SRandMember::try_from(v)?.into()))
+        .doc(Group::Set, "1.0.0", "Get one or multiple random members from a set."),
+    CommandSpec::new("sscan", Arity::AtLeast(TWO_ARGS), &[ReadOnly], FIRST_KEY, |v| Ok(SScan::try_from(v)?.into()))
+        .doc(Group::Set, "2.8.0", "Iterates over members of a set."),
+    CommandSpec::new("sinter", Arity::AtLeast(ONE_ARGS), &[ReadOnly], ALL_KEYS, |v| Ok(SetAlgebra::try_from(v)?.into()))
+        .doc(Group::Set, "1.0.0", "Returns the intersect of multiple sets."),
+    CommandSpec::new("sunion", Arity::AtLeast(ONE_ARGS), &[ReadOnly], ALL_KEYS, |v| Ok(SetAlgebra::try_from(v)?.into()))
+        .doc(Group::Set, "1.0.0", "Returns the union of multiple sets."),
+    CommandSpec::new("sdiff", Arity::AtLeast(ONE_ARGS), &[ReadOnly], ALL_KEYS, |v| Ok(SetAlgebra::try_from(v)?.into()))
+        .doc(Group::Set, "1.0.0", "Returns the difference of multiple sets."),
+    CommandSpec::new("sinterstore", Arity::AtLeast(TWO_ARGS), &[Write, DenyOom], ALL_KEYS, |v| Ok(SetAlgebra::try_from(v)?.into()))
+        .doc(Group::Set, "1.0.0", "Stores the intersect of multiple sets in a key."),
+    CommandSpec::new("sunionstore", Arity::AtLeast(TWO_ARGS), &[Write, DenyOom], ALL_KEYS, |v| Ok(SetAlgebra::try_from(v)?.into()))
+        .doc(Group::Set, "1.0.0", "Stores the union of multiple sets in a key."),
+    CommandSpec::new("sdiffstore", Arity::AtLeast(TWO_ARGS), &[Write, DenyOom], ALL_KEYS, |v| Ok(SetAlgebra::try_from(v)?.into()))
+        .doc(Group::Set, "1.0.0", "Stores the difference of multiple sets in a key."),
+    CommandSpec::new("sintercard", Arity::AtLeast(TWO_ARGS), &[ReadOnly], NO_KEYS, |v| Ok(SInterCard::try_from(v)?.into()))
+        .doc(Group::Set, "7.0.0", "Returns the number of members of the intersect of multiple sets."),
+    CommandSpec::new("smove", Arity::Exact(3), &[Write, Fast], KeySpec::new(1, 2, 1), |v| Ok(SMove::try_from(v)?.into()))
+        .doc(Group::Set, "1.0.0", "Moves a member from one set to another."),
+    // sorted sets
+    CommandSpec::new("zadd", Arity::AtLeast(3), &[Write, DenyOom, Fast], FIRST_KEY, |v| Ok(ZAdd::try_from(v)?.into()))
+        .doc(Group::SortedSet, "1.2.0", "Adds one or more members to a sorted set, or updates their scores. Creates the key if it doesn't exist."),
+    CommandSpec::new("zscore", Arity::Exact(TWO_ARGS), &[ReadOnly, Fast], FIRST_KEY, |v| Ok(ZScore::try_from(v)?.into()))
+        .doc(Group::SortedSet, "1.2.0", "Returns the score of a member in a sorted set."),
+    CommandSpec::new("zmscore", Arity::AtLeast(TWO_ARGS), &[ReadOnly, Fast], FIRST_KEY, |v| Ok(ZMScore::try_from(v)?.into()))
+        .doc(Group::SortedSet, "6.2.0", "Returns the score of one or more members in a sorted set."),
+    CommandSpec::new("zincrby", Arity::Exact(3), &[Write, DenyOom, Fast], FIRST_KEY, |v| Ok(ZIncrBy::try_from(v)?.into()))
+        .doc(Group::SortedSet, "1.2.0", "Increments the score of a member in a sorted set."),
+    CommandSpec::new("zcard", Arity::Exact(ONE_ARGS), &[ReadOnly, Fast], FIRST_KEY, |v| Ok(ZCard::try_from(v)?.into()))
+        .doc(Group::SortedSet, "1.2.0", "Returns the number of members in a sorted set."),
+    CommandSpec::new("zrank", Arity::AtLeast(TWO_ARGS), &[ReadOnly, Fast], FIRST_KEY, |v| Ok(ZRank::try_from(v)?.into()))
+        .doc(Group::SortedSet, "2.0.0", "Returns the index of a member in a sorted set ordered by ascending scores."),
+    CommandSpec::new("zrevrank", Arity::AtLeast(TWO_ARGS), &[ReadOnly, Fast], FIRST_KEY, |v| Ok(ZRank::try_from(v)?.into()))
+        .doc(Group::SortedSet, "2.0.0", "Returns the index of a member in a sorted set ordered by descending scores."),
+    CommandSpec::new("zrem", Arity::AtLeast(TWO_ARGS), &[Write, Fast], FIRST_KEY, |v| Ok(ZRem::try_from(v)?.into()))
+        .doc(Group::SortedSet, "1.2.0", "Removes one or more members from a sorted set. Deletes the sorted set if all members were removed."),
+    CommandSpec::new("zrange", Arity::AtLeast(3), &[ReadOnly], FIRST_KEY, |v| Ok(ZRange::try_from(v)?.into()))
+        .doc(Group::SortedSet, "1.2.0", "Returns members in a sorted set within a range of indexes."),
+    CommandSpec::new("zunion", Arity::AtLeast(TWO_ARGS), &[ReadOnly], NO_KEYS, |v| Ok(ZSetAlgebra::try_from(v)?.into()))
+        .doc(Group::SortedSet, "6.2.0", "Returns the union of multiple sorted sets."),
+    CommandSpec::new("zinter", Arity::AtLeast(TWO_ARGS), &[ReadOnly], NO_KEYS, |v| Ok(ZSetAlgebra::try_from(v)?.into()))
+        .doc(Group::SortedSet, "6.2.0", "Returns the intersect of multiple sorted sets."),
+    CommandSpec::new("zdiff", Arity::AtLeast(TWO_ARGS), &[ReadOnly], NO_KEYS, |v| Ok(ZSetAlgebra::try_from(v)?.into()))
+        .doc(Group::SortedSet, "6.2.0", "Returns the difference between multiple sorted sets."),
+    CommandSpec::new("zunionstore", Arity::AtLeast(3), &[Write, DenyOom], FIRST_KEY, |v| Ok(ZSetAlgebra::try_from(v)?.into()))
+        .doc(Group::SortedSet, "2.0.0", "Stores the union of multiple sorted sets in a key."),
+    CommandSpec::new("zinterstore", Arity::AtLeast(3), &[Write, DenyOom], FIRST_KEY, |v| Ok(ZSetAlgebra::try_from(v)?.into()))
+        .doc(Group::SortedSet, "2.0.0", "Stores the intersect of multiple sorted sets in a key."),
+    CommandSpec::new("zdiffstore", Arity::AtLeast(3), &[Write, DenyOom], FIRST_KEY, |v| Ok(ZSetAlgebra::try_from(v)?.into()))
+        .doc(Group::SortedSet, "6.2.0", "Stores the difference of multiple sorted sets in a key."),
+    CommandSpec::new("zremrangebyrank", Arity::Exact(3), &[Write], FIRST_KEY, |v| Ok(ZRemRange::try_from(v)?.into()))
+        .doc(Group::SortedSet, "2.0.0", "Removes members in a sorted set within a range of indexes. Deletes the sorted set if all members were removed."),
+    CommandSpec::new("zremrangebyscore", Arity::Exact(3), &[Write], FIRST_KEY, |v| Ok(ZRemRange::try_from(v)?.into()))
+        .doc(Group::SortedSet, "1.2.0", "Removes members in a sorted set within a range of scores. Deletes the sorted set if all members were removed."),
+    CommandSpec::new("zremrangebylex", Arity::Exact(3), &[Write], FIRST_KEY, |v| Ok(ZRemRange::try_from(v)?.into()))
+        .doc(Group::SortedSet, "2.8.9", "Removes members in a sorted set within a lexicographical range. Deletes the sorted set if all members were removed."),
+    CommandSpec::new("zcount", Arity::Exact(3), &[ReadOnly, Fast], FIRST_KEY, |v| Ok(ZCount::try_from(v)?.into()))
+        .doc(Group::SortedSet, "2.0.0", "Returns the count of members in a sorted set that have scores within a range."),
+    CommandSpec::new("zlexcount", Arity::Exact(3), &[ReadOnly, Fast], FIRST_KEY, |v| Ok(ZCount::try_from(v)?.into()))
+        .doc(Group::SortedSet, "2.8.9", "Returns the number of members in a sorted set within a lexicographical range."),
+    CommandSpec::new("zpopmin", Arity::AtLeast(ONE_ARGS), &[Write, Fast], FIRST_KEY, |v| Ok(ZPop::try_from(v)?.into()))
+        .doc(Group::SortedSet, "5.0.0", "Returns the lowest-scoring members from a sorted set after removing them. Deletes the sorted set if the last member was popped."),
+    CommandSpec::new("zpopmax", Arity::AtLeast(ONE_ARGS), &[Write, Fast], FIRST_KEY, |v| Ok(ZPop::try_from(v)?.into()))
+        .doc(Group::SortedSet, "5.0.0", "Returns the highest-scoring members from a sorted set after removing them. Deletes the sorted set if the last member was popped."),
+    CommandSpec::new("zmpop", Arity::AtLeast(3), &[Write], NO_KEYS, |v| Ok(ZMPop::try_from(v)?.into()))
+        .doc(Group::SortedSet, "7.0.0", "Returns the highest- or lowest-scoring members from one or more sorted sets after removing them. Deletes the sorted set if the last member was popped."),
+    CommandSpec::new("bzpopmin", Arity::AtLeast(TWO_ARGS), &[Write, Fast, Blocking], KeySpec::new(1, -2, 1), |v| Ok(BlockingPop::try_from(v)?.into()))
+        .doc(Group::SortedSet, "5.0.0", "Removes and returns the member with the lowest score from one or more sorted sets. Blocks until a member is available otherwise. Deletes the sorted set if the last element was popped."),
+    CommandSpec::new("bzpopmax", Arity::AtLeast(TWO_ARGS), &[Write, Fast, Blocking], KeySpec::new(1, -2, 1), |v| Ok(BlockingPop::try_from(v)?.into()))
+        .doc(Group::SortedSet, "5.0.0", "Removes and returns the member with the highest score from one or more sorted sets. Blocks until a member is available otherwise. Deletes the sorted set if the last element was popped."),
+    CommandSpec::new("bzmpop", Arity::AtLeast(4), &[Write, Blocking], NO_KEYS, |v| Ok(BlockingPop::try_from(v)?.into()))
+        .doc(Group::SortedSet, "7.0.0", "Removes and returns a member by score from one or more sorted sets. Blocks until a member is available otherwise. Deletes the sorted set if the last element was popped."),
+    CommandSpec::new("zrandmember", Arity::AtLeast(ONE_ARGS), &[ReadOnly], FIRST_KEY, |v| Ok(ZRandMember::try_from(v)?.into()))
+        .doc(Group::SortedSet, "6.2.0", "Returns one or more random members from a sorted set."),
+    // streams
+    CommandSpec::new("xadd", Arity::AtLeast(4), &[Write, DenyOom, Fast], FIRST_KEY, |v| Ok(XAdd::try_from(v)?.into()))
+        .doc(Group::Stream, "5.0.0", "Appends a new message to a stream. Creates the key if it doesn't exist."),
+    CommandSpec::new("xrange", Arity::AtLeast(3), &[ReadOnly], FIRST_KEY, |v| Ok(XRange::try_from(v)?.into()))
+        .doc(Group::Stream, "5.0.0", "Returns the messages from a stream within a range of IDs."),
+    CommandSpec::new("xrevrange", Arity::AtLeast(3), &[ReadOnly], FIRST_KEY, |v| Ok(XRange::try_from(v)?.into()))
+        .doc(Group::Stream, "5.0.0", "Returns the messages from a stream within a range of IDs in reverse order."),
+    CommandSpec::new("xlen", Arity::Exact(ONE_ARGS), &[ReadOnly, Fast], FIRST_KEY, |v| Ok(XLen::try_from(v)?.into()))
+        .doc(Group::Stream, "5.0.0", "Return the number of messages in a stream."),
+    CommandSpec::new("xtrim", Arity::AtLeast(3), &[Write], FIRST_KEY, |v| Ok(XTrim::try_from(v)?.into()))
+        .doc(Group::Stream, "5.0.0", "Deletes messages from the beginning of a stream."),
+    CommandSpec::new("xdel", Arity::AtLeast(TWO_ARGS), &[Write, Fast], FIRST_KEY, |v| Ok(XDel::try_from(v)?.into()))
+        .doc(Group::Stream, "5.0.0", "Returns the number of messages after removing them from a stream."),
+    CommandSpec::new("xread", Arity::AtLeast(3), &[ReadOnly, Blocking], NO_KEYS, |v| Ok(XRead::try_from(v)?.into()))
+        .doc(Group::Stream, "5.0.0", "Returns messages from multiple streams with IDs greater than the ones requested. Blocks until a message is available otherwise."),
+    CommandSpec::new("xreadgroup", Arity::AtLeast(6), &[Write, Blocking], NO_KEYS, |v| Ok(XRead::try_from(v)?.into()))
+        .doc(Group::Stream, "5.0.0", "Returns new or historical messages from a stream for a consumer in a group. Blocks until a message is available otherwise."),
+    CommandSpec::new("xack", Arity::AtLeast(3), &[Write, Fast], FIRST_KEY, |v| Ok(XAck::try_from(v)?.into()))
+        .doc(Group::Stream, "5.0.0", "Returns the number of messages that were successfully acknowledged by the consumer group member of a stream."),
+    CommandSpec::new("xpending", Arity::AtLeast(TWO_ARGS), &[ReadOnly], FIRST_KEY, |v| Ok(XPending::try_from(v)?.into()))
+        .doc(Group::Stream, "5.0.0", "Returns the information and entries from a stream consumer group's pending entries list."),
+    CommandSpec::new("xclaim", Arity::AtLeast(5), &[Write, Fast], FIRST_KEY, |v| Ok(XClaim::try_from(v)?.into()))
+        .doc(Group::Stream, "5.0.0", "Changes, or acquires, ownership of a message in a consumer group, as if the message was delivered a consumer group member."),
+    CommandSpec::new("xautoclaim", Arity::AtLeast(5), &[Write, Fast], FIRST_KEY, |v| Ok(XAutoClaim::try_from(v)?.into()))
+        .doc(Group::Stream, "6.2.0", "Changes, or acquires, ownership of messages in a consumer group, as if the messages were delivered to as consumer group member."),
+    CommandSpec::new("xgroup", Arity::AtLeast(ONE_ARGS), &[], NO_KEYS, |v| Ok(XGroup::try_from(v)?.into()))
+        .doc(Group::Stream, "5.0.0", "A container for consumer groups commands.")
+        .with_subcommands(XGROUP_SUBCOMMANDS),
+    CommandSpec::new("xinfo", Arity::AtLeast(ONE_ARGS), &[], NO_KEYS, |v| Ok(XInfo::try_from(v)?.into()))
+        .doc(Group::Stream, "5.0.0", "A container for stream introspection commands.")
+        .with_subcommands(XINFO_SUBCOMMANDS),
+    // keyspace
+    CommandSpec::new("del", Arity::AtLeast(ONE_ARGS), &[Write], ALL_KEYS, |v| Ok(Del::try_from(v)?.into()))
+        .doc(Group::Generic, "1.0.0", "Deletes one or more keys."),
+    CommandSpec::new("unlink", Arity::AtLeast(ONE_ARGS), &[Write, Fast], ALL_KEYS, |v| Ok(Del::try_from(v)?.into()))
+        .doc(Group::Generic, "4.0.0", "Asynchronously deletes one or more keys."),
+    CommandSpec::new("exists", Arity::AtLeast(ONE_ARGS), &[ReadOnly, Fast], ALL_KEYS, |v| Ok(Exists::try_from(v)?.into()))
+        .doc(Group::Generic, "1.0.0", "Determines whether one or more keys exist."),
+    CommandSpec::new("rename", Arity::Exact(TWO_ARGS), &[Write], KeySpec::new(1, 2, 1), |v| Ok(Rename::try_from(v)?.into()))
+        .doc(Group::Generic, "1.0.0", "Renames a key and overwrites the destination."),
+    CommandSpec::new("renamenx", Arity::Exact(TWO_ARGS), &[Write, Fast], KeySpec::new(1, 2, 1), |v| Ok(Rename::try_from(v)?.into()))
+        .doc(Group::Generic, "1.0.0", "Renames a key only when the target key name doesn't exist."),
+    CommandSpec::new("copy", Arity::AtLeast(TWO_ARGS), &[Write, DenyOom], KeySpec::new(1, 2, 1), |v| Ok(CopyKey::try_from(v)?.into()))
+        .doc(Group::Generic, "6.2.0", "Copies the value of a key to a new key."),
+    CommandSpec::new("keys", Arity::Exact(ONE_ARGS), &[ReadOnly], NO_KEYS, |v| Ok(Keys::try_from(v)?.into()))
+        .doc(Group::Generic, "1.0.0", "Returns all key names that match a pattern."),
+    CommandSpec::new("scan", Arity::AtLeast(ONE_ARGS), &[ReadOnly], NO_KEYS, |v| Ok(Scan::try_from(v)?.into()))
+        .doc(Group::Generic, "2.8.0", "Iterates over the key names in the database."),
+    CommandSpec::new("randomkey", Arity::Exact(0), &[ReadOnly], NO_KEYS, |v| Ok(RandomKey::try_from(v)?.into()))
+        .doc(Group::Generic, "1.0.0", "Returns a random key name from the database."),
+    CommandSpec::new("type", Arity::Exact(ONE_ARGS), &[ReadOnly, Fast], FIRST_KEY, |v| Ok(Type::try_from(v)?.into()))
+        .doc(Group::Generic, "1.0.0", "Determines the type of value stored at a key."),
+    CommandSpec::new("expire", Arity::Exact(TWO_ARGS), &[Write, Fast], FIRST_KEY, |v| Ok(Expire::try_from(v)?.into()))
+        .doc(Group::Generic, "1.0.0", "Sets the expiration time of a key in seconds."),
+    CommandSpec::new("pexpire", Arity::Exact(TWO_ARGS), &[Write, Fast], FIRST_KEY, |v| Ok(Expire::try_from(v)?.into()))
+        .doc(Group::Generic, "2.6.0", "Sets the expiration time of a key in milliseconds."),
+    CommandSpec::new("expireat", Arity::Exact(TWO_ARGS), &[Write, Fast], FIRST_KEY, |v| Ok(Expire::try_from(v)?.into()))
+        .doc(Group::Generic, "1.2.0", "Sets the expiration time of a key to a Unix timestamp."),
+    CommandSpec::new("pexpireat", Arity::Exact(TWO_ARGS), &[Write, Fast], FIRST_KEY, |v| Ok(Expire::try_from(v)?.into()))
+        .doc(Group::Generic, "2.6.0", "Sets the expiration time of a key to a Unix milliseconds timestamp."),
+    CommandSpec::new("ttl", Arity::Exact(ONE_ARGS), &[ReadOnly, Fast], FIRST_KEY, |v| Ok(Ttl::try_from(v)?.into()))
+        .doc(Group::Generic, "1.0.0", "Returns the expiration time in seconds of a key."),
+    CommandSpec::new("pttl", Arity::Exact(ONE_ARGS), &[ReadOnly, Fast], FIRST_KEY, |v| Ok(Ttl::try_from(v)?.into()))
+        .doc(Group::Generic, "2.6.0", "Returns the expiration time in milliseconds of a key."),
+    CommandSpec::new("expiretime", Arity::Exact(ONE_ARGS), &[ReadOnly, Fast], FIRST_KEY, |v| Ok(Ttl::try_from(v)?.into()))
+        .doc(Group::Generic, "7.0.0", "Returns the expiration time of a key as a Unix timestamp."),
+    CommandSpec::new("pexpiretime", Arity::Exact(ONE_ARGS), &[ReadOnly, Fast], FIRST_KEY, |v| Ok(Ttl::try_from(v)?.into()))
+        .doc(Group::Generic, "7.0.0", "Returns the expiration time of a key as a Unix milliseconds timestamp."),
+    CommandSpec::new("persist", Arity::Exact(ONE_ARGS), &[Write, Fast], FIRST_KEY, |v| Ok(Persist::try_from(v)?.into()))
+        .doc(Group::Generic, "2.2.0", "Removes the expiration time of a key."),
+    // connection
+    CommandSpec::new("hello", Arity::AtLeast(0), &[Fast], NO_KEYS, |v| Ok(Hello::try_from(v)?.into()))
+        .doc(Group::Connection, "6.0.0", "Handshakes with the Redis server."),
+    CommandSpec::new("ping", Arity::AtLeast(0), &[Fast], NO_KEYS, |v| Ok(Ping::try_from(v)?.into()))
+        .doc(Group::Connection, "1.0.0", "Returns the server's liveliness response."),
+    CommandSpec::new("echo", Arity::Exact(ONE_ARGS), &[Fast], NO_KEYS, |v| Ok(Echo::try_from(v)?.into()))
+        .doc(Group::Connection, "1.0.0", "Returns the given string."),
+    CommandSpec::new("quit", Arity::AtLeast(0), &[Fast], NO_KEYS, |v| Ok(Quit::try_from(v)?.into()))
+        .doc(Group::Connection, "1.0.0", "Closes the connection."),
+    CommandSpec::new("reset", Arity::Exact(0), &[Fast], NO_KEYS, |v| Ok(Reset::try_from(v)?.into()))
+        .doc(Group::Connection, "6.2.0", "Resets the connection."),
+    // pub/sub
+    CommandSpec::new("subscribe", Arity::AtLeast(ONE_ARGS), &[], NO_KEYS, |v| Ok(Subscribe::try_from(v)?.into()))
+        .doc(Group::PubSub, "2.0.0", "Listens for messages published to channels."),
+    CommandSpec::new("psubscribe", Arity::AtLeast(ONE_ARGS), &[], NO_KEYS, |v| Ok(Subscribe::try_from(v)?.into()))
+        .doc(Group::PubSub, "2.0.0", "Listens for messages published to channels that match one or more patterns."),
+    CommandSpec::new("unsubscribe", Arity::AtLeast(0), &[], NO_KEYS, |v| Ok(Unsubscribe::try_from(v)?.into()))
+        .doc(Group::PubSub, "2.0.0", "Stops listening to messages posted to channels."),
+    CommandSpec::new("punsubscribe", Arity::AtLeast(0), &[], NO_KEYS, |v| Ok(Unsubscribe::try_from(v)?.into()))
+        .doc(Group::PubSub, "2.0.0", "Stops listening to messages published to channels that match one or more patterns."),
+    CommandSpec::new("publish", Arity::Exact(TWO_ARGS), &[Fast], NO_KEYS, |v| Ok(Publish::try_from(v)?.into()))
+        .doc(Group::PubSub, "2.0.0", "Posts a message to a channel."),
+    CommandSpec::new("pubsub", Arity::AtLeast(ONE_ARGS), &[], NO_KEYS, |v| Ok(PubSub::try_from(v)?.into()))
+        .doc(Group::PubSub, "2.8.0", "A container for Pub/Sub commands.")
+        .with_subcommands(PUBSUB_SUBCOMMANDS),
+    // server
+    CommandSpec::new("dbsize", Arity::Exact(0), &[ReadOnly, Fast], NO_KEYS, |v| Ok(DbSize::try_from(v)?.into()))
+        .doc(Group::Server, "1.0.0", "Returns the number of keys in the database."),
+    CommandSpec::new("command", Arity::AtLeast(0), &[], NO_KEYS, |v| Ok(CommandInfo::try_from(v)?.into()))
+        .doc(Group::Server, "2.8.13", "Returns detailed information about all commands.")
+        .with_subcommands(COMMAND_SUBCOMMANDS),
+];
+
+// subcommands are named `command|subcommand` and their arity counts the subcommand name
+#[rustfmt::skip]
+static COMMAND_SUBCOMMANDS: &[CommandSpec] = &[
+    CommandSpec::new("command|count", Arity::Exact(1), &[], NO_KEYS, |v| Ok(CommandInfo::try_from(v)?.into()))
+        .doc(Group::Server, "2.8.13", "Returns a count of commands."),
+    CommandSpec::new("command|info", Arity::AtLeast(1), &[], NO_KEYS, |v| Ok(CommandInfo::try_from(v)?.into()))
+        .doc(Group::Server, "2.8.13", "Returns information about one, multiple or all commands."),
+    CommandSpec::new("command|docs", Arity::AtLeast(1), &[], NO_KEYS, |v| Ok(CommandInfo::try_from(v)?.into()))
+        .doc(Group::Server, "7.0.0", "Returns documentary information about one, multiple or all commands."),
+    CommandSpec::new("command|help", Arity::Exact(1), &[], NO_KEYS, |v| Ok(CommandInfo::try_from(v)?.into()))
+        .doc(Group::Server, "5.0.0", "Returns helpful text about the different subcommands."),
+];
+
+#[rustfmt::skip]
+static XGROUP_SUBCOMMANDS: &[CommandSpec] = &[
+    CommandSpec::new("xgroup|create", Arity::AtLeast(4), &[Write, DenyOom], KeySpec::new(2, 2, 1), |v| Ok(XGroup::try_from(v)?.into()))
+        .doc(Group::Stream, "5.0.0", "Creates a consumer group."),
+    CommandSpec::new("xgroup|setid", Arity::AtLeast(4), &[Write], KeySpec::new(2, 2, 1), |v| Ok(XGroup::try_from(v)?.into()))
+        .doc(Group::Stream, "5.0.0", "Sets the last-delivered ID of a consumer group."),
+    CommandSpec::new("xgroup|destroy", Arity::Exact(3), &[Write], KeySpec::new(2, 2, 1), |v| Ok(XGroup::try_from(v)?.into()))
+        .doc(Group::Stream, "5.0.0", "Destroys a consumer group."),
+    CommandSpec::new("xgroup|createconsumer", Arity::Exact(4), &[Write, DenyOom], KeySpec::new(2, 2, 1), |v| Ok(XGroup::try_from(v)?.into()))
+        .doc(Group::Stream, "6.2.0", "Creates a consumer in a consumer group."),
+    CommandSpec::new("xgroup|delconsumer", Arity::Exact(4), &[Write], KeySpec::new(2, 2, 1), |v| Ok(XGroup::try_from(v)?.into()))
+        .doc(Group::Stream, "5.0.0", "Deletes a consumer from a consumer group."),
+    CommandSpec::new("xgroup|help", Arity::Exact(1), &[], NO_KEYS, |v| Ok(XGroup::try_from(v)?.into()))
+        .doc(Group::Stream, "5.0.0", "Returns helpful text about the different subcommands."),
+];
+
+#[rustfmt::skip]
+static XINFO_SUBCOMMANDS: &[CommandSpec] = &[
+    CommandSpec::new("xinfo|stream", Arity::Exact(2), &[ReadOnly], KeySpec::new(2, 2, 1), |v| Ok(XInfo::try_from(v)?.into()))
+        .doc(Group::Stream, "5.0.0", "Returns information about a stream."),
+    CommandSpec::new("xinfo|groups", Arity::Exact(2), &[ReadOnly], KeySpec::new(2, 2, 1), |v| Ok(XInfo::try_from(v)?.into()))
+        .doc(Group::Stream, "5.0.0", "Returns a list of the consumer groups of a stream."),
+    CommandSpec::new("xinfo|consumers", Arity::Exact(3), &[ReadOnly], KeySpec::new(2, 2, 1), |v| Ok(XInfo::try_from(v)?.into()))
+        .doc(Group::Stream, "5.0.0", "Returns a list of the consumers in a consumer group."),
+    CommandSpec::new("xinfo|help", Arity::Exact(1), &[], NO_KEYS, |v| Ok(XInfo::try_from(v)?.into()))
+        .doc(Group::Stream, "5.0.0", "Returns helpful text about the different subcommands."),
+];
+
+#[rustfmt::skip]
+static PUBSUB_SUBCOMMANDS: &[CommandSpec] = &[
+    CommandSpec::new("pubsub|channels", Arity::AtLeast(1), &[], NO_KEYS, |v| Ok(PubSub::try_from(v)?.into()))
+        .doc(Group::PubSub, "2.8.0", "Returns the active channels."),
+    CommandSpec::new("pubsub|numsub", Arity::AtLeast(1), &[], NO_KEYS, |v| Ok(PubSub::try_from(v)?.into()))
+        .doc(Group::PubSub, "2.8.0", "Returns a count of subscribers to channels."),
+    CommandSpec::new("pubsub|numpat", Arity::Exact(1), &[], NO_KEYS, |v| Ok(PubSub::try_from(v)?.into()))
+        .doc(Group::PubSub, "2.8.0", "Returns a count of unique pattern subscriptions."),
+    CommandSpec::new("pubsub|help", Arity::Exact(1), &[], NO_KEYS, |v| Ok(PubSub::try_from(v)?.into()))
+        .doc(Group::PubSub, "6.2.0", "Returns helpful text about the different subcommands."),
+];
+
+impl CommandFlag {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Write => "write",
+            ReadOnly => "readonly",
+            DenyOom => "denyoom",
+            Fast => "fast",
+            Blocking => "blocking",
+        }
+    }
+}
+
+impl CommandGroup {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Group::Generic => "generic",
+            Group::String => "string",
+            Group::Hash => "hash",
+            Group::List => "list",
+            Group::Set => "set",
+            Group::SortedSet => "sorted-set",
+            Group::Stream => "stream",
+            Group::PubSub => "pubsub",
+            Group::Connection => "connection",
+            Group::Server => "server",
+        }
+    }
+}
+
+impl KeySpec {
+    const fn new(first: i64, last: i64, step: i64) -> Self {
+        KeySpec { first, last, step }
+    }
+}
+
+impl CommandSpec {
+    const fn new(
+        name: &'static str,
+        arity: Arity,
+        flags: &'static [CommandFlag],
+        keys: KeySpec,
+        parse: fn(RespArray) -> Result<Command, CommandError>,
+    ) -> Self {
+        CommandSpec {
+            name,
+            arity,
+            flags,
+            keys,
+            group: Group::Generic,
+            since: "",
+            summary: "",
+            subcommands: &[],
+            parse,
+        }
+    }
+
+    const fn doc(self, group: CommandGroup, since: &'static str, summary: &'static str) -> Self {
+        CommandSpec {
+            group,
+            since,
+            summary,
+            ..self
+        }
+    }
+
+    const fn with_subcommands(self, subcommands: &'static [CommandSpec]) -> Self {
+        CommandSpec {
+            subcommands,
+            ..self
+        }
+    }
+
+    /// Look a command up by name, case-insensitively.
+    pub fn lookup(name: &[u8]) -> Option<&'static CommandSpec> {
+        let name = String::from_utf8_lossy(name).to_ascii_lowercase();
+        COMMAND_INDEX.get(name.as_str()).copied()
+    }
+
+    /// Look a command or a `command|subcommand` pair up by name, case-insensitively.
+    pub fn resolve(name: &[u8]) -> Option<&'static CommandSpec> {
+        match name.iter().position(|&b| b == b'|') {
+            Some(pos) => Self::lookup(&name[..pos])?.subcommand(&name[pos + 1..]),
+            None => Self::lookup(name),
+        }
+    }
+
+    pub fn subcommand(&self, name: &[u8]) -> Option<&'static CommandSpec> {
+        let name = String::from_utf8_lossy(name).to_ascii_lowercase();
+        self.subcommands
+            .iter()
+            .find(|spec| spec.name.split_once('|').map(|(_, sub)| sub) == Some(name.as_str()))
+    }
+
+    /// Every known command, in table order.
+    pub fn all() -> &'static [CommandSpec] {
+        COMMAND_TABLE
+    }
+
+    /// The arity in Redis' convention: it counts the command name, and a negative value
+    /// means "at least".
+    pub fn redis_arity(&self) -> i64 {
+        match self.arity {
+            Arity::Exact(n) => n as i64 + 1,
+            Arity::AtLeast(n) => -(n as i64 + 1),
+            Arity::Pairs(n) => -(n as i64 + 3),
+        }
+    }
+
+    pub fn has_flag(&self, flag: CommandFlag) -> bool {
+        self.flags.contains(&flag)
+    }
+
+    pub(crate) fn parse(&self, value: RespArray) -> Result<Command, CommandError> {
+        let spec = match value.get(1) {
+            Some(RespFrame::BulkString(ref sub)) if !self.subcommands.is_empty() => self
+                .subcommand(sub)
+                .ok_or_else(|| CommandError::UnknownSubcommand {
+                    command: self.name.to_uppercase(),
+                    name: String::from_utf8_lossy(sub).chars().take(128).collect(),
+                })?,
+            _ => self,
+        };
+        if !spec.arity.accepts(value.len() - 1) {
+            return Err(CommandError::WrongArity(spec.name.to_string()));
+        }
+        (spec.parse)(value)
+    }
+}
+
+// the arguments quoted the way Redis echoes them in the unknown command error
+pub(crate) fn format_args_preview(args: &[RespFrame]) -> String {
+    let mut ret = String::new();
+    for arg in args {
+        if ret.len() >= 128 {
+            break;
+        }
+        if let RespFrame::BulkString(ref s) = arg {
+            let s = String::from_utf8_lossy(s);
+            let max = 128 - ret.len();
+            ret.push_str(&format!("'{}' ", s.chars().take(max).collect::<String>()));
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup_is_case_insensitive() {
+        let spec = CommandSpec::lookup(b"GeT").unwrap();
+        assert_eq!(spec.name, "get");
+        assert!(spec.has_flag(ReadOnly));
+        assert!(!spec.has_flag(Write));
+        assert!(CommandSpec::lookup(b"nope").is_none());
+    }
+
+    #[test]
+    fn test_table_names_are_unique_and_lowercase() {
+        assert_eq!(COMMAND_INDEX.len(), COMMAND_TABLE.len());
+        for spec in CommandSpec::all() {
+            assert_eq!(spec.name, spec.name.to_ascii_lowercase());
+            assert!(
+                !spec.summary.is_empty() && !spec.since.is_empty(),
+                "{}",
+                spec.name
+            );
+            assert!(
+                !(spec.has_flag(Write) && spec.has_flag(ReadOnly)),
+                "{}",
+                spec.name
+            );
+        }
+    }
+
+    #[test]
+    fn test_redis_arity() {
+        assert_eq!(CommandSpec::lookup(b"get").unwrap().redis_arity(), 2);
+        assert_eq!(CommandSpec::lookup(b"set").unwrap().redis_arity(), -3);
+        assert_eq!(CommandSpec::lookup(b"mset").unwrap().redis_arity(), -3);
+        assert_eq!(CommandSpec::lookup(b"hset").unwrap().redis_arity(), -4);
+        assert_eq!(CommandSpec::lookup(b"command").unwrap().redis_arity(), -1);
+        assert_eq!(
+            CommandSpec::resolve(b"command|count")
+                .unwrap()
+                .redis_arity(),
+            2
+        );
+        assert_eq!(
+            CommandSpec::resolve(b"COMMAND|Info").unwrap().redis_arity(),
+            -2
+        );
+        assert!(CommandSpec::resolve(b"command|nope").is_none());
+        assert!(CommandSpec::resolve(b"get|nope").is_none());
+    }
+}
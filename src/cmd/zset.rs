@@ -0,0 +1,1071 @@
+use std::ops::Bound;
+
+use super::blocking::parse_multi_pop;
+use super::hmap::key_and_fields;
+use super::list::parse_positive;
+use super::{
+    command_name, extract_args, extract_string, parse_float, parse_int, parse_random_count,
+    validate_command, Arity, PopOp,
+};
+use crate::{
+    Aggregate, Backend, BulkString, CommandError, CommandExecutor, RespArray, RespFrame, RespNull,
+    RespVersion, SetCondition, SetOp, ZAdd, ZAddOptions, ZCard, ZCount, ZIncrBy, ZLimit, ZMPop,
+    ZMScore, ZPop, ZRandMember, ZRange, ZRangeBy, ZRank, ZRem, ZRemRange, ZScore, ZSetAlgebra,
+};
+use std::convert::TryFrom;
+
+const ONE_ARGS: usize = 1;
+const TWO_ARGS: usize = 2;
+const THREE_ARGS: usize = 3;
+
+impl CommandExecutor for ZAdd {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if self.incr {
+            let (delta, member) = self.members.into_iter().next().expect("checked on parse");
+            return match backend.zincr_by(self.key, member, delta, self.opts) {
+                Ok(score) => score_reply(score),
+                Err(e) => e.into(),
+            };
+        }
+        match backend.zadd(self.key, self.members, self.opts) {
+            Ok(count) => RespFrame::Integer(count as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZScore {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.zscore(&self.key, &self.member) {
+            Ok(score) => score_reply(score),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZMScore {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.zmscore(&self.key, &self.members) {
+            Ok(scores) => {
+                RespArray::new(scores.into_iter().map(score_reply).collect::<Vec<_>>()).into()
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZIncrBy {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let opts = ZAddOptions::default();
+        match backend.zincr_by(self.key, self.member, self.delta, opts) {
+            Ok(score) => score_reply(score),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZCard {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.zcard(&self.key) {
+            Ok(len) => RespFrame::Integer(len as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZRank {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.zrank(&self.key, &self.member, self.rev) {
+            Ok(Some((rank, score))) if self.with_score => RespArray::new(vec![
+                RespFrame::Integer(rank as i64),
+                RespFrame::Double(score),
+            ])
+            .into(),
+            Ok(Some((rank, _))) => RespFrame::Integer(rank as i64),
+            Ok(None) => RespFrame::Null(RespNull),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZRem {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.zrem(&self.key, &self.members) {
+            Ok(removed) => RespFrame::Integer(removed as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl ZRange {
+    // with WITHSCORES, RESP3 gets an array of member score pairs and RESP2 a flat array
+    pub(super) fn reply(self, backend: &Backend, protocol: RespVersion) -> RespFrame {
+        match backend.zrange(&self.key, &self.range, self.rev, self.limit) {
+            Ok(members) => members_reply(members, self.with_scores, protocol),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZRange {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.reply(backend, RespVersion::Resp2)
+    }
+}
+
+impl ZSetAlgebra {
+    // the STORE variants reply the size of the result, the others its members
+    pub(super) fn reply(self, backend: &Backend, protocol: RespVersion) -> RespFrame {
+        let ret = match self.dst {
+            Some(dst) => backend
+                .zset_op_store(self.op, &dst, &self.keys, &self.weights, self.aggregate)
+                .map(|len| RespFrame::Integer(len as i64)),
+            None => backend
+                .zset_op(self.op, &self.keys, &self.weights, self.aggregate)
+                .map(|members| members_reply(members, self.with_scores, protocol)),
+        };
+        ret.unwrap_or_else(|e| e.into())
+    }
+}
+
+impl CommandExecutor for ZSetAlgebra {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.reply(backend, RespVersion::Resp2)
+    }
+}
+
+impl CommandExecutor for ZRemRange {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.zremrange(&self.key, &self.range) {
+            Ok(removed) => RespFrame::Integer(removed as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZCount {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.zcount(&self.key, &self.range) {
+            Ok(count) => RespFrame::Integer(count as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl ZPop {
+    // without a count the reply is a flat member score array, with one RESP3 gets pairs
+    pub(super) fn reply(self, backend: &Backend, protocol: RespVersion) -> RespFrame {
+        let protocol = if self.count.is_some() {
+            protocol
+        } else {
+            RespVersion::Resp2
+        };
+        match backend.zpop(&self.key, self.max, self.count.unwrap_or(1)) {
+            Ok(members) => members_reply(members.unwrap_or_default(), true, protocol),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZPop {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.reply(backend, RespVersion::Resp2)
+    }
+}
+
+impl CommandExecutor for ZMPop {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.pop.execute(backend)
+    }
+}
+
+impl ZRandMember {
+    // a single member or nil without a count, else an array like ZRANGE's
+    pub(super) fn reply(self, backend: &Backend, protocol: RespVersion) -> RespFrame {
+        match (
+            backend.zrandmember(&self.key, self.count.unwrap_or(1)),
+            self.count,
+        ) {
+            (Ok(members), Some(_)) => members_reply(members, self.with_scores, protocol),
+            (Ok(members), None) => match members.into_iter().next() {
+                Some((member, _)) => BulkString::from(member).into(),
+                None => RespFrame::Null(RespNull),
+            },
+            (Err(e), _) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZRandMember {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.reply(backend, RespVersion::Resp2)
+    }
+}
+
+impl TryFrom<RespArray> for ZAdd {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&array, &["zadd"], Arity::AtLeast(THREE_ARGS))?;
+        let mut args = extract_args(array, ONE_ARGS)?.into_iter().peekable();
+        let key = extract_string(args.next().ok_or(CommandError::SyntaxError)?)?;
+        let mut opts = ZAddOptions::default();
+        let (mut nx, mut xx, mut incr) = (false, false, false);
+        while let Some(RespFrame::BulkString(arg)) = args.peek() {
+            match arg.to_ascii_lowercase().as_slice() {
+                b"nx" => nx = true,
+                b"xx" => xx = true,
+                b"gt" => opts.gt = true,
+                b"lt" => opts.lt = true,
+                b"ch" => opts.ch = true,
+                b"incr" => incr = true,
+                _ => break,
+            }
+            args.next();
+        }
+        if nx && xx {
+            return Err(CommandError::InvalidArgument(
+                "XX and NX options at the same time are not compatible".to_string(),
+            ));
+        }
+        if (opts.gt && opts.lt) || (nx && (opts.gt || opts.lt)) {
+            return Err(CommandError::InvalidArgument(
+                "GT, LT, and/or NX options at the same time are not compatible".to_string(),
+            ));
+        }
+        opts.condition = match (nx, xx) {
+            (true, _) => SetCondition::IfNotExists,
+            (_, true) => SetCondition::IfExists,
+            _ => SetCondition::Always,
+        };
+
+        let args = args.collect::<Vec<_>>();
+        if args.is_empty() || args.len() % 2 != 0 {
+            return Err(CommandError::SyntaxError);
+        }
+        if incr && args.len() > 2 {
+            return Err(CommandError::InvalidArgument(
+                "INCR option supports a single increment-element pair".to_string(),
+            ));
+        }
+        let mut members = Vec::with_capacity(args.len() / 2);
+        let mut args = args.into_iter();
+        while let (Some(score), Some(member)) = (args.next(), args.next()) {
+            members.push((parse_float(&score)?, extract_string(member)?));
+        }
+        Ok(ZAdd {
+            key,
+            opts,
+            incr,
+            members,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZScore {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&array, &["zscore"], TWO_ARGS)?;
+        let (key, mut members) = key_and_fields(array)?;
+        Ok(ZScore {
+            key,
+            member: members.remove(0),
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZMScore {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&array, &["zmscore"], Arity::AtLeast(TWO_ARGS))?;
+        let (key, members) = key_and_fields(array)?;
+        Ok(ZMScore { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for ZIncrBy {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&array, &["zincrby"], THREE_ARGS)?;
+        let mut args = extract_args(array, ONE_ARGS)?.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(key), Some(ref delta), Some(member)) => Ok(ZIncrBy {
+                key: extract_string(key)?,
+                delta: parse_float(delta)?,
+                member: extract_string(member)?,
+            }),
+            _ => Err(CommandError::SyntaxError),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for ZCard {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&array, &["zcard"], ONE_ARGS)?;
+        let (key, _) = key_and_fields(array)?;
+        Ok(ZCard { key })
+    }
+}
+
+impl TryFrom<RespArray> for ZRank {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        let (name, rev) = match command_name(&array)?.as_str() {
+            "zrank" => ("zrank", false),
+            "zrevrank" => ("zrevrank", true),
+            name => {
+                return Err(CommandError::InvalidCommand(format!(
+                    "Invalid command: expected zrank, got {name}"
+                )))
+            }
+        };
+        validate_command(&array, &[name], Arity::AtLeast(TWO_ARGS))?;
+        if array.len() > 4 {
+            return Err(CommandError::SyntaxError);
+        }
+        let (key, mut args) = key_and_fields(array)?;
+        let with_score = match args.get(1) {
+            Some(arg) if arg.eq_ignore_ascii_case("withscore") => true,
+            Some(_) => return Err(CommandError::SyntaxError),
+            None => false,
+        };
+        Ok(ZRank {
+            key,
+            member: args.swap_remove(0),
+            rev,
+            with_score,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZRem {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&array, &["zrem"], Arity::AtLeast(TWO_ARGS))?;
+        let (key, members) = key_and_fields(array)?;
+        Ok(ZRem { key, members })
+    }
+}
+
+#[derive(PartialEq)]
+enum RangeKind {
+    Rank,
+    Score,
+    Lex,
+}
+
+impl TryFrom<RespArray> for ZRange {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&array, &["zrange"], Arity::AtLeast(THREE_ARGS))?;
+        let mut args = extract_args(array, ONE_ARGS)?.into_iter();
+        let key = extract_string(args.next().ok_or(CommandError::SyntaxError)?)?;
+        let start = args.next().ok_or(CommandError::SyntaxError)?;
+        let stop = args.next().ok_or(CommandError::SyntaxError)?;
+        let (mut kind, mut rev, mut with_scores, mut limit) = (RangeKind::Rank, false, false, None);
+        while let Some(arg) = args.next() {
+            match extract_string(arg)?.to_ascii_lowercase().as_str() {
+                "byscore" => kind = RangeKind::Score,
+                "bylex" => kind = RangeKind::Lex,
+                "rev" => rev = true,
+                "withscores" => with_scores = true,
+                "limit" => match (args.next(), args.next()) {
+                    (Some(ref offset), Some(ref count)) => {
+                        limit = Some(parse_limit(parse_int(offset)?, parse_int(count)?))
+                    }
+                    _ => return Err(CommandError::SyntaxError),
+                },
+                _ => return Err(CommandError::SyntaxError),
+            }
+        }
+        if limit.is_some() && kind == RangeKind::Rank {
+            return Err(CommandError::InvalidArgument(
+                "syntax error, LIMIT is only supported in combination with either BYSCORE or BYLEX"
+                    .to_string(),
+            ));
+        }
+        if with_scores && kind == RangeKind::Lex {
+            return Err(CommandError::InvalidArgument(
+                "syntax error, WITHSCORES not supported in combination with BYLEX".to_string(),
+            ));
+        }
+        // with REV the score and lex bounds come as max then min
+        let (min, max) = if rev && kind != RangeKind::Rank {
+            (stop, start)
+        } else {
+            (start, stop)
+        };
+        let range = parse_range(kind, min, max)?;
+        Ok(ZRange {
+            key,
+            range,
+            rev,
+            limit,
+            with_scores,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZSetAlgebra {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        let (name, op, store) = match command_name(&array)?.as_str() {
+            "zinter" => ("zinter", SetOp::Inter, false),
+            "zunion" => ("zunion", SetOp::Union, false),
+            "zdiff" => ("zdiff", SetOp::Diff, false),
+            "zinterstore" => ("zinterstore", SetOp::Inter, true),
+            "zunionstore" => ("zunionstore", SetOp::Union, true),
+            "zdiffstore" => ("zdiffstore", SetOp::Diff, true),
+            name => {
+                return Err(CommandError::InvalidCommand(format!(
+                    "Invalid command: expected zinter, got {name}"
+                )))
+            }
+        };
+        let min_args = if store { THREE_ARGS } else { TWO_ARGS };
+        validate_command(&array, &[name], Arity::AtLeast(min_args))?;
+        let mut args = extract_args(array, ONE_ARGS)?.into_iter().peekable();
+        let dst = if store {
+            Some(extract_string(
+                args.next().ok_or(CommandError::SyntaxError)?,
+            )?)
+        } else {
+            None
+        };
+        let numkeys = parse_int(&args.next().ok_or(CommandError::SyntaxError)?)?;
+        if numkeys <= 0 {
+            return Err(CommandError::InvalidArgument(format!(
+                "at least 1 input key is needed for '{name}' command"
+            )));
+        }
+        let keys = args
+            .by_ref()
+            .take(numkeys as usize)
+            .map(extract_string)
+            .collect::<Result<Vec<_>, _>>()?;
+        if keys.len() < numkeys as usize {
+            return Err(CommandError::SyntaxError);
+        }
+        let (mut weights, mut aggregate, mut with_scores) = (None, Aggregate::Sum, false);
+        while let Some(arg) = args.next() {
+            // ZDIFF takes neither weights nor an aggregate, the STORE variants no WITHSCORES
+            match extract_string(arg)?.to_ascii_lowercase().as_str() {
+                "weights" if op != SetOp::Diff => {
+                    let parsed = args
+                        .by_ref()
+                        .take(keys.len())
+                        .map(|weight| {
+                            parse_float(&weight).map_err(|_| {
+                                CommandError::InvalidArgument(
+                                    "weight value is not a float".to_string(),
+                                )
+                            })
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    if parsed.len() < keys.len() {
+                        return Err(CommandError::SyntaxError);
+                    }
+                    weights = Some(parsed);
+                }
+                "aggregate" if op != SetOp::Diff => {
+                    let how = extract_string(args.next().ok_or(CommandError::SyntaxError)?)?;
+                    aggregate = match how.to_ascii_lowercase().as_str() {
+                        "sum" => Aggregate::Sum,
+                        "min" => Aggregate::Min,
+                        "max" => Aggregate::Max,
+                        _ => return Err(CommandError::SyntaxError),
+                    };
+                }
+                "withscores" if !store => with_scores = true,
+                _ => return Err(CommandError::SyntaxError),
+            }
+        }
+        Ok(ZSetAlgebra {
+            op,
+            dst,
+            weights: weights.unwrap_or_else(|| vec![1.0; keys.len()]),
+            keys,
+            aggregate,
+            with_scores,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZRemRange {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        let (name, kind) = match command_name(&array)?.as_str() {
+            "zremrangebyrank" => ("zremrangebyrank", RangeKind::Rank),
+            "zremrangebyscore" => ("zremrangebyscore", RangeKind::Score),
+            "zremrangebylex" => ("zremrangebylex", RangeKind::Lex),
+            name => {
+                return Err(CommandError::InvalidCommand(format!(
+                    "Invalid command: expected zremrangebyrank, got {name}"
+                )))
+            }
+        };
+        validate_command(&array, &[name], THREE_ARGS)?;
+        let (key, range) = key_and_range(array, kind)?;
+        Ok(ZRemRange { key, range })
+    }
+}
+
+impl TryFrom<RespArray> for ZCount {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        let (name, kind) = match command_name(&array)?.as_str() {
+            "zcount" => ("zcount", RangeKind::Score),
+            "zlexcount" => ("zlexcount", RangeKind::Lex),
+            name => {
+                return Err(CommandError::InvalidCommand(format!(
+                    "Invalid command: expected zcount, got {name}"
+                )))
+            }
+        };
+        validate_command(&array, &[name], THREE_ARGS)?;
+        let (key, range) = key_and_range(array, kind)?;
+        Ok(ZCount { key, range })
+    }
+}
+
+impl TryFrom<RespArray> for ZPop {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        let (name, max) = match command_name(&array)?.as_str() {
+            "zpopmin" => ("zpopmin", false),
+            "zpopmax" => ("zpopmax", true),
+            name => {
+                return Err(CommandError::InvalidCommand(format!(
+                    "Invalid command: expected zpopmin, got {name}"
+                )))
+            }
+        };
+        validate_command(&array, &[name], Arity::AtLeast(ONE_ARGS))?;
+        if array.len() > 3 {
+            return Err(CommandError::SyntaxError);
+        }
+        let mut args = extract_args(array, ONE_ARGS)?.into_iter();
+        let key = extract_string(args.next().ok_or(CommandError::SyntaxError)?)?;
+        let count = args.next().as_ref().map(parse_positive).transpose()?;
+        Ok(ZPop { key, max, count })
+    }
+}
+
+impl TryFrom<RespArray> for ZMPop {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&array, &["zmpop"], Arity::AtLeast(THREE_ARGS))?;
+        let args = extract_args(array, ONE_ARGS)?;
+        let (keys, max, count) = parse_multi_pop(args, parse_min_max)?;
+        Ok(ZMPop {
+            pop: PopOp::ZSetMulti { keys, max, count },
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZRandMember {
+    type Error = CommandError;
+    fn try_from(array: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&array, &["zrandmember"], Arity::AtLeast(ONE_ARGS))?;
+        if array.len() > 4 {
+            return Err(CommandError::SyntaxError);
+        }
+        let mut args = extract_args(array, ONE_ARGS)?.into_iter();
+        let key = extract_string(args.next().ok_or(CommandError::SyntaxError)?)?;
+        let count = args.next().as_ref().map(parse_random_count).transpose()?;
+        let with_scores = match args.next().map(extract_string).transpose()? {
+            Some(arg) if arg.eq_ignore_ascii_case("withscores") => true,
+            Some(_) => return Err(CommandError::SyntaxError),
+            None => false,
+        };
+        Ok(ZRandMember {
+            key,
+            count,
+            with_scores,
+        })
+    }
+}
+
+// a negative offset selects nothing and a negative count everything after the offset
+fn parse_limit(offset: i64, count: i64) -> ZLimit {
+    if offset < 0 {
+        return ZLimit {
+            offset: 0,
+            count: Some(0),
+        };
+    }
+    ZLimit {
+        offset: offset as usize,
+        count: (count >= 0).then_some(count as usize),
+    }
+}
+
+// a score, exclusive when prefixed by `(`, -inf and +inf for no bound
+fn parse_score_bound(frame: &RespFrame) -> Result<Bound<f64>, CommandError> {
+    let err = || CommandError::InvalidArgument("min or max is not a float".to_string());
+    let RespFrame::BulkString(s) = frame else {
+        return Err(err());
+    };
+    let (exclusive, s) = match s.strip_prefix(b"(") {
+        Some(rest) => (true, rest),
+        None => (false, s.as_slice()),
+    };
+    let score = std::str::from_utf8(s)
+        .ok()
+        .and_then(|s| s.parse::<f64>().ok())
+        .filter(|n| !n.is_nan())
+        .ok_or_else(err)?;
+    Ok(if exclusive {
+        Bound::Excluded(score)
+    } else {
+        Bound::Included(score)
+    })
+}
+
+// `[member` inclusive, `(member` exclusive, `-` and `+` for no bound
+fn parse_lex_bound(frame: RespFrame) -> Result<Bound<String>, CommandError> {
+    let s = extract_string(frame)?;
+    match s.as_bytes().first() {
+        Some(b'-' | b'+') if s.len() == 1 => Ok(Bound::Unbounded),
+        Some(b'[') => Ok(Bound::Included(s[1..].to_string())),
+        Some(b'(') => Ok(Bound::Excluded(s[1..].to_string())),
+        _ => Err(CommandError::InvalidArgument(
+            "min or max not valid string range item".to_string(),
+        )),
+    }
+}
+
+// MIN or MAX of ZMPOP and BZMPOP, true for MAX
+pub(super) fn parse_min_max(frame: Option<RespFrame>) -> Result<bool, CommandError> {
+    let at = extract_string(frame.ok_or(CommandError::SyntaxError)?)?;
+    match at.to_ascii_lowercase().as_str() {
+        "min" => Ok(false),
+        "max" => Ok(true),
+        _ => Err(CommandError::SyntaxError),
+    }
+}
+
+// `key min max` of the commands taking a single range of the given kind
+fn key_and_range(array: RespArray, kind: RangeKind) -> Result<(String, ZRangeBy), CommandError> {
+    let mut args = extract_args(array, ONE_ARGS)?.into_iter();
+    let key = extract_string(args.next().ok_or(CommandError::SyntaxError)?)?;
+    let min = args.next().ok_or(CommandError::SyntaxError)?;
+    let max = args.next().ok_or(CommandError::SyntaxError)?;
+    Ok((key, parse_range(kind, min, max)?))
+}
+
+fn parse_range(kind: RangeKind, min: RespFrame, max: RespFrame) -> Result<ZRangeBy, CommandError> {
+    Ok(match kind {
+        RangeKind::Rank => ZRangeBy::Rank(parse_int(&min)?, parse_int(&max)?),
+        RangeKind::Score => ZRangeBy::Score(parse_score_bound(&min)?, parse_score_bound(&max)?),
+        RangeKind::Lex => ZRangeBy::Lex(parse_lex_bound(min)?, parse_lex_bound(max)?),
+    })
+}
+
+fn score_reply(score: Option<f64>) -> RespFrame {
+    match score {
+        Some(score) => RespFrame::Double(score),
+        None => RespFrame::Null(RespNull),
+    }
+}
+
+pub(super) fn members_reply(
+    members: Vec<(String, f64)>,
+    with_scores: bool,
+    protocol: RespVersion,
+) -> RespFrame {
+    let ret = members
+        .into_iter()
+        .flat_map(|(member, score)| {
+            let member = BulkString::from(member).into();
+            match (with_scores, protocol) {
+                (false, _) => vec![member],
+                (true, RespVersion::Resp2) => vec![member, RespFrame::Double(score)],
+                (true, RespVersion::Resp3) => {
+                    vec![RespArray::new(vec![member, RespFrame::Double(score)]).into()]
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+    RespArray::new(ret).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::test_helpers::{array, bulk, run, run_async};
+    use crate::{BackendError, RespNullArray};
+
+    fn invalid(msg: &str) -> RespFrame {
+        CommandError::InvalidArgument(msg.to_string()).into()
+    }
+
+    #[test]
+    fn test_zadd_command() {
+        let backend = Backend::new();
+        assert_eq!(
+            run(&backend, &["zadd", "z", "1", "a", "2", "b"]),
+            RespFrame::Integer(2)
+        );
+        assert_eq!(
+            run(&backend, &["zadd", "z", "XX", "CH", "5", "a", "3", "c"]),
+            RespFrame::Integer(1)
+        );
+        assert_eq!(
+            run(&backend, &["zadd", "z", "incr", "1.5", "a"]),
+            RespFrame::Double(6.5)
+        );
+        assert_eq!(
+            run(&backend, &["zadd", "z", "NX", "INCR", "1", "a"]),
+            RespFrame::Null(RespNull)
+        );
+        assert_eq!(run(&backend, &["zscore", "z", "a"]), RespFrame::Double(6.5));
+        assert_eq!(
+            run(&backend, &["zadd", "z", "nx", "xx", "1", "a"]),
+            invalid("XX and NX options at the same time are not compatible")
+        );
+        assert_eq!(
+            run(&backend, &["zadd", "z", "gt", "lt", "1", "a"]),
+            invalid("GT, LT, and/or NX options at the same time are not compatible")
+        );
+        assert_eq!(
+            run(&backend, &["zadd", "z", "incr", "1", "a", "2", "b"]),
+            invalid("INCR option supports a single increment-element pair")
+        );
+        assert_eq!(
+            run(&backend, &["zadd", "z", "1", "a", "2"]),
+            CommandError::SyntaxError.into()
+        );
+        assert_eq!(
+            run(&backend, &["zadd", "z", "one", "a"]),
+            CommandError::NotAFloat.into()
+        );
+    }
+
+    #[test]
+    fn test_zset_commands() {
+        let backend = Backend::new();
+        run(&backend, &["zadd", "z", "1", "a", "2", "b", "3", "c"]);
+        assert_eq!(run(&backend, &["zcard", "z"]), RespFrame::Integer(3));
+        assert_eq!(
+            run(&backend, &["zmscore", "z", "a", "x"]),
+            RespArray::new(vec![RespFrame::Double(1.0), RespFrame::Null(RespNull)]).into()
+        );
+        assert_eq!(
+            run(&backend, &["zincrby", "z", "-2", "c"]),
+            RespFrame::Double(1.0)
+        );
+        assert_eq!(run(&backend, &["zrank", "z", "a"]), RespFrame::Integer(0));
+        assert_eq!(
+            run(&backend, &["zrevrank", "z", "a", "WITHSCORE"]),
+            RespArray::new(vec![RespFrame::Integer(2), RespFrame::Double(1.0)]).into()
+        );
+        assert_eq!(
+            run(&backend, &["zrank", "z", "x"]),
+            RespFrame::Null(RespNull)
+        );
+        assert_eq!(
+            run(&backend, &["zrem", "z", "a", "x"]),
+            RespFrame::Integer(1)
+        );
+
+        run(&backend, &["set", "s", "v"]);
+        assert_eq!(
+            run(&backend, &["zadd", "s", "1", "a"]),
+            BackendError::WrongType.into()
+        );
+        assert_eq!(
+            run(&backend, &["zadd", "inf", "+inf", "a", "-inf", "b"]),
+            RespFrame::Integer(2)
+        );
+        assert_eq!(
+            run(&backend, &["zincrby", "inf", "-inf", "a"]),
+            BackendError::ScoreNan.into()
+        );
+    }
+
+    #[test]
+    fn test_zrange_command() {
+        let backend = Backend::new();
+        run(
+            &backend,
+            &["zadd", "z", "1", "a", "2", "b", "3", "c", "4", "d"],
+        );
+        assert_eq!(
+            run(&backend, &["zrange", "z", "0", "-1"]),
+            array(&["a", "b", "c", "d"])
+        );
+        assert_eq!(
+            run(&backend, &["zrange", "z", "0", "1", "REV"]),
+            array(&["d", "c"])
+        );
+        assert_eq!(
+            run(&backend, &["zrange", "z", "(1", "3", "BYSCORE"]),
+            array(&["b", "c"])
+        );
+        assert_eq!(
+            run(
+                &backend,
+                &["zrange", "z", "+inf", "-inf", "BYSCORE", "REV", "LIMIT", "1", "2"]
+            ),
+            array(&["c", "b"])
+        );
+        assert_eq!(
+            run(&backend, &["zrange", "z", "[b", "(d", "BYLEX"]),
+            array(&["b", "c"])
+        );
+        assert_eq!(
+            run(&backend, &["zrange", "z", "0", "0", "WITHSCORES"]),
+            RespArray::new(vec![bulk("a"), RespFrame::Double(1.0)]).into()
+        );
+        assert_eq!(
+            run(&backend, &["zrange", "z", "0", "1", "LIMIT", "0", "1"]),
+            invalid(
+                "syntax error, LIMIT is only supported in combination with either BYSCORE or BYLEX"
+            )
+        );
+        assert_eq!(
+            run(&backend, &["zrange", "z", "-", "+", "BYLEX", "WITHSCORES"]),
+            invalid("syntax error, WITHSCORES not supported in combination with BYLEX")
+        );
+        assert_eq!(
+            run(&backend, &["zrange", "z", "a", "1", "BYSCORE"]),
+            invalid("min or max is not a float")
+        );
+        assert_eq!(
+            run(&backend, &["zrange", "z", "a", "+", "BYLEX"]),
+            invalid("min or max not valid string range item")
+        );
+    }
+
+    #[test]
+    fn test_zrange_reply_per_protocol() {
+        let backend = Backend::new();
+        run(&backend, &["zadd", "z", "1.5", "a"]);
+        let frames = ["zrange", "z", "0", "-1", "WITHSCORES"].map(bulk).to_vec();
+        let cmd = ZRange::try_from(RespArray::new(frames.clone())).unwrap();
+        let reply = cmd.reply(&backend, RespVersion::Resp3);
+        assert_eq!(
+            reply.encode_as(RespVersion::Resp3),
+            b"*1\r\n*2\r\n$1\r\na\r\n,+1.5\r\n"
+        );
+        let cmd = ZRange::try_from(RespArray::new(frames)).unwrap();
+        let reply = cmd.reply(&backend, RespVersion::Resp2);
+        assert_eq!(
+            reply.encode_as(RespVersion::Resp2),
+            b"*2\r\n$1\r\na\r\n$3\r\n1.5\r\n"
+        );
+    }
+
+    fn pairs(members: &[(&str, f64)]) -> RespFrame {
+        let members = members
+            .iter()
+            .flat_map(|(m, score)| [bulk(m), RespFrame::Double(*score)])
+            .collect::<Vec<_>>();
+        RespArray::new(members).into()
+    }
+
+    #[test]
+    fn test_zset_algebra_commands() {
+        let backend = Backend::new();
+        run(&backend, &["zadd", "a", "1", "x", "2", "y"]);
+        run(&backend, &["zadd", "b", "10", "y", "20", "z"]);
+        run(&backend, &["sadd", "s", "x"]);
+        assert_eq!(
+            run(&backend, &["zunion", "2", "a", "b", "WITHSCORES"]),
+            pairs(&[("x", 1.0), ("y", 12.0), ("z", 20.0)])
+        );
+        assert_eq!(
+            run(
+                &backend,
+                &[
+                    "zinter",
+                    "2",
+                    "a",
+                    "b",
+                    "weights",
+                    "2",
+                    "1",
+                    "aggregate",
+                    "max",
+                    "withscores"
+                ]
+            ),
+            pairs(&[("y", 10.0)])
+        );
+        assert_eq!(run(&backend, &["zdiff", "2", "a", "b"]), array(&["x"]));
+        assert_eq!(
+            run(&backend, &["zinterstore", "dst", "2", "a", "s"]),
+            RespFrame::Integer(1)
+        );
+        assert_eq!(
+            run(&backend, &["zscore", "dst", "x"]),
+            RespFrame::Double(2.0)
+        );
+        assert_eq!(
+            run(&backend, &["zdiffstore", "dst", "1", "missing"]),
+            RespFrame::Integer(0)
+        );
+        assert_eq!(run(&backend, &["exists", "dst"]), RespFrame::Integer(0));
+
+        assert_eq!(
+            run(&backend, &["zunion", "0", "a"]),
+            invalid("at least 1 input key is needed for 'zunion' command")
+        );
+        assert_eq!(
+            run(&backend, &["zunion", "2", "a", "b", "weights", "1", "x"]),
+            invalid("weight value is not a float")
+        );
+        assert_eq!(
+            run(&backend, &["zunion", "2", "a", "b", "weights", "1"]),
+            CommandError::SyntaxError.into()
+        );
+        assert_eq!(
+            run(&backend, &["zdiff", "1", "a", "aggregate", "min"]),
+            CommandError::SyntaxError.into()
+        );
+        assert_eq!(
+            run(&backend, &["zunionstore", "dst", "1", "a", "withscores"]),
+            CommandError::SyntaxError.into()
+        );
+    }
+
+    #[test]
+    fn test_zremrange_and_zcount_commands() {
+        let backend = Backend::new();
+        run(
+            &backend,
+            &[
+                "zadd", "z", "1", "a", "2", "b", "3", "c", "4", "d", "5", "e",
+            ],
+        );
+        assert_eq!(
+            run(&backend, &["zcount", "z", "(1", "3"]),
+            RespFrame::Integer(2)
+        );
+        assert_eq!(
+            run(&backend, &["zlexcount", "z", "-", "[b"]),
+            RespFrame::Integer(2)
+        );
+        assert_eq!(
+            run(&backend, &["zremrangebyrank", "z", "-1", "-1"]),
+            RespFrame::Integer(1)
+        );
+        assert_eq!(
+            run(&backend, &["zremrangebyscore", "z", "-inf", "(2"]),
+            RespFrame::Integer(1)
+        );
+        assert_eq!(
+            run(&backend, &["zremrangebylex", "z", "[b", "(d"]),
+            RespFrame::Integer(2)
+        );
+        assert_eq!(run(&backend, &["zrange", "z", "0", "-1"]), array(&["d"]));
+        assert_eq!(
+            run(&backend, &["zcount", "z", "x", "1"]),
+            invalid("min or max is not a float")
+        );
+    }
+
+    #[test]
+    fn test_zpop_commands() {
+        let backend = Backend::new();
+        run(&backend, &["zadd", "z", "1", "a", "2", "b", "3", "c"]);
+        assert_eq!(run(&backend, &["zpopmin", "z"]), pairs(&[("a", 1.0)]));
+        assert_eq!(
+            run(&backend, &["zpopmax", "z", "5"]),
+            pairs(&[("c", 3.0), ("b", 2.0)])
+        );
+        assert_eq!(run(&backend, &["zpopmin", "z"]), array(&[]));
+        assert_eq!(
+            run(&backend, &["zpopmin", "z", "-1"]),
+            invalid("value is out of range, must be positive")
+        );
+
+        run(&backend, &["zadd", "y", "1", "a", "2", "b"]);
+        let popped = RespArray::new(vec![RespArray::new(vec![
+            bulk("b"),
+            RespFrame::Double(2.0),
+        ])
+        .into()]);
+        assert_eq!(
+            run(&backend, &["zmpop", "2", "z", "y", "MAX"]),
+            RespArray::new(vec![bulk("y"), popped.into()]).into()
+        );
+        assert_eq!(
+            run(&backend, &["zmpop", "1", "z", "min"]),
+            RespFrame::NullArray(RespNullArray)
+        );
+        assert_eq!(
+            run(&backend, &["zmpop", "1", "y", "middle"]),
+            CommandError::SyntaxError.into()
+        );
+
+        assert_eq!(run(&backend, &["zrandmember", "y"]), bulk("a"));
+        assert_eq!(
+            run(&backend, &["zrandmember", "y", "-2", "WITHSCORES"]),
+            pairs(&[("a", 1.0), ("a", 1.0)])
+        );
+        assert_eq!(
+            run(&backend, &["zrandmember", "missing"]),
+            RespFrame::Null(RespNull)
+        );
+        assert_eq!(
+            run(&backend, &["zrandmember", "y", &i64::MIN.to_string()]),
+            CommandError::InvalidArgument("value is out of range".to_string()).into()
+        );
+    }
+
+    #[test]
+    fn test_zpop_reply_per_protocol() {
+        let backend = Backend::new();
+        run(&backend, &["zadd", "z", "1", "a", "2", "b"]);
+        let frames = ["zpopmin", "z"].map(bulk).to_vec();
+        let cmd = ZPop::try_from(RespArray::new(frames)).unwrap();
+        assert_eq!(
+            cmd.reply(&backend, RespVersion::Resp3),
+            pairs(&[("a", 1.0)])
+        );
+        let frames = ["zpopmin", "z", "1"].map(bulk).to_vec();
+        let cmd = ZPop::try_from(RespArray::new(frames)).unwrap();
+        assert_eq!(
+            cmd.reply(&backend, RespVersion::Resp3),
+            RespArray::new(vec![pairs(&[("b", 2.0)])]).into()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_blocking_zpop_commands() {
+        let backend = Backend::new();
+        run(&backend, &["zadd", "b", "1", "x", "2", "y"]);
+        assert_eq!(
+            run_async(&backend, &["bzpopmax", "a", "b", "0"]).await,
+            RespArray::new(vec![bulk("b"), bulk("y"), RespFrame::Double(2.0)]).into()
+        );
+        assert_eq!(
+            run_async(&backend, &["bzpopmin", "a", "0.01"]).await,
+            RespFrame::NullArray(RespNullArray)
+        );
+
+        let client = backend.clone();
+        let waiter = tokio::spawn(async move {
+            run_async(
+                &client,
+                &["bzmpop", "0", "2", "a", "c", "MIN", "COUNT", "2"],
+            )
+            .await
+        });
+        while backend.blocked_clients() < 1 {
+            tokio::task::yield_now().await;
+        }
+        run(&backend, &["zadd", "c", "3", "m", "1", "n", "2", "o"]);
+        let popped = RespArray::new(vec![
+            RespArray::new(vec![bulk("n"), RespFrame::Double(1.0)]).into(),
+            RespArray::new(vec![bulk("o"), RespFrame::Double(2.0)]).into(),
+        ]);
+        assert_eq!(
+            waiter.await.unwrap(),
+            RespArray::new(vec![bulk("c"), popped.into()]).into()
+        );
+        assert_eq!(run(&backend, &["zcard", "c"]), RespFrame::Integer(1));
+    }
+}
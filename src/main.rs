@@ -12,6 +12,7 @@ async fn main() -> Result<()> {
     info!("Simple Redis Server is listening on {addr}");
     let listener = TcpListener::bind(addr).await?;
     let backend = Backend::new();
+    tokio::spawn(backend.clone().run_active_expire());
     loop {
         let (stream, raddr) = listener.accept().await?;
         info!("Accepted connection from {addr}");
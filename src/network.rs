@@ -1,14 +1,24 @@
-use anyhow::Result;
+use std::collections::VecDeque;
+
+use anyhow::{anyhow, Result};
+use bytes::{Buf, BytesMut};
 use futures::SinkExt;
 use tokio::net::TcpStream;
 use tokio_stream::StreamExt;
 use tokio_util::codec::{Decoder, Encoder, Framed};
-use tracing::info;
+use tracing::{info, warn};
 
-use crate::{Backend, Command, CommandExecutor, RespDecode, RespEncode, RespError, RespFrame};
+use crate::{
+    Backend, Command, CommandError, ConnectionState, RespDecode, RespError, RespFrame, RespVersion,
+};
 
-#[derive(Debug)]
-struct RespFrameCodec;
+#[derive(Debug, Default)]
+struct RespFrameCodec {
+    // set after a malformed frame, until the start of the next array is found
+    resyncing: bool,
+    // replies are downgraded to RESP2 until the client switches with HELLO 3
+    protocol: RespVersion,
+}
 
 #[derive(Debug)]
 struct RedisRequest {
@@ -22,49 +32,364 @@ struct RedisResponse {
 }
 
 pub async fn stream_handler(stream: TcpStream, backend: Backend) -> Result<()> {
+    let mut conn = ConnectionState::new();
+    let ret = serve(stream, &backend, &mut conn).await;
+    // the subscriptions end with the connection
+    conn.unsubscribe_all(&backend);
+    ret
+}
+
+// read commands and write their replies, interleaved with the messages of the subscriptions
+// as they come: pushes for RESP3 clients, arrays for RESP2 ones
+async fn serve(stream: TcpStream, backend: &Backend, conn: &mut ConnectionState) -> Result<()> {
     //how to get a frame from the stream?
-    let mut framed = Framed::new(stream, RespFrameCodec);
+    let mut framed = Framed::new(stream, RespFrameCodec::default());
+    let mut messages = conn
+        .take_inbox()
+        .ok_or_else(|| anyhow!("connection {} is already served", conn.id))?;
+    // the commands read while an earlier one was waiting, run once it replied
+    let mut pending = VecDeque::new();
     loop {
-        match framed.next().await {
-            Some(Ok(frame)) => {
+        let frame = match pending.pop_front() {
+            Some(frame) => frame,
+            None => tokio::select! {
+                frame = framed.next() => match frame {
+                    Some(frame) => frame?,
+                    None => return Ok(()),
+                },
+                // the connection holds a sender, so this only ends when the client let too
+                // many messages wait
+                message = messages.recv() => {
+                    match message {
+                        Some(message) => framed.send(message).await?,
+                        None => {
+                            warn!("Closing connection {}: too many pending messages", conn.id);
+                            return Ok(());
+                        }
+                    }
+                    continue;
+                }
+            },
+        };
+        match frame {
+            Ok(frame) => {
                 let request = RedisRequest {
                     frame,
                     backend: backend.clone(),
                 };
-                let response = request_handler(request).await?;
+                // the socket is still read while the command runs, so that a client leaving
+                // while blocked in BLPOP drops the command and with it the wait, instead of
+                // taking the next push to a dead connection
+                let response = {
+                    let handler = request_handler(request, conn);
+                    tokio::pin!(handler);
+                    loop {
+                        tokio::select! {
+                            biased;
+                            response = &mut handler => break response?,
+                            frame = framed.next() => match frame {
+                                Some(frame) => pending.push_back(frame?),
+                                None => return Ok(()),
+                            },
+                        }
+                    }
+                };
                 info!("Sending response:{:?}", response.frame);
+                // HELLO replies in the protocol it switched to
+                framed.codec_mut().protocol = conn.protocol;
+                // what the command queued goes first, like the confirmations of SUBSCRIBE
+                // for all its channels but the last
+                while let Ok(message) = messages.try_recv() {
+                    framed.feed(message).await?;
+                }
                 framed.send(response.frame).await?;
+                if conn.closing {
+                    return Ok(());
+                }
+            }
+            Err(e) => {
+                warn!("Invalid frame received: {e}");
+                framed.send(CommandError::from(e).into()).await?;
             }
-            Some(Err(e)) => return Err(e),
-            None => return Ok(()),
         }
     }
 }
 
-async fn request_handler(request: RedisRequest) -> Result<RedisResponse> {
+async fn request_handler(
+    request: RedisRequest,
+    conn: &mut ConnectionState,
+) -> Result<RedisResponse> {
     let (frame, backend) = (request.frame, request.backend);
-    let cmd = Command::try_from(frame)?;
-    let ret = cmd.execute(&backend);
+    let ret = match Command::parse_in(frame, conn) {
+        Ok(cmd) => cmd.execute_async(&backend, conn).await,
+        Err(e) => e.into(),
+    };
     Ok(RedisResponse { frame: ret })
 }
 
 impl Encoder<RespFrame> for RespFrameCodec {
     type Error = anyhow::Error;
-    fn encode(&mut self, item: RespFrame, dst: &mut bytes::BytesMut) -> Result<()> {
-        let encoded = item.encode();
+    fn encode(&mut self, item: RespFrame, dst: &mut BytesMut) -> Result<()> {
+        let encoded = item.encode_as(self.protocol);
         dst.extend_from_slice(encoded.as_slice());
         Ok(())
     }
 }
 
+// a malformed frame is reported as `Ok(Some(Err(_)))` so that the connection survives it,
+// only io errors terminate the stream
 impl Decoder for RespFrameCodec {
-    type Item = RespFrame;
+    type Item = Result<RespFrame, RespError>;
     type Error = anyhow::Error;
-    fn decode(&mut self, src: &mut bytes::BytesMut) -> Result<Option<RespFrame>> {
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>> {
+        if self.resyncing {
+            if !skip_to_next_array(src) {
+                return Ok(None);
+            }
+            self.resyncing = false;
+        }
         match RespFrame::decode(src) {
-            Ok(frame) => Ok(Some(frame)),
+            Ok(frame) => Ok(Some(Ok(frame))),
             Err(RespError::NotComplete) => Ok(None),
-            Err(e) => Err(e.into()),
+            Err(e) => {
+                self.resyncing = !skip_to_next_array(src);
+                Ok(Some(Err(e)))
+            }
+        }
+    }
+}
+
+// drop everything up to the next line starting with `*`, which is where the next command
+// begins. Returns false if no such line is in the buffer yet.
+fn skip_to_next_array(src: &mut BytesMut) -> bool {
+    let pos = src.windows(3).position(|w| w == b"\r\n*").map(|p| p + 2);
+    match pos {
+        Some(pos) => {
+            src.advance(pos);
+            true
         }
+        None => {
+            // keep a possible trailing CRLF, the `*` might be in the next read
+            let keep = if src.ends_with(b"\r\n") {
+                2
+            } else if src.ends_with(b"\r") {
+                1
+            } else {
+                0
+            };
+            src.advance(src.len() - keep);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BulkString, RespArray, RespEncode, RespNull, RespPush, SimpleString};
+    use std::time::Duration;
+
+    #[test]
+    fn test_decoder_resyncs_after_invalid_frame() -> Result<()> {
+        let mut codec = RespFrameCodec::default();
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"!oops\r\n$3\r\nbad\r\n*1\r\n$4\r\nping\r\n");
+
+        let ret = codec.decode(&mut buf)?;
+        assert!(matches!(ret, Some(Err(RespError::InvalidFrameType(_)))));
+
+        let ret = codec.decode(&mut buf)?;
+        assert_eq!(ret, Some(Ok(RespArray::new(vec![b"ping".into()]).into())));
+        assert!(buf.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decoder_waits_for_next_array_while_resyncing() -> Result<()> {
+        let mut codec = RespFrameCodec::default();
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"?garbage\r\n");
+
+        let ret = codec.decode(&mut buf)?;
+        assert!(matches!(ret, Some(Err(_))));
+
+        buf.extend_from_slice(b"more garbage\r\n");
+        assert_eq!(codec.decode(&mut buf)?, None);
+
+        buf.extend_from_slice(b"*1\r\n$4\r\nping\r\n");
+        let ret = codec.decode(&mut buf)?;
+        assert_eq!(ret, Some(Ok(RespArray::new(vec![b"ping".into()]).into())));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_encoder_follows_protocol() -> Result<()> {
+        let mut codec = RespFrameCodec::default();
+        let mut buf = BytesMut::new();
+        codec.encode(RespFrame::Null(crate::RespNull), &mut buf)?;
+        assert_eq!(&buf[..], b"$-1\r\n");
+
+        buf.clear();
+        codec.protocol = RespVersion::Resp3;
+        codec.encode(RespFrame::Null(crate::RespNull), &mut buf)?;
+        assert_eq!(&buf[..], b"_\r\n");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_request_handler_replies_with_error() -> Result<()> {
+        let request = RedisRequest {
+            frame: RespArray::new(vec![b"get".into()]).into(),
+            backend: Backend::new(),
+        };
+        let response = request_handler(request, &mut ConnectionState::new()).await?;
+        assert_eq!(
+            response.frame.encode(),
+            b"-ERR wrong number of arguments for 'get' command\r\n"
+        );
+
+        Ok(())
+    }
+
+    type Client = Framed<TcpStream, RespFrameCodec>;
+
+    async fn connect(listener: &tokio::net::TcpListener, backend: &Backend) -> Result<Client> {
+        let client = TcpStream::connect(listener.local_addr()?).await?;
+        let (server, _) = listener.accept().await?;
+        tokio::spawn(stream_handler(server, backend.clone()));
+        Ok(Framed::new(client, RespFrameCodec::default()))
+    }
+
+    async fn call(client: &mut Client, args: &[&str]) -> Result<()> {
+        let args = args
+            .iter()
+            .map(|s| BulkString::from(*s).into())
+            .collect::<Vec<RespFrame>>();
+        client.send(RespArray::new(args).into()).await
+    }
+
+    fn message(parts: &[&str], count: Option<i64>) -> RespFrame {
+        let mut parts = parts
+            .iter()
+            .map(|s| BulkString::from(*s).into())
+            .collect::<Vec<RespFrame>>();
+        parts.extend(count.map(RespFrame::Integer));
+        RespArray::new(parts).into()
+    }
+
+    async fn receive(client: &mut Client) -> Result<RespFrame> {
+        let frame = tokio::time::timeout(Duration::from_secs(5), client.next()).await?;
+        Ok(frame.ok_or_else(|| anyhow!("connection closed"))???)
+    }
+
+    #[tokio::test]
+    async fn test_messages_are_pushed_to_subscribers() -> Result<()> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let backend = Backend::new();
+        let mut subscriber = connect(&listener, &backend).await?;
+        let mut publisher = connect(&listener, &backend).await?;
+
+        call(&mut subscriber, &["subscribe", "news", "sport"]).await?;
+        let confirmed = receive(&mut subscriber).await?;
+        assert_eq!(confirmed, message(&["subscribe", "news"], Some(1)));
+        let confirmed = receive(&mut subscriber).await?;
+        assert_eq!(confirmed, message(&["subscribe", "sport"], Some(2)));
+
+        call(&mut publisher, &["publish", "news", "hi"]).await?;
+        assert_eq!(receive(&mut publisher).await?, RespFrame::Integer(1));
+        let received = receive(&mut subscriber).await?;
+        assert_eq!(received, message(&["message", "news", "hi"], None));
+
+        call(&mut subscriber, &["get", "k"]).await?;
+        let RespFrame::Error(e) = receive(&mut subscriber).await? else {
+            panic!("GET should be refused in subscribed mode");
+        };
+        assert!(e.starts_with("ERR Can't execute 'get'"));
+
+        // the subscriptions end with the connection
+        drop(subscriber);
+        for _ in 0..100 {
+            call(&mut publisher, &["pubsub", "numsub", "news"]).await?;
+            let reply = receive(&mut publisher).await?;
+            if reply == RespArray::new(vec![b"news".into(), RespFrame::Integer(0)]).into() {
+                return Ok(());
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("the subscriptions outlived the connection");
+    }
+
+    #[tokio::test]
+    async fn test_blocked_client_leaving_gives_up_its_wait() -> Result<()> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let backend = Backend::new();
+        let mut blocked = connect(&listener, &backend).await?;
+        let mut client = connect(&listener, &backend).await?;
+
+        // a command sent while blocked runs once the wait is over
+        call(&mut blocked, &["blpop", "l", "0"]).await?;
+        call(&mut blocked, &["ping"]).await?;
+        call(&mut client, &["rpush", "l", "a"]).await?;
+        assert_eq!(receive(&mut client).await?, RespFrame::Integer(1));
+        let popped = RespArray::new(vec![b"l".into(), b"a".into()]);
+        assert_eq!(receive(&mut blocked).await?, popped.into());
+        assert_eq!(
+            receive(&mut blocked).await?,
+            SimpleString::new("PONG").into()
+        );
+
+        call(&mut blocked, &["blpop", "l", "0"]).await?;
+        while backend.blocked_clients() < 1 {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        drop(blocked);
+        while backend.blocked_clients() > 0 {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        // the element stays for the clients still there
+        call(&mut client, &["rpush", "l", "b"]).await?;
+        assert_eq!(receive(&mut client).await?, RespFrame::Integer(1));
+        call(&mut client, &["lpop", "l"]).await?;
+        assert_eq!(receive(&mut client).await?, b"b".into());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_resp3_pushes_interleave_with_replies() -> Result<()> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let backend = Backend::new();
+        let mut subscriber = connect(&listener, &backend).await?;
+        let mut publisher = connect(&listener, &backend).await?;
+
+        call(&mut subscriber, &["hello", "3"]).await?;
+        assert!(matches!(receive(&mut subscriber).await?, RespFrame::Map(_)));
+        call(&mut subscriber, &["psubscribe", "n*"]).await?;
+        let confirmed = receive(&mut subscriber).await?;
+        let expected = RespPush::new(vec![
+            b"psubscribe".into(),
+            b"n*".into(),
+            RespFrame::Integer(1),
+        ]);
+        assert_eq!(confirmed, expected.into());
+
+        call(&mut publisher, &["publish", "news", "hi"]).await?;
+        assert_eq!(receive(&mut publisher).await?, RespFrame::Integer(1));
+
+        // a RESP3 client keeps running commands, the push comes before the next reply
+        call(&mut subscriber, &["get", "k"]).await?;
+        let pushed = RespPush::new(vec![
+            b"pmessage".into(),
+            b"n*".into(),
+            b"news".into(),
+            b"hi".into(),
+        ]);
+        assert_eq!(receive(&mut subscriber).await?, pushed.into());
+        assert_eq!(receive(&mut subscriber).await?, RespFrame::Null(RespNull));
+
+        Ok(())
     }
 }
@@ -20,8 +20,8 @@ impl RespDecode for bool {
 // -boolean: ":<value>\r\n"
 impl RespEncode for bool {
     fn encode(self) -> Vec<u8> {
-        let value = if self { "1" } else { "0" };
-        format!(":{value}\r\n").into_bytes()
+        let value = if self { "t" } else { "f" };
+        format!("#{value}\r\n").into_bytes()
     }
 }
 #[cfg(test)]
@@ -58,6 +58,6 @@ mod tests {
     #[test]
     fn test_encode_boolean() {
         let frame: RespFrame = true.into();
-        assert_eq!(frame.encode(), b":1\r\n");
+        assert_eq!(frame.encode(), b"#t\r\n");
     }
 }
@@ -1,6 +1,6 @@
 use crate::resp::{
-    BulkString, RespArray, RespDecode, RespError, RespMap, RespNull, RespNullArray,
-    RespNullBulkString, RespSet, SimpleError, SimpleString,
+    BulkString, RespArray, RespDecode, RespEncode, RespError, RespMap, RespNull, RespNullArray,
+    RespNullBulkString, RespPush, RespSet, RespVersion, SimpleError, SimpleString,
 };
 use bytes::BytesMut;
 use enum_dispatch::enum_dispatch;
@@ -21,6 +21,7 @@ pub enum RespFrame {
     Double(f64),
     Map(RespMap),
     Set(RespSet),
+    Push(RespPush),
 }
 
 impl RespDecode for RespFrame {
@@ -82,6 +83,10 @@ impl RespDecode for RespFrame {
                 let frame = RespSet::decode(buf)?;
                 Ok(frame.into())
             }
+            Some(b'>') => {
+                let frame = RespPush::decode(buf)?;
+                Ok(frame.into())
+            }
             None => Err(RespError::NotComplete),
             _ => Err(RespError::InvalidFrameType(format!(
                 "Unknown RESP frame type: {:?}",
@@ -95,6 +100,7 @@ impl RespDecode for RespFrame {
         match iter.peek() {
             Some(b'*') => RespArray::expect_length(buf),
             Some(b'~') => RespSet::expect_length(buf),
+            Some(b'>') => RespPush::expect_length(buf),
             Some(b'%') => RespMap::expect_length(buf),
             Some(b'$') => BulkString::expect_length(buf),
             Some(b':') => i64::expect_length(buf),
@@ -103,7 +109,11 @@ impl RespDecode for RespFrame {
             Some(b'#') => bool::expect_length(buf),
             Some(b',') => f64::expect_length(buf),
             Some(b'_') => RespNull::expect_length(buf),
-            _ => Err(RespError::NotComplete),
+            None => Err(RespError::NotComplete),
+            Some(prefix) => Err(RespError::InvalidFrameType(format!(
+                "Unknown RESP frame type: {:?}",
+                **prefix as char
+            ))),
         }
     }
 }
@@ -113,3 +123,94 @@ impl<const N: usize> From<&[u8; N]> for RespFrame {
         BulkString(s.to_vec()).into()
     }
 }
+
+impl RespFrame {
+    /// Render the RESP3-only types with their RESP2 counterparts: maps become flat arrays of
+    /// key value pairs, sets and pushes become arrays, nulls become null bulk strings, booleans become
+    /// integers and doubles become bulk strings.
+    pub fn into_resp2(self) -> RespFrame {
+        match self {
+            RespFrame::Map(map) => {
+                let frames = map
+                    .0
+                    .into_iter()
+                    .flat_map(|(k, v)| [BulkString::from(k).into(), v.into_resp2()])
+                    .collect::<Vec<_>>();
+                RespArray::new(frames).into()
+            }
+            RespFrame::Set(set) => {
+                RespArray::new(set.0.into_iter().map(Self::into_resp2).collect::<Vec<_>>()).into()
+            }
+            RespFrame::Push(push) => {
+                RespArray::new(push.0.into_iter().map(Self::into_resp2).collect::<Vec<_>>()).into()
+            }
+            RespFrame::Array(array) => RespArray::new(
+                array
+                    .0
+                    .into_iter()
+                    .map(Self::into_resp2)
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            RespFrame::Null(_) => RespNullBulkString.into(),
+            RespFrame::Boolean(b) => RespFrame::Integer(b as i64),
+            RespFrame::Double(d) => BulkString::from(d.to_string()).into(),
+            frame => frame,
+        }
+    }
+
+    /// Encode the frame for a connection speaking the given protocol version.
+    pub fn encode_as(self, version: RespVersion) -> Vec<u8> {
+        match version {
+            RespVersion::Resp2 => self.into_resp2().encode(),
+            RespVersion::Resp3 => self.encode(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_into_resp2() {
+        let map = RespMap(BTreeMap::from([
+            ("a".to_string(), RespFrame::Boolean(true)),
+            ("b".to_string(), RespFrame::Double(1.5)),
+        ]));
+        let frame: RespFrame = RespArray::new(vec![map.into(), RespNull.into()]).into();
+        assert_eq!(
+            frame.encode_as(RespVersion::Resp2),
+            b"*2\r\n*4\r\n$1\r\na\r\n:+1\r\n$1\r\nb\r\n$3\r\n1.5\r\n$-1\r\n"
+        );
+        // the nil of the array replies stays a null array
+        let frame: RespFrame = RespNullArray.into();
+        assert_eq!(frame.encode_as(RespVersion::Resp2), b"*-1\r\n");
+    }
+
+    #[test]
+    fn test_encode_as_resp3_keeps_types() {
+        let frame: RespFrame = RespSet::new(vec![RespFrame::Boolean(false)]).into();
+        assert_eq!(frame.clone().encode_as(RespVersion::Resp3), b"~1\r\n#f\r\n");
+        assert_eq!(frame.encode_as(RespVersion::Resp2), b"*1\r\n:+0\r\n");
+    }
+
+    #[test]
+    fn test_push_frames() -> anyhow::Result<()> {
+        let frame: RespFrame =
+            RespPush::new(vec![b"message".into(), RespFrame::Double(1.5)]).into();
+        let encoded = frame.clone().encode_as(RespVersion::Resp3);
+        assert_eq!(encoded, b">2\r\n$7\r\nmessage\r\n,+1.5\r\n");
+        assert_eq!(RespFrame::expect_length(&encoded)?, encoded.len());
+        assert_eq!(RespFrame::decode(&mut BytesMut::from(&encoded[..]))?, frame);
+
+        // a RESP2 client gets the array it always received
+        assert_eq!(
+            frame.encode_as(RespVersion::Resp2),
+            b"*2\r\n$7\r\nmessage\r\n$3\r\n1.5\r\n"
+        );
+
+        Ok(())
+    }
+}
@@ -6,6 +6,7 @@ mod frame;
 mod integer;
 mod map;
 mod null;
+mod push;
 mod set;
 mod simple_error;
 mod simple_string;
@@ -25,6 +26,14 @@ pub trait RespDecode: Sized {
     fn expect_length(buf: &[u8]) -> Result<usize, RespError>;
 }
 
+/// The protocol version a connection speaks, switched with HELLO. Connections start in RESP2.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RespVersion {
+    #[default]
+    Resp2,
+    Resp3,
+}
+
 #[derive(Error, Debug, PartialEq, Eq)]
 pub enum RespError {
     #[error("Invalid frame: {0}")]
@@ -50,6 +59,7 @@ pub use self::{
     frame::RespFrame,
     map::RespMap,
     null::RespNull,
+    push::RespPush,
     set::RespSet,
     simple_error::SimpleError,
     simple_string::SimpleString,
@@ -67,8 +77,8 @@ pub fn calc_total_length(
     let mut total = end + CRLF_LEN;
     let mut data = &buf[total..];
     match prefix {
-        "*" | "~" => {
-            // find nth CRLF in the buffer, for array and set, we need to find 1 CRLF for each element
+        "*" | "~" | ">" => {
+            // find nth CRLF in the buffer, for array, set and push, we need to find 1 CRLF for each element
             for _ in 0..len {
                 let len = RespFrame::expect_length(data)?;
                 data = &data[len..];
@@ -0,0 +1,89 @@
+use super::{calc_total_length, parse_length, BUF_CAP};
+use crate::resp::{RespDecode, RespEncode, RespError, RespFrame};
+use bytes::{Buf, BytesMut};
+use std::ops::Deref;
+
+/// Out-of-band data sent to a RESP3 client, like the messages of its subscriptions. Shaped
+/// like an array, its first element tells what kind of push it is.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct RespPush(pub Vec<RespFrame>);
+
+// - push: "><number-of-elements>\r\n<element-1>...<element-n>"
+impl RespDecode for RespPush {
+    const PREFIX: &'static str = ">";
+    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
+        let (end, len) = parse_length(buf, Self::PREFIX)?;
+        let mut frames = Vec::with_capacity(len);
+        let total_len = calc_total_length(buf, end, len, Self::PREFIX)?;
+        if buf.len() < total_len {
+            return Err(RespError::NotComplete);
+        }
+        buf.advance(end + 2);
+        for _ in 0..len {
+            frames.push(RespFrame::decode(buf)?);
+        }
+        Ok(RespPush::new(frames))
+    }
+    fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
+        let (end, len) = parse_length(buf, Self::PREFIX)?;
+        calc_total_length(buf, end, len, Self::PREFIX)
+    }
+}
+
+// - push: "><number-of-elements>\r\n<element-1>...<element-n>"
+impl RespEncode for RespPush {
+    fn encode(self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(BUF_CAP);
+        buf.extend_from_slice(&format!(">{}\r\n", self.len()).into_bytes());
+        for frame in self.0 {
+            buf.extend_from_slice(&frame.encode());
+        }
+        buf
+    }
+}
+
+impl Deref for RespPush {
+    type Target = Vec<RespFrame>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+impl RespPush {
+    pub fn new(s: impl Into<Vec<RespFrame>>) -> Self {
+        RespPush(s.into())
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resp::BulkString;
+    use anyhow::Result;
+
+    #[test]
+    fn test_push_decode() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b">3\r\n$7\r\nmessage\r\n$4\r\nnews\r\n$2\r\nhi\r\n");
+
+        let frame = RespPush::decode(&mut buf)?;
+        assert_eq!(
+            frame,
+            RespPush::new(vec![
+                BulkString::new(b"message".to_vec()).into(),
+                BulkString::new(b"news".to_vec()).into(),
+                BulkString::new(b"hi".to_vec()).into()
+            ])
+        );
+
+        buf.extend_from_slice(b">2\r\n$7\r\nmessage\r\n");
+        assert_eq!(RespPush::decode(&mut buf), Err(RespError::NotComplete));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_push() {
+        let push = RespPush(vec![BulkString::from("subscribe").into(), 1.into()]);
+        assert_eq!(push.encode(), b">2\r\n$9\r\nsubscribe\r\n:+1\r\n");
+    }
+}
@@ -27,10 +27,16 @@ impl Deref for SimpleError {
     }
 }
 
-// - error: "- Error message"
+impl SimpleError {
+    pub fn new(s: impl Into<String>) -> Self {
+        SimpleError(s.into())
+    }
+}
+
+// - error: "-Error message\r\n"
 impl RespEncode for SimpleError {
     fn encode(self) -> Vec<u8> {
-        format!("- {}\r\n", self.0).into_bytes()
+        format!("-{}\r\n", self.0).into_bytes()
     }
 }
 #[cfg(test)]
@@ -53,6 +59,9 @@ mod tests {
     #[test]
     fn test_encode_error() {
         let frame: RespFrame = SimpleError("error".to_string()).into();
-        assert_eq!(frame.encode(), b"- error\r\n");
+        assert_eq!(frame.encode(), b"-error\r\n");
+
+        let frame: RespFrame = SimpleError::new("ERR unknown command").into();
+        assert_eq!(frame.encode(), b"-ERR unknown command\r\n");
     }
 }